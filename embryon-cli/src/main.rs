@@ -1,18 +1,72 @@
 use clap::Parser;
+use embryon_lang::target::Target;
 use std::path::PathBuf;
 
+mod svd;
+mod xml;
+
 #[derive(Parser, Debug)]
 #[command()]
 struct Args {
     input: PathBuf,
+    /// Function to call when a runtime check fails, instead of trapping
+    #[arg(long)]
+    panic_handler: Option<String>,
+    /// How deeply expressions can be nested
+    #[arg(long, default_value_t = embryon_lang::lexer::TokenStream::DEFAULT_NESTING_LIMIT)]
+    nesting_limit: usize,
+    /// CMSIS-SVD file describing the peripherals the program can use
+    #[arg(long)]
+    svd: Vec<PathBuf>,
+    /// Processor to compile for: host, cortex-m0, cortex-m0plus, cortex-m3, cortex-m4 or cortex-m7
+    #[arg(long, default_value = "host", value_parser = parse_target)]
+    target: Target,
+    /// Generate the vector table and reset handler even if the program has no handlers
+    #[arg(long)]
+    vector_table: bool,
+}
+
+fn parse_target(name: &str) -> Result<Target, String> {
+    Target::from_name(name).ok_or_else(|| format!("unknown target `{name}`"))
 }
 
 fn main() {
     let args = Args::parse();
     let source = std::fs::read_to_string(&args.input).unwrap();
-    let tokens = embryon_lang::lex(&source);
-    let program = embryon_lang::parse(tokens).expect("failed to compile program");
-    embryon_lang::compile(&program, &args.input);
+    let tokens = embryon_lang::lex(&source).with_nesting_limit(args.nesting_limit);
+    let mut program = embryon_lang::parse(tokens).unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        std::process::exit(1);
+    });
+    for path in &args.svd {
+        let peripherals = std::fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|svd| svd::generate(&svd).map_err(|err| err.to_string()))
+            .and_then(|source| {
+                embryon_lang::parse(embryon_lang::lex(&source)).map_err(|err| err.to_string())
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("error: {}: {err}", path.display());
+                std::process::exit(1);
+            });
+        program.definitions.extend(peripherals.definitions);
+    }
+    let options = embryon_lang::CompileOptions {
+        panic_handler: args.panic_handler,
+        target: args.target,
+        vector_table: args.vector_table,
+    };
+    match embryon_lang::compile(&program, &args.input, &options) {
+        Ok(warnings) => {
+            for warning in warnings {
+                eprintln!("warning: {warning}");
+            }
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    }
 
     // let asm = args.input.with_extension("s");
 
@@ -0,0 +1,576 @@
+//! Generates Embryon peripheral declarations from a CMSIS-SVD file, the XML description of a
+//! microcontroller's peripherals that its vendor publishes.
+//!
+//! Each peripheral becomes a `peripheral` item with its registers and their bitfields. The
+//! enumerated values of a bitfield become constants named after the peripheral, register and
+//! field, such as `GPIO_PIN_CNF0_DIR_OUTPUT`. The interrupts of the peripherals become the
+//! variants of an `Interrupt` enum, numbered as in the device's vector table.
+
+use crate::xml::{self, Element, XmlError};
+use embryon_lang::lexer::is_keyword;
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+
+#[derive(Debug)]
+pub enum SvdError {
+    Xml(XmlError),
+    /// The root element isn't a `<device>`.
+    NotADevice(String),
+    MissingElement {
+        parent: String,
+        name: &'static str,
+    },
+    InvalidNumber(String),
+    InvalidAccess(String),
+    /// A register whose size isn't that of an integer type.
+    InvalidSize {
+        register: String,
+        size: u64,
+    },
+    /// A `derivedFrom` naming a peripheral that doesn't exist.
+    UnknownPeripheral(String),
+}
+
+impl From<XmlError> for SvdError {
+    fn from(err: XmlError) -> Self {
+        Self::Xml(err)
+    }
+}
+
+impl fmt::Display for SvdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Xml(err) => write!(f, "{err}"),
+            Self::NotADevice(name) => write!(f, "expected a `<device>`, found `<{name}>`"),
+            Self::MissingElement { parent, name } => write!(f, "`{parent}` has no `<{name}>`"),
+            Self::InvalidNumber(text) => write!(f, "invalid number `{text}`"),
+            Self::InvalidAccess(text) => write!(f, "invalid access `{text}`"),
+            Self::InvalidSize { register, size } => {
+                write!(f, "register `{register}` has a size of {size} bits")
+            }
+            Self::UnknownPeripheral(name) => write!(f, "cannot find peripheral `{name}`"),
+        }
+    }
+}
+
+impl std::error::Error for SvdError {}
+
+/// Generates the Embryon source declaring the peripherals of the device described by `source`.
+pub fn generate(source: &str) -> Result<String, SvdError> {
+    let device = xml::parse(source)?;
+    if device.name != "device" {
+        return Err(SvdError::NotADevice(device.name));
+    }
+    let defaults = Defaults {
+        size: 32,
+        access: "rw",
+    }
+    .inherit(&device)?;
+    let peripherals: Vec<&Element> = required(&device, "peripherals")?
+        .children("peripheral")
+        .collect();
+    let mut generator = Generator::default();
+    for peripheral in &peripherals {
+        // A derived peripheral is a copy of another at its own address, such as a second UART
+        let base = match peripheral.attribute("derivedFrom") {
+            Some(name) => Some(
+                peripherals
+                    .iter()
+                    .find(|other| other.child_text("name") == Some(name))
+                    .ok_or_else(|| SvdError::UnknownPeripheral(name.into()))?,
+            ),
+            None => None,
+        };
+        let defaults = match base {
+            Some(base) => defaults.inherit(base)?,
+            None => defaults,
+        }
+        .inherit(peripheral)?;
+        let description = peripheral
+            .child_text("description")
+            .or_else(|| base?.child_text("description"));
+        let mut registers = Vec::new();
+        if let Some(list) = peripheral
+            .child("registers")
+            .or_else(|| base?.child("registers"))
+        {
+            collect_registers(list, "", 0, defaults, &mut registers)?;
+        }
+        generator.peripheral(
+            &identifier(required_text(peripheral, "name")?),
+            description,
+            number(required_text(peripheral, "baseAddress")?)?,
+            &registers,
+        );
+        for interrupt in peripheral.children("interrupt") {
+            generator.interrupt(
+                identifier(required_text(interrupt, "name")?),
+                interrupt.child_text("description").map(describe),
+                number(required_text(interrupt, "value")?)?,
+            );
+        }
+    }
+    generator.interrupt_enum();
+    Ok(generator.output + &generator.constants)
+}
+
+/// The properties that registers inherit from the device, peripheral or cluster they are in.
+#[derive(Clone, Copy, Debug)]
+struct Defaults {
+    /// The size of registers in bits.
+    size: u64,
+    access: &'static str,
+}
+
+impl Defaults {
+    /// The defaults for the registers in `element`, which can override these.
+    fn inherit(self, element: &Element) -> Result<Self, SvdError> {
+        Ok(Self {
+            size: match element.child_text("size") {
+                Some(size) => number(size)?,
+                None => self.size,
+            },
+            access: match element.child_text("access") {
+                Some(access) => access_mode(access)?,
+                None => self.access,
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Register {
+    name: String,
+    description: Option<String>,
+    offset: u64,
+    size: u64,
+    access: &'static str,
+    fields: Vec<Field>,
+}
+
+#[derive(Debug)]
+struct Field {
+    name: String,
+    description: Option<String>,
+    offset: u64,
+    width: u64,
+    /// The field's enumerated values, with their descriptions.
+    values: Vec<(String, Option<String>, u64)>,
+}
+
+/// Collects the registers in `list`, a `<registers>` or `<cluster>` element at `offset` from the
+/// peripheral's address. The registers of clusters are prefixed with the cluster's name.
+fn collect_registers(
+    list: &Element,
+    prefix: &str,
+    offset: u64,
+    defaults: Defaults,
+    registers: &mut Vec<Register>,
+) -> Result<(), SvdError> {
+    for element in &list.children {
+        let is_cluster = match element.name.as_str() {
+            "register" => false,
+            "cluster" => true,
+            _ => continue,
+        };
+        let defaults = defaults.inherit(element)?;
+        let address_offset = number(required_text(element, "addressOffset")?)?;
+        for (name, step) in dimensions(element)? {
+            let name = format!("{prefix}{}", identifier(&name));
+            let offset = offset + address_offset + step;
+            if is_cluster {
+                collect_registers(element, &format!("{name}_"), offset, defaults, registers)?;
+                continue;
+            }
+            if !matches!(defaults.size, 8 | 16 | 32 | 64) {
+                return Err(SvdError::InvalidSize {
+                    register: name,
+                    size: defaults.size,
+                });
+            }
+            // Alternate registers can share a name, but the first is enough to reach the address
+            if registers.iter().any(|register| register.name == name) {
+                continue;
+            }
+            let mut fields = Vec::new();
+            if let Some(list) = element.child("fields") {
+                for field in list.children("field") {
+                    collect_field(field, &mut fields)?;
+                }
+            }
+            registers.push(Register {
+                name,
+                description: element.child_text("description").map(describe),
+                offset,
+                size: defaults.size,
+                access: defaults.access,
+                fields,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn collect_field(field: &Element, fields: &mut Vec<Field>) -> Result<(), SvdError> {
+    // The bits can be given as an offset and width, as the lowest and highest bit, or as a range
+    let (offset, width) = if let Some(offset) = field.child_text("bitOffset") {
+        let width = field.child_text("bitWidth").map_or(Ok(1), number)?;
+        (number(offset)?, width)
+    } else if let Some(lsb) = field.child_text("lsb") {
+        let msb = number(required_text(field, "msb")?)?;
+        let lsb = number(lsb)?;
+        (lsb, msb.saturating_sub(lsb) + 1)
+    } else {
+        let range = required_text(field, "bitRange")?;
+        let invalid = || SvdError::InvalidNumber(range.into());
+        let (msb, lsb) = range
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split_once(':')
+            .ok_or_else(invalid)?;
+        let (msb, lsb) = (number(msb)?, number(lsb)?);
+        (lsb, msb.saturating_sub(lsb) + 1)
+    };
+    let mut values = Vec::new();
+    for list in field.children("enumeratedValues") {
+        for value in list.children("enumeratedValue") {
+            // A default value stands for all the values not listed, so it has no value of its own
+            let Some(text) = value.child_text("value") else {
+                continue;
+            };
+            // Values such as `#1x` match several values, so can't be written
+            if text.starts_with('#') && text.contains(['x', 'X']) {
+                continue;
+            }
+            let name = identifier(required_text(value, "name")?).to_uppercase();
+            if values.iter().any(|(other, _, _)| *other == name) {
+                continue;
+            }
+            let description = value.child_text("description").map(describe);
+            values.push((name, description, number(text)?));
+        }
+    }
+    for (name, step) in dimensions(field)? {
+        fields.push(Field {
+            name: identifier(&name),
+            description: field.child_text("description").map(describe),
+            offset: offset + step,
+            width,
+            values: values.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// The names of the copies of an element with a `<dim>`, such as `PIN_CNF[%s]` or `PIN%s`, and
+/// how far each one is from the first. An element without one has a single copy.
+fn dimensions(element: &Element) -> Result<Vec<(String, u64)>, SvdError> {
+    let name = required_text(element, "name")?;
+    let Some(count) = element.child_text("dim") else {
+        return Ok(vec![(name.into(), 0)]);
+    };
+    let count = number(count)?;
+    let increment = number(required_text(element, "dimIncrement")?)?;
+    let indices: Vec<String> = match element.child_text("dimIndex") {
+        Some(indices) => match indices.split_once('-') {
+            Some((first, last)) if !indices.contains(',') => {
+                let (first, last) = (number(first)?, number(last)?);
+                (first..=last).map(|index| index.to_string()).collect()
+            }
+            _ => indices
+                .split(',')
+                .map(|index| index.trim().into())
+                .collect(),
+        },
+        None => (0..count).map(|index| index.to_string()).collect(),
+    };
+    let name = name.replace("[%s]", "%s");
+    Ok(indices
+        .iter()
+        .zip(0..)
+        .map(|(index, i)| (name.replace("%s", index), i * increment))
+        .collect())
+}
+
+/// Writes the generated declarations. Constants are kept separately and written after every
+/// peripheral, and interrupts are collected for the `Interrupt` enum.
+#[derive(Default)]
+struct Generator {
+    output: String,
+    constants: String,
+    constant_names: HashSet<String>,
+    /// The interrupts with their descriptions and numbers.
+    interrupts: Vec<(String, Option<String>, u64)>,
+}
+
+impl Generator {
+    fn peripheral(
+        &mut self,
+        name: &str,
+        description: Option<&str>,
+        address: u64,
+        registers: &[Register],
+    ) {
+        if let Some(description) = description {
+            writeln!(self.output, "/// {}", describe(description)).unwrap();
+        }
+        writeln!(self.output, "peripheral {name} @ {address:#x} {{").unwrap();
+        for register in registers {
+            if let Some(description) = &register.description {
+                writeln!(self.output, "    /// {description}").unwrap();
+            }
+            write!(
+                self.output,
+                "    {}: u{} @ {:#x} {}",
+                register.name, register.size, register.offset, register.access
+            )
+            .unwrap();
+            if register.fields.is_empty() {
+                writeln!(self.output, ",").unwrap();
+                continue;
+            }
+            writeln!(self.output, " {{").unwrap();
+            for field in &register.fields {
+                if let Some(description) = &field.description {
+                    writeln!(self.output, "        /// {description}").unwrap();
+                }
+                let bits = match field.width {
+                    1 => field.offset.to_string(),
+                    width => format!("{}..={}", field.offset, field.offset + width - 1),
+                };
+                writeln!(self.output, "        {}: {bits},", field.name).unwrap();
+                for (value, description, number) in &field.values {
+                    let constant = format!("{name}_{}_{}_{value}", register.name, field.name);
+                    self.constant(constant, description.as_deref(), register.size, *number);
+                }
+            }
+            writeln!(self.output, "    }},").unwrap();
+        }
+        writeln!(self.output, "}}\n").unwrap();
+    }
+
+    /// Adds an interrupt, unless it was already added. Peripherals that share an interrupt, such
+    /// as a UART and its DMA variant, each list it.
+    fn interrupt(&mut self, name: String, description: Option<String>, number: u64) {
+        let exists = self
+            .interrupts
+            .iter()
+            .any(|(other, _, other_number)| *other == name || *other_number == number);
+        if !exists {
+            self.interrupts.push((name, description, number));
+        }
+    }
+
+    /// Writes the `Interrupt` enum, whose variants are in the order of the vector table.
+    fn interrupt_enum(&mut self) {
+        if self.interrupts.is_empty() {
+            return;
+        }
+        self.interrupts.sort_by_key(|(_, _, number)| *number);
+        writeln!(self.output, "/// The interrupts of the device.").unwrap();
+        writeln!(self.output, "enum Interrupt {{").unwrap();
+        for (name, description, number) in &self.interrupts {
+            if let Some(description) = description {
+                writeln!(self.output, "    /// {description}").unwrap();
+            }
+            writeln!(self.output, "    {name} = {number},").unwrap();
+        }
+        writeln!(self.output, "}}\n").unwrap();
+    }
+
+    fn constant(&mut self, name: String, description: Option<&str>, size: u64, value: u64) {
+        if !self.constant_names.insert(name.clone()) {
+            return;
+        }
+        if let Some(description) = description {
+            writeln!(self.constants, "/// {description}").unwrap();
+        }
+        writeln!(self.constants, "const {name}: u{size} = {value:#x};").unwrap();
+    }
+}
+
+fn required<'a>(element: &'a Element, name: &'static str) -> Result<&'a Element, SvdError> {
+    element.child(name).ok_or_else(|| SvdError::MissingElement {
+        parent: element
+            .child_text("name")
+            .unwrap_or(&element.name)
+            .to_string(),
+        name,
+    })
+}
+
+fn required_text<'a>(element: &'a Element, name: &'static str) -> Result<&'a str, SvdError> {
+    required(element, name).map(|child| child.text.trim())
+}
+
+/// Parses a number as SVD writes them: decimal, hexadecimal after `0x`, or binary after `#`.
+fn number(text: &str) -> Result<u64, SvdError> {
+    let text = text.trim();
+    let value = if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(binary) = text.strip_prefix('#') {
+        u64::from_str_radix(binary, 2)
+    } else {
+        text.parse()
+    };
+    value.map_err(|_| SvdError::InvalidNumber(text.into()))
+}
+
+/// The Embryon access mode of an SVD access type.
+fn access_mode(access: &str) -> Result<&'static str, SvdError> {
+    match access {
+        "read-only" => Ok("r"),
+        "write-only" | "writeOnce" => Ok("w"),
+        "read-write" | "read-writeOnce" => Ok("rw"),
+        _ => Err(SvdError::InvalidAccess(access.into())),
+    }
+}
+
+/// Turns an SVD name into an identifier, replacing characters that can't be in one with `_`.
+fn identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    if is_keyword(&identifier) {
+        identifier.push('_');
+    }
+    identifier
+}
+
+/// Puts a description on one line, as SVD descriptions are often wrapped and indented.
+fn describe(description: &str) -> String {
+    description.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SvdError, generate, identifier};
+
+    const DEVICE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<device schemaVersion="1.1">
+  <name>nRF52833</name>
+  <size>32</size>
+  <access>read-write</access>
+  <peripherals>
+    <peripheral>
+      <name>P0</name>
+      <description>GPIO Port 1</description>
+      <baseAddress>0x50000000</baseAddress>
+      <interrupt>
+        <name>GPIOTE</name>
+        <description>GPIO tasks and events</description>
+        <value>6</value>
+      </interrupt>
+      <interrupt><name>RADIO</name><value>1</value></interrupt>
+      <registers>
+        <register>
+          <name>IN</name>
+          <description>Read GPIO port</description>
+          <addressOffset>0x510</addressOffset>
+          <access>read-only</access>
+        </register>
+        <register>
+          <dim>2</dim>
+          <dimIncrement>0x4</dimIncrement>
+          <name>PIN_CNF[%s]</name>
+          <description>Configuration of GPIO pins</description>
+          <addressOffset>0x700</addressOffset>
+          <fields>
+            <field>
+              <name>DIR</name>
+              <description>Pin direction.</description>
+              <lsb>0</lsb>
+              <msb>0</msb>
+              <enumeratedValues>
+                <enumeratedValue><name>Input</name><value>0</value></enumeratedValue>
+                <enumeratedValue><name>Output</name><value>1</value></enumeratedValue>
+              </enumeratedValues>
+            </field>
+            <field>
+              <name>PULL</name>
+              <bitRange>[3:2]</bitRange>
+            </field>
+          </fields>
+        </register>
+      </registers>
+    </peripheral>
+    <peripheral derivedFrom="P0">
+      <name>P1</name>
+      <baseAddress>0x50000300</baseAddress>
+    </peripheral>
+  </peripherals>
+</device>"#;
+
+    #[test]
+    fn generates_peripherals() {
+        let source = generate(DEVICE).unwrap();
+
+        let register = "    /// Configuration of GPIO pins
+    PIN_CNF0: u32 @ 0x700 rw {
+        /// Pin direction.
+        DIR: 0,
+        PULL: 2..=3,
+    },";
+        assert!(source.starts_with(
+            "/// GPIO Port 1
+peripheral P0 @ 0x50000000 {
+    /// Read GPIO port
+    IN: u32 @ 0x510 r,
+"
+        ));
+        assert!(source.contains(register));
+        assert!(source.contains("    PIN_CNF1: u32 @ 0x704 rw {"));
+        assert!(source.contains("/// GPIO Port 1\nperipheral P1 @ 0x50000300 {"));
+        assert!(source.contains("const P0_PIN_CNF0_DIR_OUTPUT: u32 = 0x1;"));
+        assert!(source.contains("const P1_PIN_CNF1_DIR_INPUT: u32 = 0x0;"));
+    }
+
+    #[test]
+    fn generated_source_parses() {
+        let source = generate(DEVICE).unwrap();
+        let module = embryon_lang::parse(embryon_lang::lex(&source)).unwrap();
+
+        // Two peripherals, two constants for each of their two registers with a `DIR`, and the
+        // interrupts
+        assert_eq!(module.definitions.len(), 11);
+    }
+
+    #[test]
+    fn generates_interrupts() {
+        let source = generate(DEVICE).unwrap();
+
+        assert!(source.contains(
+            "/// The interrupts of the device.
+enum Interrupt {
+    RADIO = 1,
+    /// GPIO tasks and events
+    GPIOTE = 6,
+}"
+        ));
+    }
+
+    #[test]
+    fn names_become_identifiers() {
+        assert_eq!(identifier("DMA-CH0"), "DMA_CH0");
+        assert_eq!(identifier("3V3"), "_3V3");
+        assert_eq!(identifier("in"), "in_");
+        assert_eq!(identifier("static"), "static_");
+        assert_eq!(identifier("extern"), "extern_");
+    }
+
+    #[test]
+    fn unknown_base_peripheral() {
+        let source = "<device><peripherals>
+            <peripheral derivedFrom='UART0'><name>UART1</name></peripheral>
+        </peripherals></device>";
+
+        assert!(matches!(
+            generate(source),
+            Err(SvdError::UnknownPeripheral(name)) if name == "UART0"
+        ));
+    }
+}
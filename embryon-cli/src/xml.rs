@@ -0,0 +1,318 @@
+//! A small XML parser, enough to read CMSIS-SVD files.
+//!
+//! It builds a tree of elements with their attributes and text. The XML declaration, comments,
+//! processing instructions and doctypes are skipped, and namespace prefixes are kept as part of
+//! names.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    /// The text directly inside the element, with entities decoded.
+    pub text: String,
+}
+
+impl Element {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The first child element called `name`.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The text of the first child element called `name`, without surrounding whitespace.
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlError {
+    /// The line the error was found on, starting at 1.
+    pub line: usize,
+    pub kind: XmlErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XmlErrorKind {
+    UnexpectedEnd,
+    /// Something other than what the grammar allows, such as a `<` inside an attribute value.
+    Expected(&'static str),
+    MismatchedTag {
+        open: String,
+        close: String,
+    },
+    UnknownEntity(String),
+    /// Content after the end of the root element.
+    TrailingContent,
+}
+
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            XmlErrorKind::UnexpectedEnd => write!(f, "unexpected end of XML"),
+            XmlErrorKind::Expected(what) => write!(f, "expected {what}"),
+            XmlErrorKind::MismatchedTag { open, close } => {
+                write!(f, "`<{open}>` is closed by `</{close}>`")
+            }
+            XmlErrorKind::UnknownEntity(entity) => write!(f, "unknown entity `&{entity};`"),
+            XmlErrorKind::TrailingContent => write!(f, "content after the root element"),
+        }
+    }
+}
+
+impl std::error::Error for XmlError {}
+
+/// Parses a document, returning its root element.
+pub fn parse(source: &str) -> Result<Element, XmlError> {
+    let mut parser = Parser { source, cursor: 0 };
+    parser.skip_misc()?;
+    parser.expect("<", "the root element")?;
+    let root = parser.parse_element()?;
+    parser.skip_misc()?;
+    if parser.cursor < source.len() {
+        return Err(parser.error(XmlErrorKind::TrailingContent));
+    }
+    Ok(root)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    cursor: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.cursor..]
+    }
+
+    fn error(&self, kind: XmlErrorKind) -> XmlError {
+        let line = self.source[..self.cursor].matches('\n').count() + 1;
+        XmlError { line, kind }
+    }
+
+    fn expect(&mut self, text: &str, what: &'static str) -> Result<(), XmlError> {
+        if self.rest().starts_with(text) {
+            self.cursor += text.len();
+            Ok(())
+        } else if self.rest().is_empty() {
+            Err(self.error(XmlErrorKind::UnexpectedEnd))
+        } else {
+            Err(self.error(XmlErrorKind::Expected(what)))
+        }
+    }
+
+    /// Moves the cursor past the next `end`, returning the text before it.
+    fn take_until(&mut self, end: &str) -> Result<&'a str, XmlError> {
+        let Some(length) = self.rest().find(end) else {
+            self.cursor = self.source.len();
+            return Err(self.error(XmlErrorKind::UnexpectedEnd));
+        };
+        let text = &self.source[self.cursor..self.cursor + length];
+        self.cursor += length + end.len();
+        Ok(text)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.cursor += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips whitespace, comments, processing instructions and doctypes outside the root element.
+    fn skip_misc(&mut self) -> Result<(), XmlError> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<!--") {
+                self.take_until("-->")?;
+            } else if self.rest().starts_with("<?") {
+                self.take_until("?>")?;
+            } else if self.rest().starts_with("<!") {
+                self.take_until(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, XmlError> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '='))
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.error(XmlErrorKind::Expected("a name")));
+        }
+        self.cursor += length;
+        Ok(rest[..length].to_string())
+    }
+
+    /// Parses an element, after its `<`.
+    fn parse_element(&mut self) -> Result<Element, XmlError> {
+        let mut element = Element {
+            name: self.parse_name()?,
+            attributes: Vec::new(),
+            children: Vec::new(),
+            text: String::new(),
+        };
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.cursor += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.cursor += 1;
+                break;
+            }
+            let name = self.parse_name()?;
+            self.skip_whitespace();
+            self.expect("=", "`=` after an attribute name")?;
+            self.skip_whitespace();
+            let quote = if self.rest().starts_with('\'') {
+                "'"
+            } else {
+                "\""
+            };
+            self.expect(quote, "a quoted attribute value")?;
+            let value = self.take_until(quote)?;
+            let value = self.decode(value)?;
+            element.attributes.push((name, value));
+        }
+        loop {
+            if self.rest().starts_with("</") {
+                self.cursor += 2;
+                let close = self.parse_name()?;
+                if close != element.name {
+                    return Err(self.error(XmlErrorKind::MismatchedTag {
+                        open: element.name,
+                        close,
+                    }));
+                }
+                self.skip_whitespace();
+                self.expect(">", "`>` after a closing tag")?;
+                return Ok(element);
+            } else if self.rest().starts_with("<!--") {
+                self.take_until("-->")?;
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.cursor += "<![CDATA[".len();
+                let text = self.take_until("]]>")?;
+                element.text.push_str(text);
+            } else if self.rest().starts_with("<?") {
+                self.take_until("?>")?;
+            } else if self.rest().starts_with('<') {
+                self.cursor += 1;
+                element.children.push(self.parse_element()?);
+            } else if self.rest().is_empty() {
+                return Err(self.error(XmlErrorKind::UnexpectedEnd));
+            } else {
+                let length = self.rest().find('<').unwrap_or(self.rest().len());
+                let text = &self.source[self.cursor..self.cursor + length];
+                let text = self.decode(text)?;
+                element.text.push_str(&text);
+                self.cursor += length;
+            }
+        }
+    }
+
+    /// Replaces the entities in `text`, such as `&amp;` and `&#x41;`, with what they stand for.
+    fn decode(&self, text: &str) -> Result<String, XmlError> {
+        let mut decoded = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('&') {
+            decoded.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+            let Some(end) = rest.find(';') else {
+                return Err(self.error(XmlErrorKind::Expected("`;` after an entity")));
+            };
+            let entity = &rest[..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#').and_then(|code| code.parse().ok()),
+                }
+                .and_then(char::from_u32),
+            };
+            let c = c.ok_or_else(|| self.error(XmlErrorKind::UnknownEntity(entity.into())))?;
+            decoded.push(c);
+            rest = &rest[end + 1..];
+        }
+        decoded.push_str(rest);
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{XmlError, XmlErrorKind, parse};
+
+    #[test]
+    fn elements_attributes_and_text() {
+        let source = r#"<?xml version="1.0" encoding="utf-8"?>
+            <!-- A comment -->
+            <device schemaVersion='1.1'>
+                <name>nRF52833</name>
+                <description>Tom &amp; Jerry &#x41;&#66;</description>
+                <empty/>
+                <data><![CDATA[<raw>]]></data>
+            </device>"#;
+        let device = parse(source).unwrap();
+
+        assert_eq!(device.name, "device");
+        assert_eq!(device.attribute("schemaVersion"), Some("1.1"));
+        assert_eq!(device.child_text("name"), Some("nRF52833"));
+        assert_eq!(device.child_text("description"), Some("Tom & Jerry AB"));
+        assert_eq!(
+            device.child("empty").map(|empty| empty.children.len()),
+            Some(0)
+        );
+        assert_eq!(device.child_text("data"), Some("<raw>"));
+        assert_eq!(device.children.len(), 4);
+    }
+
+    #[test]
+    fn mismatched_tag() {
+        let source = "<device>\n  <name>nRF52833</nmae>\n</device>";
+
+        assert_eq!(
+            parse(source),
+            Err(XmlError {
+                line: 2,
+                kind: XmlErrorKind::MismatchedTag {
+                    open: "name".into(),
+                    close: "nmae".into(),
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn unclosed_element() {
+        let source = "<device><name>nRF52833</name>";
+
+        assert!(matches!(
+            parse(source),
+            Err(XmlError {
+                kind: XmlErrorKind::UnexpectedEnd,
+                ..
+            })
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::types::{IntType, RegisterAccess};
+use std::fmt;
 use std::rc::Rc;
 
 macro_rules! impl_from {
@@ -23,29 +25,152 @@ macro_rules! impl_from {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Module {
     pub name: Rc<str>,
+    /// The module's `//!` doc comments, joined by newlines.
+    pub docs: Option<Rc<str>>,
     pub definitions: Vec<Definition>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Definition {
     Function(Function),
+    ExternFunction(ExternFunction),
     Constant(Variable),
+    Static(Static),
+    Struct(Struct),
+    Enum(Enum),
+    Peripheral(Peripheral),
 }
 
 impl_from!(Definition | Variable => Constant);
-impl_from!(Definition | Function);
+impl_from!(
+    Definition | Function,
+    ExternFunction,
+    Static,
+    Struct,
+    Enum,
+    Peripheral
+);
+
+/// An attribute such as `#[repr(C)]`, or one of the arguments inside one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attribute {
+    /// A bare name, such as `C` in `#[repr(C)]`.
+    Word(Rc<str>),
+    /// A name with a value, such as `#[link_section = ".data"]`.
+    NameValue(Rc<str>, Expression),
+    /// A name with arguments, such as `#[repr(C, align(4))]`.
+    List(Rc<str>, Vec<Attribute>),
+    /// A literal argument, such as the `4` in `align(4)`.
+    Literal(Expression),
+}
+
+impl Attribute {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Word(name) | Self::NameValue(name, _) | Self::List(name, _) => Some(name),
+            Self::Literal(_) => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Function {
     pub name: String,
+    /// The function's `///` doc comments, joined by newlines.
+    pub docs: Option<Rc<str>>,
+    pub attributes: Vec<Attribute>,
+    /// The ABI of an `extern "C" fn`, which C code can call, or `None` for other functions.
+    pub abi: Option<Rc<str>>,
     pub parameters: Vec<VariableSpec>,
+    /// The type after `->`, or `None` if the function returns unit.
+    pub return_type: Option<Type>,
     pub body: Expression,
 }
 
+/// A function defined outside of the module, such as in a C library, declared with
+/// `extern "C" fn name(parameters) -> ReturnType;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternFunction {
+    pub name: String,
+    pub docs: Option<Rc<str>>,
+    pub attributes: Vec<Attribute>,
+    pub abi: Rc<str>,
+    pub parameters: Vec<VariableSpec>,
+    pub return_type: Option<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Struct {
+    pub name: Rc<str>,
+    /// The struct's `///` doc comments, joined by newlines.
+    pub docs: Option<Rc<str>>,
+    pub attributes: Vec<Attribute>,
+    pub fields: Vec<StructField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructField {
+    pub name: Rc<str>,
+    pub docs: Option<Rc<str>>,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enum {
+    pub name: Rc<str>,
+    /// The enum's `///` doc comments, joined by newlines.
+    pub docs: Option<Rc<str>>,
+    pub attributes: Vec<Attribute>,
+    pub variants: Vec<Variant>,
+}
+
+/// A variant of an enum, such as `Idle = 0` or `Write(u32, u8)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub name: Rc<str>,
+    pub docs: Option<Rc<str>>,
+    pub fields: Vec<Type>,
+    /// The explicit value of the variant's tag, written `= value`.
+    pub discriminant: Option<Expression>,
+}
+
+/// A block of memory-mapped registers at a fixed address, such as
+/// `peripheral GPIO @ 0x5000_0000 { OUT: u32 @ 0x504 rw }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peripheral {
+    pub name: Rc<str>,
+    /// The peripheral's `///` doc comments, joined by newlines.
+    pub docs: Option<Rc<str>>,
+    pub address: Expression,
+    pub registers: Vec<Register>,
+}
+
+/// A register of a peripheral, such as `OUT: u32 @ 0x504 rw`, which is at an offset from the
+/// address of the peripheral. It can be followed by its bitfields, as in `rw { PIN21: 21 }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub name: Rc<str>,
+    pub docs: Option<Rc<str>>,
+    pub ty: Type,
+    pub offset: Expression,
+    pub access: RegisterAccess,
+    pub fields: Vec<Bitfield>,
+}
+
+/// A named run of bits in a register, such as `PIN21: 21` or `DRIVE: 8..=10`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitfield {
+    pub name: Rc<str>,
+    pub docs: Option<Rc<str>>,
+    /// The bit the field is, or the inclusive range of bits it spans.
+    pub bits: Expression,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VariableSpec {
     pub name: Rc<str>,
     pub is_mutable: bool,
+    pub ty: Option<Type>,
 }
 
 impl VariableSpec {
@@ -53,6 +178,7 @@ impl VariableSpec {
         Self {
             name: name.into(),
             is_mutable: false,
+            ty: None,
         }
     }
 
@@ -62,27 +188,154 @@ impl VariableSpec {
             ..self
         }
     }
+
+    pub fn with_type(self, ty: Type) -> Self {
+        Self {
+            ty: Some(ty),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Named(Rc<str>),
+    /// An array with a length known at compile time, written `[T; N]`.
+    Array(Box<Type>, Box<Expression>),
+    /// A run of elements whose length is only known at runtime, written `[T]`.
+    /// Its size is unknown, so it can only be used behind a reference.
+    Slice(Box<Type>),
+    /// A reference, written `&T`, or `&mut T` if it allows changing what it refers to.
+    Reference(Box<Type>, bool),
+    /// A raw pointer, written `*const T`, or `*mut T` if it can be written through.
+    Pointer(Box<Type>, bool),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Variable {
     pub spec: VariableSpec,
+    /// The `///` doc comments of a constant, joined by newlines.
+    pub docs: Option<Rc<str>>,
+    pub value: Box<Expression>,
+}
+
+/// A global variable, declared with `static NAME: Type = value;`, or with `static mut` if it can be
+/// changed, which only unsafe code can do. Unlike a constant, it has an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Static {
+    pub spec: VariableSpec,
+    /// The static's `///` doc comments, joined by newlines.
+    pub docs: Option<Rc<str>>,
+    pub attributes: Vec<Attribute>,
     pub value: Box<Expression>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Expression {
     Integer(u64),
+    /// An integer literal with a type suffix, such as `10u8`.
+    TypedInteger(u64, IntType),
+    Char(char),
+    Byte(u8),
+    String(Rc<str>),
+    ByteString(Rc<[u8]>),
     Variable(Rc<str>),
+    /// A register pseudo-variable such as `$sp`, which can only be used in unsafe code.
+    CoreRegister(Rc<str>),
+    Path(Path),
+    /// A prefix operator applied to an operand, such as `-x`.
+    Unary(UnaryOperator, Box<Expression>),
     BinOp(BinOp),
+    Cast(Cast),
+    Index(Index),
+    Field(Field),
+    StructLiteral(StructLiteral),
+    /// An array literal listing each element, such as `[1, 2, 3]`.
+    Array(Vec<Expression>),
+    ArrayRepeat(ArrayRepeat),
+    Range(Range),
+    Reference(Reference),
+    /// A dereference of a reference, written `*value`.
+    Deref(Box<Expression>),
     Block(Block),
-    VariableAssignment(VariableAssignment),
+    Match(Match),
+    /// An `unsafe` block, in which raw pointers can be dereferenced.
+    Unsafe(Box<Expression>),
+    /// Inline assembly, boxed as it is much larger than other expressions.
+    Asm(Box<Asm>),
+    Assignment(Assignment),
+    Call(Call),
     Loop(Box<Expression>),
+    For(For),
     Break, Continue,
 }
 
 impl_from!(Expression | u64 => Integer);
-impl_from!(Expression | BinOp, Block, VariableAssignment);
+impl_from!(
+    Expression | BinOp,
+    Cast,
+    Index,
+    Field,
+    StructLiteral,
+    ArrayRepeat,
+    Range,
+    Reference,
+    For,
+    Path,
+    Block,
+    Match,
+    Assignment,
+    Call
+);
+
+impl Expression {
+    /// Whether this expression names a place in memory, which can be assigned to.
+    pub fn is_place(&self) -> bool {
+        let mut expression = self;
+        loop {
+            match expression {
+                Self::Variable(_) | Self::Deref(_) => return true,
+                Self::Field(Field { base, .. }) | Self::Index(Index { base, .. }) => {
+                    expression = base;
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// Takes the operand that a chain of operators continues into, such as `a + b` out of
+    /// `a + b + c` or `f()` out of `f().x`, leaving `break` in its place.
+    fn take_chained_operand(&mut self) -> Option<Expression> {
+        let operand = match self {
+            Self::BinOp(op) => op.lhs_mut(),
+            Self::Cast(Cast { value: operand, .. })
+            | Self::Index(Index { base: operand, .. })
+            | Self::Field(Field { base: operand, .. })
+            | Self::Call(Call {
+                function: operand, ..
+            }) => operand,
+            _ => return None,
+        };
+        Some(std::mem::replace(operand, Self::Break))
+    }
+
+    /// Whether this expression can be assigned to: a place or a register pseudo-variable.
+    pub fn is_assignable(&self) -> bool {
+        self.is_place() || matches!(self, Self::CoreRegister(_))
+    }
+}
+
+/// The parser builds chains of left-associative and postfix operators in a loop, so they can be
+/// much longer than the nesting limit, but each operator nests the ones before it a level deeper.
+/// They are dropped in a loop too, rather than recursing once per operator.
+impl Drop for Expression {
+    fn drop(&mut self) {
+        let mut operand = self.take_chained_operand();
+        while let Some(mut expression) = operand {
+            operand = expression.take_chained_operand();
+        }
+    }
+}
 
 impl From<Expression> for Option<Box<Expression>> {
     fn from(expr: Expression) -> Self {
@@ -96,6 +349,193 @@ pub enum BinOp {
     Sub(Box<Expression>, Box<Expression>),
     Mul(Box<Expression>, Box<Expression>),
     Div(Box<Expression>, Box<Expression>),
+    Rem(Box<Expression>, Box<Expression>),
+    BitAnd(Box<Expression>, Box<Expression>),
+    BitOr(Box<Expression>, Box<Expression>),
+    BitXor(Box<Expression>, Box<Expression>),
+    Shl(Box<Expression>, Box<Expression>),
+    Shr(Box<Expression>, Box<Expression>),
+}
+
+impl BinOp {
+    pub fn new(operator: Operator, lhs: Expression, rhs: Expression) -> Self {
+        let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+        match operator {
+            Operator::Add => Self::Add(lhs, rhs),
+            Operator::Sub => Self::Sub(lhs, rhs),
+            Operator::Mul => Self::Mul(lhs, rhs),
+            Operator::Div => Self::Div(lhs, rhs),
+            Operator::Rem => Self::Rem(lhs, rhs),
+            Operator::BitAnd => Self::BitAnd(lhs, rhs),
+            Operator::BitOr => Self::BitOr(lhs, rhs),
+            Operator::BitXor => Self::BitXor(lhs, rhs),
+            Operator::Shl => Self::Shl(lhs, rhs),
+            Operator::Shr => Self::Shr(lhs, rhs),
+        }
+    }
+
+    /// The left operand, which a chain of operators such as `a + b + c` nests.
+    fn lhs_mut(&mut self) -> &mut Expression {
+        match self {
+            Self::Add(lhs, _)
+            | Self::Sub(lhs, _)
+            | Self::Mul(lhs, _)
+            | Self::Div(lhs, _)
+            | Self::Rem(lhs, _)
+            | Self::BitAnd(lhs, _)
+            | Self::BitOr(lhs, _)
+            | Self::BitXor(lhs, _)
+            | Self::Shl(lhs, _)
+            | Self::Shr(lhs, _) => lhs,
+        }
+    }
+
+    pub fn parts(&self) -> (Operator, &Expression, &Expression) {
+        match self {
+            Self::Add(lhs, rhs) => (Operator::Add, lhs, rhs),
+            Self::Sub(lhs, rhs) => (Operator::Sub, lhs, rhs),
+            Self::Mul(lhs, rhs) => (Operator::Mul, lhs, rhs),
+            Self::Div(lhs, rhs) => (Operator::Div, lhs, rhs),
+            Self::Rem(lhs, rhs) => (Operator::Rem, lhs, rhs),
+            Self::BitAnd(lhs, rhs) => (Operator::BitAnd, lhs, rhs),
+            Self::BitOr(lhs, rhs) => (Operator::BitOr, lhs, rhs),
+            Self::BitXor(lhs, rhs) => (Operator::BitXor, lhs, rhs),
+            Self::Shl(lhs, rhs) => (Operator::Shl, lhs, rhs),
+            Self::Shr(lhs, rhs) => (Operator::Shr, lhs, rhs),
+        }
+    }
+}
+
+/// The arithmetic operators, shared by `BinOp` and compound assignments such as `+=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Neg,
+}
+
+/// A conversion to another type, written `value as ty`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cast {
+    pub value: Box<Expression>,
+    pub ty: Type,
+}
+
+/// An indexing expression, written `base[index]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Index {
+    pub base: Box<Expression>,
+    pub index: Box<Expression>,
+}
+
+/// An array literal repeating one value, written `[value; count]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArrayRepeat {
+    pub value: Box<Expression>,
+    pub count: Box<Expression>,
+}
+
+/// A range of integers, such as `1..4`, `1..=3` or `2..`. Either end can be left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+    pub start: Option<Box<Expression>>,
+    pub end: Option<Box<Expression>>,
+    /// Whether the end is part of the range, as in `1..=3`.
+    pub inclusive: bool,
+}
+
+/// A borrow of a place, written `&value` or `&mut value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub value: Box<Expression>,
+    pub mutable: bool,
+}
+
+/// A field access, written `base.name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub base: Box<Expression>,
+    pub name: Rc<str>,
+}
+
+/// A struct value, written `Name { field: value, ... }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLiteral {
+    pub name: Rc<str>,
+    pub fields: Vec<FieldInit>,
+}
+
+/// A field of a struct literal. The shorthand `Name { x }` is the same as `Name { x: x }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldInit {
+    pub name: Rc<str>,
+    pub value: Expression,
+}
+
+/// A name inside a type, written `Type::Name`, such as an enum variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+    pub ty: Rc<str>,
+    pub name: Rc<str>,
+}
+
+impl Path {
+    pub fn new(ty: impl Into<Rc<str>>, name: impl Into<Rc<str>>) -> Self {
+        Self {
+            ty: ty.into(),
+            name: name.into(),
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}", self.ty, self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub scrutinee: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    /// `_`, which matches anything.
+    Wildcard,
+    /// A name, which matches anything and binds it to the name.
+    Binding(Rc<str>),
+    /// An integer, character or byte literal, which may be negated.
+    Literal(Expression),
+    /// A range of literals, such as `0..=9` or `0..10`.
+    Range {
+        start: Expression,
+        end: Expression,
+        inclusive: bool,
+    },
+    /// An enum variant, such as `State::Idle` or `Command::Write(address, _)`.
+    Variant(Path, Vec<Pattern>),
+    /// Alternatives, written `A | B`.
+    Or(Vec<Pattern>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -151,8 +591,93 @@ pub struct VariableDefinition {
     pub value: Option<Box<Expression>>,
 }
 
+/// A loop over the values of a range, or the elements of an array or slice.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct VariableAssignment {
-    pub name: Rc<str>,
+pub struct For {
+    /// The name each value is bound to in the body.
+    pub binding: Rc<str>,
+    pub iterable: Box<Expression>,
+    pub body: Box<Expression>,
+}
+
+/// An assignment to a place, such as a variable or a field of one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assignment {
+    pub target: Box<Expression>,
+    /// The operator of a compound assignment such as `+=`, or `None` for a plain `=`.
+    pub operator: Option<Operator>,
     pub value: Box<Expression>,
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    pub function: Box<Expression>,
+    pub arguments: Vec<Expression>,
+}
+
+/// Inline assembly, written `asm!("template", operands..., options(...))`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asm {
+    /// The lines of assembly. Operands are substituted for `{}`, which takes the next one, and
+    /// for `{0}`, `{1}` and so on, which take the one at that position.
+    pub template: Vec<Rc<str>>,
+    pub operands: Vec<AsmOperand>,
+    /// The calling conventions given to `clobber_abi("C")`, whose caller-saved registers the
+    /// assembly may change.
+    pub clobber_abis: Vec<Rc<str>>,
+    pub options: AsmOptions,
+}
+
+/// A value passed to inline assembly in a register, or a place a register is stored to after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmOperand {
+    /// `in(reg) value`, which is only read.
+    In {
+        register: AsmRegister,
+        value: Expression,
+    },
+    /// `out(reg) place`, which is only written. `out(reg) _` throws the value away, which marks
+    /// the register as clobbered.
+    Out {
+        register: AsmRegister,
+        place: Option<Expression>,
+    },
+    /// `inout(reg) place`, which is read and then written with the same register.
+    InOut {
+        register: AsmRegister,
+        place: Expression,
+    },
+}
+
+impl AsmOperand {
+    pub fn register(&self) -> &AsmRegister {
+        match self {
+            Self::In { register, .. }
+            | Self::Out { register, .. }
+            | Self::InOut { register, .. } => register,
+        }
+    }
+}
+
+/// The register of an inline assembly operand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmRegister {
+    /// A class of registers for the compiler to pick from, such as `reg`.
+    Class(Rc<str>),
+    /// A register given by name, such as `"r0"`.
+    Explicit(Rc<str>),
+}
+
+/// What inline assembly does besides using its operands, written `options(nomem, nostack)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AsmOptions {
+    /// `volatile`: the assembly has effects besides its outputs, so it is kept even if they
+    /// aren't used, and isn't merged with other copies. Assembly without outputs always is.
+    pub volatile: bool,
+    /// `nomem`: the assembly doesn't read or write memory.
+    pub nomem: bool,
+    /// `nostack`: the assembly doesn't push to the stack, so the stack needn't be aligned for it.
+    pub nostack: bool,
+    /// `preserves_flags`: the assembly leaves the condition flags as they were.
+    pub preserves_flags: bool,
+}
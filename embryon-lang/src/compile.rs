@@ -1,203 +1,5346 @@
 use crate::ast::{
-    BinOp, Block, Expression, Function, Module, Statement, Variable, VariableAssignment,
+    self, ArrayRepeat, Asm, AsmOperand, AsmRegister, Assignment, BinOp, Block, Call, Cast,
+    Expression, ExternFunction, Field, For, Function, Index, Match, Module, Operator, Path,
+    Pattern, Range, Reference, Statement, Static, StructLiteral, UnaryOperator, Variable,
     VariableDefinition, VariableSpec,
 };
+use crate::fold::{FoldError, fold};
+use crate::intrinsics;
+use crate::patterns::{self, Pat};
+use crate::target::Target;
+use crate::types::{
+    Bitfield, EnumType, IntType, Member, PeripheralType, Register, Repr, StructType, Type,
+};
+use crate::vectors::{self, Exception};
+use inkwell::attributes::{Attribute, AttributeLoc};
 use inkwell::basic_block::BasicBlock;
 use inkwell::builder::{Builder, BuilderError};
 use inkwell::context::Context;
-use inkwell::module::Module as LLVMModule;
-use inkwell::values::{BasicValueEnum, FunctionValue, IntValue, PointerValue};
+use inkwell::intrinsics::Intrinsic;
+use inkwell::module::{Linkage, Module as LLVMModule};
+use inkwell::types::{
+    BasicMetadataTypeEnum, BasicType, BasicTypeEnum, IntType as LLVMIntType,
+    StructType as LLVMStructType,
+};
+use inkwell::values::{
+    ArrayValue, BasicMetadataValueEnum, BasicValue, BasicValueEnum, FunctionValue, GlobalValue,
+    InstructionValue, IntValue, PointerValue,
+};
+use inkwell::{AddressSpace, IntPredicate};
 use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 
-#[derive(Clone, Debug)]
-enum NamedValue<'ctx> {
-    Constant,
-    Variable(VariableSpec, PointerValue<'ctx>),
-}
+#[derive(Debug)]
+pub enum CompileError {
+    Builder(BuilderError),
+    DivisionByZero,
+    /// A division or remainder of the minimum of a signed type by -1, whose quotient doesn't fit.
+    DivisionOverflow(IntType),
+    Overflow,
+    LiteralOutOfRange {
+        value: i128,
+        ty: IntType,
+    },
+    NotConstant(Rc<str>),
+    UnknownType(Rc<str>),
+    TypeMismatch {
+        expected: Type,
+        found: Type,
+    },
+    NotAnInteger(Type),
+    /// A shift by a constant amount that is negative, or not less than the width of the type.
+    ShiftOverflow {
+        amount: i128,
+        ty: IntType,
+    },
+    /// The length of an array type or repeat expression depends on values only known at runtime.
+    NonConstantLength,
+    NotIndexable(Type),
+    /// An index that isn't an unsigned integer.
+    InvalidIndexType(Type),
+    IndexOutOfBounds {
+        index: i128,
+        length: u64,
+    },
+    /// A slice such as `&a[2..8]` that ends past the end of what it slices.
+    RangeOutOfBounds {
+        end: i128,
+        length: u64,
+    },
+    /// A slice such as `&a[3..1]` that starts after it ends.
+    SliceIndexOrder {
+        start: i128,
+        end: i128,
+    },
+    /// An unsized slice type or value, such as `[u8]` or `a[1..]`, which wasn't borrowed.
+    SliceNotBorrowed,
+    NotIterable(Type),
+    UnknownMethod {
+        ty: Type,
+        method: Rc<str>,
+    },
+    /// A mutable borrow of a variable that isn't declared `mut`, or of something behind a shared
+    /// reference.
+    NotMutableBorrow(Rc<str>),
+    NotDereferenceable(Type),
+    /// A use of a variable while it is mutably borrowed.
+    UsedWhileBorrowed(Rc<str>),
+    /// An assignment to a variable while it is borrowed.
+    AssignedWhileBorrowed(Rc<str>),
+    /// A borrow of a variable that conflicts with a borrow of it that is still in use.
+    BorrowConflict {
+        name: Rc<str>,
+        mutable: bool,
+        existing_mutable: bool,
+    },
+    /// A reference that outlives the variable it borrows, or a temporary value (`None`).
+    DoesNotLiveLongEnough(Option<Rc<str>>),
+    /// A function returning a reference to one of its variables, or to a temporary value (`None`).
+    ReturnsLocalReference(Option<Rc<str>>),
+    /// An operation on raw pointers outside of an `unsafe` block.
+    RequiresUnsafe(&'static str),
+    InvalidCast {
+        from: Type,
+        to: Type,
+    },
+    UnknownFunction(Rc<str>),
+    UnknownVariable(Rc<str>),
+    UnknownField {
+        ty: Type,
+        field: Rc<str>,
+    },
+    UnknownVariant {
+        ty: Rc<str>,
+        variant: Rc<str>,
+    },
+    MissingField {
+        ty: Rc<str>,
+        field: Rc<str>,
+    },
+    DuplicateField {
+        ty: Rc<str>,
+        field: Rc<str>,
+    },
+    DuplicateVariant {
+        ty: Rc<str>,
+        variant: Rc<str>,
+    },
+    DuplicateDiscriminant {
+        ty: Rc<str>,
+        value: i128,
+    },
+    /// A type contains itself, so it would have an infinite size.
+    RecursiveType(Rc<str>),
+    /// A `match` doesn't cover every value. Holds a pattern for the values it misses.
+    NonExhaustive(String),
+    /// A range pattern which matches nothing, such as `5..=1`, or an empty range of bits.
+    EmptyRange,
+    UnknownRegister {
+        peripheral: Rc<str>,
+        register: Rc<str>,
+    },
+    UnknownBitfield {
+        register: Rc<str>,
+        field: Rc<str>,
+    },
+    /// A read of a write-only register, named by its path such as `UART.TXD`.
+    RegisterNotReadable(Rc<str>),
+    /// A write to a read-only register, named by its path such as `GPIO.IN`.
+    RegisterNotWritable(Rc<str>),
+    /// A register whose address isn't a multiple of its size.
+    MisalignedRegister {
+        register: Rc<str>,
+        align: u64,
+    },
+    /// A bitfield that isn't a constant bit or inclusive range of bits.
+    InvalidBitfield(Rc<str>),
+    BitOutOfRange {
+        bit: i128,
+        ty: IntType,
+    },
+    OverlappingBitfields(Rc<str>, Rc<str>),
+    /// A peripheral with the same name as a type, or a type named `Peripherals`.
+    DuplicateDefinition(Rc<str>),
+    /// A call to `take_peripherals` that may happen more than once.
+    PeripheralsAlreadyTaken,
+    /// A use of a variable, or of part of one, after its value was moved out.
+    UseOfMoved(Rc<str>),
+    /// A move out of a variable declared outside a loop, which would move it again on the next
+    /// iteration.
+    MovedInLoop(Rc<str>),
+    /// A move out of a variable, or out of part of one, while it is borrowed.
+    MovedWhileBorrowed(Rc<str>),
+    /// A move out of a place that can't be left without a value, such as through a reference.
+    CannotMoveOut {
+        name: Rc<str>,
+        from: &'static str,
+    },
+    /// A value that would have to be copied, such as the element of `[p; 2]`, but can't be.
+    NotCopy(Type),
+    /// An operand of inline assembly in a class of registers that the target doesn't have.
+    InvalidAsmRegisterClass {
+        class: Rc<str>,
+        target: Target,
+    },
+    /// An operand of inline assembly in a register that the target doesn't have, or that is kept
+    /// for the compiler, such as the stack pointer.
+    InvalidAsmRegister {
+        register: Rc<str>,
+        target: Target,
+    },
+    /// An operand of inline assembly whose type doesn't fit in a register.
+    InvalidAsmOperand {
+        ty: Type,
+        target: Target,
+    },
+    /// An output of inline assembly that isn't a place to store the register to.
+    AsmOutputNotPlace,
+    InvalidAsmTemplate(String),
+    UnsupportedClobberAbi {
+        abi: Rc<str>,
+        target: Target,
+    },
+    /// A register pseudo-variable, such as `$sp`, that the target doesn't have.
+    UnknownCoreRegister {
+        name: Rc<str>,
+        target: Target,
+    },
+    /// A call of an intrinsic, such as `intrinsics::clz`, that the target has no instruction for.
+    UnavailableIntrinsic {
+        intrinsic: intrinsics::Intrinsic,
+        target: Target,
+    },
+    /// An argument that must be known at compile time, such as the number of a breakpoint.
+    NonConstantArgument(Rc<str>),
+    /// An `extern` function with an ABI other than `"C"`.
+    UnsupportedAbi(Rc<str>),
+    /// A parameter or return type of an `extern "C"` function that C would pass differently.
+    NotFfiSafe(Type),
+    UnknownAttribute(Rc<str>),
+    /// A known attribute on an item it doesn't apply to, such as `#[naked]` on a declaration.
+    MisplacedAttribute {
+        attribute: Rc<str>,
+        item: &'static str,
+    },
+    /// A known attribute with the wrong arguments, such as `#[link_section]` without a name.
+    MalformedAttribute(Rc<str>),
+    /// A naked function whose body does more than run inline assembly.
+    InvalidNakedFunction(Rc<str>),
+    /// An exception or interrupt handler for a target without a vector table, such as the host.
+    NoVectorTable(Target),
+    UnknownException {
+        name: Rc<str>,
+        target: Target,
+    },
+    /// An `#[interrupt]` naming something that isn't a variant of the device's `Interrupt` enum.
+    UnknownInterrupt(Rc<str>),
+    /// A variant of the `Interrupt` enum whose number has no place in the vector table.
+    InvalidInterruptNumber {
+        interrupt: Rc<str>,
+        number: i128,
+    },
+    /// A handler that takes parameters or returns a value, which the core neither gives nor uses.
+    InvalidHandler(Rc<str>),
+    /// A second handler for the same exception or interrupt.
+    DuplicateHandler(Rc<str>),
+    InvalidRepr(&'static str),
+    /// An assignment to a constant or to a variable that isn't declared `mut`.
+    NotMutable(Rc<str>),
+    UnsignedNegation(IntType),
+    Unsupported(&'static str),
+    WrongArgumentCount {
+        function: Rc<str>,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl From<BuilderError> for CompileError {
+    fn from(err: BuilderError) -> Self {
+        Self::Builder(err)
+    }
+}
+
+impl From<FoldError> for CompileError {
+    fn from(err: FoldError) -> Self {
+        match err {
+            FoldError::DivisionByZero => Self::DivisionByZero,
+            FoldError::Overflow => Self::Overflow,
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Builder(err) => write!(f, "{err}"),
+            Self::DivisionByZero => write!(f, "attempt to divide by zero"),
+            Self::DivisionOverflow(ty) => {
+                write!(
+                    f,
+                    "attempt to divide the minimum of `{ty}` by -1, which overflows"
+                )
+            }
+            Self::Overflow => write!(f, "constant value overflows its type"),
+            Self::LiteralOutOfRange { value, ty } => {
+                write!(f, "literal `{value}` is out of range for `{ty}`")
+            }
+            Self::NotConstant(name) => {
+                write!(f, "value of constant `{name}` is not known at compile time")
+            }
+            Self::UnknownType(name) => write!(f, "cannot find type `{name}`"),
+            Self::TypeMismatch { expected, found } => {
+                write!(
+                    f,
+                    "mismatched types: expected `{expected}`, found `{found}`"
+                )
+            }
+            Self::ShiftOverflow { amount, ty } => {
+                write!(f, "cannot shift a value of type `{ty}` by {amount} bits")
+            }
+            Self::NotAnInteger(ty) => write!(f, "expected an integer, found `{ty}`"),
+            Self::NonConstantLength => write!(f, "array length is not known at compile time"),
+            Self::NotIndexable(ty) => write!(f, "cannot index into a value of type `{ty}`"),
+            Self::InvalidIndexType(ty) => write!(f, "arrays cannot be indexed by `{ty}`"),
+            Self::IndexOutOfBounds { index, length } => write!(
+                f,
+                "index out of bounds: the length is {length} but the index is {index}"
+            ),
+            Self::RangeOutOfBounds { end, length } => write!(
+                f,
+                "range end index {end} out of range for slice of length {length}"
+            ),
+            Self::SliceIndexOrder { start, end } => {
+                write!(f, "slice index starts at {start} but ends at {end}")
+            }
+            Self::SliceNotBorrowed => write!(
+                f,
+                "slices can only be used behind a reference, as in `&[u8]` or `&a[1..]`"
+            ),
+            Self::NotIterable(ty) => write!(f, "`{ty}` is not iterable"),
+            Self::NotMutableBorrow(name) => write!(
+                f,
+                "cannot borrow `{name}` as mutable, as it is not declared as mutable"
+            ),
+            Self::NotDereferenceable(ty) => write!(f, "type `{ty}` cannot be dereferenced"),
+            Self::UsedWhileBorrowed(name) => {
+                write!(f, "cannot use `{name}` because it is mutably borrowed")
+            }
+            Self::AssignedWhileBorrowed(name) => {
+                write!(f, "cannot assign to `{name}` because it is borrowed")
+            }
+            Self::BorrowConflict {
+                name,
+                mutable,
+                existing_mutable,
+            } => {
+                let kind = |mutable| if mutable { "mutable" } else { "immutable" };
+                write!(
+                    f,
+                    "cannot borrow `{name}` as {} because it is also borrowed as {}",
+                    kind(*mutable),
+                    kind(*existing_mutable)
+                )
+            }
+            Self::DoesNotLiveLongEnough(Some(name)) => {
+                write!(f, "`{name}` does not live long enough")
+            }
+            Self::DoesNotLiveLongEnough(None) => {
+                write!(f, "temporary value does not live long enough")
+            }
+            Self::ReturnsLocalReference(Some(name)) => {
+                write!(f, "cannot return a reference to local variable `{name}`")
+            }
+            Self::ReturnsLocalReference(None) => {
+                write!(f, "cannot return a reference to a temporary value")
+            }
+            Self::RequiresUnsafe(operation) => {
+                write!(f, "{operation} is unsafe and requires an `unsafe` block")
+            }
+            Self::InvalidCast { from, to } => write!(f, "casting `{from}` as `{to}` is invalid"),
+            Self::UnknownMethod { ty, method } => {
+                write!(f, "no method named `{method}` found for `{ty}`")
+            }
+            Self::UnknownFunction(name) => write!(f, "cannot find function `{name}`"),
+            Self::UnknownVariable(name) => write!(f, "cannot find value `{name}`"),
+            Self::UnknownField { ty, field } => write!(f, "no field `{field}` on type `{ty}`"),
+            Self::MissingField { ty, field } => {
+                write!(f, "missing field `{field}` in initializer of `{ty}`")
+            }
+            Self::DuplicateField { ty, field } => {
+                write!(f, "field `{field}` of `{ty}` is specified more than once")
+            }
+            Self::UnknownVariant { ty, variant } => {
+                write!(f, "no variant named `{variant}` in enum `{ty}`")
+            }
+            Self::DuplicateVariant { ty, variant } => {
+                write!(f, "variant `{variant}` of `{ty}` is defined more than once")
+            }
+            Self::DuplicateDiscriminant { ty, value } => {
+                write!(
+                    f,
+                    "discriminant value `{value}` is assigned more than once in `{ty}`"
+                )
+            }
+            Self::RecursiveType(name) => write!(f, "recursive type `{name}` has infinite size"),
+            Self::NonExhaustive(pattern) => {
+                write!(f, "non-exhaustive patterns: `{pattern}` not covered")
+            }
+            Self::EmptyRange => {
+                write!(f, "lower range bound must be less than or equal to upper")
+            }
+            Self::UnknownRegister {
+                peripheral,
+                register,
+            } => write!(f, "no register `{register}` in peripheral `{peripheral}`"),
+            Self::UnknownBitfield { register, field } => {
+                write!(f, "no bitfield `{field}` in register `{register}`")
+            }
+            Self::RegisterNotReadable(register) => {
+                write!(f, "cannot read `{register}`, as it is write-only")
+            }
+            Self::RegisterNotWritable(register) => {
+                write!(f, "cannot write to `{register}`, as it is read-only")
+            }
+            Self::MisalignedRegister { register, align } => write!(
+                f,
+                "register `{register}` is not aligned to its size of {align} bytes"
+            ),
+            Self::InvalidBitfield(name) => write!(
+                f,
+                "bitfield `{name}` must be a constant bit, such as `3`, or an inclusive range of \
+                 bits, such as `0..=3`"
+            ),
+            Self::BitOutOfRange { bit, ty } => write!(f, "bit {bit} is out of range for `{ty}`"),
+            Self::OverlappingBitfields(first, second) => {
+                write!(f, "bitfields `{first}` and `{second}` overlap")
+            }
+            Self::DuplicateDefinition(name) => write!(f, "`{name}` is defined more than once"),
+            Self::PeripheralsAlreadyTaken => {
+                write!(f, "`take_peripherals` can only be called once")
+            }
+            Self::UseOfMoved(name) => write!(f, "use of moved value `{name}`"),
+            Self::MovedInLoop(name) => {
+                write!(
+                    f,
+                    "value moved out of `{name}` in previous iteration of loop"
+                )
+            }
+            Self::MovedWhileBorrowed(name) => {
+                write!(f, "cannot move out of `{name}` because it is borrowed")
+            }
+            Self::CannotMoveOut { name, from } => {
+                write!(f, "cannot move out of `{name}`, which is behind {from}")
+            }
+            Self::NotCopy(ty) => write!(f, "the type `{ty}` cannot be copied"),
+            Self::InvalidAsmRegisterClass { class, target } => {
+                write!(f, "`{class}` is not a register class of {target}")
+            }
+            Self::InvalidAsmRegister { register, target } => {
+                write!(
+                    f,
+                    "register `{register}` cannot be used for operands on {target}"
+                )
+            }
+            Self::InvalidAsmOperand { ty, target } => {
+                write!(
+                    f,
+                    "a value of type `{ty}` cannot be passed in a register on {target}"
+                )
+            }
+            Self::AsmOutputNotPlace => {
+                write!(
+                    f,
+                    "outputs of inline assembly must be places, such as variables"
+                )
+            }
+            Self::InvalidAsmTemplate(reason) => write!(f, "invalid asm template: {reason}"),
+            Self::UnsupportedClobberAbi { abi, target } => {
+                write!(f, "`clobber_abi(\"{abi}\")` is not supported on {target}")
+            }
+            Self::UnknownCoreRegister { name, target } => {
+                write!(f, "`${name}` is not a register of {target}")
+            }
+            Self::UnavailableIntrinsic { intrinsic, target } => {
+                write!(f, "`{intrinsic}` is not available on {target}")
+            }
+            Self::NonConstantArgument(function) => {
+                write!(
+                    f,
+                    "the argument of `{function}` must be known at compile time"
+                )
+            }
+            Self::UnsupportedAbi(abi) => write!(f, "the ABI `\"{abi}\"` is not supported"),
+            Self::NotFfiSafe(ty) => {
+                write!(
+                    f,
+                    "values of type `{ty}` cannot be passed to or from `extern \"C\"` functions"
+                )
+            }
+            Self::UnknownAttribute(name) => write!(f, "cannot find attribute `{name}`"),
+            Self::MisplacedAttribute { attribute, item } => {
+                write!(f, "attribute `{attribute}` cannot be used on {item}")
+            }
+            Self::MalformedAttribute(name) => write!(f, "malformed `{name}` attribute"),
+            Self::InvalidNakedFunction(name) => {
+                write!(
+                    f,
+                    "the body of naked function `{name}` may only contain inline assembly"
+                )
+            }
+            Self::NoVectorTable(target) => {
+                write!(
+                    f,
+                    "{target} has no vector table, so it cannot have exception or interrupt handlers"
+                )
+            }
+            Self::UnknownException { name, target } => {
+                write!(f, "`{name}` is not an exception of {target}")
+            }
+            Self::UnknownInterrupt(name) => {
+                write!(
+                    f,
+                    "cannot find interrupt `{name}` in enum `{}`",
+                    vectors::INTERRUPTS
+                )
+            }
+            Self::InvalidInterruptNumber { interrupt, number } => {
+                write!(
+                    f,
+                    "interrupt `{interrupt}` has number {number}, but Cortex-M cores have at most {} interrupts",
+                    vectors::MAX_INTERRUPTS
+                )
+            }
+            Self::InvalidHandler(name) => {
+                write!(
+                    f,
+                    "handler `{name}` must take no parameters and return nothing"
+                )
+            }
+            Self::DuplicateHandler(name) => write!(f, "`{name}` has more than one handler"),
+            Self::InvalidRepr(reason) => write!(f, "invalid `repr` attribute: {reason}"),
+            Self::NotMutable(name) => {
+                write!(
+                    f,
+                    "cannot assign to `{name}`, as it is not declared as mutable"
+                )
+            }
+            Self::UnsignedNegation(ty) => {
+                write!(f, "cannot negate a value of unsigned type `{ty}`")
+            }
+            Self::Unsupported(feature) => write!(f, "{feature} are not supported yet"),
+            Self::WrongArgumentCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{function}` takes {expected} arguments but {found} were supplied"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Something suspicious in a program that still compiles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// A `match` arm that can only match values which earlier arms already match.
+    UnreachablePattern(String),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnreachablePattern(pattern) => write!(f, "unreachable pattern `{pattern}`"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum NamedValue<'ctx> {
+    /// A constant, along with its folded value.
+    Constant(i128, IntType),
+    /// A constant that is too large to fold, such as a lookup table. It is only read from memory.
+    Global(Type, PointerValue<'ctx>),
+    /// A static, which lives as long as the program. Only unsafe code can use one that is `mut`.
+    Static(VariableSpec, Type, PointerValue<'ctx>),
+    Variable(VariableSpec, Type, PointerValue<'ctx>),
+}
+
+/// The result of compiling an expression, along with its type.
+#[derive(Clone, Debug)]
+struct Value<'ctx> {
+    value: BasicValueEnum<'ctx>,
+    ty: Type,
+}
+
+/// An integer operand, as used by arithmetic.
+#[derive(Clone, Copy, Debug)]
+struct Integer<'ctx> {
+    value: IntValue<'ctx>,
+    ty: IntType,
+}
+
+impl<'ctx> From<Integer<'ctx>> for Value<'ctx> {
+    fn from(integer: Integer<'ctx>) -> Self {
+        Self {
+            value: integer.value.into(),
+            ty: integer.ty.into(),
+        }
+    }
+}
+
+impl<'ctx> Integer<'ctx> {
+    /// Takes the integer out of the result of an expression, which may be unit.
+    fn from_value(value: Option<Value<'ctx>>) -> Result<Self, CompileError> {
+        match value {
+            Some(Value {
+                value,
+                ty: Type::Int(ty),
+            }) => Ok(Self {
+                value: value.into_int_value(),
+                ty,
+            }),
+            Some(value) => Err(CompileError::NotAnInteger(value.ty)),
+            None => Err(CompileError::NotAnInteger(Type::Unit)),
+        }
+    }
+}
+
+/// A location in memory that can be loaded from, and stored to if it is mutable.
+#[derive(Clone, Debug)]
+struct Place<'ctx> {
+    pointer: PointerValue<'ctx>,
+    ty: Type,
+    /// The alignment known for the pointer, which may be less than that of `ty` in packed structs.
+    align: u64,
+    /// The variable or constant the place is part of.
+    root: Rc<str>,
+    is_mutable: bool,
+    owner: Owner<'ctx>,
+}
+
+/// What the memory of a place belongs to, which decides what borrowing it borrows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Owner<'ctx> {
+    /// A local variable, identified by its stack slot.
+    Local(PointerValue<'ctx>),
+    /// Memory reached through a reference in a local variable. Borrowing it borrows the variable,
+    /// but the memory outlives it.
+    Behind(PointerValue<'ctx>),
+    /// A temporary value, which lives until the function returns.
+    Temporary,
+    /// Memory that outlives the function, such as a constant.
+    Outside,
+}
+
+impl<'ctx> Owner<'ctx> {
+    /// The local variable that borrowing the memory borrows, if any.
+    fn local(self) -> Option<PointerValue<'ctx>> {
+        match self {
+            Self::Local(local) | Self::Behind(local) => Some(local),
+            Self::Temporary | Self::Outside => None,
+        }
+    }
+
+    /// The owner of memory reached through a reference stored in memory with this owner.
+    fn behind(self) -> Self {
+        match self {
+            Self::Local(local) | Self::Behind(local) => Self::Behind(local),
+            Self::Temporary | Self::Outside => Self::Outside,
+        }
+    }
+
+    /// Whether the memory is in the stack frame of the function, so is gone once it returns.
+    fn is_in_frame(self) -> bool {
+        matches!(self, Self::Local(_) | Self::Temporary)
+    }
+}
+
+/// How a place is used, which decides which borrows of it are in the way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+    Borrow { mutable: bool },
+}
+
+/// A borrow that may still be in use.
+///
+/// Borrows are of whole variables, and last as long as the variable holding the reference is in
+/// scope, or until the end of the expression or statement that made them if it isn't stored.
+#[derive(Clone, Debug)]
+struct Borrow<'ctx> {
+    owner: Owner<'ctx>,
+    /// The name of what is borrowed, for errors.
+    name: Rc<str>,
+    mutable: bool,
+    /// The local variable that holds the reference, if it has been stored in one.
+    holder: Option<PointerValue<'ctx>>,
+}
+
+impl Borrow<'_> {
+    /// The name of the variable that is borrowed, or `None` for a temporary value.
+    fn borrowed_name(&self) -> Option<Rc<str>> {
+        match self.owner {
+            Owner::Temporary => None,
+            _ => Some(self.name.clone()),
+        }
+    }
+}
+
+/// A value moved out of a local variable, or out of a field of one. It can't be used again until
+/// it is assigned a new value.
+#[derive(Clone, Debug, PartialEq)]
+struct Move<'ctx> {
+    local: PointerValue<'ctx>,
+    /// The fields that lead to the moved value, or none if the whole variable was moved.
+    path: Vec<Rc<str>>,
+    /// The moved value, such as `p.GPIO`, for errors.
+    name: Rc<str>,
+}
+
+/// The names and local variables that were in scope when a scope started, to go back to when it
+/// ends.
+struct Scope<'ctx> {
+    named_values: HashMap<Rc<str>, NamedValue<'ctx>>,
+    locals: usize,
+}
+
+/// The elements of an array or slice.
+struct Elements<'ctx> {
+    /// The first element. It is only there to be read if the length isn't zero.
+    first: Place<'ctx>,
+    length: Length<'ctx>,
+}
+
+enum Length<'ctx> {
+    /// The length of an array, which is part of its type.
+    Known(u64),
+    /// The length of a slice, which is only known at runtime.
+    Runtime(IntValue<'ctx>),
+}
+
+/// The types of a function's parameters and its return value.
+#[derive(Clone, Debug)]
+struct Signature {
+    parameters: Vec<Type>,
+    return_type: Type,
+    /// Whether the function is defined outside of the module, so calling it is unsafe.
+    is_extern: bool,
+}
+
+/// A register of a peripheral, or a bitfield of one, named by a path such as `GPIO.OUT.PIN21`.
+#[derive(Clone, Debug)]
+struct RegisterPath {
+    /// The path, for errors.
+    name: Rc<str>,
+    address: u64,
+    register: Register,
+    field: Option<Bitfield>,
+}
+
+/// The kinds of items that can have attributes for their symbol, such as `#[link_section]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ItemKind {
+    Function,
+    ExternFunction,
+    Static,
+}
+
+impl ItemKind {
+    fn description(self) -> &'static str {
+        match self {
+            Self::Function => "functions",
+            Self::ExternFunction => "`extern` function declarations",
+            Self::Static => "statics",
+        }
+    }
+}
+
+/// What the attributes of an item ask of its symbol and of the code generated for it.
+#[derive(Clone, Debug, Default)]
+struct ItemAttributes {
+    /// The name of the symbol from `#[export_name = "..."]`, instead of the item's name.
+    export_name: Option<Rc<str>>,
+    link_section: Option<Rc<str>>,
+    /// Whether the symbol is kept even if nothing refers to it, as for an interrupt vector table.
+    used: bool,
+    /// Whether another definition of the symbol can replace this one when linking.
+    weak: bool,
+    /// Whether the function has no prologue or epilogue, so its body can only be inline assembly.
+    naked: bool,
+    /// LLVM's function attributes, such as `noinline` for `#[inline(never)]`.
+    llvm_attributes: Vec<&'static str>,
+    /// The exceptions and interrupts that the function handles, from `#[exception(...)]` and
+    /// `#[interrupt(...)]`.
+    handlers: Vec<Handler>,
+}
+
+/// An exception or interrupt that a function handles, as named by its attribute.
+#[derive(Clone, Debug)]
+enum Handler {
+    Exception(Rc<str>),
+    Interrupt(Rc<str>),
+}
+
+impl Handler {
+    fn name(&self) -> &Rc<str> {
+        match self {
+            Self::Exception(name) | Self::Interrupt(name) => name,
+        }
+    }
+}
+
+/// The struct that `take_peripherals` gives, which has a field for each peripheral.
+const PERIPHERALS: &str = "Peripherals";
+
+/// A type defined in the module, before it is resolved.
+#[derive(Clone, Copy)]
+enum TypeDefinition<'m> {
+    Struct(&'m ast::Struct),
+    Enum(&'m ast::Enum),
+}
+
+/// Whether `expression` only runs inline assembly, as the body of a naked function must.
+fn is_asm_only(expression: &Expression) -> bool {
+    match expression {
+        Expression::Asm(_) => true,
+        Expression::Unsafe(inner) => is_asm_only(inner),
+        Expression::Block(block) => {
+            block.body.iter().all(|statement| {
+                matches!(statement, Statement::Expression(expression) if is_asm_only(expression))
+            }) && block.last.as_deref().is_none_or(is_asm_only)
+        }
+        _ => false,
+    }
+}
+
+/// Turns the template of inline assembly into LLVM's syntax, where operands are `${0}`, `${1}`
+/// and so on, numbered as `numbers` says for each operand, and a literal `$` is `$$`.
+fn asm_template(template: &[Rc<str>], numbers: &[usize]) -> Result<String, CompileError> {
+    let template = template.join("\n");
+    let mut assembly = String::new();
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => assembly.push_str("$$"),
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                assembly.push(c);
+            }
+            '{' => {
+                let mut position = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => position.push(c),
+                        None => {
+                            return Err(CompileError::InvalidAsmTemplate("unclosed `{`".into()));
+                        }
+                    }
+                }
+                let position = if position.is_empty() {
+                    next += 1;
+                    next - 1
+                } else {
+                    position.parse().map_err(|_| {
+                        CompileError::InvalidAsmTemplate(format!(
+                            "invalid operand `{{{position}}}`"
+                        ))
+                    })?
+                };
+                let number = numbers.get(position).ok_or_else(|| {
+                    CompileError::InvalidAsmTemplate(format!("there is no operand {position}"))
+                })?;
+                assembly.push_str(&format!("${{{number}}}"));
+            }
+            '}' => return Err(CompileError::InvalidAsmTemplate("unmatched `}`".into())),
+            c => assembly.push(c),
+        }
+    }
+    Ok(assembly)
+}
+
+/// The alignment of what is `offset` bytes past something aligned to `align`, such as a field or
+/// an element. It is only as aligned as both allow, and zero-sized elements are all at offset 0.
+fn align_at_offset(align: u64, offset: u64) -> u64 {
+    match offset {
+        0 => align,
+        offset => align.min(1 << offset.trailing_zeros()),
+    }
+}
+
+/// Sets the alignment of a load, store or alloca.
+fn set_alignment(instruction: Option<InstructionValue>, align: u64) {
+    if let Some(instruction) = instruction {
+        instruction
+            .set_alignment(align as u32)
+            .expect("loads, stores and allocas have an alignment");
+    }
+}
+
+pub struct Compiler<'a, 'ctx> {
+    pub context: &'ctx Context,
+    pub builder: &'a Builder<'ctx>,
+    pub module: &'a LLVMModule<'ctx>,
+
+    current_function: Option<FunctionValue<'ctx>>,
+    loop_headers: Vec<BasicBlock<'ctx>>,
+    loop_exits: Vec<BasicBlock<'ctx>>,
+    named_values: HashMap<Rc<str>, NamedValue<'ctx>>,
+    /// The stack slots of the local variables in scope, innermost last.
+    locals: Vec<PointerValue<'ctx>>,
+    borrows: Vec<Borrow<'ctx>>,
+    moves: Vec<Move<'ctx>>,
+    /// Whether the code being compiled is in an `unsafe` block.
+    is_unsafe: bool,
+    structs: HashMap<Rc<str>, (Rc<StructType>, LLVMStructType<'ctx>)>,
+    enums: HashMap<Rc<str>, (Rc<EnumType>, LLVMStructType<'ctx>)>,
+    functions: HashMap<Rc<str>, (FunctionValue<'ctx>, Signature)>,
+    peripherals: HashMap<Rc<str>, Rc<PeripheralType>>,
+    /// Whether `take_peripherals` has been called somewhere in the module.
+    peripherals_taken: bool,
+    /// The function called when a runtime check fails. If unset, `llvm.trap` is used instead.
+    panic_handler: Option<Rc<str>>,
+    target: Target,
+    /// The symbols marked `#[used]`, which are listed in `llvm.used` so they are kept.
+    used: Vec<PointerValue<'ctx>>,
+    /// The functions marked as handlers, by their position in the vector table.
+    handlers: HashMap<u32, FunctionValue<'ctx>>,
+    /// Whether to generate the vector table even if the module has no handlers.
+    vector_table: bool,
+    warnings: Vec<Warning>,
+}
+
+impl<'a, 'ctx> Compiler<'a, 'ctx> {
+    /// Compiles the specified `Function` in the given `Context` and using the specified `Builder` and `Module`.
+    pub fn new(
+        context: &'ctx Context,
+        builder: &'a Builder<'ctx>,
+        module: &'a LLVMModule<'ctx>,
+    ) -> Self {
+        Self {
+            context,
+            builder,
+            module,
+            current_function: None,
+            loop_headers: Vec::new(),
+            loop_exits: Vec::new(),
+            named_values: HashMap::new(),
+            locals: Vec::new(),
+            borrows: Vec::new(),
+            moves: Vec::new(),
+            is_unsafe: false,
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            functions: HashMap::new(),
+            peripherals: HashMap::new(),
+            peripherals_taken: false,
+            panic_handler: None,
+            target: Target::Host,
+            used: Vec::new(),
+            handlers: HashMap::new(),
+            vector_table: false,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Calls the function named `name` when a runtime check fails, instead of trapping.
+    ///
+    /// The handler must not return.
+    pub fn with_panic_handler(self, name: impl Into<Rc<str>>) -> Self {
+        Self {
+            panic_handler: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Compiles for `target`, which decides what inline assembly can use.
+    pub fn with_target(self, target: Target) -> Self {
+        Self { target, ..self }
+    }
+
+    /// Generates the vector table and the reset handler when compiling for a Cortex-M core, even
+    /// if the module has no handlers, as the module that starts a program needs them. Otherwise
+    /// they are only generated for modules with handlers, so that libraries can be linked into
+    /// programs that have their own.
+    pub fn with_vector_table(self, vector_table: bool) -> Self {
+        Self {
+            vector_table,
+            ..self
+        }
+    }
+
+    /// Compiles every definition in `module`.
+    ///
+    /// Types are resolved first, then constants and peripherals, then the signatures of all
+    /// functions, so that definitions can be used before the point where they appear.
+    pub fn compile_module(&mut self, module: &Module) -> Result<(), CompileError> {
+        println!("Compiling");
+        let types: HashMap<&str, TypeDefinition> = module
+            .definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                ast::Definition::Struct(definition) => {
+                    Some((&*definition.name, TypeDefinition::Struct(definition)))
+                }
+                ast::Definition::Enum(definition) => {
+                    Some((&*definition.name, TypeDefinition::Enum(definition)))
+                }
+                _ => None,
+            })
+            .collect();
+        // Array lengths in types can refer to integer constants, so those are folded first
+        let (arrays, integers): (Vec<_>, Vec<_>) = module
+            .definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                ast::Definition::Constant(constant) => Some(constant),
+                _ => None,
+            })
+            .partition(|constant| matches!(constant.spec.ty, Some(ast::Type::Array(..))));
+        for constant in integers {
+            self.compile_constant(constant)?;
+        }
+        // Peripherals only depend on integer constants, and can be used as the types of fields
+        let mut peripherals = Vec::new();
+        for definition in &module.definitions {
+            if let ast::Definition::Peripheral(peripheral) = definition {
+                if types.contains_key(&*peripheral.name)
+                    || self.peripherals.contains_key(&peripheral.name)
+                {
+                    return Err(CompileError::DuplicateDefinition(peripheral.name.clone()));
+                }
+                peripherals.push((
+                    peripheral.name.clone(),
+                    self.declare_peripheral(peripheral)?,
+                ));
+            }
+        }
+        if !peripherals.is_empty() {
+            if types.contains_key(PERIPHERALS) {
+                return Err(CompileError::DuplicateDefinition(PERIPHERALS.into()));
+            }
+            let ty = StructType::new(
+                PERIPHERALS.into(),
+                peripherals,
+                Repr::default(),
+                self.pointer_size(),
+            );
+            self.lower_struct(&Rc::new(ty));
+        }
+        for name in types.keys() {
+            self.declare_type(name, &types, &mut Vec::new())?;
+        }
+        for constant in arrays {
+            self.compile_constant(constant)?;
+        }
+        for definition in &module.definitions {
+            if let ast::Definition::Static(definition) = definition {
+                self.declare_static(definition)?;
+            }
+        }
+        for definition in &module.definitions {
+            match definition {
+                ast::Definition::Function(function) => self.declare_function(function)?,
+                ast::Definition::ExternFunction(function) => {
+                    self.declare_extern_function(function)?
+                }
+                _ => {}
+            }
+        }
+        for definition in &module.definitions {
+            if let ast::Definition::Function(function) = definition {
+                self.compile_function(function)?;
+            }
+        }
+        if self.target.is_arm() && (self.vector_table || !self.handlers.is_empty()) {
+            self.emit_vector_table()?;
+        }
+        self.emit_used();
+        Ok(())
+    }
+
+    /// Lists the symbols marked `#[used]` in `llvm.used`, which keeps the compiler and the
+    /// linker from removing them.
+    fn emit_used(&mut self) {
+        if self.used.is_empty() {
+            return;
+        }
+        let pointer_type = self.context.ptr_type(AddressSpace::default());
+        let symbols = pointer_type.const_array(&self.used);
+        let used = self
+            .module
+            .add_global(symbols.get_type(), None, "llvm.used");
+        used.set_linkage(Linkage::Appending);
+        used.set_section(Some("llvm.metadata"));
+        used.set_initializer(&symbols);
+    }
+
+    /// Builds the vector table, from which the core loads the initial stack pointer and the
+    /// address of the handler of each exception and interrupt. Vectors without a handler get a
+    /// weak one named after them, which another object file can replace, and reserved ones are
+    /// zero. The reset vector always points to the generated `Reset`, which sets up RAM before
+    /// running the program's own reset handler.
+    fn emit_vector_table(&mut self) -> Result<(), CompileError> {
+        let exceptions = Exception::ALL
+            .into_iter()
+            .filter(|exception| exception.is_available(self.target))
+            .map(|exception| (Rc::from(exception.name()), exception.number()));
+        let vectors: Vec<_> = exceptions.chain(self.interrupts()?).collect();
+        let length = vectors
+            .iter()
+            .map(|(_, number)| number + 1)
+            .fold(vectors::FIRST_INTERRUPT, u32::max);
+        let pointer_type = self.context.ptr_type(AddressSpace::default());
+        let mut entries = vec![pointer_type.const_null(); length as usize];
+        entries[0] = self.linker_symbol(vectors::STACK_START);
+        let default_handler = self.build_default_handler()?;
+        for (name, number) in vectors {
+            let handler = self.handlers.get(&number).copied();
+            let handler = match handler {
+                _ if number == Exception::Reset.number() => self.build_reset_handler(handler)?,
+                Some(handler) => handler,
+                None => self.build_weak_handler(&name, default_handler)?,
+            };
+            entries[number as usize] = handler.as_global_value().as_pointer_value();
+        }
+        let table = pointer_type.const_array(&entries);
+        let global = self
+            .module
+            .add_global(table.get_type(), None, "__vector_table");
+        global.set_initializer(&table);
+        global.set_constant(true);
+        global.set_section(Some(vectors::SECTION));
+        self.used.push(global.as_pointer_value());
+        Ok(())
+    }
+
+    /// The interrupts of the device, the variants of its `Interrupt` enum, with their positions
+    /// in the vector table.
+    fn interrupts(&self) -> Result<Vec<(Rc<str>, u32)>, CompileError> {
+        let Some((ty, _)) = self.enums.get(vectors::INTERRUPTS) else {
+            return Ok(Vec::new());
+        };
+        ty.variants
+            .iter()
+            .map(|variant| {
+                let number = u32::try_from(variant.discriminant)
+                    .ok()
+                    .filter(|number| *number < vectors::MAX_INTERRUPTS)
+                    .ok_or_else(|| CompileError::InvalidInterruptNumber {
+                        interrupt: variant.name.clone(),
+                        number: variant.discriminant,
+                    })?;
+                Ok((variant.name.clone(), vectors::FIRST_INTERRUPT + number))
+            })
+            .collect()
+    }
+
+    /// The position in the vector table of the exception or interrupt that `handler` names.
+    fn vector_number(&self, handler: &Handler) -> Result<u32, CompileError> {
+        if !self.target.is_arm() {
+            return Err(CompileError::NoVectorTable(self.target));
+        }
+        match handler {
+            Handler::Exception(name) => Exception::from_name(name)
+                .filter(|exception| exception.is_available(self.target))
+                .map(|exception| exception.number())
+                .ok_or_else(|| CompileError::UnknownException {
+                    name: name.clone(),
+                    target: self.target,
+                }),
+            Handler::Interrupt(name) => self
+                .interrupts()?
+                .into_iter()
+                .find(|(interrupt, _)| interrupt == name)
+                .map(|(_, number)| number)
+                .ok_or_else(|| CompileError::UnknownInterrupt(name.clone())),
+        }
+    }
+
+    /// Adds a weak function taking and returning nothing for the vector table, with the builder
+    /// at the start of its body. LLVM would rename it if the program has a symbol of the same
+    /// name, leaving that symbol out of the table, so that is an error.
+    fn add_handler(&self, name: &str) -> Result<FunctionValue<'ctx>, CompileError> {
+        if self.module.get_function(name).is_some() || self.module.get_global(name).is_some() {
+            return Err(CompileError::DuplicateDefinition(name.into()));
+        }
+        let function_type = self.context.void_type().fn_type(&[], false);
+        let func = self
+            .module
+            .add_function(name, function_type, Some(Linkage::WeakAny));
+        let entry = self.context.append_basic_block(func, "entry");
+        self.builder.position_at_end(entry);
+        Ok(func)
+    }
+
+    /// Builds the handler that vectors without one end up in. It waits forever, so that a
+    /// debugger can see where the program stopped.
+    fn build_default_handler(&self) -> Result<FunctionValue<'ctx>, CompileError> {
+        let func = self.add_handler(vectors::DEFAULT_HANDLER)?;
+        self.build_halt(func)?;
+        Ok(func)
+    }
+
+    /// Builds the handler of a vector that the program doesn't handle, which calls the default
+    /// handler.
+    fn build_weak_handler(
+        &self,
+        name: &str,
+        default_handler: FunctionValue<'ctx>,
+    ) -> Result<FunctionValue<'ctx>, CompileError> {
+        let func = self.add_handler(name)?;
+        self.builder.build_call(default_handler, &[], "")?;
+        self.builder.build_return(None)?;
+        Ok(func)
+    }
+
+    /// Builds the reset handler. It copies the initial values of the statics in `.data` from
+    /// flash to RAM, zeroes `.bss`, then calls the program's `#[exception(Reset)]` handler, or
+    /// `main` if it has none, and waits once it returns. The bounds of the sections are symbols
+    /// that the linker script defines.
+    fn build_reset_handler(
+        &self,
+        handler: Option<FunctionValue<'ctx>>,
+    ) -> Result<FunctionValue<'ctx>, CompileError> {
+        let func = self.add_handler(Exception::Reset.name())?;
+        self.build_word_loop(
+            func,
+            self.linker_symbol("_sdata"),
+            self.linker_symbol("_edata"),
+            Some(self.linker_symbol("_sidata")),
+        )?;
+        self.build_word_loop(
+            func,
+            self.linker_symbol("_sbss"),
+            self.linker_symbol("_ebss"),
+            None,
+        )?;
+        match (handler, self.functions.get("main")) {
+            (Some(handler), _) => {
+                self.builder.build_call(handler, &[], "")?;
+            }
+            (None, Some((main, signature))) if signature.parameters.is_empty() => {
+                self.builder.build_call(*main, &[], "")?;
+            }
+            _ => {}
+        }
+        self.build_halt(func)?;
+        Ok(func)
+    }
+
+    /// Fills the words from `start` up to `end` with those from `source`, or with zero. The
+    /// accesses are volatile, so that LLVM doesn't turn the loop into a call of `memcpy` or
+    /// `memset`, which there is no library for.
+    fn build_word_loop(
+        &self,
+        func: FunctionValue<'ctx>,
+        start: PointerValue<'ctx>,
+        end: PointerValue<'ctx>,
+        source: Option<PointerValue<'ctx>>,
+    ) -> Result<(), CompileError> {
+        let pointer_type = self.context.ptr_type(AddressSpace::default());
+        let word_type = self.context.i32_type();
+        let one = word_type.const_int(1, false);
+        let before = self
+            .builder
+            .get_insert_block()
+            .expect("the builder is in the reset handler");
+        let check = self.context.append_basic_block(func, "check");
+        let fill = self.context.append_basic_block(func, "fill");
+        let filled = self.context.append_basic_block(func, "filled");
+        self.builder.build_unconditional_branch(check)?;
+        self.builder.position_at_end(check);
+        let current = self.builder.build_phi(pointer_type, "current")?;
+        let from = self.builder.build_phi(pointer_type, "from")?;
+        let current_pointer = current.as_basic_value().into_pointer_value();
+        let from_pointer = from.as_basic_value().into_pointer_value();
+        let is_filled =
+            self.builder
+                .build_int_compare(IntPredicate::UGE, current_pointer, end, "is_filled")?;
+        self.builder
+            .build_conditional_branch(is_filled, filled, fill)?;
+        self.builder.position_at_end(fill);
+        let word = match source {
+            Some(_) => {
+                let word = self.builder.build_load(word_type, from_pointer, "word")?;
+                if let Some(load) = word.as_instruction_value() {
+                    load.set_volatile(true).expect("loads can be volatile");
+                }
+                word
+            }
+            None => word_type.const_zero().into(),
+        };
+        let store = self.builder.build_store(current_pointer, word)?;
+        store.set_volatile(true).expect("stores can be volatile");
+        // SAFETY: the linker script keeps the sections word aligned, so neither pointer goes
+        // further than one word past the end of its section
+        let (next, next_from) = unsafe {
+            (
+                self.builder
+                    .build_in_bounds_gep(word_type, current_pointer, &[one], "next")?,
+                self.builder
+                    .build_in_bounds_gep(word_type, from_pointer, &[one], "next_from")?,
+            )
+        };
+        self.builder.build_unconditional_branch(check)?;
+        current.add_incoming(&[(&start, before), (&next, fill)]);
+        from.add_incoming(&[(&source.unwrap_or(start), before), (&next_from, fill)]);
+        self.builder.position_at_end(filled);
+        Ok(())
+    }
+
+    /// Ends the current block in a loop that never exits.
+    fn build_halt(&self, func: FunctionValue<'ctx>) -> Result<(), CompileError> {
+        let halt = self.context.append_basic_block(func, "halt");
+        self.builder.build_unconditional_branch(halt)?;
+        self.builder.position_at_end(halt);
+        self.builder.build_unconditional_branch(halt)?;
+        Ok(())
+    }
+
+    /// The address of a symbol that the linker script defines, such as the top of the stack.
+    fn linker_symbol(&self, name: &str) -> PointerValue<'ctx> {
+        self.module
+            .get_global(name)
+            .unwrap_or_else(|| self.module.add_global(self.context.i32_type(), None, name))
+            .as_pointer_value()
+    }
+
+    /// Returns the warnings found so far, such as unreachable `match` arms.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Resolves the type defined as `name`, after any types it contains.
+    ///
+    /// `visiting` holds the types whose fields are being resolved, to find types that contain themselves.
+    fn declare_type(
+        &mut self,
+        name: &str,
+        definitions: &HashMap<&str, TypeDefinition>,
+        visiting: &mut Vec<Rc<str>>,
+    ) -> Result<Type, CompileError> {
+        if let Some(ty) = self.lookup_type(name) {
+            return Ok(ty);
+        }
+        let Some(definition) = definitions.get(name).copied() else {
+            return Err(CompileError::UnknownType(name.into()));
+        };
+        if visiting.iter().any(|visited| **visited == *name) {
+            return Err(CompileError::RecursiveType(name.into()));
+        }
+        visiting.push(name.into());
+        let ty = match definition {
+            TypeDefinition::Struct(definition) => {
+                self.declare_struct(definition, definitions, visiting)
+            }
+            TypeDefinition::Enum(definition) => {
+                self.declare_enum(definition, definitions, visiting)
+            }
+        };
+        visiting.pop();
+        ty
+    }
+
+    /// Resolves the type of a field, declaring it first if it is defined in the module.
+    fn resolve_field_type(
+        &mut self,
+        ty: &ast::Type,
+        definitions: &HashMap<&str, TypeDefinition>,
+        visiting: &mut Vec<Rc<str>>,
+    ) -> Result<Type, CompileError> {
+        match ty {
+            ast::Type::Named(name) => match IntType::from_name(name) {
+                Some(ty) => Ok(ty.into()),
+                None => self.declare_type(name, definitions, visiting),
+            },
+            ast::Type::Array(element, length) => {
+                let element = self.resolve_field_type(element, definitions, visiting)?;
+                Ok(Type::Array(Box::new(element), self.array_length(length)?))
+            }
+            ast::Type::Slice(_) => Err(CompileError::SliceNotBorrowed),
+            ast::Type::Reference(ty, mutable) => match ty.as_ref() {
+                ast::Type::Slice(element) => Ok(Type::Slice {
+                    element: Box::new(self.resolve_field_type(element, definitions, visiting)?),
+                    mutable: *mutable,
+                }),
+                ty => Ok(Type::Reference {
+                    target: Box::new(self.resolve_field_type(ty, definitions, visiting)?),
+                    mutable: *mutable,
+                }),
+            },
+            ast::Type::Pointer(ty, mutable) => Ok(Type::Pointer {
+                target: Box::new(self.resolve_field_type(ty, definitions, visiting)?),
+                mutable: *mutable,
+            }),
+        }
+    }
+
+    /// Resolves the address of a peripheral and of each of its registers, and their bitfields.
+    fn declare_peripheral(&mut self, definition: &ast::Peripheral) -> Result<Type, CompileError> {
+        let address = self.address(&definition.address, &definition.name)?;
+        let mut registers: Vec<Register> = Vec::new();
+        for register in &definition.registers {
+            if registers.iter().any(|other| other.name == register.name) {
+                return Err(CompileError::DuplicateField {
+                    ty: definition.name.clone(),
+                    field: register.name.clone(),
+                });
+            }
+            let ty = match self.resolve_type(&register.ty)? {
+                Type::Int(ty) => ty,
+                ty => return Err(CompileError::NotAnInteger(ty)),
+            };
+            let offset = self.address(&register.offset, &register.name)?;
+            if !IntType::INDEX.contains(i128::from(address + offset)) {
+                return Err(CompileError::LiteralOutOfRange {
+                    value: i128::from(address + offset),
+                    ty: IntType::INDEX,
+                });
+            }
+            // Registers are only ever accessed whole
+            if (address + offset) % ty.size() != 0 {
+                return Err(CompileError::MisalignedRegister {
+                    register: register.name.clone(),
+                    align: ty.size(),
+                });
+            }
+            registers.push(Register {
+                name: register.name.clone(),
+                ty,
+                offset,
+                access: register.access,
+                fields: self.declare_bitfields(register, ty)?,
+            });
+        }
+        let peripheral = Rc::new(PeripheralType {
+            name: definition.name.clone(),
+            address,
+            registers,
+        });
+        self.peripherals
+            .insert(definition.name.clone(), peripheral.clone());
+        Ok(Type::Peripheral(peripheral))
+    }
+
+    /// Folds an address, or an offset from one, of the item `name`.
+    fn address(&self, address: &Expression, name: &Rc<str>) -> Result<u64, CompileError> {
+        let value = self
+            .fold_constant(address)?
+            .ok_or_else(|| CompileError::NotConstant(name.clone()))?;
+        if !IntType::INDEX.contains(value) {
+            return Err(CompileError::LiteralOutOfRange {
+                value,
+                ty: IntType::INDEX,
+            });
+        }
+        Ok(value as u64)
+    }
+
+    /// Resolves the bits of the fields of a register of type `ty`, which must not overlap.
+    fn declare_bitfields(
+        &self,
+        register: &ast::Register,
+        ty: IntType,
+    ) -> Result<Vec<Bitfield>, CompileError> {
+        let mut fields: Vec<Bitfield> = Vec::new();
+        for field in &register.fields {
+            if fields.iter().any(|other| other.name == field.name) {
+                return Err(CompileError::DuplicateField {
+                    ty: register.name.clone(),
+                    field: field.name.clone(),
+                });
+            }
+            let bit = |bit: &Expression| -> Result<u32, CompileError> {
+                let bit = self
+                    .fold_constant(bit)?
+                    .ok_or_else(|| CompileError::InvalidBitfield(field.name.clone()))?;
+                if !(0..i128::from(ty.bits)).contains(&bit) {
+                    return Err(CompileError::BitOutOfRange { bit, ty });
+                }
+                Ok(bit as u32)
+            };
+            let (start, end) = match &field.bits {
+                Expression::Range(Range {
+                    start: Some(start),
+                    end: Some(end),
+                    inclusive: true,
+                }) => (bit(start)?, bit(end)?),
+                Expression::Range(_) => {
+                    return Err(CompileError::InvalidBitfield(field.name.clone()));
+                }
+                bits => (bit(bits)?, bit(bits)?),
+            };
+            if end < start {
+                return Err(CompileError::EmptyRange);
+            }
+            let field = Bitfield {
+                name: field.name.clone(),
+                offset: start,
+                width: end - start + 1,
+            };
+            if let Some(other) = fields.iter().find(|other| other.mask() & field.mask() != 0) {
+                return Err(CompileError::OverlappingBitfields(
+                    other.name.clone(),
+                    field.name,
+                ));
+            }
+            fields.push(field);
+        }
+        Ok(fields)
+    }
+
+    fn declare_struct(
+        &mut self,
+        definition: &ast::Struct,
+        definitions: &HashMap<&str, TypeDefinition>,
+        visiting: &mut Vec<Rc<str>>,
+    ) -> Result<Type, CompileError> {
+        let repr = Self::resolve_repr(&definition.attributes)?;
+        let mut fields: Vec<(Rc<str>, Type)> = Vec::new();
+        for field in &definition.fields {
+            if fields.iter().any(|(name, _)| *name == field.name) {
+                return Err(CompileError::DuplicateField {
+                    ty: definition.name.clone(),
+                    field: field.name.clone(),
+                });
+            }
+            let ty = self.resolve_field_type(&field.ty, definitions, visiting)?;
+            fields.push((field.name.clone(), ty));
+        }
+        let ty = Rc::new(StructType::new(
+            definition.name.clone(),
+            fields,
+            repr,
+            self.pointer_size(),
+        ));
+        self.lower_struct(&ty);
+        Ok(Type::Struct(ty))
+    }
+
+    /// Creates the LLVM type of a struct whose fields have LLVM types already.
+    fn lower_struct(&mut self, ty: &Rc<StructType>) {
+        // Padding is explicit, so the LLVM struct is always packed and matches our layout exactly
+        let members: Vec<BasicTypeEnum> = ty
+            .members()
+            .into_iter()
+            .map(|member| match member {
+                Member::Field(index) => self
+                    .basic_type(&ty.fields[index].ty)
+                    .expect("struct fields are never unit"),
+                Member::Padding(bytes) => self.context.i8_type().array_type(bytes as u32).into(),
+            })
+            .collect();
+        let llvm_type = self.context.opaque_struct_type(&ty.name);
+        llvm_type.set_body(&members, true);
+        self.structs
+            .insert(ty.name.clone(), (ty.clone(), llvm_type));
+    }
+
+    fn declare_enum(
+        &mut self,
+        definition: &ast::Enum,
+        definitions: &HashMap<&str, TypeDefinition>,
+        visiting: &mut Vec<Rc<str>>,
+    ) -> Result<Type, CompileError> {
+        let tag = Self::resolve_enum_repr(&definition.attributes)?;
+        let mut variants: Vec<(Rc<str>, i128, Vec<Type>)> = Vec::new();
+        // Like in C, a variant without a discriminant comes after the one before it
+        let mut next_discriminant = 0;
+        for variant in &definition.variants {
+            if variants.iter().any(|(name, ..)| *name == variant.name) {
+                return Err(CompileError::DuplicateVariant {
+                    ty: definition.name.clone(),
+                    variant: variant.name.clone(),
+                });
+            }
+            let discriminant = match &variant.discriminant {
+                Some(value) => self.fold_constant(value)?.ok_or_else(|| {
+                    CompileError::NotConstant(
+                        format!("{}::{}", definition.name, variant.name).into(),
+                    )
+                })?,
+                None => next_discriminant,
+            };
+            if !tag.contains(discriminant) {
+                return Err(CompileError::LiteralOutOfRange {
+                    value: discriminant,
+                    ty: tag,
+                });
+            }
+            if variants.iter().any(|(_, other, _)| *other == discriminant) {
+                return Err(CompileError::DuplicateDiscriminant {
+                    ty: definition.name.clone(),
+                    value: discriminant,
+                });
+            }
+            next_discriminant = discriminant + 1;
+            let fields = variant
+                .fields
+                .iter()
+                .map(|ty| self.resolve_field_type(ty, definitions, visiting))
+                .collect::<Result<_, _>>()?;
+            variants.push((variant.name.clone(), discriminant, fields));
+        }
+
+        let ty = Rc::new(EnumType::new(
+            definition.name.clone(),
+            tag,
+            variants,
+            self.pointer_size(),
+        ));
+        for variant in &ty.variants {
+            self.lower_struct(&variant.payload);
+        }
+        // The tag, then the bytes that the fields of every variant share
+        let mut members: Vec<BasicTypeEnum> = vec![self.int_type(tag).into()];
+        let padding = ty.payload_offset - tag.size();
+        if padding > 0 {
+            members.push(self.context.i8_type().array_type(padding as u32).into());
+        }
+        let payload_size = ty.size - ty.payload_offset;
+        members.push(
+            self.context
+                .i8_type()
+                .array_type(payload_size as u32)
+                .into(),
+        );
+        let llvm_type = self.context.opaque_struct_type(&definition.name);
+        llvm_type.set_body(&members, true);
+        self.enums
+            .insert(definition.name.clone(), (ty.clone(), llvm_type));
+        Ok(Type::Enum(ty))
+    }
+
+    /// The arguments of the `#[repr(...)]` attributes of a type, which are the only attributes types can have.
+    fn repr_arguments(attributes: &[ast::Attribute]) -> Result<Vec<&ast::Attribute>, CompileError> {
+        let mut repr = Vec::new();
+        for attribute in attributes {
+            let ast::Attribute::List(name, arguments) = attribute else {
+                let name = attribute.name().expect("attributes start with a name");
+                return Err(CompileError::UnknownAttribute(name.into()));
+            };
+            if &**name != "repr" {
+                return Err(CompileError::UnknownAttribute(name.clone()));
+            }
+            repr.extend(arguments);
+        }
+        Ok(repr)
+    }
+
+    fn resolve_repr(attributes: &[ast::Attribute]) -> Result<Repr, CompileError> {
+        let mut repr = Repr::default();
+        for argument in Self::repr_arguments(attributes)? {
+            match argument {
+                ast::Attribute::Word(word) if &**word == "C" => repr.c = true,
+                ast::Attribute::Word(word) if &**word == "packed" => repr.packed = true,
+                ast::Attribute::List(name, arguments) if &**name == "align" => {
+                    let [ast::Attribute::Literal(Expression::Integer(align))] =
+                        arguments.as_slice()
+                    else {
+                        return Err(CompileError::InvalidRepr("expected `align(N)`"));
+                    };
+                    if !align.is_power_of_two() {
+                        return Err(CompileError::InvalidRepr(
+                            "alignment must be a power of two",
+                        ));
+                    }
+                    repr.align = Some(*align);
+                }
+                _ => {
+                    return Err(CompileError::InvalidRepr(
+                        "expected `C`, `packed` or `align(N)`",
+                    ));
+                }
+            }
+        }
+        if repr.packed && repr.align.is_some() {
+            return Err(CompileError::InvalidRepr(
+                "a struct can't be both `packed` and `align`ed",
+            ));
+        }
+        Ok(repr)
+    }
+
+    /// Reads `#[repr(...)]` on an enum, which sets the type of its tag.
+    fn resolve_enum_repr(attributes: &[ast::Attribute]) -> Result<IntType, CompileError> {
+        let mut tag = IntType::DEFAULT;
+        for argument in Self::repr_arguments(attributes)? {
+            tag = match argument {
+                ast::Attribute::Word(name) => IntType::from_name(name),
+                _ => None,
+            }
+            .ok_or(CompileError::InvalidRepr(
+                "expected an integer type for the tag, such as `repr(u8)`",
+            ))?;
+        }
+        Ok(tag)
+    }
+
+    fn declare_function(&mut self, function: &Function) -> Result<(), CompileError> {
+        let signature = self.resolve_signature(
+            &function.parameters,
+            function.return_type.as_ref(),
+            function.abi.as_ref(),
+        )?;
+        let attributes = Self::resolve_item_attributes(&function.attributes, ItemKind::Function)?;
+        let name: Rc<str> = function.name.as_str().into();
+        if !attributes.handlers.is_empty()
+            && (!signature.parameters.is_empty() || signature.return_type != Type::Unit)
+        {
+            return Err(CompileError::InvalidHandler(name));
+        }
+        self.add_function(&name, signature, &attributes)?;
+        let func = self.functions[&name].0;
+        for handler in &attributes.handlers {
+            let number = self.vector_number(handler)?;
+            if self.handlers.insert(number, func).is_some() {
+                return Err(CompileError::DuplicateHandler(handler.name().clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Declares a function defined outside of the module, which the linker will find.
+    fn declare_extern_function(&mut self, function: &ExternFunction) -> Result<(), CompileError> {
+        let signature = self.resolve_signature(
+            &function.parameters,
+            function.return_type.as_ref(),
+            Some(&function.abi),
+        )?;
+        let attributes =
+            Self::resolve_item_attributes(&function.attributes, ItemKind::ExternFunction)?;
+        self.add_function(
+            &function.name,
+            Signature {
+                is_extern: true,
+                ..signature
+            },
+            &attributes,
+        )
+    }
+
+    /// Reads the attributes of a function, static or other item with a symbol, checking that each is
+    /// known and applies to `kind`. Names are never mangled, so `#[no_mangle]` changes nothing.
+    fn resolve_item_attributes(
+        attributes: &[ast::Attribute],
+        kind: ItemKind,
+    ) -> Result<ItemAttributes, CompileError> {
+        let mut resolved = ItemAttributes::default();
+        for attribute in attributes {
+            let name: Rc<str> = attribute
+                .name()
+                .expect("attributes start with a name")
+                .into();
+            let allowed = match &*name {
+                "no_mangle" | "weak" => true,
+                "export_name" | "link_section" | "used" => kind != ItemKind::ExternFunction,
+                "naked" | "inline" | "exception" | "interrupt" => kind == ItemKind::Function,
+                "cold" => kind != ItemKind::Static,
+                "repr" => false,
+                _ => return Err(CompileError::UnknownAttribute(name)),
+            };
+            if !allowed {
+                return Err(CompileError::MisplacedAttribute {
+                    attribute: name,
+                    item: kind.description(),
+                });
+            }
+            match attribute {
+                ast::Attribute::Word(word) => match &**word {
+                    "no_mangle" => {}
+                    "used" => resolved.used = true,
+                    "weak" => resolved.weak = true,
+                    "naked" => {
+                        resolved.naked = true;
+                        resolved.llvm_attributes.extend(["naked", "noinline"]);
+                    }
+                    "inline" => resolved.llvm_attributes.push("inlinehint"),
+                    "cold" => resolved.llvm_attributes.push("cold"),
+                    _ => return Err(CompileError::MalformedAttribute(name)),
+                },
+                ast::Attribute::NameValue(key, Expression::String(value)) => match &**key {
+                    "export_name" => resolved.export_name = Some(value.clone()),
+                    "link_section" => resolved.link_section = Some(value.clone()),
+                    _ => return Err(CompileError::MalformedAttribute(name)),
+                },
+                ast::Attribute::List(list, arguments) if &**list == "inline" => {
+                    let inline = match arguments.as_slice() {
+                        [ast::Attribute::Word(word)] if &**word == "always" => "alwaysinline",
+                        [ast::Attribute::Word(word)] if &**word == "never" => "noinline",
+                        _ => return Err(CompileError::MalformedAttribute(name)),
+                    };
+                    resolved.llvm_attributes.push(inline);
+                }
+                ast::Attribute::List(list, arguments) => {
+                    let [ast::Attribute::Word(vector)] = arguments.as_slice() else {
+                        return Err(CompileError::MalformedAttribute(name));
+                    };
+                    resolved.handlers.push(match &**list {
+                        "exception" => Handler::Exception(vector.clone()),
+                        "interrupt" => Handler::Interrupt(vector.clone()),
+                        _ => return Err(CompileError::MalformedAttribute(name)),
+                    });
+                }
+                _ => return Err(CompileError::MalformedAttribute(name)),
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Resolves the types of a function's parameters and return value. Functions with an ABI
+    /// can only use types that C passes the same way.
+    fn resolve_signature(
+        &self,
+        parameters: &[VariableSpec],
+        return_type: Option<&ast::Type>,
+        abi: Option<&Rc<str>>,
+    ) -> Result<Signature, CompileError> {
+        let parameters = parameters
+            .iter()
+            .map(|parameter| match &parameter.ty {
+                Some(ty) => self.resolve_type(ty),
+                None => Err(CompileError::Unsupported("parameters without a type")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let return_type = match return_type {
+            Some(ty) => self.resolve_type(ty)?,
+            None => Type::Unit,
+        };
+        match abi {
+            Some(abi) if &**abi != "C" => return Err(CompileError::UnsupportedAbi(abi.clone())),
+            Some(_) => {
+                let unsafe_type = parameters
+                    .iter()
+                    .chain(Some(&return_type).filter(|ty| **ty != Type::Unit))
+                    .find(|ty| !ty.is_ffi_safe());
+                if let Some(ty) = unsafe_type {
+                    return Err(CompileError::NotFfiSafe(ty.clone()));
+                }
+            }
+            None => {}
+        }
+        Ok(Signature {
+            parameters,
+            return_type,
+            is_extern: false,
+        })
+    }
+
+    /// Adds a function to the LLVM module, with the symbol its attributes ask for. Every function
+    /// uses the C calling convention, which is LLVM's default, so C can call the ones whose types
+    /// it can pass.
+    fn add_function(
+        &mut self,
+        name: &str,
+        signature: Signature,
+        attributes: &ItemAttributes,
+    ) -> Result<(), CompileError> {
+        if self.functions.contains_key(name) {
+            return Err(CompileError::DuplicateDefinition(name.into()));
+        }
+        let parameter_types: Vec<BasicMetadataTypeEnum> = signature
+            .parameters
+            .iter()
+            .map(|ty| {
+                self.basic_type(ty)
+                    .expect("parameters are never unit")
+                    .into()
+            })
+            .collect();
+        let function_type = match self.basic_type(&signature.return_type) {
+            Some(ty) => ty.fn_type(&parameter_types, false),
+            None => self.context.void_type().fn_type(&parameter_types, false),
+        };
+        let symbol = attributes.export_name.as_deref().unwrap_or(name);
+        let func = self.module.add_function(symbol, function_type, None);
+        self.apply_symbol_attributes(func.as_global_value(), attributes, signature.is_extern);
+        for attribute in &attributes.llvm_attributes {
+            let kind = Attribute::get_named_enum_kind_id(attribute);
+            func.add_attribute(
+                AttributeLoc::Function,
+                self.context.create_enum_attribute(kind, 0),
+            );
+        }
+        self.functions.insert(name.into(), (func, signature));
+        Ok(())
+    }
+
+    /// Sets where the symbol of a function or static goes, and how the linker treats it.
+    /// `is_declaration` is set for symbols defined elsewhere.
+    fn apply_symbol_attributes(
+        &mut self,
+        global: GlobalValue<'ctx>,
+        attributes: &ItemAttributes,
+        is_declaration: bool,
+    ) {
+        if let Some(section) = &attributes.link_section {
+            global.set_section(Some(section));
+        }
+        if attributes.weak {
+            global.set_linkage(if is_declaration {
+                Linkage::ExternalWeak
+            } else {
+                Linkage::WeakAny
+            });
+        }
+        if attributes.used {
+            self.used.push(global.as_pointer_value());
+        }
+    }
+
+    fn compile_function(&mut self, function: &Function) -> Result<(), CompileError> {
+        let (func, signature) = self.functions[function.name.as_str()].clone();
+        if Self::resolve_item_attributes(&function.attributes, ItemKind::Function)?.naked {
+            return self.compile_naked_function(function, func);
+        }
+        let entry = self.context.append_basic_block(func, "entry");
+        self.builder.position_at_end(entry);
+        self.current_function = Some(func);
+        // Parameters are only in scope in this function, but constants stay visible
+        let scope = self.start_scope();
+        for ((spec, ty), value) in function
+            .parameters
+            .iter()
+            .zip(&signature.parameters)
+            .zip(func.get_param_iter())
+        {
+            let pointer = self.build_alloca(ty, &spec.name)?;
+            let place = Place {
+                pointer,
+                ty: ty.clone(),
+                align: ty.align(self.pointer_size()),
+                root: spec.name.clone(),
+                is_mutable: spec.is_mutable,
+                owner: Owner::Local(pointer),
+            };
+            self.build_store(&place, value)?;
+            self.declare_local(spec.clone(), ty.clone(), pointer);
+        }
+
+        let body = self.compile_expression(&function.body, Some(&signature.return_type))?;
+        // Only references to what the caller passed in are still valid once the function returns
+        let escaping = self
+            .borrows
+            .iter()
+            .find(|borrow| borrow.holder.is_none() && borrow.owner.is_in_frame());
+        match escaping {
+            Some(borrow) if signature.return_type.contains_references() => {
+                return Err(CompileError::ReturnsLocalReference(borrow.borrowed_name()));
+            }
+            _ => {}
+        }
+        match body {
+            Some(body) => {
+                let body = self.coerce(body, &signature.return_type)?;
+                self.builder.build_return(Some(&body.value))?;
+            }
+            None if signature.return_type == Type::Unit => {
+                self.builder.build_return(None)?;
+            }
+            // The body never finishes, for example because it ends in an infinite loop
+            None if self.is_unreachable(entry) => {
+                self.builder.build_unreachable()?;
+            }
+            None => {
+                return Err(CompileError::TypeMismatch {
+                    expected: signature.return_type,
+                    found: Type::Unit,
+                });
+            }
+        }
+        self.end_scope(scope, None)?;
+        // Borrows of temporaries end with the function too
+        self.borrows.clear();
+        self.moves.clear();
+        self.current_function = None;
+
+        if func.verify(true) {
+            return Ok(());
+        }
+        println!("{func:#?}");
+        panic!("Function verification failed");
+    }
+
+    /// Compiles a `#[naked]` function, which has no prologue or epilogue. Its body can only be
+    /// inline assembly, which finds the parameters in the registers the calling convention puts
+    /// them in, and must return or jump away itself.
+    fn compile_naked_function(
+        &mut self,
+        function: &Function,
+        func: FunctionValue<'ctx>,
+    ) -> Result<(), CompileError> {
+        if !is_asm_only(&function.body) {
+            return Err(CompileError::InvalidNakedFunction(
+                function.name.as_str().into(),
+            ));
+        }
+        let entry = self.context.append_basic_block(func, "entry");
+        self.builder.position_at_end(entry);
+        self.current_function = Some(func);
+        self.compile_expression(&function.body, None)?;
+        self.builder.build_unreachable()?;
+        self.current_function = None;
+        Ok(())
+    }
+
+    /// Whether the current block can't be reached, as nothing branches to it.
+    ///
+    /// `start` is the block that the code being compiled started in, which is reachable.
+    fn is_unreachable(&self, start: BasicBlock<'ctx>) -> bool {
+        self.builder
+            .get_insert_block()
+            .is_some_and(|block| block != start && block.get_first_use().is_none())
+    }
+
+    /// Whether the current block already ends, for example in a `break`.
+    fn is_terminated(&self) -> bool {
+        self.builder
+            .get_insert_block()
+            .is_some_and(|block| block.get_terminator().is_some())
+    }
+
+    fn compile_constant(&mut self, constant: &Variable) -> Result<(), CompileError> {
+        let name = constant.spec.name.clone();
+        let ty = match &constant.spec.ty {
+            Some(ty) => match self.resolve_type(ty)? {
+                Type::Int(ty) => ty,
+                ty @ Type::Array(..) => return self.compile_constant_array(constant, ty),
+                _ => return Err(CompileError::Unsupported("constants of struct type")),
+            },
+            None => IntType::DEFAULT,
+        };
+        let value = self
+            .fold_constant(&constant.value)?
+            .ok_or_else(|| CompileError::NotConstant(name.clone()))?;
+        if !ty.contains(value) {
+            return Err(CompileError::Overflow);
+        }
+        // The value is built into the code that uses it, so the constant has no storage
+        self.named_values
+            .insert(name, NamedValue::Constant(value, ty));
+        Ok(())
+    }
+
+    /// The value of `name` if it is an integer constant.
+    fn constant_value(&self, name: &str) -> Option<(i128, IntType)> {
+        match self.named_values.get(name) {
+            Some(NamedValue::Constant(value, ty)) => Some((*value, *ty)),
+            _ => None,
+        }
+    }
+
+    /// Places an array constant, such as a lookup table, in read-only memory. Indexing it at
+    /// runtime needs memory, but the memory has no symbol, so it is removed if unused.
+    fn compile_constant_array(
+        &mut self,
+        constant: &Variable,
+        ty: Type,
+    ) -> Result<(), CompileError> {
+        let name = constant.spec.name.clone();
+        let initializer = self.constant_initializer(&constant.value, &ty, &name)?;
+        let global = self.module.add_global(initializer.get_type(), None, &name);
+        global.set_constant(true);
+        global.set_linkage(Linkage::Private);
+        global.set_unnamed_addr(true);
+        global.set_alignment(ty.align(self.pointer_size()) as u32);
+        global.set_initializer(&initializer);
+        self.named_values
+            .insert(name, NamedValue::Global(ty, global.as_pointer_value()));
+        Ok(())
+    }
+
+    /// Places a static in memory: in flash if it is immutable, or in RAM if it is `mut`, where it
+    /// is in `.bss` if it starts as zero and in `.data` otherwise.
+    fn declare_static(&mut self, definition: &Static) -> Result<(), CompileError> {
+        let name = definition.spec.name.clone();
+        if self.named_values.contains_key(&name) {
+            return Err(CompileError::DuplicateDefinition(name));
+        }
+        let ty = definition.spec.ty.as_ref().expect("statics have a type");
+        let ty = self.resolve_type(ty)?;
+        let attributes = Self::resolve_item_attributes(&definition.attributes, ItemKind::Static)?;
+        let initializer = self.constant_initializer(&definition.value, &ty, &name)?;
+        let symbol = attributes.export_name.as_deref().unwrap_or(&name);
+        let global = self.module.add_global(initializer.get_type(), None, symbol);
+        global.set_constant(!definition.spec.is_mutable);
+        global.set_alignment(ty.align(self.pointer_size()) as u32);
+        global.set_initializer(&initializer);
+        self.apply_symbol_attributes(global, &attributes, false);
+        let value = NamedValue::Static(definition.spec.clone(), ty, global.as_pointer_value());
+        self.named_values.insert(name, value);
+        Ok(())
+    }
+
+    /// The value of the constant `name` of type `ty`, for initializing a global.
+    fn constant_initializer(
+        &self,
+        expression: &Expression,
+        ty: &Type,
+        name: &Rc<str>,
+    ) -> Result<BasicValueEnum<'ctx>, CompileError> {
+        let Type::Array(element, length) = ty else {
+            let Type::Int(ty) = ty else {
+                return Err(CompileError::Unsupported("constants of struct type"));
+            };
+            let value = self
+                .fold_constant(expression)?
+                .ok_or_else(|| CompileError::NotConstant(name.clone()))?;
+            return Ok(self.compile_constant_value(value, *ty)?.value.into());
+        };
+        let mismatch = |found: u64| CompileError::TypeMismatch {
+            expected: ty.clone(),
+            found: Type::Array(element.clone(), found),
+        };
+        let elements = match expression {
+            Expression::Array(elements) => {
+                if elements.len() as u64 != *length {
+                    return Err(mismatch(elements.len() as u64));
+                }
+                elements
+                    .iter()
+                    .map(|element_value| self.constant_initializer(element_value, element, name))
+                    .collect::<Result<_, _>>()?
+            }
+            Expression::ArrayRepeat(repeat) => {
+                let count = self.array_length(&repeat.count)?;
+                if count != *length {
+                    return Err(mismatch(count));
+                }
+                let value = self.constant_initializer(&repeat.value, element, name)?;
+                vec![value; count as usize]
+            }
+            _ => return Err(CompileError::NotConstant(name.clone())),
+        };
+        Ok(self.const_array(element, &elements).into())
+    }
+
+    /// Builds a constant array from constant `elements` of type `element`.
+    fn const_array(&self, element: &Type, elements: &[BasicValueEnum<'ctx>]) -> ArrayValue<'ctx> {
+        match self.basic_type(element).expect("arrays never hold unit") {
+            BasicTypeEnum::IntType(ty) => {
+                let elements: Vec<_> = elements.iter().map(|e| e.into_int_value()).collect();
+                ty.const_array(&elements)
+            }
+            BasicTypeEnum::ArrayType(ty) => {
+                let elements: Vec<_> = elements.iter().map(|e| e.into_array_value()).collect();
+                ty.const_array(&elements)
+            }
+            BasicTypeEnum::StructType(ty) => {
+                let elements: Vec<_> = elements.iter().map(|e| e.into_struct_value()).collect();
+                ty.const_array(&elements)
+            }
+            ty => unreachable!("no Embryon type is lowered to {ty}"),
+        }
+    }
+
+    /// Evaluates the length of an array, which must be known at compile time.
+    fn array_length(&self, length: &Expression) -> Result<u64, CompileError> {
+        let value = self
+            .fold_constant(length)?
+            .ok_or(CompileError::NonConstantLength)?;
+        if !IntType::INDEX.contains(value) {
+            return Err(CompileError::LiteralOutOfRange {
+                value,
+                ty: IntType::INDEX,
+            });
+        }
+        Ok(value as u64)
+    }
+
+    /// Evaluates `expression` at compile time, if it only depends on constants.
+    fn fold_constant(&self, expression: &Expression) -> Result<Option<i128>, CompileError> {
+        let lookup = |name: &str| self.constant_value(name).map(|(value, _)| value);
+        Ok(fold(expression, &lookup)?)
+    }
+
+    fn resolve_type(&self, ty: &ast::Type) -> Result<Type, CompileError> {
+        match ty {
+            ast::Type::Named(name) => match IntType::from_name(name) {
+                Some(ty) => Ok(ty.into()),
+                None => self
+                    .lookup_type(name)
+                    .ok_or_else(|| CompileError::UnknownType(name.clone())),
+            },
+            ast::Type::Array(element, length) => {
+                let element = self.resolve_type(element)?;
+                Ok(Type::Array(Box::new(element), self.array_length(length)?))
+            }
+            ast::Type::Slice(_) => Err(CompileError::SliceNotBorrowed),
+            ast::Type::Reference(ty, mutable) => match ty.as_ref() {
+                ast::Type::Slice(element) => Ok(Type::Slice {
+                    element: Box::new(self.resolve_type(element)?),
+                    mutable: *mutable,
+                }),
+                ty => Ok(Type::Reference {
+                    target: Box::new(self.resolve_type(ty)?),
+                    mutable: *mutable,
+                }),
+            },
+            ast::Type::Pointer(ty, mutable) => Ok(Type::Pointer {
+                target: Box::new(self.resolve_type(ty)?),
+                mutable: *mutable,
+            }),
+        }
+    }
+
+    /// Finds a struct, enum or peripheral that has been declared.
+    fn lookup_type(&self, name: &str) -> Option<Type> {
+        if let Some((ty, _)) = self.structs.get(name) {
+            return Some(Type::Struct(ty.clone()));
+        }
+        if let Some(ty) = self.peripherals.get(name) {
+            return Some(Type::Peripheral(ty.clone()));
+        }
+        self.enums.get(name).map(|(ty, _)| Type::Enum(ty.clone()))
+    }
+
+    /// The size in bytes of a pointer on the target, which types are laid out for.
+    fn pointer_size(&self) -> u64 {
+        self.target.pointer_size()
+    }
+
+    fn int_type(&self, ty: IntType) -> LLVMIntType<'ctx> {
+        self.context.custom_width_int_type(ty.bits)
+    }
+
+    /// The LLVM type of a value of type `ty`, or `None` for unit, which has no values.
+    fn basic_type(&self, ty: &Type) -> Option<BasicTypeEnum<'ctx>> {
+        match ty {
+            Type::Unit => None,
+            Type::Int(ty) => Some(self.int_type(*ty).into()),
+            Type::Struct(ty) => Some(self.struct_type(ty).into()),
+            Type::Enum(ty) => Some(self.enums[&ty.name].1.into()),
+            Type::Array(element, length) => {
+                let element = self.basic_type(element)?;
+                Some(element.array_type(*length as u32).into())
+            }
+            Type::Slice { .. } => {
+                let pointer = self.context.ptr_type(AddressSpace::default());
+                let length = self.int_type(IntType::INDEX);
+                Some(
+                    self.context
+                        .struct_type(&[pointer.into(), length.into()], false)
+                        .into(),
+                )
+            }
+            Type::Reference { .. } | Type::Pointer { .. } => {
+                Some(self.context.ptr_type(AddressSpace::default()).into())
+            }
+            // The registers are at a fixed address, so there is nothing to store
+            Type::Peripheral(_) => Some(self.context.struct_type(&[], false).into()),
+        }
+    }
+
+    fn struct_type(&self, ty: &StructType) -> LLVMStructType<'ctx> {
+        self.structs[&ty.name].1
+    }
+
+    fn expect_type(&self, expected: &Type, found: &Type) -> Result<(), CompileError> {
+        // A mutable reference, slice or pointer can be used where a shared one is expected, as
+        // they are stored the same way. References can also be used as raw pointers.
+        let coerces = match (expected, found) {
+            (
+                Type::Slice {
+                    element,
+                    mutable: false,
+                },
+                Type::Slice {
+                    element: found_element,
+                    ..
+                },
+            ) => element == found_element,
+            (
+                Type::Reference {
+                    target,
+                    mutable: false,
+                },
+                Type::Reference {
+                    target: found_target,
+                    ..
+                },
+            ) => target == found_target,
+            (
+                Type::Pointer { target, mutable },
+                Type::Pointer {
+                    target: found_target,
+                    mutable: found_mutable,
+                }
+                | Type::Reference {
+                    target: found_target,
+                    mutable: found_mutable,
+                },
+            ) => target == found_target && (*found_mutable || !*mutable),
+            _ => false,
+        };
+        if expected == found || coerces {
+            Ok(())
+        } else {
+            Err(CompileError::TypeMismatch {
+                expected: expected.clone(),
+                found: found.clone(),
+            })
+        }
+    }
+
+    fn expect_int_type(&self, expected: IntType, found: IntType) -> Result<(), CompileError> {
+        self.expect_type(&expected.into(), &found.into())
+    }
+
+    /// Checks that `value` can be used where a value of type `expected` is, converting a reference
+    /// to an array into a slice of the whole array if that is what is expected.
+    fn coerce(&self, value: Value<'ctx>, expected: &Type) -> Result<Value<'ctx>, CompileError> {
+        // A reference used as a raw pointer is the same pointer, but no longer borrows anything
+        if let (Type::Pointer { .. }, Type::Reference { .. }) = (expected, &value.ty) {
+            self.expect_type(expected, &value.ty)?;
+            return Ok(Value {
+                ty: expected.clone(),
+                ..value
+            });
+        }
+        let length = match (expected, &value.ty) {
+            (
+                Type::Slice { element, mutable },
+                Type::Reference {
+                    target,
+                    mutable: found_mutable,
+                },
+            ) if *found_mutable || !*mutable => match target.as_ref() {
+                Type::Array(found_element, length) if found_element == element => Some(*length),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(length) = length else {
+            self.expect_type(expected, &value.ty)?;
+            return Ok(value);
+        };
+        let length = self.int_type(IntType::INDEX).const_int(length, false);
+        self.build_slice(value.value.into_pointer_value(), length, expected.clone())
+    }
+
+    /// Compiles `expression`, giving any untyped integer literals the `expected` type.
+    fn compile_expression(
+        &mut self,
+        expression: &Expression,
+        expected: Option<&Type>,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let int_hint = match expected {
+            Some(Type::Int(ty)) => Some(*ty),
+            _ => None,
+        };
+        let borrows = self.borrows.len();
+        let value = match expression {
+            Expression::Integer(x) => {
+                self.compile_integer(*x, int_hint.unwrap_or(IntType::DEFAULT))
+            }
+            Expression::TypedInteger(x, ty) => self.compile_integer(*x, *ty),
+            // There is no `char` type yet, so characters are their Unicode scalar value
+            Expression::Char(c) => self.compile_integer(*c as u64, IntType::new(32, false)),
+            Expression::Byte(b) => self.compile_integer(*b as u64, IntType::new(8, false)),
+            Expression::String(_) => Err(CompileError::Unsupported("string literals as values")),
+            Expression::ByteString(bytes) => self.compile_byte_string(bytes).map(Some),
+            Expression::Variable(_) | Expression::Deref(_) => self.compile_read(expression),
+            Expression::CoreRegister(name) => {
+                let value = self.read_core_register(name)?;
+                let ty = IntType::new(32, false);
+                Ok(Some(Integer { value, ty }.into()))
+            }
+            Expression::Path(path) => self.compile_variant(path, &[]),
+            Expression::Assignment(assign) => self.compile_assignment(assign),
+            Expression::Unary(operator, operand) => self
+                .compile_unary(*operator, operand, int_hint)
+                .map(|value| Some(value.into())),
+            Expression::BinOp(op) => self.compile_binop(op, int_hint),
+            Expression::Cast(cast) => self.compile_cast(cast),
+            Expression::Index(_) | Expression::Field(_) => self.compile_postfix(expression),
+            Expression::StructLiteral(literal) => self.compile_struct_literal(literal),
+            Expression::Array(elements) => self.compile_array(elements, expected),
+            Expression::ArrayRepeat(repeat) => self.compile_array_repeat(repeat, expected),
+            Expression::Range(_) => Err(CompileError::Unsupported("ranges as values")),
+            Expression::Reference(reference) => self.compile_reference(reference),
+            Expression::Call(call) if matches!(*call.function, Expression::Field(_)) => {
+                self.compile_postfix(expression)
+            }
+            Expression::Call(call) => self.compile_call(call, int_hint),
+            Expression::Block(block) => self.compile_block(block, expected),
+            Expression::Match(match_) => self.compile_match(match_, expected),
+            Expression::Unsafe(body) => self.compile_unsafe(body, expected),
+            Expression::Asm(asm) => self.compile_asm(asm),
+            Expression::Loop(body) => self.compile_loop(None, None, None, body),
+            Expression::For(for_) => self.compile_for(for_),
+            f @ Expression::Break | f @ Expression::Continue => self.compile_loop_flow(f),
+        }?;
+        // A value without references can't hold on to the borrows made while computing it
+        if !value
+            .as_ref()
+            .is_some_and(|value| value.ty.contains_references())
+        {
+            self.end_temporary_borrows(borrows);
+        }
+        Ok(value)
+    }
+
+    /// Compiles an expression that must be an integer, such as the operand of arithmetic.
+    fn compile_integer_operand(
+        &mut self,
+        expression: &Expression,
+        expected: Option<IntType>,
+    ) -> Result<Integer<'ctx>, CompileError> {
+        let expected = expected.map(Type::Int);
+        let value = self.compile_expression(expression, expected.as_ref())?;
+        Integer::from_value(value)
+    }
+
+    fn compile_integer(
+        &self,
+        value: u64,
+        ty: IntType,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        self.compile_constant_value(value as i128, ty)
+            .map(|value| Some(value.into()))
+    }
+
+    fn compile_constant_value(
+        &self,
+        value: i128,
+        ty: IntType,
+    ) -> Result<Integer<'ctx>, CompileError> {
+        if !ty.contains(value) {
+            return Err(CompileError::LiteralOutOfRange { value, ty });
+        }
+        Ok(Integer {
+            value: self.int_type(ty).const_int(value as u64, value < 0),
+            ty,
+        })
+    }
+
+    /// Converts a value with `as`, or with a chain of casts such as `x as u8 as u32`, which is
+    /// compiled in a loop from the innermost cast out.
+    fn compile_cast(&mut self, cast: &Cast) -> Result<Option<Value<'ctx>>, CompileError> {
+        let mut types = Vec::new();
+        let mut cast = cast;
+        let operand = loop {
+            types.push(self.resolve_type(&cast.ty)?);
+            match cast.value.as_ref() {
+                Expression::Cast(inner) => cast = inner,
+                operand => break operand,
+            }
+        };
+        // An untyped literal cast to a pointer is an address, such as `0x5000_0000 as *mut u32`
+        let hint = match types.last() {
+            Some(Type::Pointer { .. }) => Some(IntType::INDEX.into()),
+            _ => None,
+        };
+        let mut value = self.compile_expression(operand, hint.as_ref())?;
+        for ty in types.into_iter().rev() {
+            value = Some(self.build_cast(value, ty)?);
+        }
+        Ok(value)
+    }
+
+    /// Casts `value` to `ty`. Integers can be cast to other integer types, which truncates or
+    /// extends them, and to and from raw pointers, which can be cast to other raw pointers.
+    fn build_cast(
+        &self,
+        value: Option<Value<'ctx>>,
+        ty: Type,
+    ) -> Result<Value<'ctx>, CompileError> {
+        let Some(value) = value else {
+            return Err(CompileError::InvalidCast {
+                from: Type::Unit,
+                to: ty,
+            });
+        };
+        let pointer_type = self.context.ptr_type(AddressSpace::default());
+        let result: BasicValueEnum = match (&value.ty, &ty) {
+            (Type::Int(from), Type::Int(to)) => {
+                let int = value.value.into_int_value();
+                let target = self.int_type(*to);
+                if to.bits < from.bits {
+                    self.builder.build_int_truncate(int, target, "cast")?
+                } else if to.bits == from.bits {
+                    int
+                } else if from.signed {
+                    self.builder.build_int_s_extend(int, target, "cast")?
+                } else {
+                    self.builder.build_int_z_extend(int, target, "cast")?
+                }
+                .into()
+            }
+            (Type::Int(_), Type::Pointer { .. }) => self
+                .builder
+                .build_int_to_ptr(value.value.into_int_value(), pointer_type, "cast")?
+                .into(),
+            (Type::Pointer { .. }, Type::Int(to)) => self
+                .builder
+                .build_ptr_to_int(value.value.into_pointer_value(), self.int_type(*to), "cast")?
+                .into(),
+            (Type::Pointer { .. }, Type::Pointer { .. }) => value.value,
+            (Type::Reference { .. }, Type::Pointer { .. }) => {
+                self.coerce(value.clone(), &ty)
+                    .map_err(|_| CompileError::InvalidCast {
+                        from: value.ty.clone(),
+                        to: ty.clone(),
+                    })?
+                    .value
+            }
+            _ => {
+                return Err(CompileError::InvalidCast {
+                    from: value.ty,
+                    to: ty,
+                });
+            }
+        };
+        Ok(Value { value: result, ty })
+    }
+
+    /// Compiles the body of an `unsafe` block, in which raw pointers can be dereferenced.
+    fn compile_unsafe(
+        &mut self,
+        body: &Expression,
+        expected: Option<&Type>,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let was_unsafe = std::mem::replace(&mut self.is_unsafe, true);
+        let value = self.compile_expression(body, expected);
+        self.is_unsafe = was_unsafe;
+        value
+    }
+
+    /// Compiles inline assembly to a call of LLVM inline assembly. Each operand becomes a
+    /// constraint, checked against the target: `reg` for any general purpose register, or a
+    /// register by name. Unless the options say otherwise, the assembly may use the stack, read and
+    /// write memory and change the condition flags.
+    fn compile_asm(&mut self, asm: &Asm) -> Result<Option<Value<'ctx>>, CompileError> {
+        if !self.is_unsafe {
+            return Err(CompileError::RequiresUnsafe("use of inline assembly"));
+        }
+        let mut clobbers = Vec::new();
+        // A caller-saved register that holds an input can't also be a clobber, so it is an
+        // output that is thrown away instead
+        let mut clobbered_inputs = Vec::new();
+        for abi in &asm.clobber_abis {
+            let registers = match &**abi {
+                "C" => self.target.caller_saved_registers(),
+                _ => None,
+            };
+            let registers = registers.ok_or_else(|| CompileError::UnsupportedClobberAbi {
+                abi: abi.clone(),
+                target: self.target,
+            })?;
+            for &register in registers {
+                let explicit = AsmRegister::Explicit(register.into());
+                let operand = asm
+                    .operands
+                    .iter()
+                    .find(|operand| *operand.register() == explicit);
+                match operand {
+                    Some(AsmOperand::In { .. }) => clobbered_inputs.push(register),
+                    Some(_) => {}
+                    None => clobbers.push(format!("~{{{register}}}")),
+                }
+            }
+        }
+
+        // LLVM numbers the outputs before the inputs, so the template numbers operands differently
+        let output_count = asm
+            .operands
+            .iter()
+            .filter(|operand| !matches!(operand, AsmOperand::In { .. }))
+            .count()
+            + clobbered_inputs.len();
+        let mut numbers = Vec::new();
+        let mut outputs: Vec<(Option<Place<'ctx>>, Type)> = Vec::new();
+        let mut output_constraints = Vec::new();
+        let mut inputs: Vec<BasicMetadataValueEnum> = Vec::new();
+        let mut input_types: Vec<BasicMetadataTypeEnum> = Vec::new();
+        let mut input_constraints = Vec::new();
+        for operand in &asm.operands {
+            let register = self.asm_register(operand.register())?;
+            let value = match operand {
+                AsmOperand::In { value, .. } => {
+                    numbers.push(output_count + inputs.len());
+                    let value = self.compile_expression(value, None)?;
+                    let value = value.ok_or(CompileError::InvalidAsmOperand {
+                        ty: Type::Unit,
+                        target: self.target,
+                    })?;
+                    self.check_asm_type(&value.ty)?;
+                    input_constraints.push(register);
+                    value
+                }
+                AsmOperand::Out { place, .. } => {
+                    numbers.push(outputs.len());
+                    let place = place
+                        .as_ref()
+                        .map(|place| self.asm_output(place))
+                        .transpose()?;
+                    let ty = place
+                        .as_ref()
+                        .map_or(IntType::INDEX.into(), |place| place.ty.clone());
+                    // The output may be written before every input is read, so it can't share a
+                    // register with one
+                    output_constraints.push(format!("=&{register}"));
+                    outputs.push((place, ty));
+                    continue;
+                }
+                AsmOperand::InOut { place, .. } => {
+                    numbers.push(outputs.len());
+                    let place = self.asm_output(place)?;
+                    let value = self.build_load(&place)?;
+                    input_constraints.push(outputs.len().to_string());
+                    output_constraints.push(format!("={register}"));
+                    outputs.push((Some(place), value.ty.clone()));
+                    value
+                }
+            };
+            let basic_type = self.basic_type(&value.ty).expect("operands are never unit");
+            input_types.push(basic_type.into());
+            inputs.push(value.value.into());
+        }
+        for register in clobbered_inputs {
+            output_constraints.push(format!("={{{register}}}"));
+            outputs.push((None, IntType::INDEX.into()));
+        }
+        if !asm.options.nomem {
+            clobbers.push("~{memory}".into());
+        }
+        if self.target.is_arm() && !asm.options.preserves_flags {
+            clobbers.push("~{cpsr}".into());
+        }
+
+        let output_types: Vec<BasicTypeEnum> = outputs
+            .iter()
+            .map(|(_, ty)| self.basic_type(ty).expect("operands are never unit"))
+            .collect();
+        let function_type = match output_types.as_slice() {
+            [] => self.context.void_type().fn_type(&input_types, false),
+            [ty] => ty.fn_type(&input_types, false),
+            types => self
+                .context
+                .struct_type(types, false)
+                .fn_type(&input_types, false),
+        };
+        let constraints = [output_constraints, input_constraints, clobbers]
+            .concat()
+            .join(",");
+        // Assembly without outputs is only there for its effects
+        let has_effects = asm.options.volatile || outputs.is_empty();
+        let assembly = self.context.create_inline_asm(
+            function_type,
+            asm_template(&asm.template, &numbers)?,
+            constraints,
+            has_effects,
+            !asm.options.nostack,
+            None,
+            false,
+        );
+        let call = self
+            .builder
+            .build_indirect_call(function_type, assembly, &inputs, "asm")?;
+        let result = call.try_as_basic_value().left();
+        for (index, (place, _)) in outputs.iter().enumerate() {
+            let Some(place) = place else {
+                continue;
+            };
+            let result = result.expect("assembly with outputs returns them");
+            let value = if outputs.len() == 1 {
+                result
+            } else {
+                self.builder.build_extract_value(
+                    result.into_struct_value(),
+                    index as u32,
+                    "asm_output",
+                )?
+            };
+            self.build_store(place, value)?;
+        }
+        Ok(None)
+    }
+
+    /// Checks that a register pseudo-variable exists on the target and is used in unsafe code.
+    fn check_core_register(&self, name: &Rc<str>) -> Result<(), CompileError> {
+        if !self.target.has_core_register(name) {
+            return Err(CompileError::UnknownCoreRegister {
+                name: name.clone(),
+                target: self.target,
+            });
+        }
+        if !self.is_unsafe {
+            return Err(CompileError::RequiresUnsafe(
+                "use of a register pseudo-variable",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads a register pseudo-variable. The stack pointer is read with `llvm.read_register`, the
+    /// link register is copied with `mov` and the special registers with `mrs`.
+    fn read_core_register(&mut self, name: &Rc<str>) -> Result<IntValue<'ctx>, CompileError> {
+        self.check_core_register(name)?;
+        let value = match &**name {
+            "sp" => {
+                let read = self.i32_intrinsic("llvm.read_register");
+                let call = self
+                    .builder
+                    .build_call(read, &[self.register_metadata(name)], name)?;
+                call.try_as_basic_value().left()
+            }
+            "lr" => self.build_inline_asm("mov $0, lr", "=r", &[], true)?,
+            _ => self.build_inline_asm(&format!("mrs $0, {name}"), "=r", &[], true)?,
+        };
+        Ok(value
+            .expect("reading a register returns its value")
+            .into_int_value())
+    }
+
+    /// Writes a register pseudo-variable, the reverse of [`Self::read_core_register`]. Writes to
+    /// `control` are followed by an `isb`, so that the next instructions see the change.
+    ///
+    /// LLVM addresses the locals of a function from `sp`, and doesn't move them when `$sp` is
+    /// written, so a write only makes sense before the function uses its stack, such as at reset.
+    /// Switching to another stack is done through `$msp` or `$psp` instead.
+    fn write_core_register(
+        &mut self,
+        name: &Rc<str>,
+        value: IntValue<'ctx>,
+    ) -> Result<(), CompileError> {
+        let assembly = match &**name {
+            "sp" => {
+                let write = self.i32_intrinsic("llvm.write_register");
+                let arguments = [self.register_metadata(name), value.into()];
+                self.builder.build_call(write, &arguments, "")?;
+                return Ok(());
+            }
+            // LLVM keeps the return address in `lr` unless it is told the assembly changes it.
+            "lr" => {
+                self.build_inline_asm("mov lr, $0", "r,~{lr},~{memory}", &[value], false)?;
+                return Ok(());
+            }
+            "control" => "msr control, $0\n\tisb".to_string(),
+            _ => format!("msr {name}, $0"),
+        };
+        self.build_inline_asm(&assembly, "r,~{memory}", &[value], false)?;
+        Ok(())
+    }
+
+    /// Declares an LLVM intrinsic for 32-bit integers, such as `llvm.read_register`.
+    fn i32_intrinsic(&self, name: &str) -> FunctionValue<'ctx> {
+        Intrinsic::find(name)
+            .and_then(|intrinsic| {
+                intrinsic.get_declaration(self.module, &[self.context.i32_type().into()])
+            })
+            .unwrap_or_else(|| panic!("LLVM should provide {name}"))
+    }
+
+    /// The metadata naming a register, as the named register intrinsics take it.
+    fn register_metadata(&self, name: &str) -> BasicMetadataValueEnum<'ctx> {
+        let name = self.context.metadata_string(name);
+        self.context.metadata_node(&[name.into()]).into()
+    }
+
+    /// Calls a piece of inline assembly that has side effects and takes and returns 32-bit
+    /// integers, such as an instruction that reads a special register.
+    fn build_inline_asm(
+        &self,
+        assembly: &str,
+        constraints: &str,
+        inputs: &[IntValue<'ctx>],
+        has_output: bool,
+    ) -> Result<Option<BasicValueEnum<'ctx>>, CompileError> {
+        let i32_type = self.context.i32_type();
+        let input_types: Vec<BasicMetadataTypeEnum> =
+            inputs.iter().map(|_| i32_type.into()).collect();
+        let function_type = if has_output {
+            i32_type.fn_type(&input_types, false)
+        } else {
+            self.context.void_type().fn_type(&input_types, false)
+        };
+        let assembly = self.context.create_inline_asm(
+            function_type,
+            assembly.into(),
+            constraints.into(),
+            true,
+            false,
+            None,
+            false,
+        );
+        let inputs: Vec<BasicMetadataValueEnum> =
+            inputs.iter().map(|&input| input.into()).collect();
+        let call = self
+            .builder
+            .build_indirect_call(function_type, assembly, &inputs, "asm")?;
+        Ok(call.try_as_basic_value().left())
+    }
+
+    /// The constraint for the register of an inline assembly operand.
+    fn asm_register(&self, register: &AsmRegister) -> Result<String, CompileError> {
+        match register {
+            AsmRegister::Class(class) if &**class == "reg" => Ok("r".into()),
+            AsmRegister::Class(class) => Err(CompileError::InvalidAsmRegisterClass {
+                class: class.clone(),
+                target: self.target,
+            }),
+            AsmRegister::Explicit(name) if self.target.has_register(name) => {
+                Ok(format!("{{{name}}}"))
+            }
+            AsmRegister::Explicit(name) => Err(CompileError::InvalidAsmRegister {
+                register: name.clone(),
+                target: self.target,
+            }),
+        }
+    }
+
+    /// Checks that a value of type `ty` fits in a general purpose register.
+    fn check_asm_type(&self, ty: &Type) -> Result<(), CompileError> {
+        match ty {
+            Type::Int(int) if int.bits <= self.target.register_bits() => Ok(()),
+            Type::Pointer { .. } => Ok(()),
+            ty => Err(CompileError::InvalidAsmOperand {
+                ty: ty.clone(),
+                target: self.target,
+            }),
+        }
+    }
+
+    /// The place that an output of inline assembly is stored to.
+    fn asm_output(&mut self, expression: &Expression) -> Result<Place<'ctx>, CompileError> {
+        if !expression.is_place() {
+            return Err(CompileError::AsmOutputNotPlace);
+        }
+        let place = self.compile_place(expression, Access::Write)?;
+        if !place.is_mutable {
+            return Err(CompileError::NotMutable(place.root));
+        }
+        self.check_asm_type(&place.ty)?;
+        Ok(place)
+    }
+
+    fn compile_unary(
+        &mut self,
+        operator: UnaryOperator,
+        operand: &Expression,
+        expected: Option<IntType>,
+    ) -> Result<Integer<'ctx>, CompileError> {
+        match operator {
+            UnaryOperator::Neg => {
+                // A negated literal is a negative literal, so it can be the minimum of its type
+                let literal = match operand {
+                    Expression::Integer(x) => Some((*x, expected.unwrap_or(IntType::DEFAULT))),
+                    Expression::TypedInteger(x, ty) => Some((*x, *ty)),
+                    _ => None,
+                };
+                if let Some((x, ty)) = literal {
+                    if !ty.signed {
+                        return Err(CompileError::UnsignedNegation(ty));
+                    }
+                    return self.compile_constant_value(-(x as i128), ty);
+                }
+                let operand = self.compile_integer_operand(operand, expected)?;
+                if !operand.ty.signed {
+                    return Err(CompileError::UnsignedNegation(operand.ty));
+                }
+                let value = self.builder.build_int_neg(operand.value, "neg")?;
+                Ok(Integer {
+                    value,
+                    ty: operand.ty,
+                })
+            }
+        }
+    }
+
+    /// Finds the memory that a place expression, such as `x`, `x.y` or `*x`, refers to, checking
+    /// that it can be used as `access` says.
+    ///
+    /// References are followed to what they refer to when accessing fields and elements.
+    fn compile_place(
+        &mut self,
+        expression: &Expression,
+        access: Access,
+    ) -> Result<Place<'ctx>, CompileError> {
+        self.check_moves(expression, access)?;
+        self.find_place(expression, access)
+    }
+
+    /// Finds the memory of a place like `compile_place`, without checking whether it was moved.
+    fn find_place(
+        &mut self,
+        expression: &Expression,
+        access: Access,
+    ) -> Result<Place<'ctx>, CompileError> {
+        match expression {
+            Expression::Variable(name) => match self.named_values.get(name).cloned() {
+                Some(NamedValue::Constant(value, ty)) => {
+                    // Borrowing a constant borrows a copy that lives as long as the program
+                    let global = self.module.add_global(self.int_type(ty), None, name);
+                    global.set_constant(true);
+                    global.set_linkage(Linkage::Private);
+                    global.set_unnamed_addr(true);
+                    global.set_initializer(&self.compile_constant_value(value, ty)?.value);
+                    Ok(Place {
+                        pointer: global.as_pointer_value(),
+                        ty: ty.into(),
+                        align: ty.size(),
+                        root: name.clone(),
+                        is_mutable: false,
+                        owner: Owner::Outside,
+                    })
+                }
+                Some(NamedValue::Global(ty, pointer)) => Ok(Place {
+                    pointer,
+                    align: ty.align(self.pointer_size()),
+                    ty,
+                    root: name.clone(),
+                    is_mutable: false,
+                    owner: Owner::Outside,
+                }),
+                Some(NamedValue::Static(spec, ty, pointer)) => {
+                    if spec.is_mutable && !self.is_unsafe {
+                        return Err(CompileError::RequiresUnsafe("use of a mutable static"));
+                    }
+                    Ok(Place {
+                        pointer,
+                        align: ty.align(self.pointer_size()),
+                        ty,
+                        root: name.clone(),
+                        is_mutable: spec.is_mutable,
+                        owner: Owner::Outside,
+                    })
+                }
+                Some(NamedValue::Variable(spec, ty, pointer)) => {
+                    self.check_access(pointer, name, access)?;
+                    if access != Access::Write && ty.contains_references() {
+                        self.copy_held_borrows(pointer);
+                    }
+                    Ok(Place {
+                        pointer,
+                        align: ty.align(self.pointer_size()),
+                        ty,
+                        root: name.clone(),
+                        is_mutable: spec.is_mutable,
+                        owner: Owner::Local(pointer),
+                    })
+                }
+                None => Err(CompileError::UnknownVariable(name.clone())),
+            },
+            Expression::Field(_) | Expression::Index(_) => {
+                self.find_member_place(expression, access)
+            }
+            Expression::Deref(value) => {
+                let reference = if value.is_place() {
+                    self.find_place(value, access)?
+                } else {
+                    let reference = self.compile_expression(value, None)?;
+                    let reference =
+                        reference.ok_or(CompileError::NotDereferenceable(Type::Unit))?;
+                    self.build_temporary(reference, "dereferenced")?
+                };
+                self.deref_place(&reference)
+            }
+            _ => unreachable!("only places are compiled as places"),
+        }
+    }
+
+    /// Finds the place of a field or element, such as `a.x[i].y`. Each is a member of the place
+    /// before it, so the chain is followed in a loop from the innermost member out.
+    fn find_member_place(
+        &mut self,
+        expression: &Expression,
+        access: Access,
+    ) -> Result<Place<'ctx>, CompileError> {
+        let mut members = Vec::new();
+        let mut base = expression;
+        while let Expression::Field(Field { base: inner, .. })
+        | Expression::Index(Index { base: inner, .. }) = base
+        {
+            members.push(base);
+            base = inner;
+        }
+        let mut place = self.find_place(base, access)?;
+        for member in members.into_iter().rev() {
+            let base = self.auto_deref(place)?;
+            place = match member {
+                Expression::Field(field) => {
+                    let (ty, index) = self.find_field(&base.ty, &field.name)?;
+                    self.struct_field_place(&base, &ty, index)?
+                }
+                Expression::Index(index) => self.element_place(&base, &index.index)?,
+                _ => unreachable!("only fields and elements are members"),
+            };
+        }
+        Ok(place)
+    }
+
+    /// The place that the reference or raw pointer at `place` refers to.
+    fn deref_place(&self, place: &Place<'ctx>) -> Result<Place<'ctx>, CompileError> {
+        let (target, mutable, owner) = match &place.ty {
+            Type::Reference { target, mutable } => (target, *mutable, place.owner.behind()),
+            // Nothing is known about what a raw pointer points to, so it isn't borrow checked
+            Type::Pointer { target, mutable } if self.is_unsafe => {
+                (target, *mutable, Owner::Outside)
+            }
+            Type::Pointer { .. } => {
+                return Err(CompileError::RequiresUnsafe("dereference of raw pointer"));
+            }
+            ty => return Err(CompileError::NotDereferenceable(ty.clone())),
+        };
+        let pointer = self.build_load(place)?.value.into_pointer_value();
+        Ok(Place {
+            pointer,
+            ty: (**target).clone(),
+            align: target.align(self.pointer_size()),
+            root: place.root.clone(),
+            is_mutable: mutable,
+            owner,
+        })
+    }
+
+    /// Follows the references at `place`, if it holds any, to the place they refer to.
+    fn auto_deref(&self, mut place: Place<'ctx>) -> Result<Place<'ctx>, CompileError> {
+        while let Type::Reference { .. } = place.ty {
+            place = self.deref_place(&place)?;
+        }
+        Ok(place)
+    }
+
+    /// The local variable that a place expression is in, and the path to the place in it: the
+    /// names of fields, `[]` for an element and `*` for what a reference refers to. `None` for
+    /// places outside the function's variables, such as constants.
+    fn local_path(&self, expression: &Expression) -> Option<(PointerValue<'ctx>, Vec<Rc<str>>)> {
+        // The segments are found from the outermost in, and reversed once the variable is
+        let mut path = Vec::new();
+        let mut expression = expression;
+        let local = loop {
+            let (base, segment) = match expression {
+                Expression::Variable(name) => match self.named_values.get(name) {
+                    Some(NamedValue::Variable(_, _, pointer)) => break *pointer,
+                    _ => return None,
+                },
+                Expression::Field(field) => (&field.base, field.name.clone()),
+                Expression::Index(index) => (&index.base, "[]".into()),
+                Expression::Deref(value) => (value, "*".into()),
+                _ => return None,
+            };
+            path.push(segment);
+            expression = base;
+        };
+        path.reverse();
+        Some((local, path))
+    }
+
+    /// Checks that a place isn't used after its value, or a value it is part of or contains, was
+    /// moved out. Assigning to a moved value is fine, as it gives it a value again, but assigning
+    /// to part of one isn't.
+    fn check_moves(&self, expression: &Expression, access: Access) -> Result<(), CompileError> {
+        let Some((local, path)) = self.local_path(expression) else {
+            return Ok(());
+        };
+        for moved in self.moves.iter().filter(|moved| moved.local == local) {
+            let is_reassigned = access == Access::Write && moved.path.starts_with(&path);
+            if !is_reassigned && (moved.path.starts_with(&path) || path.starts_with(&moved.path)) {
+                return Err(CompileError::UseOfMoved(moved.name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the value out of `place`, the place of `expression`, if its type can't be copied. It
+    /// can't be used again until it is assigned a new value.
+    fn move_out(
+        &mut self,
+        expression: &Expression,
+        place: &Place<'ctx>,
+    ) -> Result<(), CompileError> {
+        if place.ty.is_copy() {
+            return Ok(());
+        }
+        let from = match place.owner {
+            // Nothing else can use a temporary
+            Owner::Temporary => return Ok(()),
+            Owner::Behind(_) => "a reference",
+            Owner::Outside => "a raw pointer",
+            Owner::Local(local) => {
+                let (_, path) = self
+                    .local_path(expression)
+                    .expect("places in local variables have a path");
+                // Which element is moved isn't known at compile time, so none can be
+                if path.iter().any(|segment| &**segment == "[]") {
+                    "an array"
+                } else if self
+                    .borrows
+                    .iter()
+                    .any(|borrow| borrow.owner.local() == Some(local))
+                {
+                    return Err(CompileError::MovedWhileBorrowed(place.root.clone()));
+                } else {
+                    let name = std::iter::once(place.root.clone())
+                        .chain(path.iter().cloned())
+                        .collect::<Vec<_>>()
+                        .join(".");
+                    self.moves.push(Move {
+                        local,
+                        path,
+                        name: name.into(),
+                    });
+                    return Ok(());
+                }
+            }
+        };
+        Err(CompileError::CannotMoveOut {
+            name: place.root.clone(),
+            from,
+        })
+    }
+
+    /// Forgets the moves out of a place that has been assigned a new value.
+    fn reassign(&mut self, expression: &Expression) {
+        if let Some((local, path)) = self.local_path(expression) {
+            self.moves
+                .retain(|moved| moved.local != local || !moved.path.starts_with(&path));
+        }
+    }
+
+    /// Checks that a loop body didn't move out of a variable declared before the loop, as it would
+    /// be moved again on the next iteration. `moves` are the moves from before the body, and
+    /// `locals` the number of local variables that were in scope.
+    fn check_loop_moves(&self, moves: &[Move<'ctx>], locals: usize) -> Result<(), CompileError> {
+        let outer = &self.locals[..locals];
+        let moved = self
+            .moves
+            .iter()
+            .find(|moved| outer.contains(&moved.local) && !moves.contains(moved));
+        match moved {
+            Some(moved) => Err(CompileError::MovedInLoop(moved.name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that using the local variable at `local` as `access` says doesn't conflict with a
+    /// borrow of it that is still in use: it can have many shared borrows, or one mutable borrow.
+    fn check_access(
+        &self,
+        local: PointerValue<'ctx>,
+        name: &Rc<str>,
+        access: Access,
+    ) -> Result<(), CompileError> {
+        let borrows = self
+            .borrows
+            .iter()
+            .filter(|borrow| borrow.owner.local() == Some(local));
+        for borrow in borrows {
+            match access {
+                Access::Read if borrow.mutable => {
+                    return Err(CompileError::UsedWhileBorrowed(name.clone()));
+                }
+                Access::Write => return Err(CompileError::AssignedWhileBorrowed(name.clone())),
+                Access::Borrow { mutable } if mutable || borrow.mutable => {
+                    return Err(CompileError::BorrowConflict {
+                        name: name.clone(),
+                        mutable,
+                        existing_mutable: borrow.mutable,
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the borrows held by the local variable at `local` for a value read from it, which
+    /// holds the same references.
+    fn copy_held_borrows(&mut self, local: PointerValue<'ctx>) {
+        let held: Vec<_> = self
+            .borrows
+            .iter()
+            .filter(|borrow| borrow.holder == Some(local))
+            .map(|borrow| Borrow {
+                holder: None,
+                ..borrow.clone()
+            })
+            .collect();
+        self.borrows.extend(held);
+    }
+
+    /// Ends the borrows made since there were `count` borrows, unless they were stored in a
+    /// variable.
+    fn end_temporary_borrows(&mut self, count: usize) {
+        let mut index = 0;
+        self.borrows.retain(|borrow| {
+            index += 1;
+            index <= count || borrow.holder.is_some()
+        });
+    }
+
+    /// Makes the borrows made since there were `count` borrows last as long as the variable at
+    /// `place`, which a value holding the references has been stored in.
+    fn hold_borrows(&mut self, count: usize, place: &Place<'ctx>) -> Result<(), CompileError> {
+        let Owner::Local(holder) = place.owner else {
+            // Memory outside the function's variables outlives all of them
+            let dangling = self
+                .borrows
+                .iter()
+                .skip(count)
+                .find(|borrow| borrow.holder.is_none() && borrow.owner.is_in_frame());
+            return match dangling {
+                Some(borrow) => Err(CompileError::DoesNotLiveLongEnough(borrow.borrowed_name())),
+                None => Ok(()),
+            };
+        };
+        for borrow in self.borrows.iter_mut().skip(count) {
+            if borrow.holder.is_none() {
+                borrow.holder = Some(holder);
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts a scope for local variables, which ends with `end_scope`.
+    fn start_scope(&self) -> Scope<'ctx> {
+        Scope {
+            named_values: self.named_values.clone(),
+            locals: self.locals.len(),
+        }
+    }
+
+    /// Brings a local variable into the current scope.
+    fn declare_local(&mut self, spec: VariableSpec, ty: Type, pointer: PointerValue<'ctx>) {
+        self.locals.push(pointer);
+        self.named_values
+            .insert(spec.name.clone(), NamedValue::Variable(spec, ty, pointer));
+    }
+
+    /// Ends a scope, along with the borrows held by its variables.
+    ///
+    /// `result` is the type of the value the scope gives, which may carry borrows of its variables
+    /// out of it.
+    fn end_scope(&mut self, scope: Scope<'ctx>, result: Option<&Type>) -> Result<(), CompileError> {
+        self.named_values = scope.named_values;
+        let ended = self.locals.split_off(scope.locals);
+        self.moves.retain(|moved| !ended.contains(&moved.local));
+        self.borrows
+            .retain(|borrow| !borrow.holder.is_some_and(|holder| ended.contains(&holder)));
+        let escapes = result.is_some_and(Type::contains_references);
+        let dangling = self.borrows.iter().find(|borrow| {
+            let Owner::Local(local) = borrow.owner else {
+                return false;
+            };
+            ended.contains(&local) && (escapes || borrow.holder.is_some())
+        });
+        if let Some(borrow) = dangling {
+            return Err(CompileError::DoesNotLiveLongEnough(borrow.borrowed_name()));
+        }
+        self.borrows.retain(|borrow| {
+            !borrow
+                .owner
+                .local()
+                .is_some_and(|local| ended.contains(&local))
+        });
+        Ok(())
+    }
+
+    /// The place of field `index` of the struct at `base`.
+    fn struct_field_place(
+        &self,
+        base: &Place<'ctx>,
+        ty: &StructType,
+        index: usize,
+    ) -> Result<Place<'ctx>, CompileError> {
+        let field = &ty.fields[index];
+        let member = ty.member_index(index);
+        self.member_place(base, self.struct_type(ty), member, field.offset, &field.ty)
+    }
+
+    /// The place of field `index` of variant `variant` of the enum at `base`.
+    fn variant_field_place(
+        &self,
+        base: &Place<'ctx>,
+        ty: &EnumType,
+        variant: usize,
+        index: usize,
+    ) -> Result<Place<'ctx>, CompileError> {
+        let payload = &ty.variants[variant].payload;
+        // The payload comes after the tag, and the padding after it if there is any
+        let member = if ty.payload_offset > ty.tag.size() {
+            2
+        } else {
+            1
+        };
+        let payload_type = Type::Struct(payload.clone());
+        let enum_type = self.enums[&ty.name].1;
+        let payload_place =
+            self.member_place(base, enum_type, member, ty.payload_offset, &payload_type)?;
+        self.struct_field_place(&payload_place, payload, index)
+    }
+
+    /// The place of a member at byte `offset` in the LLVM struct at `base`.
+    fn member_place(
+        &self,
+        base: &Place<'ctx>,
+        llvm_type: LLVMStructType<'ctx>,
+        member: u32,
+        offset: u64,
+        ty: &Type,
+    ) -> Result<Place<'ctx>, CompileError> {
+        let pointer = self
+            .builder
+            .build_struct_gep(llvm_type, base.pointer, member, "member")?;
+        Ok(Place {
+            pointer,
+            ty: ty.clone(),
+            align: align_at_offset(base.align, offset),
+            ..base.clone()
+        })
+    }
+
+    /// Finds the elements of the array or slice at `base`, or `None` if it holds something else.
+    fn elements(&self, base: &Place<'ctx>) -> Result<Option<Elements<'ctx>>, CompileError> {
+        match &base.ty {
+            Type::Array(element, length) => Ok(Some(Elements {
+                first: Place {
+                    ty: (**element).clone(),
+                    ..base.clone()
+                },
+                length: Length::Known(*length),
+            })),
+            Type::Slice { element, mutable } => {
+                let slice = self.build_load(base)?.value.into_struct_value();
+                let pointer = self
+                    .builder
+                    .build_extract_value(slice, 0, "slice_pointer")?
+                    .into_pointer_value();
+                let length = self
+                    .builder
+                    .build_extract_value(slice, 1, "slice_length")?
+                    .into_int_value();
+                Ok(Some(Elements {
+                    first: Place {
+                        pointer,
+                        ty: (**element).clone(),
+                        align: element.align(self.pointer_size()),
+                        root: base.root.clone(),
+                        is_mutable: *mutable,
+                        owner: base.owner.behind(),
+                    },
+                    length: Length::Runtime(length),
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The length of some elements, widened to 64 bits to compare with indices of any type.
+    fn build_length(&self, length: &Length<'ctx>) -> Result<IntValue<'ctx>, CompileError> {
+        let i64_type = self.context.i64_type();
+        match length {
+            Length::Known(length) => Ok(i64_type.const_int(*length, false)),
+            Length::Runtime(length) => Ok(self
+                .builder
+                .build_int_z_extend(*length, i64_type, "length")?),
+        }
+    }
+
+    /// Compiles an index, which must be an unsigned integer. It is widened to 64 bits, as GEP
+    /// indices are signed.
+    fn compile_index_operand(
+        &mut self,
+        index: &Expression,
+    ) -> Result<(IntValue<'ctx>, IntType), CompileError> {
+        let value = self.compile_expression(index, Some(&IntType::INDEX.into()))?;
+        let (value, ty) = match value {
+            Some(Value {
+                value,
+                ty: Type::Int(ty),
+            }) if !ty.signed => (value.into_int_value(), ty),
+            value => {
+                let ty = value.map_or(Type::Unit, |value| value.ty);
+                return Err(CompileError::InvalidIndexType(ty));
+            }
+        };
+        let i64_type = self.context.i64_type();
+        let value = self
+            .builder
+            .build_int_z_extend_or_bit_cast(value, i64_type, "index")?;
+        Ok((value, ty))
+    }
+
+    /// The place of the element at `index` of the array or slice at `base`, checking that it is
+    /// in bounds.
+    ///
+    /// Indexing an array leaves the check out if the index is a constant, or its type can't hold
+    /// a value as large as the length.
+    fn element_place(
+        &mut self,
+        base: &Place<'ctx>,
+        index: &Expression,
+    ) -> Result<Place<'ctx>, CompileError> {
+        let elements = self
+            .elements(base)?
+            .ok_or_else(|| CompileError::NotIndexable(base.ty.clone()))?;
+        if let Expression::Range(_) = index {
+            return Err(CompileError::SliceNotBorrowed);
+        }
+        let (offset, ty) = self.compile_index_operand(index)?;
+        let constant = self.fold_constant(index)?;
+        match (constant, &elements.length) {
+            (Some(index), Length::Known(length)) if index >= i128::from(*length) => {
+                return Err(CompileError::IndexOutOfBounds {
+                    index,
+                    length: *length,
+                });
+            }
+            (Some(_), Length::Known(_)) => {}
+            (None, Length::Known(length)) if ty.max() < i128::from(*length) => {}
+            (_, length) => {
+                let length = self.build_length(length)?;
+                let out_of_bounds = self.builder.build_int_compare(
+                    IntPredicate::UGE,
+                    offset,
+                    length,
+                    "out_of_bounds",
+                )?;
+                self.build_runtime_check(out_of_bounds, "index_out_of_bounds")?;
+            }
+        }
+        self.element_at(&elements.first, offset, constant)
+    }
+
+    /// The place of the element `offset` elements after `first`, which must be in bounds.
+    /// `constant` is the offset, if it is known at compile time.
+    fn element_at(
+        &self,
+        first: &Place<'ctx>,
+        offset: IntValue<'ctx>,
+        constant: Option<i128>,
+    ) -> Result<Place<'ctx>, CompileError> {
+        let element_type = self.basic_type(&first.ty).expect("elements are never unit");
+        // SAFETY: callers check that the offset is in bounds
+        let pointer = unsafe {
+            self.builder
+                .build_in_bounds_gep(element_type, first.pointer, &[offset], "element")?
+        };
+        let align = match constant {
+            Some(index) => align_at_offset(
+                first.align,
+                index as u64 * first.ty.size(self.pointer_size()),
+            ),
+            None => first.align.min(first.ty.align(self.pointer_size())),
+        };
+        Ok(Place {
+            pointer,
+            align,
+            ..first.clone()
+        })
+    }
+
+    /// Builds a slice of the elements `range` of the array or slice at `base`, checking that the
+    /// range is in bounds.
+    ///
+    /// The checks are done at compile time where the bounds are constants, and left out where they
+    /// always pass, as for `&a[..]`.
+    fn compile_slice(
+        &mut self,
+        base: &Place<'ctx>,
+        range: &Range,
+        mutable: bool,
+    ) -> Result<Value<'ctx>, CompileError> {
+        let elements = self
+            .elements(base)?
+            .ok_or_else(|| CompileError::NotIndexable(base.ty.clone()))?;
+        if mutable && !elements.first.is_mutable {
+            return Err(CompileError::NotMutableBorrow(base.root.clone()));
+        }
+        let i64_type = self.context.i64_type();
+        let length = self.build_length(&elements.length)?;
+        let (start, start_constant) = match &range.start {
+            Some(start) => (
+                self.compile_index_operand(start)?.0,
+                self.fold_constant(start)?,
+            ),
+            None => (i64_type.const_zero(), Some(0)),
+        };
+        let (end, end_constant) = match &range.end {
+            Some(end) => {
+                let (mut value, _) = self.compile_index_operand(end)?;
+                let mut constant = self.fold_constant(end)?;
+                if range.inclusive {
+                    let one = i64_type.const_int(1, false);
+                    value = self.builder.build_int_add(value, one, "end")?;
+                    constant = constant.map(|end| end + 1);
+                }
+                (value, constant)
+            }
+            None => (length, None),
+        };
+
+        let starts_before_end = match (start_constant, end_constant) {
+            (Some(start), Some(end)) if start > end => {
+                return Err(CompileError::SliceIndexOrder { start, end });
+            }
+            (Some(_), Some(_)) | (Some(0), None) => true,
+            _ => false,
+        };
+        let ends_in_bounds = match (&range.end, end_constant, &elements.length) {
+            (None, _, _) => true,
+            (_, Some(end), Length::Known(length)) if end > i128::from(*length) => {
+                return Err(CompileError::RangeOutOfBounds {
+                    end,
+                    length: *length,
+                });
+            }
+            (_, Some(_), Length::Known(_)) => true,
+            _ => false,
+        };
+        let mut checks = Vec::new();
+        if !starts_before_end {
+            let check = self.builder.build_int_compare(
+                IntPredicate::UGT,
+                start,
+                end,
+                "starts_after_end",
+            )?;
+            checks.push(check);
+        }
+        if !ends_in_bounds {
+            let check = self.builder.build_int_compare(
+                IntPredicate::UGT,
+                end,
+                length,
+                "ends_out_of_bounds",
+            )?;
+            checks.push(check);
+        }
+        if let Some(first) = checks.pop() {
+            let failed = match checks.pop() {
+                Some(second) => self.builder.build_or(first, second, "out_of_bounds")?,
+                None => first,
+            };
+            self.build_runtime_check(failed, "slice_out_of_bounds")?;
+        }
+
+        let first = self.element_at(&elements.first, start, start_constant)?;
+        let length = self.builder.build_int_sub(end, start, "slice_length")?;
+        let length = self.builder.build_int_truncate_or_bit_cast(
+            length,
+            self.int_type(IntType::INDEX),
+            "length",
+        )?;
+        let ty = Type::Slice {
+            element: Box::new(elements.first.ty),
+            mutable,
+        };
+        self.build_slice(first.pointer, length, ty)
+    }
+
+    /// Places the bytes of a byte string in read-only memory, as a private `[u8; N]` constant
+    /// without a symbol, and slices all of it.
+    fn compile_byte_string(&self, bytes: &[u8]) -> Result<Value<'ctx>, CompileError> {
+        let initializer = self.context.const_string(bytes, false);
+        let global = self
+            .module
+            .add_global(initializer.get_type(), None, "byte_string");
+        global.set_constant(true);
+        global.set_linkage(Linkage::Private);
+        global.set_unnamed_addr(true);
+        global.set_alignment(1);
+        global.set_initializer(&initializer);
+        let length = self
+            .int_type(IntType::INDEX)
+            .const_int(bytes.len() as u64, false);
+        let ty = Type::Slice {
+            element: Box::new(IntType::new(8, false).into()),
+            mutable: false,
+        };
+        self.build_slice(global.as_pointer_value(), length, ty)
+    }
+
+    /// Builds a slice of type `ty`, of `length` elements starting at `pointer`.
+    fn build_slice(
+        &self,
+        pointer: PointerValue<'ctx>,
+        length: IntValue<'ctx>,
+        ty: Type,
+    ) -> Result<Value<'ctx>, CompileError> {
+        let slice = self
+            .basic_type(&ty)
+            .expect("slices are never unit")
+            .into_struct_type()
+            .get_undef();
+        let slice = self
+            .builder
+            .build_insert_value(slice, pointer, 0, "slice")?;
+        let slice = self.builder.build_insert_value(slice, length, 1, "slice")?;
+        Ok(Value {
+            value: slice.into_struct_value().into(),
+            ty,
+        })
+    }
+
+    /// Looks up the field `name` of a value of type `ty`, returning the struct type and the field's index.
+    fn find_field(
+        &self,
+        ty: &Type,
+        name: &Rc<str>,
+    ) -> Result<(Rc<StructType>, usize), CompileError> {
+        let unknown = || CompileError::UnknownField {
+            ty: ty.clone(),
+            field: name.clone(),
+        };
+        let Type::Struct(struct_type) = ty else {
+            return Err(unknown());
+        };
+        let (index, _) = struct_type.field(name).ok_or_else(unknown)?;
+        Ok((struct_type.clone(), index))
+    }
+
+    fn build_alloca(&self, ty: &Type, name: &str) -> Result<PointerValue<'ctx>, CompileError> {
+        let basic_type = self.basic_type(ty).expect("variables are never unit");
+        let pointer = self.builder.build_alloca(basic_type, name)?;
+        set_alignment(
+            pointer.as_instruction_value(),
+            ty.align(self.pointer_size()),
+        );
+        Ok(pointer)
+    }
+
+    fn build_load(&self, place: &Place<'ctx>) -> Result<Value<'ctx>, CompileError> {
+        let basic_type = self.basic_type(&place.ty).expect("places are never unit");
+        let value =
+            self.builder
+                .build_load(basic_type, place.pointer, &format!("load_{}", place.root))?;
+        set_alignment(value.as_instruction_value(), place.align);
+        Ok(Value {
+            value,
+            ty: place.ty.clone(),
+        })
+    }
+
+    /// Stores `value` in a new stack slot, so that parts of it can be addressed.
+    fn build_temporary(&self, value: Value<'ctx>, name: &str) -> Result<Place<'ctx>, CompileError> {
+        // Nothing else refers to a temporary, so it is free to change
+        let place = Place {
+            pointer: self.build_alloca(&value.ty, name)?,
+            align: value.ty.align(self.pointer_size()),
+            ty: value.ty,
+            root: name.into(),
+            is_mutable: true,
+            owner: Owner::Temporary,
+        };
+        self.build_store(&place, value.value)?;
+        Ok(place)
+    }
+
+    fn build_store(
+        &self,
+        place: &Place<'ctx>,
+        value: impl BasicValue<'ctx>,
+    ) -> Result<(), CompileError> {
+        let store = self.builder.build_store(place.pointer, value)?;
+        set_alignment(Some(store), place.align);
+        Ok(())
+    }
+
+    /// Loads from `place` without letting the optimizer remove the load or reorder it with other
+    /// volatile accesses, as a read of a memory-mapped register may have side effects.
+    fn build_volatile_load(&self, place: &Place<'ctx>) -> Result<Value<'ctx>, CompileError> {
+        let value = self.build_load(place)?;
+        if let Some(load) = value.value.as_instruction_value() {
+            load.set_volatile(true).expect("loads can be volatile");
+        }
+        Ok(value)
+    }
+
+    fn build_volatile_store(
+        &self,
+        place: &Place<'ctx>,
+        value: impl BasicValue<'ctx>,
+    ) -> Result<(), CompileError> {
+        let store = self.builder.build_store(place.pointer, value)?;
+        set_alignment(Some(store), place.align);
+        store.set_volatile(true).expect("stores can be volatile");
+        Ok(())
+    }
+
+    /// Assigns to a place. Like in Rust, the assignment itself is unit.
+    fn compile_assignment(
+        &mut self,
+        assign: &Assignment,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        if let Expression::CoreRegister(name) = &*assign.target {
+            return self.assign_core_register(name, assign);
+        }
+        let place = self.compile_place(&assign.target, Access::Write)?;
+        if !place.is_mutable {
+            return Err(CompileError::NotMutable(place.root));
+        }
+        let Some(operator) = assign.operator else {
+            let borrows = self.borrows.len();
+            let value = self.compile_expression(&assign.value, Some(&place.ty))?;
+            if let Some(value) = value {
+                let value = self.coerce(value, &place.ty)?;
+                if value.ty.contains_references() {
+                    self.hold_borrows(borrows, &place)?;
+                }
+                self.build_store(&place, value.value)?;
+            }
+            // The value is compiled first, so it can move out of the place it is assigned to
+            self.reassign(&assign.target);
+            return Ok(None);
+        };
+
+        // The place is only evaluated once: its address is known, so load, operate and store back
+        let Type::Int(ty) = place.ty else {
+            return Err(CompileError::NotAnInteger(place.ty));
+        };
+        let check_divisor = self.needs_divisor_check(operator, &assign.value)?;
+        let hint = (!Self::is_shift(operator)).then_some(ty);
+        let rhs = self.compile_integer_operand(&assign.value, hint)?;
+        let lhs = Integer::from_value(Some(self.build_load(&place)?))?;
+        let value = self.build_operation(operator, lhs, rhs, check_divisor)?;
+        self.build_store(&place, value.value)?;
+        Ok(None)
+    }
+
+    /// Assigns to a register pseudo-variable. A compound assignment reads the register first.
+    fn assign_core_register(
+        &mut self,
+        name: &Rc<str>,
+        assign: &Assignment,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        self.check_core_register(name)?;
+        let ty = IntType::new(32, false);
+        let hint = (!assign.operator.is_some_and(Self::is_shift)).then_some(ty);
+        let check_divisor = match assign.operator {
+            Some(operator) => self.needs_divisor_check(operator, &assign.value)?,
+            None => false,
+        };
+        let rhs = self.compile_integer_operand(&assign.value, hint)?;
+        let value = match assign.operator {
+            Some(operator) => {
+                let value = self.read_core_register(name)?;
+                let lhs = Integer { value, ty };
+                self.build_operation(operator, lhs, rhs, check_divisor)?
+            }
+            None => {
+                self.expect_type(&ty.into(), &rhs.ty.into())?;
+                rhs
+            }
+        };
+        self.write_core_register(name, value.value)?;
+        Ok(None)
+    }
+
+    /// Reads a variable, or what a reference or pointer points to. Constants have no storage, so
+    /// their value is built into the code instead.
+    fn compile_read(
+        &mut self,
+        expression: &Expression,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let constant = match expression {
+            Expression::Variable(name) => self.constant_value(name),
+            _ => None,
+        };
+        if let Some((value, ty)) = constant {
+            return self
+                .compile_constant_value(value, ty)
+                .map(|value| Some(value.into()));
+        }
+        let place = self.compile_place(expression, Access::Read)?;
+        self.move_out(expression, &place)?;
+        self.build_load(&place).map(Some)
+    }
+
+    /// Compiles a field access, indexing or method call, or a chain of them such as
+    /// `f().x[1].len()`. Each operates on the value of the one before it, so the chain is compiled
+    /// in a loop from the innermost operator out. One on a place, such as `a.x[1]`, uses the
+    /// place instead, so fields and elements of places are loaded without loading all of it.
+    fn compile_postfix(
+        &mut self,
+        expression: &Expression,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let mut operators = Vec::new();
+        let mut operator = expression;
+        let mut value = loop {
+            let operand = match operator {
+                Expression::Field(field) => &field.base,
+                Expression::Index(index) => &index.base,
+                Expression::Call(Call { function, .. }) => match function.as_ref() {
+                    Expression::Field(method) => &method.base,
+                    _ => break self.compile_expression(operator, None)?,
+                },
+                _ => break self.compile_expression(operator, None)?,
+            };
+            if !operand.is_place() {
+                operators.push(operator);
+                operator = operand;
+                continue;
+            }
+            break match operator {
+                Expression::Index(index) => {
+                    let base = self.indexed_place(&index.base, Access::Read)?;
+                    self.compile_index(operator, &base, index)?
+                }
+                Expression::Call(call) => {
+                    let Expression::Field(method) = call.function.as_ref() else {
+                        unreachable!("only method calls are postfix operators on a place");
+                    };
+                    self.compile_method(method, &call.arguments)?
+                }
+                _ => {
+                    // A field of a place is a place too
+                    let place = self.compile_place(operator, Access::Read)?;
+                    self.move_out(operator, &place)?;
+                    Some(self.build_load(&place)?)
+                }
+            };
+        };
+        for operator in operators.into_iter().rev() {
+            value = match operator {
+                Expression::Field(field) => self.compile_field(value, field)?,
+                Expression::Index(index) => {
+                    let base = self.indexed_value(value)?;
+                    self.compile_index(operator, &base, index)?
+                }
+                Expression::Call(call) => {
+                    let Expression::Field(method) = call.function.as_ref() else {
+                        unreachable!("only method calls are postfix operators");
+                    };
+                    let base = self.indexed_value(value)?;
+                    self.compile_len(base, method, &call.arguments)?
+                }
+                _ => unreachable!("only fields, indexing and method calls are postfix operators"),
+            };
+        }
+        Ok(value)
+    }
+
+    /// Reads a field of a value that isn't a place.
+    fn compile_field(
+        &mut self,
+        base: Option<Value<'ctx>>,
+        field: &Field,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let base = base.ok_or_else(|| CompileError::UnknownField {
+            ty: Type::Unit,
+            field: field.name.clone(),
+        })?;
+        if let Type::Reference { .. } = base.ty {
+            let base = self.build_temporary(base, "dereferenced")?;
+            let base = self.auto_deref(base)?;
+            let (ty, index) = self.find_field(&base.ty, &field.name)?;
+            let place = self.struct_field_place(&base, &ty, index)?;
+            return self.build_load(&place).map(Some);
+        }
+        let (ty, index) = self.find_field(&base.ty, &field.name)?;
+        let value = self.builder.build_extract_value(
+            base.value.into_struct_value(),
+            ty.member_index(index),
+            &field.name,
+        )?;
+        Ok(Some(Value {
+            value,
+            ty: ty.fields[index].ty.clone(),
+        }))
+    }
+
+    /// The place of an array or slice that is indexed, sliced or iterated over, following any
+    /// references to it. If `base` isn't a place already, its value is stored in a temporary.
+    fn indexed_place(
+        &mut self,
+        base: &Expression,
+        access: Access,
+    ) -> Result<Place<'ctx>, CompileError> {
+        if base.is_place() {
+            let place = self.compile_place(base, access)?;
+            return self.auto_deref(place);
+        }
+        let value = self.compile_expression(base, None)?;
+        self.indexed_value(value)
+    }
+
+    /// The place of a value that is indexed, sliced or iterated over, stored in a temporary.
+    fn indexed_value(&mut self, value: Option<Value<'ctx>>) -> Result<Place<'ctx>, CompileError> {
+        let value = value.ok_or(CompileError::NotIndexable(Type::Unit))?;
+        let place = self.build_temporary(value, "indexed")?;
+        self.auto_deref(place)
+    }
+
+    /// Reads the element that `expression` indexes out of the array or slice at `base`. Elements
+    /// of places are loaded directly, like fields.
+    fn compile_index(
+        &mut self,
+        expression: &Expression,
+        base: &Place<'ctx>,
+        index: &Index,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let element = self.element_place(base, &index.index)?;
+        self.move_out(expression, &element)?;
+        self.build_load(&element).map(Some)
+    }
+
+    /// Borrows a place, as in `&x` or `&mut a[i]`. Borrowing a range of an array or slice, as
+    /// in `&a[1..4]`, gives a slice.
+    ///
+    /// Borrowing a value that isn't a place borrows a temporary holding it.
+    fn compile_reference(
+        &mut self,
+        reference: &Reference,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let access = Access::Borrow {
+            mutable: reference.mutable,
+        };
+        let range = match reference.value.as_ref() {
+            Expression::Index(Index { index, .. }) => match index.as_ref() {
+                Expression::Range(range) => Some(range),
+                _ => None,
+            },
+            _ => None,
+        };
+        let place = match reference.value.as_ref() {
+            Expression::Index(Index { base, .. }) if range.is_some() => {
+                self.indexed_place(base, access)?
+            }
+            value if value.is_place() => self.compile_place(value, access)?,
+            value => {
+                let value = self.compile_expression(value, None)?;
+                let value = value.ok_or(CompileError::Unsupported("references to `()`"))?;
+                self.build_temporary(value, "borrowed")?
+            }
+        };
+        // The elements of a slice are behind the reference it is
+        let owner = match place.ty {
+            Type::Slice { .. } if range.is_some() => place.owner.behind(),
+            _ => place.owner,
+        };
+        self.borrows.push(Borrow {
+            owner,
+            name: place.root.clone(),
+            mutable: reference.mutable,
+            holder: None,
+        });
+        if let Some(range) = range {
+            return self
+                .compile_slice(&place, range, reference.mutable)
+                .map(Some);
+        }
+        if reference.mutable && !place.is_mutable {
+            return Err(CompileError::NotMutableBorrow(place.root));
+        }
+        Ok(Some(Value {
+            value: place.pointer.into(),
+            ty: Type::Reference {
+                target: Box::new(place.ty),
+                mutable: reference.mutable,
+            },
+        }))
+    }
+
+    /// Compiles the element of an array literal, which must have the type of the elements before it.
+    fn compile_element(
+        &mut self,
+        element: &Expression,
+        ty: &mut Option<Type>,
+    ) -> Result<BasicValueEnum<'ctx>, CompileError> {
+        let Some(value) = self.compile_expression(element, ty.as_ref())? else {
+            return Err(CompileError::Unsupported("arrays of `()`"));
+        };
+        let value = match ty {
+            Some(ty) => self.coerce(value, ty)?,
+            None => {
+                *ty = Some(value.ty.clone());
+                value
+            }
+        };
+        Ok(value.value)
+    }
+
+    fn compile_array(
+        &mut self,
+        elements: &[Expression],
+        expected: Option<&Type>,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let mut element_type = match expected {
+            Some(Type::Array(element, _)) => Some((**element).clone()),
+            _ => None,
+        };
+        let values = elements
+            .iter()
+            .map(|element| self.compile_element(element, &mut element_type))
+            .collect::<Result<Vec<_>, _>>()?;
+        // An empty array without a type from its context holds the default integer type
+        let element_type = element_type.unwrap_or(IntType::DEFAULT.into());
+        let ty = Type::Array(Box::new(element_type), values.len() as u64);
+        let mut array = self
+            .basic_type(&ty)
+            .expect("arrays are never unit")
+            .into_array_type()
+            .get_undef();
+        for (index, value) in values.into_iter().enumerate() {
+            array = self
+                .builder
+                .build_insert_value(array, value, index as u32, "element")?
+                .into_array_value();
+        }
+        Ok(Some(Value {
+            value: array.into(),
+            ty,
+        }))
+    }
+
+    /// Compiles `[value; count]`. A constant value gives a constant array, while any other value
+    /// is stored to each element in a loop.
+    fn compile_array_repeat(
+        &mut self,
+        repeat: &ArrayRepeat,
+        expected: Option<&Type>,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let count = self.array_length(&repeat.count)?;
+        let mut element_type = match expected {
+            Some(Type::Array(element, _)) => Some((**element).clone()),
+            _ => None,
+        };
+        let value = self.compile_element(&repeat.value, &mut element_type)?;
+        let element_type = element_type.expect("the element was compiled");
+        if count > 1 && !element_type.is_copy() {
+            return Err(CompileError::NotCopy(element_type));
+        }
+        let ty = Type::Array(Box::new(element_type.clone()), count);
+        let is_constant = match value {
+            BasicValueEnum::IntValue(value) => value.is_const(),
+            BasicValueEnum::ArrayValue(value) => value.is_const(),
+            BasicValueEnum::StructValue(value) => value.is_const(),
+            _ => false,
+        };
+        if is_constant || count == 0 {
+            let elements = vec![value; count as usize];
+            return Ok(Some(Value {
+                value: self.const_array(&element_type, &elements).into(),
+                ty,
+            }));
+        }
+
+        let function = self
+            .current_function
+            .unwrap_or_else(|| unreachable!("Can't codegen repeat without a function."));
+        let array = Place {
+            pointer: self.build_alloca(&ty, "repeat")?,
+            align: ty.align(self.pointer_size()),
+            ty: ty.clone(),
+            root: "repeat".into(),
+            is_mutable: true,
+            owner: Owner::Temporary,
+        };
+        let array_type = self.basic_type(&ty).expect("arrays are never unit");
+        let i64_type = self.context.i64_type();
+        let entry = self.builder.get_insert_block().expect("code is in a block");
+        let fill = self.context.append_basic_block(function, "repeat_fill");
+        let end = self.context.append_basic_block(function, "repeat_end");
+        self.builder.build_unconditional_branch(fill)?;
+
+        self.builder.position_at_end(fill);
+        let index = self.builder.build_phi(i64_type, "repeat_index")?;
+        let index_value = index.as_basic_value().into_int_value();
+        // SAFETY: the index stays below the length of the array
+        let pointer = unsafe {
+            self.builder.build_in_bounds_gep(
+                array_type,
+                array.pointer,
+                &[i64_type.const_zero(), index_value],
+                "element",
+            )?
+        };
+        let element = Place {
+            pointer,
+            ty: element_type.clone(),
+            align: element_type.align(self.pointer_size()),
+            ..array.clone()
+        };
+        self.build_store(&element, value)?;
+        let next = self
+            .builder
+            .build_int_add(index_value, i64_type.const_int(1, false), "next")?;
+        let done = self.builder.build_int_compare(
+            IntPredicate::EQ,
+            next,
+            i64_type.const_int(count, false),
+            "done",
+        )?;
+        self.builder.build_conditional_branch(done, end, fill)?;
+        index.add_incoming(&[(&i64_type.const_zero(), entry), (&next, fill)]);
+
+        self.builder.position_at_end(end);
+        self.build_load(&array).map(Some)
+    }
+
+    fn compile_struct_literal(
+        &mut self,
+        literal: &StructLiteral,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let (ty, llvm_type) = self
+            .structs
+            .get(&literal.name)
+            .cloned()
+            .ok_or_else(|| CompileError::UnknownType(literal.name.clone()))?;
+        let struct_type = Type::Struct(ty.clone());
+        // Padding is left undefined
+        let mut value = llvm_type.get_undef();
+        let mut initialized = vec![false; ty.fields.len()];
+        for init in &literal.fields {
+            let (index, field) =
+                ty.field(&init.name)
+                    .ok_or_else(|| CompileError::UnknownField {
+                        ty: struct_type.clone(),
+                        field: init.name.clone(),
+                    })?;
+            if std::mem::replace(&mut initialized[index], true) {
+                return Err(CompileError::DuplicateField {
+                    ty: ty.name.clone(),
+                    field: init.name.clone(),
+                });
+            }
+            let field_value = self.compile_expression(&init.value, Some(&field.ty))?;
+            let Some(field_value) = field_value else {
+                return Err(CompileError::TypeMismatch {
+                    expected: field.ty.clone(),
+                    found: Type::Unit,
+                });
+            };
+            let field_value = self.coerce(field_value, &field.ty)?;
+            value = self
+                .builder
+                .build_insert_value(value, field_value.value, ty.member_index(index), &init.name)?
+                .into_struct_value();
+        }
+        if let Some(missing) = initialized.iter().position(|initialized| !initialized) {
+            return Err(CompileError::MissingField {
+                ty: ty.name.clone(),
+                field: ty.fields[missing].name.clone(),
+            });
+        }
+        Ok(Some(Value {
+            value: value.into(),
+            ty: struct_type,
+        }))
+    }
+
+    /// Compiles a binary operator, or a chain of them such as `a + b - c`. The left operand of each
+    /// is the operator before it, so the chain is compiled in a loop from the innermost operator
+    /// out, with each result the left operand of the next.
+    fn compile_binop(
+        &mut self,
+        op: &BinOp,
+        expected: Option<IntType>,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let mut operations = Vec::new();
+        let mut op = op;
+        let first = loop {
+            let (operator, lhs, rhs) = op.parts();
+            let check_divisor = self.needs_divisor_check(operator, rhs)?;
+            operations.push((operator, rhs, check_divisor));
+            match lhs {
+                Expression::BinOp(lhs) => op = lhs,
+                lhs => break lhs,
+            }
+        };
+        let (operator, rhs, check_divisor) = operations.pop().expect("a chain has an operator");
+        let (lhs, rhs) = self.compile_operands(operator, first, rhs, expected)?;
+        let mut value = self.build_operation(operator, lhs, rhs, check_divisor)?;
+        for (operator, rhs, check_divisor) in operations.into_iter().rev() {
+            let hint = (!Self::is_shift(operator)).then_some(value.ty);
+            let rhs = self.compile_integer_operand(rhs, hint)?;
+            value = self.build_operation(operator, value, rhs, check_divisor)?;
+        }
+        Ok(Some(value.into()))
+    }
+
+    fn is_shift(operator: Operator) -> bool {
+        matches!(operator, Operator::Shl | Operator::Shr)
+    }
+
+    /// Whether dividing by `divisor` needs a runtime check for zero.
+    ///
+    /// A divisor that is zero at compile time is always rejected.
+    fn needs_divisor_check(
+        &self,
+        operator: Operator,
+        divisor: &Expression,
+    ) -> Result<bool, CompileError> {
+        if !matches!(operator, Operator::Div | Operator::Rem) {
+            return Ok(false);
+        }
+        match self.fold_constant(divisor)? {
+            Some(0) => Err(CompileError::DivisionByZero),
+            Some(_) => Ok(false),
+            None => Ok(true),
+        }
+    }
+
+    /// Compiles both operands of `operator`, so that they agree on a type where possible.
+    fn compile_operands(
+        &mut self,
+        operator: Operator,
+        lhs: &Expression,
+        rhs: &Expression,
+        expected: Option<IntType>,
+    ) -> Result<(Integer<'ctx>, Integer<'ctx>), CompileError> {
+        // The amount to shift by can have any integer type, so it doesn't take the type of the other operand
+        let is_shift = Self::is_shift(operator);
+        // An untyped literal on the left takes its type from the right. Literals don't emit any code,
+        // so the right can be compiled first without changing the order of evaluation.
+        if expected.is_none() && !is_shift && matches!(lhs, Expression::Integer(_)) {
+            let rhs = self.compile_integer_operand(rhs, None)?;
+            let lhs = self.compile_integer_operand(lhs, Some(rhs.ty))?;
+            return Ok((lhs, rhs));
+        }
+        let lhs = self.compile_integer_operand(lhs, expected)?;
+        let rhs = self.compile_integer_operand(rhs, (!is_shift).then_some(lhs.ty))?;
+        Ok((lhs, rhs))
+    }
+
+    /// Applies `operator`, choosing signed or unsigned instructions by the type of `lhs`.
+    ///
+    /// If `check_divisor` is set, division and remainder trap at runtime on a zero divisor, and
+    /// on dividing the minimum of a signed type by -1. Shifts by as many bits as the type has or
+    /// more are rejected if the amount is constant, and trap at runtime otherwise, as LLVM leaves
+    /// their result undefined.
+    fn build_operation(
+        &mut self,
+        operator: Operator,
+        lhs: Integer<'ctx>,
+        rhs: Integer<'ctx>,
+        check_divisor: bool,
+    ) -> Result<Integer<'ctx>, CompileError> {
+        let ty = lhs.ty;
+        let (lhs, rhs) = if Self::is_shift(operator) {
+            self.check_shift_amount(ty, rhs)?;
+            let rhs = self.builder.build_int_cast_sign_flag(
+                rhs.value,
+                self.int_type(ty),
+                rhs.ty.signed,
+                "shift_amount",
+            )?;
+            (lhs.value, rhs)
+        } else {
+            self.expect_int_type(ty, rhs.ty)?;
+            (lhs.value, rhs.value)
+        };
+
+        if check_divisor {
+            let is_zero = self.builder.build_int_compare(
+                IntPredicate::EQ,
+                rhs,
+                rhs.get_type().const_zero(),
+                "divisor_is_zero",
+            )?;
+            self.build_runtime_check(is_zero, "div_by_zero")?;
+        }
+        if ty.signed && matches!(operator, Operator::Div | Operator::Rem) {
+            self.check_division_overflow(ty, lhs, rhs, check_divisor)?;
+        }
+
+        let value = match operator {
+            Operator::Add => self.builder.build_int_add(lhs, rhs, "addtmp"),
+            Operator::Sub => self.builder.build_int_sub(lhs, rhs, "subtmp"),
+            Operator::Mul => self.builder.build_int_mul(lhs, rhs, "multmp"),
+            Operator::Div if ty.signed => self.builder.build_int_signed_div(lhs, rhs, "divtmp"),
+            Operator::Div => self.builder.build_int_unsigned_div(lhs, rhs, "divtmp"),
+            Operator::Rem if ty.signed => self.builder.build_int_signed_rem(lhs, rhs, "remtmp"),
+            Operator::Rem => self.builder.build_int_unsigned_rem(lhs, rhs, "remtmp"),
+            Operator::BitAnd => self.builder.build_and(lhs, rhs, "andtmp"),
+            Operator::BitOr => self.builder.build_or(lhs, rhs, "ortmp"),
+            Operator::BitXor => self.builder.build_xor(lhs, rhs, "xortmp"),
+            Operator::Shl => self.builder.build_left_shift(lhs, rhs, "shltmp"),
+            Operator::Shr => self
+                .builder
+                .build_right_shift(lhs, rhs, ty.signed, "shrtmp"),
+        }?;
+        Ok(Integer { value, ty })
+    }
+
+    /// Checks that a signed division doesn't divide the minimum of `ty` by -1, which is undefined
+    /// in LLVM. Constant operands are rejected, while others are checked at runtime if `runtime`
+    /// is set.
+    fn check_division_overflow(
+        &mut self,
+        ty: IntType,
+        lhs: IntValue<'ctx>,
+        rhs: IntValue<'ctx>,
+        runtime: bool,
+    ) -> Result<(), CompileError> {
+        let lhs_constant = lhs.get_sign_extended_constant().map(i128::from);
+        let rhs_constant = rhs.get_sign_extended_constant().map(i128::from);
+        match (lhs_constant, rhs_constant) {
+            (Some(lhs), Some(-1)) if lhs == ty.min() => Err(CompileError::DivisionOverflow(ty)),
+            (Some(_), Some(_)) => Ok(()),
+            (_, Some(rhs)) if rhs != -1 => Ok(()),
+            (Some(lhs), None) if lhs != ty.min() => Ok(()),
+            _ if !runtime => Ok(()),
+            _ => {
+                let int_type = lhs.get_type();
+                let min = int_type.const_int(ty.min() as u64, true);
+                let is_min =
+                    self.builder
+                        .build_int_compare(IntPredicate::EQ, lhs, min, "is_min")?;
+                let is_minus_one = self.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    rhs,
+                    int_type.const_all_ones(),
+                    "is_minus_one",
+                )?;
+                let overflows = self.builder.build_and(is_min, is_minus_one, "overflows")?;
+                self.build_runtime_check(overflows, "div_overflow")
+            }
+        }
+    }
+
+    /// Checks that `amount` is less than the width of `ty`. Negative amounts are also out of
+    /// range, and being compared as unsigned, they always are.
+    fn check_shift_amount(
+        &mut self,
+        ty: IntType,
+        amount: Integer<'ctx>,
+    ) -> Result<(), CompileError> {
+        let constant = if amount.ty.signed {
+            amount.value.get_sign_extended_constant().map(i128::from)
+        } else {
+            amount.value.get_zero_extended_constant().map(i128::from)
+        };
+        match constant {
+            Some(value) if (0..i128::from(ty.bits)).contains(&value) => Ok(()),
+            Some(value) => Err(CompileError::ShiftOverflow { amount: value, ty }),
+            None => {
+                let bits = amount.value.get_type().const_int(u64::from(ty.bits), false);
+                let too_large = self.builder.build_int_compare(
+                    IntPredicate::UGE,
+                    amount.value,
+                    bits,
+                    "shift_too_large",
+                )?;
+                self.build_runtime_check(too_large, "shift_overflow")
+            }
+        }
+    }
+
+    /// Branches to the panic handler if `failed` is true, then continues in a new block.
+    fn build_runtime_check(
+        &mut self,
+        failed: IntValue<'ctx>,
+        name: &str,
+    ) -> Result<(), CompileError> {
+        let function = self
+            .current_function
+            .unwrap_or_else(|| unreachable!("Can't codegen runtime check without a function."));
+        let fail = self.context.append_basic_block(function, name);
+        let ok = self
+            .context
+            .append_basic_block(function, &format!("{name}_ok"));
+        self.builder.build_conditional_branch(failed, fail, ok)?;
+
+        self.builder.position_at_end(fail);
+        let handler = self.get_panic_handler();
+        self.builder.build_call(handler, &[], "")?;
+        self.builder.build_unreachable()?;
+
+        self.builder.position_at_end(ok);
+        Ok(())
+    }
+
+    fn get_panic_handler(&self) -> FunctionValue<'ctx> {
+        let Some(name) = &self.panic_handler else {
+            return Intrinsic::find("llvm.trap")
+                .and_then(|trap| trap.get_declaration(self.module, &[]))
+                .expect("LLVM should provide llvm.trap");
+        };
+        if let Some(handler) = self.module.get_function(name) {
+            return handler;
+        }
+        let handler =
+            self.module
+                .add_function(name, self.context.void_type().fn_type(&[], false), None);
+        let noreturn = Attribute::get_named_enum_kind_id("noreturn");
+        handler.add_attribute(
+            AttributeLoc::Function,
+            self.context.create_enum_attribute(noreturn, 0),
+        );
+        handler
+    }
+
+    fn compile_call(
+        &mut self,
+        call: &Call,
+        expected: Option<IntType>,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let function = match call.function.as_ref() {
+            Expression::Variable(function) => function,
+            Expression::Path(path)
+                if &*path.ty == intrinsics::MODULE && !self.enums.contains_key(&path.ty) =>
+            {
+                return self.compile_intrinsic(path, &call.arguments);
+            }
+            Expression::Path(path) => return self.compile_variant(path, &call.arguments),
+            _ => return Err(CompileError::Unsupported("calls to function values")),
+        };
+        let operator = match function.as_ref() {
+            "unchecked_div" => Operator::Div,
+            "unchecked_rem" => Operator::Rem,
+            "read_volatile" | "write_volatile" => {
+                return self.compile_volatile(function, &call.arguments);
+            }
+            "take_peripherals" if !self.peripherals.is_empty() => {
+                return self.compile_take_peripherals(function, &call.arguments);
+            }
+            _ => return self.compile_function_call(function, &call.arguments),
+        };
+        let [lhs, rhs] = call.arguments.as_slice() else {
+            return Err(CompileError::WrongArgumentCount {
+                function: function.clone(),
+                expected: 2,
+                found: call.arguments.len(),
+            });
+        };
+        // Skipping the runtime check doesn't make a constant zero divisor acceptable
+        self.needs_divisor_check(operator, rhs)?;
+        let (lhs, rhs) = self.compile_operands(operator, lhs, rhs, expected)?;
+        self.build_operation(operator, lhs, rhs, false)
+            .map(|value| Some(value.into()))
+    }
+
+    /// Compiles a call of a function of the `intrinsics` module, which is a single instruction.
+    /// Fixed instructions are inline assembly, while the bit operations are LLVM intrinsics, which
+    /// the optimizer understands.
+    fn compile_intrinsic(
+        &mut self,
+        path: &Path,
+        arguments: &[Expression],
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let intrinsic = intrinsics::Intrinsic::from_name(&path.name)
+            .ok_or_else(|| CompileError::UnknownFunction(path.to_string().into()))?;
+        if !intrinsic.is_available(self.target) {
+            return Err(CompileError::UnavailableIntrinsic {
+                intrinsic,
+                target: self.target,
+            });
+        }
+        let expected = intrinsic.parameter_count();
+        if arguments.len() != expected {
+            return Err(CompileError::WrongArgumentCount {
+                function: path.to_string().into(),
+                expected,
+                found: arguments.len(),
+            });
+        }
+        if let Some(assembly) = intrinsic.assembly() {
+            let constraints = if intrinsic.orders_memory() {
+                "~{memory}"
+            } else {
+                ""
+            };
+            self.build_inline_asm(assembly, constraints, &[], false)?;
+            return Ok(None);
+        }
+        if intrinsic == intrinsics::Intrinsic::Bkpt {
+            let number = self
+                .fold_constant(&arguments[0])?
+                .ok_or_else(|| CompileError::NonConstantArgument(path.to_string().into()))?;
+            let ty = IntType::new(8, false);
+            if !ty.contains(number) {
+                return Err(CompileError::LiteralOutOfRange { value: number, ty });
+            }
+            self.build_inline_asm(&format!("bkpt #{number}"), "", &[], false)?;
+            return Ok(None);
+        }
 
-pub struct Compiler<'a, 'ctx> {
-    pub context: &'ctx Context,
-    pub builder: &'a Builder<'ctx>,
-    pub module: &'a LLVMModule<'ctx>,
+        let ty = IntType::new(32, false);
+        let operand = self.compile_integer_operand(&arguments[0], Some(ty))?;
+        self.expect_type(&ty.into(), &operand.ty.into())?;
+        let name = intrinsic
+            .llvm_intrinsic()
+            .expect("the other intrinsics are bit operations");
+        let mut llvm_arguments: Vec<BasicMetadataValueEnum> = vec![operand.value.into()];
+        if intrinsic == intrinsics::Intrinsic::Clz {
+            // Zero isn't poison: it has 32 leading zeros
+            llvm_arguments.push(self.context.bool_type().const_zero().into());
+        }
+        let call =
+            self.builder
+                .build_call(self.i32_intrinsic(name), &llvm_arguments, intrinsic.name())?;
+        let value = call
+            .try_as_basic_value()
+            .left()
+            .expect("bit operations return a value")
+            .into_int_value();
+        Ok(Some(Integer { value, ty }.into()))
+    }
 
-    current_function: Option<FunctionValue<'ctx>>,
-    loop_headers: Vec<BasicBlock<'ctx>>,
-    loop_exits: Vec<BasicBlock<'ctx>>,
-    named_values: HashMap<Rc<str>, NamedValue<'ctx>>,
-}
+    /// Reads memory through a raw pointer with `read_volatile(p)`, or writes it with
+    /// `write_volatile(p, value)`. The optimizer can neither remove volatile accesses nor reorder
+    /// them with each other, which is what memory-mapped registers need.
+    fn compile_volatile(
+        &mut self,
+        function: &Rc<str>,
+        arguments: &[Expression],
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let write = &**function == "write_volatile";
+        if !self.is_unsafe {
+            return Err(CompileError::RequiresUnsafe(if write {
+                "call to `write_volatile`"
+            } else {
+                "call to `read_volatile`"
+            }));
+        }
+        let expected = if write { 2 } else { 1 };
+        if arguments.len() != expected {
+            return Err(CompileError::WrongArgumentCount {
+                function: function.clone(),
+                expected,
+                found: arguments.len(),
+            });
+        }
+        let pointer = self.compile_expression(&arguments[0], None)?;
+        let pointer = pointer.ok_or(CompileError::NotDereferenceable(Type::Unit))?;
+        let (Type::Pointer { target, mutable } | Type::Reference { target, mutable }) = &pointer.ty
+        else {
+            return Err(CompileError::NotDereferenceable(pointer.ty));
+        };
+        let place = Place {
+            pointer: pointer.value.into_pointer_value(),
+            ty: (**target).clone(),
+            align: target.align(self.pointer_size()),
+            root: function.clone(),
+            is_mutable: *mutable,
+            owner: Owner::Outside,
+        };
+        if !write {
+            return self.build_volatile_load(&place).map(Some);
+        }
+        if !place.is_mutable {
+            return Err(CompileError::TypeMismatch {
+                expected: Type::Pointer {
+                    target: target.clone(),
+                    mutable: true,
+                },
+                found: pointer.ty,
+            });
+        }
+        let Some(value) = self.compile_expression(&arguments[1], Some(&place.ty))? else {
+            return Err(CompileError::TypeMismatch {
+                expected: place.ty,
+                found: Type::Unit,
+            });
+        };
+        let value = self.coerce(value, &place.ty)?;
+        self.build_volatile_store(&place, value.value)?;
+        Ok(None)
+    }
 
-impl<'a, 'ctx> Compiler<'a, 'ctx> {
-    /// Compiles the specified `Function` in the given `Context` and using the specified `Builder` and `Module`.
-    pub fn new(
-        context: &'ctx Context,
-        builder: &'a Builder<'ctx>,
-        module: &'a LLVMModule<'ctx>,
-    ) -> Self {
-        Self {
-            context,
-            builder,
-            module,
-            current_function: None,
-            loop_headers: Vec::new(),
-            loop_exits: Vec::new(),
-            named_values: HashMap::new(),
+    /// Takes ownership of every peripheral with `take_peripherals()`, which gives a `Peripherals`
+    /// struct with a field for each. There must be only one call, outside of any loop, and a
+    /// runtime check catches it running twice because the function it is in is called again.
+    fn compile_take_peripherals(
+        &mut self,
+        function: &Rc<str>,
+        arguments: &[Expression],
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        if !arguments.is_empty() {
+            return Err(CompileError::WrongArgumentCount {
+                function: function.clone(),
+                expected: 0,
+                found: arguments.len(),
+            });
+        }
+        if self.peripherals_taken || !self.loop_headers.is_empty() {
+            return Err(CompileError::PeripheralsAlreadyTaken);
         }
+        self.peripherals_taken = true;
+        let flag_type = self.context.bool_type();
+        let flag = self.module.add_global(flag_type, None, "peripherals_taken");
+        flag.set_linkage(Linkage::Internal);
+        flag.set_initializer(&flag_type.const_zero());
+        let taken = self
+            .builder
+            .build_load(flag_type, flag.as_pointer_value(), "taken")?;
+        self.build_runtime_check(taken.into_int_value(), "peripherals_taken")?;
+        self.builder
+            .build_store(flag.as_pointer_value(), flag_type.const_all_ones())?;
+        let (ty, llvm_type) = self.structs[PERIPHERALS].clone();
+        Ok(Some(Value {
+            value: llvm_type.const_zero().into(),
+            ty: Type::Struct(ty),
+        }))
     }
 
-    pub fn compile_module(&mut self, module: &Module) -> Result<(), BuilderError> {
-        println!("Compiling");
-        println!("{:#?}", module.definitions);
-        for definition in &module.definitions {
-            match definition {
-                crate::ast::Definition::Function(function) => self.compile_function(function)?,
-                crate::ast::Definition::Constant(constant) => self.compile_constant(constant)?,
-            }
+    /// Calls a function defined in the module. Arguments and return values are passed by value.
+    fn compile_function_call(
+        &mut self,
+        function: &Rc<str>,
+        arguments: &[Expression],
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let (func, signature) = self
+            .functions
+            .get(function)
+            .cloned()
+            .ok_or_else(|| CompileError::UnknownFunction(function.clone()))?;
+        if signature.is_extern && !self.is_unsafe {
+            return Err(CompileError::RequiresUnsafe("call to an `extern` function"));
         }
-        Ok(())
+        if arguments.len() != signature.parameters.len() {
+            return Err(CompileError::WrongArgumentCount {
+                function: function.clone(),
+                expected: signature.parameters.len(),
+                found: arguments.len(),
+            });
+        }
+        let mut values: Vec<BasicMetadataValueEnum> = Vec::new();
+        for (argument, ty) in arguments.iter().zip(&signature.parameters) {
+            let Some(value) = self.compile_expression(argument, Some(ty))? else {
+                return Err(CompileError::TypeMismatch {
+                    expected: ty.clone(),
+                    found: Type::Unit,
+                });
+            };
+            let value = self.coerce(value, ty)?;
+            values.push(value.value.into());
+        }
+        let result = self.builder.build_call(func, &values, "call")?;
+        Ok(result.try_as_basic_value().left().map(|value| Value {
+            value,
+            ty: signature.return_type,
+        }))
     }
 
-    fn compile_function(&mut self, function: &Function) -> Result<(), BuilderError> {
-        let function_type = self.context.i32_type().fn_type(&[], false);
-        let func = self
-            .module
-            .add_function(&function.name, function_type, None);
-        let entry = self.context.append_basic_block(func, "entry");
-        self.builder.position_at_end(entry);
-        self.current_function = Some(func);
-        let body = self.compile_expression(&function.body)?;
-        if let Some(body) = body {
-            self.builder.build_return(Some(&body))?;
-        } else {
-            // TODO: when actual function types are implemented, this case should return void (unit)
-            self.builder
-                .build_return(Some(&self.context.i32_type().const_int(0, false)))?;
+    /// Calls a method on a place: one of a register, or `len`.
+    fn compile_method(
+        &mut self,
+        method: &Field,
+        arguments: &[Expression],
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        if let Some(register) = self.find_register(&method.base)? {
+            return self.compile_register_method(&register, method, arguments);
         }
-        self.current_function = None;
+        let base = self.indexed_place(&method.base, Access::Read)?;
+        self.compile_len(base, method, arguments)
+    }
 
-        if func.verify(true) {
-            return Ok(());
+    /// Calls a method of the array or slice at `base`. The only one so far is `len`, the number of
+    /// elements.
+    fn compile_len(
+        &mut self,
+        base: Place<'ctx>,
+        method: &Field,
+        arguments: &[Expression],
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let unknown = || CompileError::UnknownMethod {
+            ty: base.ty.clone(),
+            method: method.name.clone(),
+        };
+        if &*method.name != "len" {
+            return Err(unknown());
         }
-        println!("{func:#?}");
-        panic!("Function verification failed");
+        let elements = self.elements(&base)?.ok_or_else(unknown)?;
+        if !arguments.is_empty() {
+            return Err(CompileError::WrongArgumentCount {
+                function: method.name.clone(),
+                expected: 0,
+                found: arguments.len(),
+            });
+        }
+        let length = match elements.length {
+            Length::Known(length) => self.int_type(IntType::INDEX).const_int(length, false),
+            Length::Runtime(length) => length,
+        };
+        Ok(Some(Value {
+            value: length.into(),
+            ty: IntType::INDEX.into(),
+        }))
     }
 
-    fn compile_constant(&mut self, constant: &Variable) -> Result<(), BuilderError> {
-        let name = constant.spec.name.clone();
-        let value = self.compile_expression(&constant.value)?;
-        let const_type = self.context.i32_type();
-        let constant = self
-            .module
-            .add_global(const_type, None, &constant.spec.name);
-        constant.set_constant(true);
-        if let Some(value) = value {
-            constant.set_initializer(&value);
+    /// Finds the register or bitfield that an expression such as `gpio.OUT` or `p.GPIO.OUT.PIN21`
+    /// names, if it names one.
+    fn find_register(
+        &mut self,
+        expression: &Expression,
+    ) -> Result<Option<RegisterPath>, CompileError> {
+        // The fields are followed from the innermost out: a field of a peripheral is a register,
+        // and a field of a register a bitfield
+        let mut fields = Vec::new();
+        let mut base = expression;
+        while let Expression::Field(field) = base {
+            fields.push(field);
+            base = &field.base;
         }
-        self.named_values.insert(name, NamedValue::Constant);
-        Ok(())
+        let mut found: Option<RegisterPath> = None;
+        for field in fields.into_iter().rev() {
+            if let Some(register) = found {
+                if register.field.is_some() {
+                    return Err(CompileError::UnknownField {
+                        ty: register.register.ty.into(),
+                        field: field.name.clone(),
+                    });
+                }
+                let bitfield = register.register.field(&field.name).cloned();
+                let bitfield = bitfield.ok_or_else(|| CompileError::UnknownBitfield {
+                    register: register.name.clone(),
+                    field: field.name.clone(),
+                })?;
+                found = Some(RegisterPath {
+                    name: format!("{}.{}", register.name, field.name).into(),
+                    field: Some(bitfield),
+                    ..register
+                });
+                continue;
+            }
+            let Some(peripheral) = self.find_peripheral(&field.base)? else {
+                continue;
+            };
+            let register =
+                peripheral
+                    .register(&field.name)
+                    .ok_or_else(|| CompileError::UnknownRegister {
+                        peripheral: peripheral.name.clone(),
+                        register: field.name.clone(),
+                    })?;
+            found = Some(RegisterPath {
+                name: format!("{}.{}", peripheral.name, register.name).into(),
+                address: peripheral.address + register.offset,
+                register: register.clone(),
+                field: None,
+            });
+        }
+        Ok(found)
     }
 
-    fn compile_expression(
+    /// Finds the peripheral that an expression such as `gpio` or `p.GPIO` refers to, if it refers
+    /// to one, checking that it is owned and hasn't been moved. Only variables and their fields
+    /// are looked at, so that nothing with side effects is compiled twice.
+    fn find_peripheral(
         &mut self,
         expression: &Expression,
-    ) -> Result<Option<IntValue<'ctx>>, BuilderError> {
+    ) -> Result<Option<Rc<PeripheralType>>, CompileError> {
         match expression {
-            Expression::Integer(x) => Ok(Some(self.context.i32_type().const_int(*x, false))),
-            Expression::Variable(name) => self.compile_variable_access(name),
-            Expression::VariableAssignment(assign) => self.compile_variable_assign(assign),
-            Expression::BinOp(op) => self.compile_binop(op),
-            Expression::Block(block) => self.compile_block(block),
-            Expression::Loop(body) => self.compile_loop(None, None, None, body),
-            f @ Expression::Break | f @ Expression::Continue => self.compile_loop_flow(f),
+            // A variable can shadow a peripheral
+            Expression::Variable(name) if !self.named_values.contains_key(name) => {
+                let Some(peripheral) = self.peripherals.get(name).cloned() else {
+                    return Ok(None);
+                };
+                // Whoever owns the peripheral expects to be the only one using it
+                if !self.is_unsafe {
+                    return Err(CompileError::RequiresUnsafe(
+                        "access to a peripheral by name",
+                    ));
+                }
+                Ok(Some(peripheral))
+            }
+            expression if Self::is_path(expression) => {
+                let place = self.compile_place(expression, Access::Read)?;
+                match self.auto_deref(place)?.ty {
+                    Type::Peripheral(peripheral) => Ok(Some(peripheral)),
+                    _ => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether an expression is a variable, or a field of one or what it refers to.
+    fn is_path(expression: &Expression) -> bool {
+        let mut expression = expression;
+        loop {
+            match expression {
+                Expression::Variable(_) => return true,
+                Expression::Field(Field { base, .. }) | Expression::Deref(base) => {
+                    expression = base;
+                }
+                _ => return false,
+            }
         }
     }
 
-    fn compile_variable_access(
+    /// Reads or writes a register with `read()` and `write(value)`, or a bitfield of one with
+    /// `read()`, `write(value)` and `modify(value)`. Writing a bitfield writes zero to the rest of
+    /// the register, while modifying it reads the register first to keep the rest as it is.
+    fn compile_register_method(
         &mut self,
-        name: &Rc<str>,
-    ) -> Result<Option<IntValue<'ctx>>, BuilderError> {
-        match self.named_values.get(name) {
-            Some(NamedValue::Constant) => {
-                let c = self
-                    .module
-                    .get_global(name)
-                    .unwrap_or_else(|| panic!("Constant {name} not found"));
-                self.builder
-                    .build_load(self.context.i32_type(), c.as_pointer_value(), "loadconst")
-                    .map(BasicValueEnum::into_int_value)
-                    .map(Some)
+        path: &RegisterPath,
+        method: &Field,
+        arguments: &[Expression],
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let ty = path.register.ty;
+        let (reads, writes) = match (&*method.name, &path.field) {
+            ("read", _) => (true, false),
+            ("write", _) => (false, true),
+            ("modify", Some(_)) => (true, true),
+            _ => {
+                return Err(CompileError::UnknownMethod {
+                    ty: ty.into(),
+                    method: method.name.clone(),
+                });
+            }
+        };
+        if reads && !path.register.access.can_read() {
+            return Err(CompileError::RegisterNotReadable(path.name.clone()));
+        }
+        if writes && !path.register.access.can_write() {
+            return Err(CompileError::RegisterNotWritable(path.name.clone()));
+        }
+        let expected = usize::from(writes);
+        if arguments.len() != expected {
+            return Err(CompileError::WrongArgumentCount {
+                function: method.name.clone(),
+                expected,
+                found: arguments.len(),
+            });
+        }
+        let pointer_type = self.context.ptr_type(AddressSpace::default());
+        let place = Place {
+            pointer: self
+                .int_type(IntType::INDEX)
+                .const_int(path.address, false)
+                .const_to_pointer(pointer_type),
+            ty: ty.into(),
+            align: ty.size(),
+            root: path.name.clone(),
+            is_mutable: true,
+            owner: Owner::Outside,
+        };
+        let current = if reads {
+            Some(self.build_volatile_load(&place)?.value.into_int_value())
+        } else {
+            None
+        };
+        let Some(field) = &path.field else {
+            if let Some(value) = current {
+                return Ok(Some(Integer { value, ty }.into()));
             }
-            Some(NamedValue::Variable(_, ptr)) => self
+            let value = self.compile_integer_operand(&arguments[0], Some(ty))?;
+            self.expect_int_type(ty, value.ty)?;
+            self.build_volatile_store(&place, value.value)?;
+            return Ok(None);
+        };
+        let int_type = self.int_type(ty);
+        let mask = int_type.const_int(field.mask(), false);
+        let offset = int_type.const_int(field.offset.into(), false);
+        let Some(value) = arguments.first() else {
+            let current = current.expect("reading a bitfield reads its register");
+            let bits = self.builder.build_and(current, mask, "bits")?;
+            let value = self
                 .builder
-                .build_load(self.context.i32_type(), *ptr, &format!("load_{name}"))
-                .map(BasicValueEnum::into_int_value)
-                .map(Some),
-            None => panic!("Variable {name} not found"),
-        }
-    }
-
-    fn compile_variable_assign(
-        &mut self,
-        assign: &VariableAssignment,
-    ) -> Result<Option<IntValue<'ctx>>, BuilderError> {
-        let named_value = self.named_values.get(&assign.name).cloned();
-        match named_value {
-            Some(NamedValue::Constant) => panic!("Can't reassign to constant"),
-            Some(NamedValue::Variable(spec, ptr)) => {
-                assert!(spec.is_mutable, "Variable {} is not mutable", spec.name);
-                let value = self.compile_expression(&assign.value)?;
-                if let Some(value) = value {
-                    self.builder.build_store(ptr, value)?;
-                }
-                Ok(value)
-            }
-            None => panic!("Variable {} not found", assign.name),
-        }
-    }
-
-    fn compile_binop(&mut self, op: &BinOp) -> Result<Option<IntValue<'ctx>>, BuilderError> {
-        match op {
-            BinOp::Add(lhs, rhs) => {
-                let lhs = self
-                    .compile_expression(lhs)?
-                    .expect("Cannot add unit to number");
-                let rhs = self
-                    .compile_expression(rhs)?
-                    .expect("Cannot add number to unit");
-                self.builder.build_int_add(lhs, rhs, "addtmp")
-            }
-            BinOp::Sub(lhs, rhs) => {
-                let lhs = self
-                    .compile_expression(lhs)?
-                    .expect("Cannot add unit to number");
-                let rhs = self
-                    .compile_expression(rhs)?
-                    .expect("Cannot add number to unit");
-                self.builder.build_int_sub(lhs, rhs, "subtmp")
-            }
-            BinOp::Mul(lhs, rhs) => {
-                let lhs = self
-                    .compile_expression(lhs)?
-                    .expect("Cannot add unit to number");
-                let rhs = self
-                    .compile_expression(rhs)?
-                    .expect("Cannot add number to unit");
-                self.builder.build_int_mul(lhs, rhs, "multmp")
-            }
-            BinOp::Div(lhs, rhs) => {
-                let lhs = self
-                    .compile_expression(lhs)?
-                    .expect("Cannot add unit to number");
-                let rhs = self
-                    .compile_expression(rhs)?
-                    .expect("Cannot add number to unit");
-                self.builder.build_int_signed_div(lhs, rhs, "divtmp")
-            }
-        }
-        .map(Some)
-    }
-
-    fn compile_block(&mut self, block: &Block) -> Result<Option<IntValue<'ctx>>, BuilderError> {
+                .build_right_shift(bits, offset, false, "field")?;
+            return Ok(Some(Integer { value, ty }.into()));
+        };
+        let fits = |constant: &i128| (0..=i128::from(field.max())).contains(constant);
+        if let Some(constant) = self
+            .fold_constant(value)?
+            .filter(|constant| !fits(constant))
+        {
+            return Err(CompileError::LiteralOutOfRange {
+                value: constant,
+                ty: IntType::new(field.width, false),
+            });
+        }
+        let value = self.compile_integer_operand(value, Some(ty))?;
+        self.expect_int_type(ty, value.ty)?;
+        let shifted = self
+            .builder
+            .build_left_shift(value.value, offset, "shifted")?;
+        let mut bits = self.builder.build_and(shifted, mask, "bits")?;
+        if let Some(current) = current {
+            let kept = self.builder.build_and(current, mask.const_not(), "kept")?;
+            bits = self.builder.build_or(kept, bits, "modified")?;
+        }
+        self.build_volatile_store(&place, bits)?;
+        Ok(None)
+    }
+
+    /// Builds a value of an enum variant, such as `State::Idle` or `Command::Write(1, 2)`.
+    fn compile_variant(
+        &mut self,
+        path: &Path,
+        arguments: &[Expression],
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let (ty, index) = self.find_variant(path)?;
+        let variant = &ty.variants[index];
+        if arguments.len() != variant.payload.fields.len() {
+            return Err(CompileError::WrongArgumentCount {
+                function: path.to_string().into(),
+                expected: variant.payload.fields.len(),
+                found: arguments.len(),
+            });
+        }
+        // The variants' fields overlap, so the value is built in memory
+        let enum_type = Type::Enum(ty.clone());
+        let place = Place {
+            pointer: self.build_alloca(&enum_type, &path.name)?,
+            ty: enum_type,
+            align: ty.align,
+            root: path.name.clone(),
+            is_mutable: true,
+            owner: Owner::Temporary,
+        };
+        let discriminant = variant.discriminant;
+        let tag = self
+            .int_type(ty.tag)
+            .const_int(discriminant as u64, discriminant < 0);
+        self.build_store(&self.tag_place(&place, &ty), tag)?;
+        for (field_index, argument) in arguments.iter().enumerate() {
+            let field_place = self.variant_field_place(&place, &ty, index, field_index)?;
+            let Some(value) = self.compile_expression(argument, Some(&field_place.ty))? else {
+                return Err(CompileError::TypeMismatch {
+                    expected: field_place.ty,
+                    found: Type::Unit,
+                });
+            };
+            let value = self.coerce(value, &field_place.ty)?;
+            self.build_store(&field_place, value.value)?;
+        }
+        self.build_load(&place).map(Some)
+    }
+
+    fn find_variant(&self, path: &Path) -> Result<(Rc<EnumType>, usize), CompileError> {
+        let (ty, _) = self
+            .enums
+            .get(&path.ty)
+            .ok_or_else(|| CompileError::UnknownType(path.ty.clone()))?;
+        let (index, _) = ty
+            .variant(&path.name)
+            .ok_or_else(|| CompileError::UnknownVariant {
+                ty: path.ty.clone(),
+                variant: path.name.clone(),
+            })?;
+        Ok((ty.clone(), index))
+    }
+
+    /// The place of the tag of the enum at `base`.
+    fn tag_place(&self, base: &Place<'ctx>, ty: &EnumType) -> Place<'ctx> {
+        // The tag is the first member, so it starts at the same address as the enum
+        Place {
+            ty: ty.tag.into(),
+            ..base.clone()
+        }
+    }
+
+    /// Compiles a `match`, checking that its arms cover every value of the scrutinee.
+    ///
+    /// Matches on integers and enum tags become a `switch` when every pattern is a single value or a wildcard.
+    /// Otherwise, the arms' patterns are tested in order.
+    fn compile_match(
+        &mut self,
+        match_: &Match,
+        expected: Option<&Type>,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        // The scrutinee is kept in memory, so that patterns can look at the fields of enums
+        let place = if match_.scrutinee.is_place() {
+            self.compile_place(&match_.scrutinee, Access::Read)?
+        } else {
+            let Some(value) = self.compile_expression(&match_.scrutinee, None)? else {
+                return Err(CompileError::Unsupported("matches on unit"));
+            };
+            self.build_temporary(value, "scrutinee")?
+        };
+
+        let patterns = match_
+            .arms
+            .iter()
+            .map(|arm| self.lower_pattern(&arm.pattern, &place.ty))
+            .collect::<Result<Vec<_>, _>>()?;
+        let check = patterns::check_match(&patterns, &place.ty);
+        if let Some(missing) = check.missing {
+            return Err(CompileError::NonExhaustive(patterns::describe(
+                &missing, &place.ty,
+            )));
+        }
+        for index in check.unreachable {
+            let pattern = patterns::describe(&patterns[index], &place.ty);
+            self.warnings.push(Warning::UnreachablePattern(pattern));
+        }
+
+        let function = self
+            .current_function
+            .unwrap_or_else(|| unreachable!("Can't codegen match without a function."));
+        let arm_blocks: Vec<_> = match_
+            .arms
+            .iter()
+            .map(|_| self.context.append_basic_block(function, "match_arm"))
+            .collect();
+        let end = self.context.append_basic_block(function, "match_end");
+        if !self.build_match_switch(&place, &patterns, &arm_blocks)? {
+            self.build_match_tests(&place, &patterns, &arm_blocks)?;
+        }
+
+        // Every arm that finishes must give the same type, or all give unit
+        let mut result: Option<Option<Type>> = None;
+        let mut incoming = Vec::new();
+        // Only one arm runs, so each is checked against the borrows and moves from before the
+        // match, and those of all of them are kept for after it
+        let borrows = self.borrows.len();
+        let mut arm_borrows = Vec::new();
+        let moves = self.moves.clone();
+        let mut arm_moves: Vec<Move> = Vec::new();
+        for (arm, block) in match_.arms.iter().zip(&arm_blocks) {
+            self.builder.position_at_end(*block);
+            self.moves = moves.clone();
+            if Self::has_bindings(&arm.pattern) {
+                self.move_out(&match_.scrutinee, &place)?;
+            }
+            let scope = self.start_scope();
+            self.bind_pattern(&arm.pattern, &place)?;
+            let hint = expected
+                .or(result.as_ref().and_then(Option::as_ref))
+                .cloned();
+            let value = self.compile_expression(&arm.body, hint.as_ref())?;
+            self.end_scope(scope, value.as_ref().map(|value| &value.ty))?;
+            arm_borrows.extend(self.borrows.drain(borrows..));
+            for moved in std::mem::take(&mut self.moves) {
+                if !arm_moves.contains(&moved) {
+                    arm_moves.push(moved);
+                }
+            }
+            if self.is_terminated() {
+                continue;
+            }
+            if self.is_unreachable(*block) {
+                self.builder.build_unreachable()?;
+                continue;
+            }
+            let ty = value.as_ref().map(|value| value.ty.clone());
+            match &result {
+                Some(expected) if *expected != ty => {
+                    return Err(CompileError::TypeMismatch {
+                        expected: expected.clone().unwrap_or(Type::Unit),
+                        found: ty.unwrap_or(Type::Unit),
+                    });
+                }
+                Some(_) => {}
+                None => result = Some(ty),
+            }
+            if let Some(value) = value {
+                let block = self.builder.get_insert_block().expect("arm is in a block");
+                incoming.push((value.value, block));
+            }
+            self.builder.build_unconditional_branch(end)?;
+        }
+
+        self.borrows.extend(arm_borrows);
+        self.moves = arm_moves;
+        self.builder.position_at_end(end);
+        let Some(Some(ty)) = result else {
+            return Ok(None);
+        };
+        let basic_type = self.basic_type(&ty).expect("values are never unit");
+        let phi = self.builder.build_phi(basic_type, "match")?;
+        for (value, block) in &incoming {
+            phi.add_incoming(&[(value, *block)]);
+        }
+        Ok(Some(Value {
+            value: phi.as_basic_value(),
+            ty,
+        }))
+    }
+
+    /// Branches to the arm for the scrutinee with a `switch`, if every pattern is a single value,
+    /// a variant with wildcard fields, or a wildcard. Returns whether it could.
+    fn build_match_switch(
+        &mut self,
+        place: &Place<'ctx>,
+        patterns: &[Pat],
+        arm_blocks: &[BasicBlock<'ctx>],
+    ) -> Result<bool, CompileError> {
+        let (value, int_type) = match &place.ty {
+            Type::Int(ty) => (self.build_load(place)?, *ty),
+            Type::Enum(ty) => (self.build_load(&self.tag_place(place, ty))?, ty.tag),
+            _ => return Ok(false),
+        };
+        let alternatives = |pattern: &Pat| match pattern {
+            Pat::Or(alternatives) => alternatives.clone(),
+            pattern => vec![pattern.clone()],
+        };
+        let is_case = |pattern: &Pat| match pattern {
+            Pat::Wild => true,
+            Pat::Range(start, end) => start == end,
+            Pat::Variant(_, fields) => fields.iter().all(|field| *field == Pat::Wild),
+            Pat::Or(_) => false,
+        };
+        if !patterns
+            .iter()
+            .all(|pattern| alternatives(pattern).iter().all(is_case))
+        {
+            return Ok(false);
+        }
+
+        let mut cases = Vec::new();
+        let mut default = None;
+        for (pattern, block) in patterns.iter().zip(arm_blocks) {
+            for alternative in alternatives(pattern) {
+                let case = match (alternative, &place.ty) {
+                    (Pat::Range(value, _), _) => value,
+                    (Pat::Variant(index, _), Type::Enum(ty)) => ty.variants[index].discriminant,
+                    _ => {
+                        default.get_or_insert(*block);
+                        continue;
+                    }
+                };
+                // Values matched by an earlier arm are already covered
+                if default.is_none() && !cases.iter().any(|(value, _)| *value == case) {
+                    cases.push((case, *block));
+                }
+            }
+        }
+        let cases: Vec<_> = cases
+            .into_iter()
+            .map(|(value, block)| {
+                let value = self.int_type(int_type).const_int(value as u64, value < 0);
+                (value, block)
+            })
+            .collect();
+        let function = self
+            .current_function
+            .unwrap_or_else(|| unreachable!("Can't codegen match without a function."));
+        // The arms are exhaustive, so without a wildcard every other value is impossible
+        let default = match default {
+            Some(block) => block,
+            None => self
+                .context
+                .append_basic_block(function, "match_unreachable"),
+        };
+        self.builder
+            .build_switch(value.value.into_int_value(), default, &cases)?;
+        if default.get_terminator().is_none() && !arm_blocks.contains(&default) {
+            self.builder.position_at_end(default);
+            self.builder.build_unreachable()?;
+        }
+        Ok(true)
+    }
+
+    /// Tests the arms' patterns one after another, branching to the first that matches.
+    fn build_match_tests(
+        &mut self,
+        place: &Place<'ctx>,
+        patterns: &[Pat],
+        arm_blocks: &[BasicBlock<'ctx>],
+    ) -> Result<(), CompileError> {
+        let function = self
+            .current_function
+            .unwrap_or_else(|| unreachable!("Can't codegen match without a function."));
+        for (pattern, block) in patterns.iter().zip(arm_blocks) {
+            let matched = self.build_pattern_test(pattern, place)?;
+            let next = self.context.append_basic_block(function, "match_next");
+            self.builder
+                .build_conditional_branch(matched, *block, next)?;
+            self.builder.position_at_end(next);
+        }
+        // The arms are exhaustive, so one of them always matches
+        self.builder.build_unreachable()?;
+        Ok(())
+    }
+
+    /// Builds a check of whether the value at `place` matches `pattern`.
+    fn build_pattern_test(
+        &mut self,
+        pattern: &Pat,
+        place: &Place<'ctx>,
+    ) -> Result<IntValue<'ctx>, CompileError> {
+        let bool_type = self.context.bool_type();
+        match (pattern, &place.ty) {
+            (Pat::Wild, _) => Ok(bool_type.const_all_ones()),
+            (Pat::Range(start, end), Type::Int(ty)) => {
+                let value = self.build_load(place)?.value.into_int_value();
+                let constant = |value: i128| self.int_type(*ty).const_int(value as u64, value < 0);
+                if start == end {
+                    return Ok(self.builder.build_int_compare(
+                        IntPredicate::EQ,
+                        value,
+                        constant(*start),
+                        "is_value",
+                    )?);
+                }
+                let (at_least, at_most) = if ty.signed {
+                    (IntPredicate::SGE, IntPredicate::SLE)
+                } else {
+                    (IntPredicate::UGE, IntPredicate::ULE)
+                };
+                let above =
+                    self.builder
+                        .build_int_compare(at_least, value, constant(*start), "above")?;
+                let below =
+                    self.builder
+                        .build_int_compare(at_most, value, constant(*end), "below")?;
+                Ok(self.builder.build_and(above, below, "in_range")?)
+            }
+            (Pat::Variant(index, fields), Type::Enum(ty)) => {
+                let tag = self.build_load(&self.tag_place(place, ty))?;
+                let discriminant = ty.variants[*index].discriminant;
+                let discriminant = self
+                    .int_type(ty.tag)
+                    .const_int(discriminant as u64, discriminant < 0);
+                let mut matched = self.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    tag.value.into_int_value(),
+                    discriminant,
+                    "is_variant",
+                )?;
+                for (field_index, field) in fields.iter().enumerate() {
+                    if *field == Pat::Wild {
+                        continue;
+                    }
+                    let field_place = self.variant_field_place(place, ty, *index, field_index)?;
+                    let field_matched = self.build_pattern_test(field, &field_place)?;
+                    matched = self
+                        .builder
+                        .build_and(matched, field_matched, "fields_match")?;
+                }
+                Ok(matched)
+            }
+            (Pat::Or(alternatives), _) => {
+                let mut matched = bool_type.const_zero();
+                for alternative in alternatives {
+                    let alternative_matched = self.build_pattern_test(alternative, place)?;
+                    matched = self
+                        .builder
+                        .build_or(matched, alternative_matched, "any_match")?;
+                }
+                Ok(matched)
+            }
+            _ => unreachable!("patterns are checked against the type when lowered"),
+        }
+    }
+
+    /// Brings the names bound by `pattern` into scope, as copies of parts of the value at `place`.
+    fn bind_pattern(&mut self, pattern: &Pattern, place: &Place<'ctx>) -> Result<(), CompileError> {
+        match (pattern, &place.ty) {
+            (Pattern::Binding(name), ty) => {
+                let value = self.build_load(place)?;
+                let pointer = self.build_alloca(ty, name)?;
+                let binding = Place {
+                    pointer,
+                    ty: ty.clone(),
+                    align: ty.align(self.pointer_size()),
+                    root: name.clone(),
+                    is_mutable: false,
+                    owner: Owner::Local(pointer),
+                };
+                self.build_store(&binding, value.value)?;
+                self.declare_local(VariableSpec::new(name.clone()), binding.ty, pointer);
+            }
+            (Pattern::Variant(path, fields), Type::Enum(ty)) => {
+                let (index, _) = ty.variant(&path.name).expect("the variant was checked");
+                for (field_index, field) in fields.iter().enumerate() {
+                    let field_place = self.variant_field_place(place, ty, index, field_index)?;
+                    self.bind_pattern(field, &field_place)?;
+                }
+            }
+            // Or-patterns can't bind names, and no other pattern can
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Checks `pattern` against the type of the scrutinee, and reduces it to the values it matches.
+    fn lower_pattern(&self, pattern: &Pattern, ty: &Type) -> Result<Pat, CompileError> {
+        match pattern {
+            Pattern::Wildcard | Pattern::Binding(_) => Ok(Pat::Wild),
+            Pattern::Literal(literal) => {
+                let value = self.pattern_value(literal, ty)?;
+                Ok(Pat::Range(value, value))
+            }
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let start = self.pattern_value(start, ty)?;
+                let end = self.pattern_value(end, ty)? - i128::from(!inclusive);
+                if start > end {
+                    return Err(CompileError::EmptyRange);
+                }
+                Ok(Pat::Range(start, end))
+            }
+            Pattern::Variant(path, fields) => {
+                let (enum_type, index) = self.find_variant(path)?;
+                let found = Type::Enum(enum_type.clone());
+                self.expect_type(ty, &found)?;
+                let payload = &enum_type.variants[index].payload;
+                if fields.len() != payload.fields.len() {
+                    return Err(CompileError::WrongArgumentCount {
+                        function: path.to_string().into(),
+                        expected: payload.fields.len(),
+                        found: fields.len(),
+                    });
+                }
+                let fields = fields
+                    .iter()
+                    .zip(&payload.fields)
+                    .map(|(pattern, field)| self.lower_pattern(pattern, &field.ty))
+                    .collect::<Result<_, _>>()?;
+                Ok(Pat::Variant(index, fields))
+            }
+            Pattern::Or(alternatives) => {
+                if alternatives.iter().any(Self::has_bindings) {
+                    return Err(CompileError::Unsupported("bindings in or-patterns"));
+                }
+                let alternatives = alternatives
+                    .iter()
+                    .map(|pattern| self.lower_pattern(pattern, ty))
+                    .collect::<Result<_, _>>()?;
+                Ok(Pat::Or(alternatives))
+            }
+        }
+    }
+
+    fn has_bindings(pattern: &Pattern) -> bool {
+        match pattern {
+            Pattern::Binding(_) => true,
+            Pattern::Variant(_, fields) => fields.iter().any(Self::has_bindings),
+            Pattern::Or(alternatives) => alternatives.iter().any(Self::has_bindings),
+            _ => false,
+        }
+    }
+
+    /// The value of a literal in a pattern, which must fit the type of the scrutinee.
+    fn pattern_value(&self, literal: &Expression, ty: &Type) -> Result<i128, CompileError> {
+        let literal_type = Self::literal_type(literal);
+        let Type::Int(int_type) = ty else {
+            return Err(CompileError::TypeMismatch {
+                expected: ty.clone(),
+                found: literal_type.unwrap_or(IntType::DEFAULT).into(),
+            });
+        };
+        if let Some(literal_type) = literal_type {
+            self.expect_int_type(*int_type, literal_type)?;
+        }
+        let value = self
+            .fold_constant(literal)?
+            .expect("literals are known at compile time");
+        if !int_type.contains(value) {
+            return Err(CompileError::LiteralOutOfRange {
+                value,
+                ty: *int_type,
+            });
+        }
+        Ok(value)
+    }
+
+    /// The type of a literal, if it has one of its own rather than taking one from its context.
+    fn literal_type(literal: &Expression) -> Option<IntType> {
+        match literal {
+            Expression::TypedInteger(_, ty) => Some(*ty),
+            Expression::Char(_) => Some(IntType::new(32, false)),
+            Expression::Byte(_) => Some(IntType::new(8, false)),
+            Expression::Unary(_, operand) => Self::literal_type(operand),
+            _ => None,
+        }
+    }
+
+    fn compile_block(
+        &mut self,
+        block: &Block,
+        expected: Option<&Type>,
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
+        let scope = self.start_scope();
         if let Some(err) = block
             .body
             .iter()
@@ -206,47 +5349,74 @@ impl<'a, 'ctx> Compiler<'a, 'ctx> {
         {
             return Err(err);
         }
-        if let Some(last) = &block.last {
-            self.compile_expression(last)
+        let value = if let Some(last) = &block.last {
+            self.compile_expression(last, expected)?
         } else {
-            Ok(None)
-        }
+            None
+        };
+        self.end_scope(scope, value.as_ref().map(|value| &value.ty))?;
+        Ok(value)
     }
 
-    fn compile_statement(&mut self, stmt: &Statement) -> Result<(), BuilderError> {
+    fn compile_statement(&mut self, stmt: &Statement) -> Result<(), CompileError> {
+        let borrows = self.borrows.len();
         match stmt {
-            Statement::Expression(expr) => self.compile_expression(expr).map(|_| ()),
-            Statement::VariableDefinition(def) => self.compile_variable_definition(def),
+            Statement::Expression(expr) => self.compile_expression(expr, None).map(|_| ())?,
+            Statement::VariableDefinition(def) => self.compile_variable_definition(def)?,
         }
+        // Borrows that weren't stored in a variable end with the statement
+        self.end_temporary_borrows(borrows);
+        Ok(())
     }
 
     fn compile_variable_definition(
         &mut self,
         def: &VariableDefinition,
-    ) -> Result<(), BuilderError> {
-        let var = self
-            .builder
-            .build_alloca(self.context.i32_type(), &def.spec.name)?;
-        if let Some(value) = &def.value {
-            let value = self.compile_expression(value)?;
-            if let Some(value) = value {
-                self.builder.build_store(var, value)?;
+    ) -> Result<(), CompileError> {
+        let declared = match &def.spec.ty {
+            Some(ty) => Some(self.resolve_type(ty)?),
+            None => None,
+        };
+        let borrows = self.borrows.len();
+        let value = match &def.value {
+            Some(value) => self.compile_expression(value, declared.as_ref())?,
+            None => None,
+        };
+        let value = match (&declared, value) {
+            (Some(ty), Some(value)) => Some(self.coerce(value, ty)?),
+            (_, value) => value,
+        };
+        let ty = match (declared, &value) {
+            (Some(ty), _) => ty,
+            (None, Some(value)) => value.ty.clone(),
+            (None, None) => IntType::DEFAULT.into(),
+        };
+        let var = self.build_alloca(&ty, &def.spec.name)?;
+        if let Some(value) = value {
+            let place = Place {
+                pointer: var,
+                ty: ty.clone(),
+                align: ty.align(self.pointer_size()),
+                root: def.spec.name.clone(),
+                is_mutable: def.spec.is_mutable,
+                owner: Owner::Local(var),
+            };
+            if ty.contains_references() {
+                self.hold_borrows(borrows, &place)?;
             }
+            self.build_store(&place, value.value)?;
         }
-        self.named_values.insert(
-            def.spec.name.clone(),
-            NamedValue::Variable(def.spec.clone(), var),
-        );
+        self.declare_local(def.spec.clone(), ty, var);
         Ok(())
     }
 
     fn compile_loop(
         &mut self,
-        initializer: Option<&Statement>,
-        condition: Option<&Expression>,
-        update: Option<&Statement>,
+        _initializer: Option<&Statement>,
+        _condition: Option<&Expression>,
+        _update: Option<&Statement>,
         body: &Expression,
-    ) -> Result<Option<IntValue<'ctx>>, BuilderError> {
+    ) -> Result<Option<Value<'ctx>>, CompileError> {
         let header = self.context.append_basic_block(
             self.current_function
                 .unwrap_or_else(|| unreachable!("Can't codegen loop without a function.")),
@@ -263,9 +5433,17 @@ impl<'a, 'ctx> Compiler<'a, 'ctx> {
 
         self.builder.build_unconditional_branch(header)?;
         self.builder.position_at_end(header);
-        self.compile_expression(body)?;
+        let moves = self.moves.clone();
+        let locals = self.locals.len();
+        self.compile_expression(body, None)?;
+        self.check_loop_moves(&moves, locals)?;
 
-        if header.get_terminator().is_none() {
+        // The body may have branched away from the header, so close off whichever block it ended in
+        if self
+            .builder
+            .get_insert_block()
+            .is_some_and(|block| block.get_terminator().is_none())
+        {
             self.builder.build_unconditional_branch(header)?;
         }
 
@@ -275,10 +5453,135 @@ impl<'a, 'ctx> Compiler<'a, 'ctx> {
         Ok(None)
     }
 
-    fn compile_loop_flow(
-        &mut self,
-        typ: &Expression,
-    ) -> Result<Option<IntValue<'ctx>>, BuilderError> {
+    /// Compiles a `for` loop, which counts through a range of integers, or through the indices of
+    /// an array or slice to give a copy of each element in turn.
+    fn compile_for(&mut self, for_: &For) -> Result<Option<Value<'ctx>>, CompileError> {
+        let function = self
+            .current_function
+            .unwrap_or_else(|| unreachable!("Can't codegen loop without a function."));
+        let (start, end, inclusive, elements) = match for_.iterable.as_ref() {
+            Expression::Range(range) => {
+                let (Some(start), Some(end)) = (&range.start, &range.end) else {
+                    return Err(CompileError::Unsupported(
+                        "`for` loops over unbounded ranges",
+                    ));
+                };
+                let (start, end) = self.compile_operands(Operator::Sub, start, end, None)?;
+                self.expect_int_type(start.ty, end.ty)?;
+                (start, end, range.inclusive, None)
+            }
+            iterable => {
+                let base = self.indexed_place(iterable, Access::Read)?;
+                let elements = self
+                    .elements(&base)?
+                    .ok_or_else(|| CompileError::NotIterable(base.ty.clone()))?;
+                // Elements that can't be copied are moved out, which moves the whole array
+                if !elements.first.ty.is_copy() {
+                    self.move_out(iterable, &base)?;
+                }
+                let index_type = self.int_type(IntType::INDEX);
+                let length = match elements.length {
+                    Length::Known(length) => index_type.const_int(length, false),
+                    Length::Runtime(length) => length,
+                };
+                let start = Integer {
+                    value: index_type.const_zero(),
+                    ty: IntType::INDEX,
+                };
+                let end = Integer {
+                    value: length,
+                    ty: IntType::INDEX,
+                };
+                (start, end, false, Some(elements.first))
+            }
+        };
+        let ty = start.ty;
+        let counter = self.build_temporary(start.into(), "counter")?;
+        let binding_type = match &elements {
+            Some(first) => first.ty.clone(),
+            None => ty.into(),
+        };
+        let pointer = self.build_alloca(&binding_type, &for_.binding)?;
+        let binding = Place {
+            pointer,
+            align: binding_type.align(self.pointer_size()),
+            ty: binding_type,
+            root: for_.binding.clone(),
+            is_mutable: false,
+            owner: Owner::Local(pointer),
+        };
+
+        let header = self.context.append_basic_block(function, "for_header");
+        let body = self.context.append_basic_block(function, "for_body");
+        let next = self.context.append_basic_block(function, "for_next");
+        let exit = self.context.append_basic_block(function, "for_exit");
+        self.builder.build_unconditional_branch(header)?;
+
+        self.builder.position_at_end(header);
+        let index = self.build_load(&counter)?.value.into_int_value();
+        let predicate = match (inclusive, ty.signed) {
+            (false, false) => IntPredicate::ULT,
+            (false, true) => IntPredicate::SLT,
+            (true, false) => IntPredicate::ULE,
+            (true, true) => IntPredicate::SLE,
+        };
+        let in_range = self
+            .builder
+            .build_int_compare(predicate, index, end.value, "in_range")?;
+        self.builder
+            .build_conditional_branch(in_range, body, exit)?;
+
+        self.builder.position_at_end(body);
+        let value = match &elements {
+            Some(first) => {
+                let offset =
+                    self.builder
+                        .build_int_z_extend(index, self.context.i64_type(), "offset")?;
+                let element = self.element_at(first, offset, None)?;
+                self.build_load(&element)?.value
+            }
+            None => index.into(),
+        };
+        self.build_store(&binding, value)?;
+        let moves = self.moves.clone();
+        let locals = self.locals.len();
+        let scope = self.start_scope();
+        self.declare_local(VariableSpec::new(for_.binding.clone()), binding.ty, pointer);
+        // `continue` goes on to the next value, rather than straight back to the check
+        self.loop_headers.push(next);
+        self.loop_exits.push(exit);
+        self.compile_expression(&for_.body, None)?;
+        self.loop_headers.pop();
+        self.loop_exits.pop();
+        self.end_scope(scope, None)?;
+        self.check_loop_moves(&moves, locals)?;
+        if !self.is_terminated() {
+            self.builder.build_unconditional_branch(next)?;
+        }
+
+        self.builder.position_at_end(next);
+        let index = self.build_load(&counter)?.value.into_int_value();
+        if inclusive {
+            // The last value of an inclusive range may be the maximum of its type, so the loop
+            // stops there instead of incrementing past it
+            let increment = self.context.append_basic_block(function, "for_increment");
+            let at_end =
+                self.builder
+                    .build_int_compare(IntPredicate::EQ, index, end.value, "at_end")?;
+            self.builder
+                .build_conditional_branch(at_end, exit, increment)?;
+            self.builder.position_at_end(increment);
+        }
+        let one = self.int_type(ty).const_int(1, false);
+        let incremented = self.builder.build_int_add(index, one, "incremented")?;
+        self.build_store(&counter, incremented)?;
+        self.builder.build_unconditional_branch(header)?;
+
+        self.builder.position_at_end(exit);
+        Ok(None)
+    }
+
+    fn compile_loop_flow(&mut self, typ: &Expression) -> Result<Option<Value<'ctx>>, CompileError> {
         let target = match typ {
             Expression::Break => self.loop_exits.last().expect("`break` used outside of a loop"),
             Expression::Continue => self.loop_headers.last().expect("`continue` used outside of a loop"),
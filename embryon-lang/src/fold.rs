@@ -0,0 +1,210 @@
+use crate::ast::{BinOp, Call, Expression, Operator, UnaryOperator};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FoldError {
+    DivisionByZero,
+    Overflow,
+}
+
+/// Evaluates `expression` at compile time, if it only depends on constants.
+///
+/// Values are computed without wrapping; it is up to the caller to check that the result fits its type.
+/// `lookup` resolves the name of a constant to its folded value.
+/// Returns `Ok(None)` when the value is only known at runtime.
+pub fn fold(
+    expression: &Expression,
+    lookup: &impl Fn(&str) -> Option<i128>,
+) -> Result<Option<i128>, FoldError> {
+    match expression {
+        Expression::Integer(x) | Expression::TypedInteger(x, _) => Ok(Some(*x as i128)),
+        Expression::Char(c) => Ok(Some(*c as i128)),
+        Expression::Byte(b) => Ok(Some(*b as i128)),
+        Expression::Variable(name) => Ok(lookup(name)),
+        Expression::Unary(UnaryOperator::Neg, operand) => match fold(operand, lookup)? {
+            Some(value) => value.checked_neg().map(Some).ok_or(FoldError::Overflow),
+            None => Ok(None),
+        },
+        Expression::BinOp(op) => fold_chain(op, lookup),
+        Expression::Block(block) if block.body.is_empty() => match &block.last {
+            Some(last) => fold(last, lookup),
+            None => Ok(None),
+        },
+        Expression::Call(call) => fold_call(call, lookup),
+        _ => Ok(None),
+    }
+}
+
+/// Folds a chain of binary operators such as `1 + 2 - 3`, whose left operands nest, in a loop from
+/// the innermost operator out.
+fn fold_chain(
+    op: &BinOp,
+    lookup: &impl Fn(&str) -> Option<i128>,
+) -> Result<Option<i128>, FoldError> {
+    let mut operations = Vec::new();
+    let mut op = op;
+    let first = loop {
+        let (operator, lhs, rhs) = op.parts();
+        operations.push((operator, rhs));
+        match lhs {
+            Expression::BinOp(lhs) => op = lhs,
+            lhs => break lhs,
+        }
+    };
+    let mut value = fold(first, lookup)?;
+    for (operator, rhs) in operations.into_iter().rev() {
+        value = apply(operator, value, fold(rhs, lookup)?)?;
+    }
+    Ok(value)
+}
+
+fn fold_operation(
+    operator: Operator,
+    lhs: &Expression,
+    rhs: &Expression,
+    lookup: &impl Fn(&str) -> Option<i128>,
+) -> Result<Option<i128>, FoldError> {
+    let lhs = fold(lhs, lookup)?;
+    let rhs = fold(rhs, lookup)?;
+    apply(operator, lhs, rhs)
+}
+
+/// Applies `operator` to operands that have been folded, if they are both known.
+fn apply(
+    operator: Operator,
+    lhs: Option<i128>,
+    rhs: Option<i128>,
+) -> Result<Option<i128>, FoldError> {
+    // A constant divisor of zero is always an error, even when the dividend is only known at runtime
+    if matches!(operator, Operator::Div | Operator::Rem) && rhs == Some(0) {
+        return Err(FoldError::DivisionByZero);
+    }
+    let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+        return Ok(None);
+    };
+    let shift = || u32::try_from(rhs).map_err(|_| FoldError::Overflow);
+    let value = match operator {
+        Operator::Add => lhs.checked_add(rhs),
+        Operator::Sub => lhs.checked_sub(rhs),
+        Operator::Mul => lhs.checked_mul(rhs),
+        Operator::Div => lhs.checked_div(rhs),
+        Operator::Rem => lhs.checked_rem(rhs),
+        Operator::BitAnd => Some(lhs & rhs),
+        Operator::BitOr => Some(lhs | rhs),
+        Operator::BitXor => Some(lhs ^ rhs),
+        Operator::Shl => {
+            let shift = shift()?;
+            lhs.checked_shl(shift).filter(|value| value >> shift == lhs)
+        }
+        Operator::Shr => lhs.checked_shr(shift()?),
+    };
+    value.map(Some).ok_or(FoldError::Overflow)
+}
+
+fn fold_call(
+    call: &Call,
+    lookup: &impl Fn(&str) -> Option<i128>,
+) -> Result<Option<i128>, FoldError> {
+    let Expression::Variable(function) = call.function.as_ref() else {
+        return Ok(None);
+    };
+    match (function.as_ref(), call.arguments.as_slice()) {
+        ("unchecked_div", [lhs, rhs]) => fold_operation(Operator::Div, lhs, rhs, lookup),
+        ("unchecked_rem", [lhs, rhs]) => fold_operation(Operator::Rem, lhs, rhs, lookup),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{BinOp, Expression};
+    use crate::fold::{FoldError, fold};
+
+    fn parse(source: &str) -> Expression {
+        let mut tokens = crate::lexer::TokenStream::new(source.into());
+        Expression::parse(&mut tokens).unwrap()
+    }
+
+    fn no_constants(_: &str) -> Option<i128> {
+        None
+    }
+
+    #[test]
+    fn fold_arithmetic() {
+        assert_eq!(fold(&parse("1 + 2 * 3"), &no_constants), Ok(Some(7)));
+        assert_eq!(fold(&parse("(10 - 4) / 2"), &no_constants), Ok(Some(3)));
+        assert_eq!(fold(&parse("{ 5 }"), &no_constants), Ok(Some(5)));
+        assert_eq!(fold(&parse("17 % 5"), &no_constants), Ok(Some(2)));
+        assert_eq!(
+            fold(&parse("1 << 4 | 3 & 1 ^ 8"), &no_constants),
+            Ok(Some(25))
+        );
+        assert_eq!(fold(&parse("256 >> 4"), &no_constants), Ok(Some(16)));
+        assert_eq!(fold(&parse("-3 * -(2 - 4)"), &no_constants), Ok(Some(-6)));
+    }
+
+    #[test]
+    fn fold_overflow() {
+        assert_eq!(
+            fold(&parse("1 << 200"), &no_constants),
+            Err(FoldError::Overflow)
+        );
+        assert_eq!(
+            fold(
+                &parse("18446744073709551615 * 18446744073709551615 * 2"),
+                &no_constants
+            ),
+            Err(FoldError::Overflow)
+        );
+    }
+
+    #[test]
+    fn fold_constants() {
+        let lookup = |name: &str| (name == "X").then_some(4);
+        assert_eq!(fold(&parse("X * 2"), &lookup), Ok(Some(8)));
+        assert_eq!(fold(&parse("Y * 2"), &lookup), Ok(None));
+    }
+
+    #[test]
+    fn fold_long_chains() {
+        let source = format!("1{}", " + 1".repeat(100_000));
+        assert_eq!(fold(&parse(&source), &no_constants), Ok(Some(100_001)));
+    }
+
+    #[test]
+    fn fold_division_by_zero() {
+        assert_eq!(
+            fold(&parse("1 / 0"), &no_constants),
+            Err(FoldError::DivisionByZero)
+        );
+        assert_eq!(
+            fold(&parse("1 / (2 - 2)"), &no_constants),
+            Err(FoldError::DivisionByZero)
+        );
+        assert_eq!(
+            fold(&parse("unchecked_div(1, 0)"), &no_constants),
+            Err(FoldError::DivisionByZero)
+        );
+        assert_eq!(
+            fold(&parse("5 % 0"), &no_constants),
+            Err(FoldError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn fold_division_by_zero_with_runtime_dividend() {
+        assert_eq!(
+            fold(&parse("x / 0"), &no_constants),
+            Err(FoldError::DivisionByZero)
+        );
+        assert_eq!(
+            fold(
+                &Expression::BinOp(BinOp::Div(
+                    Box::new(Expression::Variable("x".into())),
+                    Box::new(Expression::Integer(2)),
+                )),
+                &no_constants
+            ),
+            Ok(None)
+        );
+    }
+}
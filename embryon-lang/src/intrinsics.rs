@@ -0,0 +1,165 @@
+//! The built-in `intrinsics` module, whose functions are single instructions of the Cortex-M cores,
+//! such as `intrinsics::wfi()`.
+
+use crate::target::Target;
+use std::fmt;
+
+/// The name of the module that intrinsics are called through.
+pub const MODULE: &str = "intrinsics";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intrinsic {
+    /// Sleeps until an interrupt.
+    Wfi,
+    /// Sleeps until an event or interrupt.
+    Wfe,
+    /// Sends an event, waking cores waiting in `wfe`.
+    Sev,
+    Nop,
+    /// Stops in the debugger, with a number from 0 to 255 that it can show.
+    Bkpt,
+    /// Waits for every memory access before it to finish.
+    Dsb,
+    /// Keeps memory accesses from being reordered across it.
+    Dmb,
+    /// Fetches the instructions after it again, so that they see changes to the core's settings.
+    Isb,
+    /// Disables interrupts.
+    Cpsid,
+    /// Enables interrupts.
+    Cpsie,
+    /// Counts the leading zero bits of a `u32`.
+    Clz,
+    /// Reverses the bits of a `u32`.
+    Rbit,
+    /// Reverses the bytes of a `u32`.
+    Rev,
+}
+
+impl Intrinsic {
+    pub const ALL: [Self; 13] = [
+        Self::Wfi,
+        Self::Wfe,
+        Self::Sev,
+        Self::Nop,
+        Self::Bkpt,
+        Self::Dsb,
+        Self::Dmb,
+        Self::Isb,
+        Self::Cpsid,
+        Self::Cpsie,
+        Self::Clz,
+        Self::Rbit,
+        Self::Rev,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|intrinsic| intrinsic.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Wfi => "wfi",
+            Self::Wfe => "wfe",
+            Self::Sev => "sev",
+            Self::Nop => "nop",
+            Self::Bkpt => "bkpt",
+            Self::Dsb => "dsb",
+            Self::Dmb => "dmb",
+            Self::Isb => "isb",
+            Self::Cpsid => "cpsid",
+            Self::Cpsie => "cpsie",
+            Self::Clz => "clz",
+            Self::Rbit => "rbit",
+            Self::Rev => "rev",
+        }
+    }
+
+    /// Whether `target` has the instruction. ARMv6-M has no `clz` or `rbit`, while the bit
+    /// operations also work on the host, where LLVM picks its own instructions for them.
+    pub fn is_available(&self, target: Target) -> bool {
+        match self {
+            Self::Clz | Self::Rbit => !target.is_armv6m(),
+            Self::Rev => true,
+            _ => target.is_arm(),
+        }
+    }
+
+    /// The number of arguments: the number of `bkpt`, or the operand of a bit operation.
+    pub fn parameter_count(&self) -> usize {
+        match self {
+            Self::Bkpt | Self::Clz | Self::Rbit | Self::Rev => 1,
+            _ => 0,
+        }
+    }
+
+    /// The instruction of an intrinsic that is always the same, `None` for `bkpt` and the bit
+    /// operations.
+    pub fn assembly(&self) -> Option<&'static str> {
+        match self {
+            Self::Wfi => Some("wfi"),
+            Self::Wfe => Some("wfe"),
+            Self::Sev => Some("sev"),
+            Self::Nop => Some("nop"),
+            Self::Dsb => Some("dsb sy"),
+            Self::Dmb => Some("dmb sy"),
+            Self::Isb => Some("isb sy"),
+            Self::Cpsid => Some("cpsid i"),
+            Self::Cpsie => Some("cpsie i"),
+            Self::Bkpt | Self::Clz | Self::Rbit | Self::Rev => None,
+        }
+    }
+
+    /// Whether memory accesses must not be moved across the instruction, as for the barriers
+    /// and the critical sections that `cpsid` and `cpsie` delimit.
+    pub fn orders_memory(&self) -> bool {
+        matches!(
+            self,
+            Self::Dsb | Self::Dmb | Self::Isb | Self::Cpsid | Self::Cpsie
+        )
+    }
+
+    /// The LLVM intrinsic that a bit operation is lowered to.
+    pub fn llvm_intrinsic(&self) -> Option<&'static str> {
+        match self {
+            Self::Clz => Some("llvm.ctlz"),
+            Self::Rbit => Some("llvm.bitreverse"),
+            Self::Rev => Some("llvm.bswap"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Intrinsic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{MODULE}::{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intrinsics::Intrinsic;
+    use crate::target::Target;
+
+    #[test]
+    fn intrinsic_names() {
+        for intrinsic in Intrinsic::ALL {
+            assert_eq!(Intrinsic::from_name(intrinsic.name()), Some(intrinsic));
+        }
+        assert_eq!(Intrinsic::from_name("svc"), None);
+        assert_eq!(Intrinsic::Wfi.to_string(), "intrinsics::wfi");
+    }
+
+    #[test]
+    fn intrinsics_on_cores() {
+        assert!(Intrinsic::Clz.is_available(Target::CortexM3));
+        assert!(!Intrinsic::Clz.is_available(Target::CortexM0));
+        assert!(!Intrinsic::Rbit.is_available(Target::CortexM0Plus));
+        assert!(Intrinsic::Rev.is_available(Target::CortexM0));
+        assert!(Intrinsic::Cpsid.is_available(Target::CortexM0));
+        assert!(!Intrinsic::Wfi.is_available(Target::Host));
+        assert!(Intrinsic::Clz.is_available(Target::Host));
+    }
+}
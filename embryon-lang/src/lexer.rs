@@ -1,13 +1,36 @@
 use crate::parse::ParseError;
-use crate::tokens::Token;
+use crate::syntax::{self, Checkpoint, GreenNodeBuilder, GreenToken, SyntaxKind, SyntaxNode};
+use crate::tokens::{LexError, LexErrorKind, Position, Token};
+use crate::types::IntType;
 use std::collections::VecDeque;
+use std::ops::Range;
 use std::rc::Rc;
+use unicode_ident::{is_xid_continue, is_xid_start};
 
 #[must_use = "A TokenStream does nothing unless iterated over or queried"]
 pub struct TokenStream {
     source: Rc<str>,
-    head: VecDeque<Token>,
+    head: VecDeque<Lexeme>,
     cursor: usize,
+    tree: Option<TreeRecorder>,
+    /// How many expressions the parser is currently inside of.
+    nesting: usize,
+    nesting_limit: usize,
+    /// Whether `Name {` can start a struct literal, which it can't in the scrutinee of a `match`.
+    struct_literals: bool,
+}
+
+/// A token with the byte range of its text in the source.
+struct Lexeme {
+    token: Token,
+    span: Range<usize>,
+}
+
+/// Collects the consumed tokens, and the trivia before them, into a syntax tree.
+struct TreeRecorder {
+    builder: GreenNodeBuilder,
+    /// The end of the source text which is already in the tree.
+    recorded: usize,
 }
 
 impl TokenStream {
@@ -16,7 +39,120 @@ impl TokenStream {
             source,
             head: VecDeque::new(),
             cursor: 0,
+            tree: None,
+            nesting: 0,
+            nesting_limit: Self::DEFAULT_NESTING_LIMIT,
+            struct_literals: true,
+        }
+    }
+
+    /// How deeply expressions can be nested by default, which comfortably fits on the stack of
+    /// a thread with the default 2 MiB stack size.
+    pub const DEFAULT_NESTING_LIMIT: usize = 256;
+
+    /// Sets how deeply expressions can be nested before parsing fails, rather than overflowing the stack.
+    pub fn with_nesting_limit(mut self, limit: usize) -> Self {
+        self.nesting_limit = limit;
+        self
+    }
+
+    /// Notes that the parser has started an expression nested in the current one.
+    pub(crate) fn enter_nesting(&mut self) -> Result<(), ParseError> {
+        if self.nesting >= self.nesting_limit {
+            return Err(ParseError::NestingTooDeep(self.nesting_limit));
+        }
+        self.nesting += 1;
+        Ok(())
+    }
+
+    pub(crate) fn exit_nesting(&mut self) {
+        self.nesting -= 1;
+    }
+
+    pub(crate) fn struct_literals_allowed(&self) -> bool {
+        self.struct_literals
+    }
+
+    /// Sets whether struct literals can be parsed, returning the previous setting.
+    pub(crate) fn allow_struct_literals(&mut self, allowed: bool) -> bool {
+        std::mem::replace(&mut self.struct_literals, allowed)
+    }
+
+    /// Records a lossless syntax tree of everything the parser consumes from this stream.
+    pub fn with_syntax_tree(mut self) -> Self {
+        self.tree = Some(TreeRecorder {
+            builder: GreenNodeBuilder::default(),
+            recorded: 0,
+        });
+        self
+    }
+
+    pub(crate) fn start_node(&mut self, kind: SyntaxKind) {
+        self.record_leading_trivia();
+        if let Some(tree) = &mut self.tree {
+            tree.builder.start_node(kind);
+        }
+    }
+
+    pub(crate) fn checkpoint(&mut self) -> Option<Checkpoint> {
+        self.record_leading_trivia();
+        self.tree.as_ref().map(|tree| tree.builder.checkpoint())
+    }
+
+    /// Records the trivia before the next token in the node that is open, so that a node about to
+    /// start begins at its first token rather than at the whitespace before it. The root node has
+    /// nothing before it, so it starts with the trivia at the start of the source.
+    fn record_leading_trivia(&mut self) {
+        if self
+            .tree
+            .as_ref()
+            .is_none_or(|tree| tree.builder.depth() == 0)
+        {
+            return;
         }
+        let start = match self.peek() {
+            Some(_) => self.head[0].span.start,
+            None => self.source.len(),
+        };
+        if let Some(tree) = &mut self.tree {
+            tree.record_trivia(&self.source, start);
+        }
+    }
+
+    pub(crate) fn start_node_at(&mut self, checkpoint: Option<Checkpoint>, kind: SyntaxKind) {
+        if let (Some(tree), Some(checkpoint)) = (&mut self.tree, checkpoint) {
+            tree.builder.start_node_at(checkpoint, kind);
+        }
+    }
+
+    pub(crate) fn finish_node(&mut self) {
+        let Some(depth) = self.tree.as_ref().map(|tree| tree.builder.depth()) else {
+            return;
+        };
+        let at_end = depth == 1 && self.peek().is_none();
+        if let Some(tree) = &mut self.tree {
+            if at_end {
+                // Trailing trivia belongs to the root
+                tree.record_trivia(&self.source, self.source.len());
+            }
+            tree.builder.finish_node();
+        }
+    }
+
+    /// Returns the recorded syntax tree, if [`Self::with_syntax_tree`] was used.
+    ///
+    /// Any input the parser stopped before, for example because of an error, is put in an
+    /// [`SyntaxKind::Error`] node so that the tree still covers the whole source.
+    pub fn finish_syntax_tree(&mut self) -> Option<SyntaxNode> {
+        self.tree.as_ref()?;
+        if self.peek().is_some() {
+            self.start_node(SyntaxKind::Error);
+            while self.next().is_some() {}
+            self.finish_node();
+        }
+        let mut tree = self.tree.take()?;
+        tree.record_trivia(&self.source, self.source.len());
+        Some(SyntaxNode::new_root(tree.builder.finish()))
     }
 
     pub fn peek(&mut self) -> Option<&Token> {
@@ -26,7 +162,7 @@ impl TokenStream {
     pub fn expect(&mut self, token: Token) -> Result<Token, ParseError> {
         match self.next() {
             Some(t) if t == token => Ok(token),
-            Some(t) => Err(ParseError::UnexpectedToken(t)),
+            Some(t) => Err(ParseError::unexpected(t)),
             None => Err(ParseError::UnexpectedEoF),
         }
     }
@@ -34,17 +170,20 @@ impl TokenStream {
     pub fn expect_identifier(&mut self) -> Result<String, ParseError> {
         match self.next() {
             Some(Token::Identifier(name)) => Ok(name.to_string()),
-            Some(token) => Err(ParseError::UnexpectedToken(token)),
+            Some(token) => Err(ParseError::unexpected(token)),
             None => Err(ParseError::UnexpectedEoF),
         }
     }
 
     fn peek_char(&self, lookahead: usize) -> Option<char> {
-        self.source
-            .as_bytes()
-            .get(self.cursor + lookahead)
-            .copied()
-            .map(|c| c as char)
+        self.source[self.cursor..].chars().nth(lookahead)
+    }
+
+    /// Moves the cursor past the next character, returning it.
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek_char(0)?;
+        self.cursor += c.len_utf8();
+        Some(c)
     }
 
     fn skip_whitespace(&mut self) {
@@ -52,70 +191,158 @@ impl TokenStream {
             if !c.is_whitespace() {
                 break;
             }
-            self.cursor += 1;
+            self.bump();
         }
     }
 
     fn skip_until(&mut self, sequence: &str) {
         while self.cursor < self.source.len() && !self.source[self.cursor..].starts_with(sequence) {
-            self.cursor += 1;
+            self.bump();
         }
     }
 
-    fn skip_comment(&mut self) {
-        self.skip_whitespace();
-        if self.peek_char(0) != Some('/') {
-            return;
-        }
-        if self.peek_char(1) == Some('/') {
-            self.skip_until("\n");
+    /// Skips whitespace and comments, stopping at doc comments as they are kept as tokens.
+    fn skip_comment(&mut self) -> Result<(), LexError> {
+        loop {
             self.skip_whitespace();
-            self.skip_comment();
-            return;
+            let rest = &self.source[self.cursor..];
+            if Self::is_doc_comment(rest) {
+                return Ok(());
+            }
+            if rest.starts_with("//") {
+                self.skip_until("\n");
+            } else if rest.starts_with("/*") {
+                self.skip_block_comment()?;
+            } else {
+                return Ok(());
+            }
         }
-        if self.peek_char(1) == Some('*') {
-            self.skip_until("*/");
-            self.cursor += 2;
-            self.skip_whitespace();
-            self.skip_comment();
+    }
+
+    /// Whether `source` starts with a `///` or `//!` doc comment. Four or more slashes are a regular comment.
+    fn is_doc_comment(source: &str) -> bool {
+        (source.starts_with("///") && !source.starts_with("////")) || source.starts_with("//!")
+    }
+
+    /// Skips a block comment, including any block comments nested inside it.
+    fn skip_block_comment(&mut self) -> Result<(), LexError> {
+        let start = self.cursor;
+        let mut depth = 0;
+        loop {
+            let rest = &self.source[self.cursor..];
+            if rest.starts_with("/*") {
+                depth += 1;
+                self.cursor += 2;
+            } else if rest.starts_with("*/") {
+                depth -= 1;
+                self.cursor += 2;
+                if depth == 0 {
+                    return Ok(());
+                }
+            } else if self.bump().is_none() {
+                return Err(self.error(LexErrorKind::UnterminatedComment, start));
+            }
+        }
+    }
+
+    /// Reads a `///` or `//!` doc comment up to the end of the line.
+    fn read_doc_comment(&mut self) -> Token {
+        let inner = self.source[self.cursor..].starts_with("//!");
+        self.cursor += 3;
+        let start = self.cursor;
+        self.skip_until("\n");
+        let text = self.source[start..self.cursor].trim_end_matches('\r');
+        let text: Rc<str> = text.strip_prefix(' ').unwrap_or(text).into();
+        if inner {
+            Token::InnerDocComment(text)
+        } else {
+            Token::DocComment(text)
+        }
+    }
+
+    /// Finds the line and column of the byte at `offset` in the source.
+    fn position(&self, offset: usize) -> Position {
+        let before = &self.source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Position::new(
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+
+    fn error(&self, kind: LexErrorKind, offset: usize) -> LexError {
+        LexError {
+            kind,
+            position: self.position(offset),
         }
     }
 
     fn read_next(&mut self) -> Option<Token> {
-        let h = self.peek_ahead(0).cloned();
-        self.consume();
-        h
+        self.peek_ahead(0)?;
+        let lexeme = self.head.pop_front()?;
+        if let Some(tree) = &mut self.tree {
+            tree.record_trivia(&self.source, lexeme.span.start);
+            let text = self.source[lexeme.span.clone()].into();
+            tree.builder
+                .token(GreenToken::Token(lexeme.token.clone(), text));
+            tree.recorded = lexeme.span.end;
+        }
+        Some(lexeme.token)
     }
 
     pub fn peek_ahead(&mut self, n: usize) -> Option<&Token> {
         if n < self.head.len() {
-            let h = self.head.get(n);
-            return h;
+            return self.head.get(n).map(|lexeme| &lexeme.token);
         }
         if self.cursor >= self.source.len() {
             // There are no more tokens to get
             return None;
         }
-        self.skip_comment();
+        let trivia_start = self.cursor;
+        if let Err(err) = self.skip_comment() {
+            // The unterminated comment becomes the text of the error token
+            self.push_lexeme(Token::Error(err), trivia_start);
+            return self.peek_ahead(n);
+        }
         let c = self.peek_char(0)?;
-        if let Some(token) = Self::from_symbol(c) {
+        let start = self.cursor;
+        if Self::is_doc_comment(&self.source[self.cursor..]) {
+            let token = self.read_doc_comment();
+            self.push_lexeme(token, start);
+        } else if let Some((token, length)) = self.read_compound_symbol() {
+            self.cursor += length;
+            self.push_lexeme(token, start);
+        } else if let Some(token) = Self::from_symbol(c) {
             self.cursor += 1;
-            self.head.push_back(token);
+            self.push_lexeme(token, start);
         } else {
-            let token = match c {
-                '0'..='9' => self.read_number(),
-                _ => self.read_identifier()?,
+            let token = match (c, self.peek_char(1)) {
+                ('0'..='9', _) => self.read_number(),
+                ('\'', _) => self.read_char(false),
+                ('"', _) => self.read_string(false),
+                ('b', Some('\'')) => {
+                    self.bump();
+                    self.read_char(true)
+                }
+                ('b', Some('"')) => {
+                    self.bump();
+                    self.read_string(true)
+                }
+                (c, _) if c == '_' || is_xid_start(c) => self.read_identifier(),
+                ('$', Some(c)) if c == '_' || is_xid_start(c) => self.read_core_register(),
+                _ => self.read_unexpected(),
             };
-            self.head.push_back(token);
+            self.push_lexeme(token, start);
         };
 
         self.peek_ahead(n)
     }
 
-    fn consume(&mut self) {
-        if !self.head.is_empty() {
-            self.head.pop_front();
-        }
+    fn push_lexeme(&mut self, token: Token, start: usize) {
+        self.head.push_back(Lexeme {
+            token,
+            span: start..self.cursor,
+        });
     }
 
     fn from_symbol(symbol: char) -> Option<Token> {
@@ -124,56 +351,307 @@ impl TokenStream {
             ')' => Some(Token::CloseParen),
             '{' => Some(Token::OpenBrace),
             '}' => Some(Token::CloseBrace),
+            '[' => Some(Token::OpenBracket),
+            ']' => Some(Token::CloseBracket),
             ';' => Some(Token::Semi),
+            ',' => Some(Token::Comma),
+            ':' => Some(Token::Colon),
+            '.' => Some(Token::Dot),
+            '#' => Some(Token::Hash),
+            '@' => Some(Token::At),
+            '!' => Some(Token::Bang),
             '+' => Some(Token::Plus),
             '-' => Some(Token::Minus),
             '*' => Some(Token::Star),
             '/' => Some(Token::Slash),
+            '%' => Some(Token::Percent),
+            '&' => Some(Token::Ampersand),
+            '|' => Some(Token::Pipe),
+            '^' => Some(Token::Caret),
             '=' => Some(Token::Equal),
             _ => None,
         }
     }
 
+    /// Reads the longest symbol made up of more than one character at the cursor, if there is one.
+    fn read_compound_symbol(&self) -> Option<(Token, usize)> {
+        (2..=3).rev().find_map(|length| {
+            let symbol = self.source.get(self.cursor..self.cursor + length)?;
+            Self::from_compound_symbol(symbol).map(|token| (token, length))
+        })
+    }
+
+    fn from_compound_symbol(symbol: &str) -> Option<Token> {
+        match symbol {
+            "->" => Some(Token::Arrow),
+            "=>" => Some(Token::FatArrow),
+            "::" => Some(Token::PathSeparator),
+            ".." => Some(Token::DotDot),
+            "..=" => Some(Token::DotDotEqual),
+            "<<" => Some(Token::ShiftLeft),
+            ">>" => Some(Token::ShiftRight),
+            "+=" => Some(Token::PlusEqual),
+            "-=" => Some(Token::MinusEqual),
+            "*=" => Some(Token::StarEqual),
+            "/=" => Some(Token::SlashEqual),
+            "%=" => Some(Token::PercentEqual),
+            "&=" => Some(Token::AmpersandEqual),
+            "|=" => Some(Token::PipeEqual),
+            "^=" => Some(Token::CaretEqual),
+            "<<=" => Some(Token::ShiftLeftEqual),
+            ">>=" => Some(Token::ShiftRightEqual),
+            _ => None,
+        }
+    }
+
+    /// Reads an integer literal, such as `42`, `0x2000_2000` or `0b1010u8`.
     fn read_number(&mut self) -> Token {
         let start = self.cursor;
-        while let Some(c) = self.source.get(self.cursor..=self.cursor) {
-            if c.chars().all(|c| c.is_ascii_digit()) {
+        let radix = match (self.peek_char(0), self.peek_char(1)) {
+            (Some('0'), Some('x')) => 16,
+            (Some('0'), Some('o')) => 8,
+            (Some('0'), Some('b')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.cursor += 2;
+        }
+        let digits_start = self.cursor;
+        while let Some(c) = self.peek_char(0) {
+            if c.is_ascii_alphanumeric() || c == '_' {
                 self.cursor += 1;
             } else {
                 break;
             }
         }
-        let value = self
-            .source
-            .get(start..self.cursor)
-            .unwrap()
-            .parse()
-            .unwrap();
-        Token::Integer(value)
+        let text: Rc<str> = self.source[start..self.cursor].into();
+
+        let literal = &self.source[digits_start..self.cursor];
+        let suffix_start = literal
+            .find(|c: char| c != '_' && !c.is_digit(radix))
+            .unwrap_or(literal.len());
+        let (digits, suffix) = literal.split_at(suffix_start);
+        let digits = digits.replace('_', "");
+        if suffix.starts_with(|c: char| c.is_ascii_digit()) {
+            return Token::Error(self.error(LexErrorKind::InvalidDigit(text), start));
+        }
+        if digits.is_empty() {
+            return Token::Error(self.error(LexErrorKind::MissingDigits(text), start));
+        }
+        let Ok(value) = u64::from_str_radix(&digits, radix) else {
+            return Token::Error(self.error(LexErrorKind::IntegerOutOfRange(text), start));
+        };
+        match suffix {
+            "" => Token::Integer(value),
+            suffix => match IntType::from_name(suffix) {
+                Some(ty) => Token::TypedInteger(value, ty),
+                None => Token::Error(self.error(LexErrorKind::InvalidSuffix(text), start)),
+            },
+        }
     }
 
-    fn read_identifier(&mut self) -> Option<Token> {
+    /// Reads an identifier, as defined by Unicode Standard Annex #31.
+    fn read_identifier(&mut self) -> Token {
         let start = self.cursor;
-        while let Some(c) = self.source.get(self.cursor..=self.cursor) {
-            if c.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
-                self.cursor += 1;
+        while let Some(c) = self.peek_char(0) {
+            if is_xid_continue(c) {
+                self.bump();
             } else {
                 break;
             }
         }
-        let name = self.source.get(start..self.cursor).unwrap().to_string();
-        match name.as_str() {
-            "const" => Some(Token::Const),
-            "let" => Some(Token::Let),
-            "mut" => Some(Token::Mut),
-            "fn" => Some(Token::Fn),
-            "loop" => Some(Token::Loop),
-            "break" => Some(Token::Break),
-            "continue" => Some(Token::Continue),
-            _ if !name.is_empty() => Some(Token::Identifier(Rc::from(name))),
-            _ => None,
+        let name = &self.source[start..self.cursor];
+        keyword(name).unwrap_or_else(|| Token::Identifier(Rc::from(name)))
+    }
+
+    /// Reads a register pseudo-variable such as `$sp`. The cursor should be on the `$`.
+    fn read_core_register(&mut self) -> Token {
+        self.bump();
+        let start = self.cursor;
+        self.read_identifier();
+        Token::CoreRegister(self.source[start..self.cursor].into())
+    }
+
+    /// Reads a character literal such as `'a'` or `'\n'`, or a byte literal if `byte` is set.
+    /// The cursor should be on the opening quote.
+    fn read_char(&mut self, byte: bool) -> Token {
+        let start = self.cursor - usize::from(byte);
+        self.bump();
+        let c = match self.bump() {
+            Some('\\') => self.read_escape(byte),
+            Some('\'') => Err(LexErrorKind::InvalidCharacterLiteral),
+            Some(c) if byte && !c.is_ascii() => Err(LexErrorKind::NonAsciiByte(c)),
+            Some(c) => Ok(c),
+            None => Err(LexErrorKind::UnterminatedCharacter),
+        };
+        let c = match c {
+            Err(
+                kind
+                @ (LexErrorKind::InvalidCharacterLiteral | LexErrorKind::UnterminatedCharacter),
+            ) => Err(kind),
+            c if self.peek_char(0) == Some('\'') => {
+                self.bump();
+                c
+            }
+            c => {
+                // Skip the rest of the literal if it is on this line, so lexing resumes after it
+                let rest = &self.source[self.cursor..];
+                match rest.find(['\'', '\n']) {
+                    Some(end) if rest[end..].starts_with('\'') => {
+                        self.cursor += end + 1;
+                        c.and(Err(LexErrorKind::InvalidCharacterLiteral))
+                    }
+                    _ => Err(LexErrorKind::UnterminatedCharacter),
+                }
+            }
+        };
+        match c {
+            Ok(c) if byte => Token::Byte(c as u8),
+            Ok(c) => Token::Char(c),
+            Err(kind) => Token::Error(self.error(kind, start)),
         }
     }
+
+    /// Reads a string literal such as `"hello\n"`, or a byte string literal if `byte` is set.
+    /// The cursor should be on the opening quote.
+    fn read_string(&mut self, byte: bool) -> Token {
+        let start = self.cursor - usize::from(byte);
+        self.bump();
+        let mut value = String::new();
+        let mut error = None;
+        loop {
+            let escape_start = self.cursor;
+            let c = match self.bump() {
+                Some('"') => break,
+                Some('\\') if self.peek_char(0) == Some('\n') => {
+                    // A backslash at the end of a line skips the line break and any leading whitespace
+                    self.skip_whitespace();
+                    continue;
+                }
+                Some('\\') => self.read_escape(byte),
+                Some(c) if byte && !c.is_ascii() => Err(LexErrorKind::NonAsciiByte(c)),
+                Some(c) => Ok(c),
+                None => return Token::Error(self.error(LexErrorKind::UnterminatedString, start)),
+            };
+            match c {
+                Ok(c) => value.push(c),
+                Err(kind) => {
+                    // Keep going to find the end of the string, but report the first error
+                    error.get_or_insert_with(|| self.error(kind, escape_start));
+                }
+            }
+        }
+        match error {
+            Some(err) => Token::Error(err),
+            // Escapes in byte strings are at most `\xFF`, so each character is a single byte
+            None if byte => Token::ByteString(value.chars().map(|c| c as u8).collect()),
+            None => Token::String(value.into()),
+        }
+    }
+
+    /// Reads the rest of an escape sequence after its backslash, returning the character it stands for.
+    ///
+    /// Byte literals allow `\x` escapes up to `\xFF`, but not `\u{..}` escapes.
+    fn read_escape(&mut self, byte: bool) -> Result<char, LexErrorKind> {
+        let start = self.cursor - 1;
+        let c = match self.bump() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('\'') => '\'',
+            Some('"') => '"',
+            Some('x') => {
+                let value = self
+                    .source
+                    .get(self.cursor..self.cursor + 2)
+                    .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+                match value {
+                    Some(value) if byte || value.is_ascii() => {
+                        self.cursor += 2;
+                        char::from(value)
+                    }
+                    _ => return Err(self.invalid_escape(start)),
+                }
+            }
+            Some('u') if !byte => {
+                let digits = self.source[self.cursor..]
+                    .strip_prefix('{')
+                    .and_then(|rest| rest.split_once('}'))
+                    .map(|(digits, _)| digits)
+                    .filter(|digits| (1..=6).contains(&digits.len()));
+                let value = digits
+                    .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                    .and_then(char::from_u32);
+                match (digits, value) {
+                    (Some(digits), Some(value)) => {
+                        self.cursor += digits.len() + 2;
+                        value
+                    }
+                    _ => return Err(self.invalid_escape(start)),
+                }
+            }
+            _ => return Err(self.invalid_escape(start)),
+        };
+        Ok(c)
+    }
+
+    /// Reports the escape sequence starting at `start`, up to and including the cursor, as invalid.
+    fn invalid_escape(&self, start: usize) -> LexErrorKind {
+        LexErrorKind::InvalidEscape(self.source[start..self.cursor].into())
+    }
+
+    /// Skips over a character that can't start a token, reporting it as an error.
+    fn read_unexpected(&mut self) -> Token {
+        let start = self.cursor;
+        let c = self.source[start..]
+            .chars()
+            .next()
+            .expect("There should be a character at the cursor");
+        self.cursor += c.len_utf8();
+        Token::Error(self.error(LexErrorKind::UnexpectedCharacter(c), start))
+    }
+}
+
+impl TreeRecorder {
+    /// Records the trivia between the last recorded token and `end`.
+    fn record_trivia(&mut self, source: &str, end: usize) {
+        for trivia in syntax::split_trivia(&source[self.recorded..end]) {
+            self.builder.token(trivia);
+        }
+        self.recorded = end;
+    }
+}
+
+/// The keyword `name` is, if it is one rather than an identifier.
+fn keyword(name: &str) -> Option<Token> {
+    Some(match name {
+        "const" => Token::Const,
+        "static" => Token::Static,
+        "let" => Token::Let,
+        "mut" => Token::Mut,
+        "fn" => Token::Fn,
+        "extern" => Token::Extern,
+        "struct" => Token::Struct,
+        "enum" => Token::Enum,
+        "peripheral" => Token::Peripheral,
+        "match" => Token::Match,
+        "unsafe" => Token::Unsafe,
+        "loop" => Token::Loop,
+        "for" => Token::For,
+        "in" => Token::In,
+        "break" => Token::Break,
+        "continue" => Token::Continue,
+        "as" => Token::As,
+        _ => return None,
+    })
+}
+
+/// Whether `name` is a keyword, and so can't be used as an identifier.
+pub fn is_keyword(name: &str) -> bool {
+    keyword(name).is_some()
 }
 
 impl Iterator for TokenStream {
@@ -187,22 +665,73 @@ impl Iterator for TokenStream {
 #[cfg(test)]
 mod tests {
     use crate::lexer::TokenStream;
-    use crate::tokens::Token;
+    use crate::tokens::{LexError, LexErrorKind, Position, Token};
+    use crate::types::IntType;
 
     #[test]
     fn lex_symbols() {
-        let source = "(){}+-*/=;";
+        let source = "(){}[]+-*/ =;,.";
         let mut lex = TokenStream::new(source.into());
         assert_eq!(lex.next(), Some(Token::OpenParen));
         assert_eq!(lex.next(), Some(Token::CloseParen));
         assert_eq!(lex.next(), Some(Token::OpenBrace));
         assert_eq!(lex.next(), Some(Token::CloseBrace));
+        assert_eq!(lex.next(), Some(Token::OpenBracket));
+        assert_eq!(lex.next(), Some(Token::CloseBracket));
         assert_eq!(lex.next(), Some(Token::Plus));
         assert_eq!(lex.next(), Some(Token::Minus));
         assert_eq!(lex.next(), Some(Token::Star));
         assert_eq!(lex.next(), Some(Token::Slash));
         assert_eq!(lex.next(), Some(Token::Equal));
         assert_eq!(lex.next(), Some(Token::Semi));
+        assert_eq!(lex.next(), Some(Token::Comma));
+        assert_eq!(lex.next(), Some(Token::Dot));
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn lex_operators() {
+        let source = "% & | ^ << >> : # @ ! -> => :: .. ..= += -= *= /= %= &= |= ^= <<= >>=";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Percent));
+        assert_eq!(lex.next(), Some(Token::Ampersand));
+        assert_eq!(lex.next(), Some(Token::Pipe));
+        assert_eq!(lex.next(), Some(Token::Caret));
+        assert_eq!(lex.next(), Some(Token::ShiftLeft));
+        assert_eq!(lex.next(), Some(Token::ShiftRight));
+        assert_eq!(lex.next(), Some(Token::Colon));
+        assert_eq!(lex.next(), Some(Token::Hash));
+        assert_eq!(lex.next(), Some(Token::At));
+        assert_eq!(lex.next(), Some(Token::Bang));
+        assert_eq!(lex.next(), Some(Token::Arrow));
+        assert_eq!(lex.next(), Some(Token::FatArrow));
+        assert_eq!(lex.next(), Some(Token::PathSeparator));
+        assert_eq!(lex.next(), Some(Token::DotDot));
+        assert_eq!(lex.next(), Some(Token::DotDotEqual));
+        assert_eq!(lex.next(), Some(Token::PlusEqual));
+        assert_eq!(lex.next(), Some(Token::MinusEqual));
+        assert_eq!(lex.next(), Some(Token::StarEqual));
+        assert_eq!(lex.next(), Some(Token::SlashEqual));
+        assert_eq!(lex.next(), Some(Token::PercentEqual));
+        assert_eq!(lex.next(), Some(Token::AmpersandEqual));
+        assert_eq!(lex.next(), Some(Token::PipeEqual));
+        assert_eq!(lex.next(), Some(Token::CaretEqual));
+        assert_eq!(lex.next(), Some(Token::ShiftLeftEqual));
+        assert_eq!(lex.next(), Some(Token::ShiftRightEqual));
+        assert_eq!(lex.next(), None);
+
+        let source = "x<<=1";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Identifier("x".into())));
+        assert_eq!(lex.next(), Some(Token::ShiftLeftEqual));
+        assert_eq!(lex.next(), Some(Token::Integer(1)));
+        assert_eq!(lex.next(), None);
+
+        let source = "0..=9";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Integer(0)));
+        assert_eq!(lex.next(), Some(Token::DotDotEqual));
+        assert_eq!(lex.next(), Some(Token::Integer(9)));
         assert_eq!(lex.next(), None);
     }
 
@@ -213,6 +742,16 @@ mod tests {
         assert_eq!(lex.next(), Some(Token::Identifier("foobar".into())));
     }
 
+    #[test]
+    fn lex_core_registers() {
+        let source = "$sp $loop $ x";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::CoreRegister("sp".into())));
+        assert_eq!(lex.next(), Some(Token::CoreRegister("loop".into())));
+        assert!(matches!(lex.next(), Some(Token::Error(_))));
+        assert_eq!(lex.next(), Some(Token::Identifier("x".into())));
+    }
+
     #[test]
     fn lex_numbers() {
         let source = "123";
@@ -220,24 +759,236 @@ mod tests {
         assert_eq!(lex.next(), Some(Token::Integer(123)));
     }
 
+    #[test]
+    fn lex_number_radixes() {
+        let source = "0x2000_2000 0b1010_0000 0o17 1_000_000 0xFFFF_FFFF_FFFF_FFFF";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Integer(0x2000_2000)));
+        assert_eq!(lex.next(), Some(Token::Integer(0b1010_0000)));
+        assert_eq!(lex.next(), Some(Token::Integer(0o17)));
+        assert_eq!(lex.next(), Some(Token::Integer(1_000_000)));
+        assert_eq!(lex.next(), Some(Token::Integer(u64::MAX)));
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn lex_number_suffixes() {
+        let source = "10u8 0xFFu32 0b1_i64 7_u16";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(
+            lex.next(),
+            Some(Token::TypedInteger(10, IntType::new(8, false)))
+        );
+        assert_eq!(
+            lex.next(),
+            Some(Token::TypedInteger(0xFF, IntType::new(32, false)))
+        );
+        assert_eq!(
+            lex.next(),
+            Some(Token::TypedInteger(1, IntType::new(64, true)))
+        );
+        assert_eq!(
+            lex.next(),
+            Some(Token::TypedInteger(7, IntType::new(16, false)))
+        );
+        assert_eq!(lex.next(), None);
+    }
+
+    fn error(kind: LexErrorKind, line: usize, column: usize) -> Option<Token> {
+        Some(Token::Error(LexError {
+            kind,
+            position: Position::new(line, column),
+        }))
+    }
+
+    #[test]
+    fn lex_invalid_numbers() {
+        let source = "18446744073709551616 0b102 0x 10u7 12abc";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(
+            lex.next(),
+            error(
+                LexErrorKind::IntegerOutOfRange("18446744073709551616".into()),
+                1,
+                1
+            )
+        );
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::InvalidDigit("0b102".into()), 1, 22)
+        );
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::MissingDigits("0x".into()), 1, 28)
+        );
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::InvalidSuffix("10u7".into()), 1, 31)
+        );
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::InvalidSuffix("12abc".into()), 1, 36)
+        );
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn lex_unexpected_characters() {
+        let source = "x $ y\n  ~`€ z";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Identifier("x".into())));
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::UnexpectedCharacter('$'), 1, 3)
+        );
+        assert_eq!(lex.next(), Some(Token::Identifier("y".into())));
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::UnexpectedCharacter('~'), 2, 3)
+        );
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::UnexpectedCharacter('`'), 2, 4)
+        );
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::UnexpectedCharacter('€'), 2, 5)
+        );
+        assert_eq!(lex.next(), Some(Token::Identifier("z".into())));
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn lex_unterminated_comment() {
+        let source = "x /* this comment\n never ends";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Identifier("x".into())));
+        assert_eq!(lex.next(), error(LexErrorKind::UnterminatedComment, 1, 3));
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn lex_unicode_identifiers() {
+        let source = "größe _x1 日本 café";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Identifier("größe".into())));
+        assert_eq!(lex.next(), Some(Token::Identifier("_x1".into())));
+        assert_eq!(lex.next(), Some(Token::Identifier("日本".into())));
+        assert_eq!(lex.next(), Some(Token::Identifier("café".into())));
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn lex_unicode_comments() {
+        let source = "// ünïcödé ✓\n/* ∀x ∈ ℕ */ x";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Identifier("x".into())));
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn lex_chars() {
+        let source = r"'a' 'é' '\n' '\'' '\x7f' '\u{1F600}' b'a' b'\xFF' b'\\'";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Char('a')));
+        assert_eq!(lex.next(), Some(Token::Char('é')));
+        assert_eq!(lex.next(), Some(Token::Char('\n')));
+        assert_eq!(lex.next(), Some(Token::Char('\'')));
+        assert_eq!(lex.next(), Some(Token::Char('\x7f')));
+        assert_eq!(lex.next(), Some(Token::Char('😀')));
+        assert_eq!(lex.next(), Some(Token::Byte(b'a')));
+        assert_eq!(lex.next(), Some(Token::Byte(0xFF)));
+        assert_eq!(lex.next(), Some(Token::Byte(b'\\')));
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn lex_strings() {
+        let source = r#""hello, world\r\n" "\"é\u{e9}\"" b"UART\x00\xff" "a\
+            b""#;
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::String("hello, world\r\n".into())));
+        assert_eq!(lex.next(), Some(Token::String("\"éé\"".into())));
+        assert_eq!(
+            lex.next(),
+            Some(Token::ByteString(b"UART\x00\xff".as_slice().into()))
+        );
+        assert_eq!(lex.next(), Some(Token::String("ab".into())));
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn lex_invalid_literals() {
+        let source = r#"'' 'ab' '\q' "\x80" b'é' "\u{110000}" x"#;
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::InvalidCharacterLiteral, 1, 1)
+        );
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::InvalidCharacterLiteral, 1, 4)
+        );
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::InvalidEscape(r"\q".into()), 1, 9)
+        );
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::InvalidEscape(r"\x".into()), 1, 15)
+        );
+        assert_eq!(lex.next(), error(LexErrorKind::NonAsciiByte('é'), 1, 21));
+        assert_eq!(
+            lex.next(),
+            error(LexErrorKind::InvalidEscape(r"\u".into()), 1, 27)
+        );
+        assert_eq!(lex.next(), Some(Token::Identifier("x".into())));
+        assert_eq!(lex.next(), None);
+
+        let source = r#""never closed"#;
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), error(LexErrorKind::UnterminatedString, 1, 1));
+        assert_eq!(lex.next(), None);
+    }
+
     #[test]
     fn lex_keywords() {
         // Definitions
-        let source = "const let mut fn";
+        let source = "const static let mut fn extern struct enum peripheral";
         let mut lex = TokenStream::new(source.into());
         assert_eq!(lex.next(), Some(Token::Const));
+        assert_eq!(lex.next(), Some(Token::Static));
         assert_eq!(lex.next(), Some(Token::Let));
         assert_eq!(lex.next(), Some(Token::Mut));
         assert_eq!(lex.next(), Some(Token::Fn));
+        assert_eq!(lex.next(), Some(Token::Extern));
+        assert_eq!(lex.next(), Some(Token::Struct));
+        assert_eq!(lex.next(), Some(Token::Enum));
+        assert_eq!(lex.next(), Some(Token::Peripheral));
         assert_eq!(lex.next(), None);
 
         // Loop keywords
-        let source = "loop break continue";
+        let source = "loop for in break continue";
         let mut lex = TokenStream::new(source.into());
         assert_eq!(lex.next(), Some(Token::Loop));
+        assert_eq!(lex.next(), Some(Token::For));
+        assert_eq!(lex.next(), Some(Token::In));
         assert_eq!(lex.next(), Some(Token::Break));
         assert_eq!(lex.next(), Some(Token::Continue));
         assert_eq!(lex.next(), None);
+
+        // Operator keywords
+        let source = "as";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::As));
+        assert_eq!(lex.next(), None);
+
+        // Expression keywords
+        let source = "match unsafe";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Match));
+        assert_eq!(lex.next(), Some(Token::Unsafe));
+        assert_eq!(lex.next(), None);
     }
 
     #[test]
@@ -262,6 +1013,30 @@ mod tests {
         assert_eq!(lex.next(), None);
     }
 
+    #[test]
+    fn lex_nested_comments() {
+        let source = "/* outer /* inner */ still a comment */ x /* /* */";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(lex.next(), Some(Token::Identifier("x".into())));
+        assert_eq!(lex.next(), error(LexErrorKind::UnterminatedComment, 1, 43));
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn lex_doc_comments() {
+        let source = "//! Module docs\n/// Item docs\n///\n///indented\n//// not docs\nfn";
+        let mut lex = TokenStream::new(source.into());
+        assert_eq!(
+            lex.next(),
+            Some(Token::InnerDocComment("Module docs".into()))
+        );
+        assert_eq!(lex.next(), Some(Token::DocComment("Item docs".into())));
+        assert_eq!(lex.next(), Some(Token::DocComment("".into())));
+        assert_eq!(lex.next(), Some(Token::DocComment("indented".into())));
+        assert_eq!(lex.next(), Some(Token::Fn));
+        assert_eq!(lex.next(), None);
+    }
+
     #[test]
     fn lex_mixed_comments() {
         let source = "/* this is a comment */ // this is another comment";
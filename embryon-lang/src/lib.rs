@@ -1,19 +1,42 @@
 use crate::compile::Compiler;
 use inkwell::context::Context;
+use inkwell::targets::TargetTriple;
 // use inkwell::targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple};
 // use inkwell::OptimizationLevel;
 use std::path::Path;
 
 pub mod ast;
 mod compile;
+mod fold;
+pub mod intrinsics;
 pub mod lexer;
+mod lower;
 pub mod parse;
+mod patterns;
+pub mod syntax;
+pub mod target;
 pub mod tokens;
+pub mod types;
 pub mod utils;
+pub mod vectors;
 
 #[cfg(test)]
 mod tests;
 
+pub use compile::{CompileError, Warning};
+
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// The function to call when a runtime check (such as division by zero) fails.
+    /// If unset, the program traps instead.
+    pub panic_handler: Option<String>,
+    /// The processor to compile for.
+    pub target: target::Target,
+    /// Whether to generate the vector table for a Cortex-M core even if the program has no
+    /// exception or interrupt handlers.
+    pub vector_table: bool,
+}
+
 pub fn lex(source: &str) -> lexer::TokenStream {
     lexer::TokenStream::new(source.into())
 }
@@ -22,7 +45,30 @@ pub fn parse(mut tokens: lexer::TokenStream) -> Result<ast::Module, parse::Parse
     ast::Module::parse_body(&mut tokens, "main".into())
 }
 
-pub fn compile(program: &ast::Module, path: &Path) {
+/// Parses `source` into a lossless syntax tree, and lowers the tree to the AST.
+///
+/// The tree covers the whole source even if it fails to parse, and the trivia between two nodes
+/// belongs to their parent rather than to the second node. Only a tree without errors is
+/// lowered, so the AST is the same as [`parse`] returns, and tools working on the tree see the
+/// same program as the compiler.
+pub fn parse_lossless(
+    source: &str,
+) -> (syntax::SyntaxNode, Result<ast::Module, parse::ParseError>) {
+    let mut tokens = lex(source).with_syntax_tree();
+    let parsed = ast::Module::parse_body(&mut tokens, "main".into());
+    let tree = tokens
+        .finish_syntax_tree()
+        .expect("the token stream records a syntax tree");
+    let module = parsed.map(|_| lower::lower(&tree, "main".into()));
+    (tree, module)
+}
+
+/// Compiles `program` to LLVM IR, next to `path`. Returns any warnings about the program.
+pub fn compile(
+    program: &ast::Module,
+    path: &Path,
+    options: &CompileOptions,
+) -> Result<Vec<Warning>, CompileError> {
     // TODO: we need an optimisation pass on the AST which removes unnecessary allocations,
     //       as infinite loops with a stack allocation in the body currently cause a stack overflow
     let context = Context::create();
@@ -30,8 +76,18 @@ pub fn compile(program: &ast::Module, path: &Path) {
     module.set_source_file_name(path.to_str().unwrap());
     let builder = context.create_builder();
 
-    let mut compiler = Compiler::new(&context, &builder, &module);
-    compiler.compile_module(program).unwrap();
+    if let Some(triple) = options.target.triple() {
+        module.set_triple(&TargetTriple::create(triple));
+    }
+
+    let mut compiler = Compiler::new(&context, &builder, &module)
+        .with_target(options.target)
+        .with_vector_table(options.vector_table);
+    if let Some(panic_handler) = &options.panic_handler {
+        compiler = compiler.with_panic_handler(panic_handler.as_str());
+    }
+    compiler.compile_module(program)?;
+    let warnings = compiler.take_warnings();
 
     // Write ll to file
     module.print_to_file(path.with_extension("ll")).unwrap();
@@ -52,4 +108,6 @@ pub fn compile(program: &ast::Module, path: &Path) {
     // target_machine
     //     .write_to_file(&module, FileType::Assembly, &path.with_extension("s"))
     //     .unwrap();
+
+    Ok(warnings)
 }
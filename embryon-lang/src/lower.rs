@@ -0,0 +1,815 @@
+//! Lowering of a lossless syntax tree to the AST.
+//!
+//! The tree keeps every token the parser consumed, in nodes of the same shape as the AST, so
+//! lowering only picks out the tokens that carry meaning and skips punctuation and trivia. It is
+//! only given trees the parser built without errors, so a malformed tree is a bug in the parser.
+
+use crate::ast::{
+    ArrayRepeat, Asm, AsmOperand, AsmOptions, AsmRegister, Assignment, Attribute, BinOp, Bitfield,
+    Block, Call, Cast, Definition, Enum, Expression, ExternFunction, Field, FieldInit, For,
+    Function, Index, Match, MatchArm, Module, Path, Pattern, Peripheral, Range, Reference,
+    Register, Statement, Static, Struct, StructField, StructLiteral, Type, UnaryOperator, Variable,
+    VariableDefinition, VariableSpec, Variant,
+};
+use crate::parse::{Infix, infix_operator};
+use crate::syntax::{GreenToken, SyntaxElement, SyntaxKind, SyntaxNode};
+use crate::tokens::Token;
+use crate::types::RegisterAccess;
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::vec::IntoIter;
+
+/// Lowers the tree of a module, as built by [`crate::parse_lossless`], to its AST.
+pub fn lower(root: &SyntaxNode, name: Rc<str>) -> Module {
+    let mut children = Children::new(root);
+    let docs = docs(&mut children, |token| match token {
+        Token::InnerDocComment(line) => Some(line.clone()),
+        _ => None,
+    });
+    let mut definitions = Vec::new();
+    while let Some(node) = children.next_node() {
+        definitions.push(match node.kind() {
+            SyntaxKind::Function => lower_function(&node),
+            SyntaxKind::Constant => lower_constant(&node),
+            SyntaxKind::Static => lower_static(&node).into(),
+            SyntaxKind::Struct => lower_struct(&node).into(),
+            SyntaxKind::Enum => lower_enum(&node).into(),
+            SyntaxKind::Peripheral => lower_peripheral(&node).into(),
+            kind => unexpected(kind),
+        });
+    }
+    Module {
+        name,
+        docs,
+        definitions,
+    }
+}
+
+/// The children of a node which aren't trivia, taken in order.
+struct Children(Peekable<IntoIter<SyntaxElement>>);
+
+impl Children {
+    fn new(node: &SyntaxNode) -> Self {
+        let children: Vec<_> = node
+            .children_with_tokens()
+            .into_iter()
+            .filter(|child| match child {
+                SyntaxElement::Node(_) => true,
+                SyntaxElement::Token(token) => !token.is_trivia(),
+            })
+            .collect();
+        Self(children.into_iter().peekable())
+    }
+
+    fn peek_token(&mut self) -> Option<&Token> {
+        match self.0.peek()? {
+            SyntaxElement::Token(token) => match token.green() {
+                GreenToken::Token(token, _) => Some(token),
+                _ => None,
+            },
+            SyntaxElement::Node(_) => None,
+        }
+    }
+
+    fn peek_kind(&mut self) -> Option<SyntaxKind> {
+        match self.0.peek()? {
+            SyntaxElement::Node(node) => Some(node.kind()),
+            SyntaxElement::Token(_) => None,
+        }
+    }
+
+    fn at_node(&mut self) -> bool {
+        self.peek_kind().is_some()
+    }
+
+    fn token(&mut self) -> Token {
+        match self.0.next() {
+            Some(SyntaxElement::Token(token)) => match token.green() {
+                GreenToken::Token(token, _) => token.clone(),
+                _ => unreachable!("trivia is skipped"),
+            },
+            element => panic!("expected a token, found {element:?}"),
+        }
+    }
+
+    /// Takes the next token if it is `expected`, returning whether it was.
+    fn eat(&mut self, expected: &Token) -> bool {
+        let found = self.peek_token() == Some(expected);
+        if found {
+            self.0.next();
+        }
+        found
+    }
+
+    fn identifier(&mut self) -> Rc<str> {
+        match self.token() {
+            Token::Identifier(name) => name,
+            token => panic!("expected an identifier, found {token:?}"),
+        }
+    }
+
+    fn string(&mut self) -> Rc<str> {
+        match self.token() {
+            Token::String(string) => string,
+            token => panic!("expected a string, found {token:?}"),
+        }
+    }
+
+    /// Skips tokens up to the next node, and takes it.
+    fn next_node(&mut self) -> Option<SyntaxNode> {
+        self.0.find_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    fn node(&mut self) -> SyntaxNode {
+        self.next_node().expect("expected a node")
+    }
+
+    /// The nodes left, skipping the tokens between them.
+    fn nodes(self) -> impl Iterator<Item = SyntaxNode> {
+        self.0.filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+}
+
+fn unexpected(kind: SyntaxKind) -> ! {
+    panic!("unexpected {kind:?} node")
+}
+
+/// Collects consecutive doc comment lines, as selected by `line`, joining them by newlines.
+fn docs(children: &mut Children, line: impl Fn(&Token) -> Option<Rc<str>>) -> Option<Rc<str>> {
+    let mut lines = Vec::new();
+    while let Some(text) = children.peek_token().and_then(&line) {
+        children.token();
+        lines.push(text);
+    }
+    (!lines.is_empty()).then(|| lines.join("\n").into())
+}
+
+fn outer_docs(children: &mut Children) -> Option<Rc<str>> {
+    docs(children, |token| match token {
+        Token::DocComment(line) => Some(line.clone()),
+        _ => None,
+    })
+}
+
+/// Lowers the doc comments and attributes before an item, which may be interleaved.
+fn item_prelude(children: &mut Children) -> (Option<Rc<str>>, Vec<Attribute>) {
+    let mut lines = Vec::new();
+    let mut attributes = Vec::new();
+    loop {
+        if let Some(Token::DocComment(line)) = children.peek_token() {
+            lines.push(line.clone());
+            children.token();
+        } else if children.peek_kind() == Some(SyntaxKind::Attribute) {
+            attributes.push(lower_attribute(&children.node()));
+        } else {
+            break;
+        }
+    }
+    let docs = (!lines.is_empty()).then(|| lines.join("\n").into());
+    (docs, attributes)
+}
+
+fn lower_attribute(node: &SyntaxNode) -> Attribute {
+    let mut children = Children::new(node);
+    children.token();
+    children.token();
+    lower_meta(&mut children)
+}
+
+/// Lowers the contents of an attribute, or one of the arguments of a list.
+fn lower_meta(children: &mut Children) -> Attribute {
+    if children.at_node() {
+        return Attribute::Literal(lower_expression(&children.node()));
+    }
+    let name = children.identifier();
+    if children.eat(&Token::Equal) {
+        return Attribute::NameValue(name, lower_expression(&children.node()));
+    }
+    if !children.eat(&Token::OpenParen) {
+        return Attribute::Word(name);
+    }
+    let mut arguments = Vec::new();
+    while !children.eat(&Token::CloseParen) {
+        arguments.push(lower_meta(children));
+        children.eat(&Token::Comma);
+    }
+    Attribute::List(name, arguments)
+}
+
+fn lower_function(node: &SyntaxNode) -> Definition {
+    let mut children = Children::new(node);
+    let (docs, attributes) = item_prelude(&mut children);
+    let abi = (!children.eat(&Token::Fn)).then(|| {
+        let mut abi = Children::new(&children.node());
+        abi.token();
+        let abi = match abi.peek_token() {
+            Some(Token::String(_)) => abi.string(),
+            _ => "C".into(),
+        };
+        children.token();
+        abi
+    });
+    let name = children.identifier().to_string();
+    let parameters = Children::new(&children.node())
+        .nodes()
+        .map(|parameter| lower_parameter(&parameter))
+        .collect();
+    let mut node = children.next_node();
+    let return_type = match &node {
+        Some(return_type) if return_type.kind() == SyntaxKind::ReturnType => {
+            let ty = lower_type(&Children::new(return_type).node());
+            node = children.next_node();
+            Some(ty)
+        }
+        _ => None,
+    };
+    let Some(body) = node else {
+        return ExternFunction {
+            name,
+            docs,
+            attributes,
+            abi: abi.expect("only extern functions can leave out their body"),
+            parameters,
+            return_type,
+        }
+        .into();
+    };
+    Function {
+        name,
+        docs,
+        attributes,
+        abi,
+        parameters,
+        return_type,
+        body: lower_expression(&body),
+    }
+    .into()
+}
+
+fn lower_parameter(node: &SyntaxNode) -> VariableSpec {
+    let mut children = Children::new(node);
+    let is_mutable = children.eat(&Token::Mut);
+    VariableSpec {
+        name: children.identifier(),
+        is_mutable,
+        ty: Some(lower_type(&children.node())),
+    }
+}
+
+fn lower_constant(node: &SyntaxNode) -> Definition {
+    let mut children = Children::new(node);
+    let docs = outer_docs(&mut children);
+    children.token();
+    let name = children.identifier();
+    let ty = children
+        .eat(&Token::Colon)
+        .then(|| lower_type(&children.node()));
+    Definition::Constant(Variable {
+        spec: VariableSpec {
+            name,
+            is_mutable: false,
+            ty,
+        },
+        docs,
+        value: Box::new(lower_expression(&children.node())),
+    })
+}
+
+fn lower_static(node: &SyntaxNode) -> Static {
+    let mut children = Children::new(node);
+    let (docs, attributes) = item_prelude(&mut children);
+    children.token();
+    let is_mutable = children.eat(&Token::Mut);
+    let name = children.identifier();
+    let ty = lower_type(&children.node());
+    Static {
+        spec: VariableSpec {
+            name,
+            is_mutable,
+            ty: Some(ty),
+        },
+        docs,
+        attributes,
+        value: Box::new(lower_expression(&children.node())),
+    }
+}
+
+fn lower_struct(node: &SyntaxNode) -> Struct {
+    let mut children = Children::new(node);
+    let (docs, attributes) = item_prelude(&mut children);
+    children.token();
+    let name = children.identifier();
+    let fields = children
+        .nodes()
+        .map(|field| {
+            let mut children = Children::new(&field);
+            let docs = outer_docs(&mut children);
+            StructField {
+                name: children.identifier(),
+                docs,
+                ty: lower_type(&children.node()),
+            }
+        })
+        .collect();
+    Struct {
+        name,
+        docs,
+        attributes,
+        fields,
+    }
+}
+
+fn lower_enum(node: &SyntaxNode) -> Enum {
+    let mut children = Children::new(node);
+    let (docs, attributes) = item_prelude(&mut children);
+    children.token();
+    let name = children.identifier();
+    let variants = children
+        .nodes()
+        .map(|variant| lower_variant(&variant))
+        .collect();
+    Enum {
+        name,
+        docs,
+        attributes,
+        variants,
+    }
+}
+
+fn lower_variant(node: &SyntaxNode) -> Variant {
+    let mut children = Children::new(node);
+    let docs = outer_docs(&mut children);
+    let name = children.identifier();
+    let mut fields = Vec::new();
+    let mut discriminant = None;
+    while let Some(node) = children.next_node() {
+        if is_type(node.kind()) {
+            fields.push(lower_type(&node));
+        } else {
+            discriminant = Some(lower_expression(&node));
+        }
+    }
+    Variant {
+        name,
+        docs,
+        fields,
+        discriminant,
+    }
+}
+
+fn lower_peripheral(node: &SyntaxNode) -> Peripheral {
+    let mut children = Children::new(node);
+    let docs = outer_docs(&mut children);
+    children.token();
+    let name = children.identifier();
+    let address = lower_expression(&children.node());
+    let registers = children
+        .nodes()
+        .map(|register| lower_register(&register))
+        .collect();
+    Peripheral {
+        name,
+        docs,
+        address,
+        registers,
+    }
+}
+
+fn lower_register(node: &SyntaxNode) -> Register {
+    let mut children = Children::new(node);
+    let docs = outer_docs(&mut children);
+    let name = children.identifier();
+    let ty = lower_type(&children.node());
+    let offset = lower_expression(&children.node());
+    let access = RegisterAccess::from_name(&children.identifier())
+        .expect("the parser only accepts valid access modes");
+    let fields = children
+        .nodes()
+        .map(|field| {
+            let mut children = Children::new(&field);
+            let docs = outer_docs(&mut children);
+            Bitfield {
+                name: children.identifier(),
+                docs,
+                bits: lower_expression(&children.node()),
+            }
+        })
+        .collect();
+    Register {
+        name,
+        docs,
+        ty,
+        offset,
+        access,
+        fields,
+    }
+}
+
+fn is_type(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::Type
+            | SyntaxKind::ArrayType
+            | SyntaxKind::SliceType
+            | SyntaxKind::ReferenceType
+            | SyntaxKind::PointerType
+    )
+}
+
+fn lower_type(node: &SyntaxNode) -> Type {
+    let mut children = Children::new(node);
+    match node.kind() {
+        SyntaxKind::Type => Type::Named(children.identifier()),
+        SyntaxKind::ArrayType => Type::Array(
+            Box::new(lower_type(&children.node())),
+            Box::new(lower_expression(&children.node())),
+        ),
+        SyntaxKind::SliceType => Type::Slice(Box::new(lower_type(&children.node()))),
+        SyntaxKind::ReferenceType => {
+            children.token();
+            let mutable = children.eat(&Token::Mut);
+            Type::Reference(Box::new(lower_type(&children.node())), mutable)
+        }
+        SyntaxKind::PointerType => {
+            children.token();
+            let mutable = children.token() == Token::Mut;
+            Type::Pointer(Box::new(lower_type(&children.node())), mutable)
+        }
+        kind => unexpected(kind),
+    }
+}
+
+/// The operand that a chain of left-associative or postfix operators continues into, such as
+/// `a + b` in `a + b + c`.
+fn chained_operand(node: &SyntaxNode) -> Option<SyntaxNode> {
+    match node.kind() {
+        SyntaxKind::BinaryExpression
+        | SyntaxKind::CastExpression
+        | SyntaxKind::IndexExpression
+        | SyntaxKind::FieldExpression
+        | SyntaxKind::CallExpression => node.children().into_iter().next(),
+        _ => None,
+    }
+}
+
+/// Lowers an expression. Chains of operators can be much longer than the nesting limit, so they
+/// are lowered in a loop from the innermost operand out.
+fn lower_expression(node: &SyntaxNode) -> Expression {
+    let mut chain = vec![node.clone()];
+    while let Some(operand) = chain.last().and_then(chained_operand) {
+        chain.push(operand);
+    }
+    let innermost = chain.pop().expect("the chain starts with the expression");
+    let mut expression = lower_operand(&innermost);
+    // Popping drops the innermost nodes first, so their parents don't have to be dropped
+    // recursively
+    while let Some(node) = chain.pop() {
+        expression = lower_chained(&node, expression);
+    }
+    expression
+}
+
+/// Lowers an operator of a chain, given its already lowered operand.
+fn lower_chained(node: &SyntaxNode, operand: Expression) -> Expression {
+    let mut children = Children::new(node);
+    children.node();
+    let operand = Box::new(operand);
+    match node.kind() {
+        SyntaxKind::BinaryExpression => {
+            let Some((Infix::Binary(operator), ..)) = infix_operator(&children.token()) else {
+                unreachable!("a binary expression has a binary operator");
+            };
+            BinOp::new(operator, *operand, lower_expression(&children.node())).into()
+        }
+        SyntaxKind::CastExpression => Cast {
+            value: operand,
+            ty: lower_type(&children.node()),
+        }
+        .into(),
+        SyntaxKind::IndexExpression => Index {
+            base: operand,
+            index: Box::new(lower_expression(&children.node())),
+        }
+        .into(),
+        SyntaxKind::FieldExpression => {
+            children.token();
+            Field {
+                base: operand,
+                name: children.identifier(),
+            }
+            .into()
+        }
+        SyntaxKind::CallExpression => Call {
+            function: operand,
+            arguments: Children::new(&children.node())
+                .nodes()
+                .map(|argument| lower_expression(&argument))
+                .collect(),
+        }
+        .into(),
+        kind => unexpected(kind),
+    }
+}
+
+/// Lowers an expression which isn't an operator of a chain.
+fn lower_operand(node: &SyntaxNode) -> Expression {
+    let mut children = Children::new(node);
+    match node.kind() {
+        SyntaxKind::Literal => match children.token() {
+            Token::Integer(value) => Expression::Integer(value),
+            Token::TypedInteger(value, ty) => Expression::TypedInteger(value, ty),
+            Token::Char(c) => Expression::Char(c),
+            Token::Byte(b) => Expression::Byte(b),
+            Token::String(s) => Expression::String(s),
+            Token::ByteString(s) => Expression::ByteString(s),
+            token => panic!("expected a literal, found {token:?}"),
+        },
+        SyntaxKind::Name => Expression::Variable(children.identifier()),
+        SyntaxKind::CoreRegister => match children.token() {
+            Token::CoreRegister(name) => Expression::CoreRegister(name),
+            token => panic!("expected a register, found {token:?}"),
+        },
+        SyntaxKind::Path => lower_path(node).into(),
+        SyntaxKind::ParenExpression => lower_expression(&children.node()),
+        SyntaxKind::PrefixExpression => Expression::Unary(
+            UnaryOperator::Neg,
+            Box::new(lower_expression(&children.node())),
+        ),
+        SyntaxKind::Assignment => {
+            let target = Box::new(lower_expression(&children.node()));
+            let Some((Infix::Assign(operator), ..)) = infix_operator(&children.token()) else {
+                unreachable!("an assignment has an assignment operator");
+            };
+            Assignment {
+                target,
+                operator,
+                value: Box::new(lower_expression(&children.node())),
+            }
+            .into()
+        }
+        SyntaxKind::RangeExpression => {
+            let start = children
+                .at_node()
+                .then(|| Box::new(lower_expression(&children.node())));
+            Range {
+                start,
+                inclusive: children.token() == Token::DotDotEqual,
+                end: children
+                    .next_node()
+                    .map(|end| Box::new(lower_expression(&end))),
+            }
+            .into()
+        }
+        SyntaxKind::StructLiteral => {
+            let name = children.identifier();
+            let fields = children
+                .nodes()
+                .map(|field| {
+                    let mut children = Children::new(&field);
+                    let name = children.identifier();
+                    let value = match children.next_node() {
+                        Some(value) => lower_expression(&value),
+                        None => Expression::Variable(name.clone()),
+                    };
+                    FieldInit { name, value }
+                })
+                .collect();
+            StructLiteral { name, fields }.into()
+        }
+        SyntaxKind::ArrayExpression => lower_array(node),
+        SyntaxKind::ReferenceExpression => {
+            children.token();
+            let mutable = children.eat(&Token::Mut);
+            Reference {
+                value: Box::new(lower_expression(&children.node())),
+                mutable,
+            }
+            .into()
+        }
+        SyntaxKind::DerefExpression => {
+            Expression::Deref(Box::new(lower_expression(&children.node())))
+        }
+        SyntaxKind::Block => lower_block(node).into(),
+        SyntaxKind::MatchExpression => {
+            let scrutinee = Box::new(lower_expression(&children.node()));
+            let arms = children
+                .nodes()
+                .map(|arm| {
+                    let mut children = Children::new(&arm);
+                    MatchArm {
+                        pattern: lower_pattern(&children.node()),
+                        body: lower_expression(&children.node()),
+                    }
+                })
+                .collect();
+            Match { scrutinee, arms }.into()
+        }
+        SyntaxKind::UnsafeExpression => {
+            Expression::Unsafe(Box::new(lower_expression(&children.node())))
+        }
+        SyntaxKind::AsmExpression => Expression::Asm(Box::new(lower_asm(node))),
+        SyntaxKind::LoopExpression => {
+            Expression::Loop(Box::new(lower_expression(&children.node())))
+        }
+        SyntaxKind::ForExpression => {
+            children.token();
+            For {
+                binding: children.identifier(),
+                iterable: Box::new(lower_expression(&children.node())),
+                body: Box::new(lower_expression(&children.node())),
+            }
+            .into()
+        }
+        SyntaxKind::BreakExpression => Expression::Break,
+        SyntaxKind::ContinueExpression => Expression::Continue,
+        kind => unexpected(kind),
+    }
+}
+
+fn lower_path(node: &SyntaxNode) -> Path {
+    let mut children = Children::new(node);
+    let ty = children.identifier();
+    children.token();
+    Path::new(ty, children.identifier())
+}
+
+fn lower_pattern(node: &SyntaxNode) -> Pattern {
+    let mut children = Children::new(node);
+    if !children.at_node() {
+        return match children.identifier() {
+            name if &*name == "_" => Pattern::Wildcard,
+            name => Pattern::Binding(name),
+        };
+    }
+    let first = children.node();
+    match first.kind() {
+        SyntaxKind::Pattern => Pattern::Or(
+            std::iter::once(first)
+                .chain(children.nodes())
+                .map(|alternative| lower_pattern(&alternative))
+                .collect(),
+        ),
+        SyntaxKind::Path => Pattern::Variant(
+            lower_path(&first),
+            children
+                .nodes()
+                .map(|field| lower_pattern(&field))
+                .collect(),
+        ),
+        _ => {
+            let start = lower_expression(&first);
+            match children.peek_token() {
+                Some(Token::DotDot | Token::DotDotEqual) => Pattern::Range {
+                    start,
+                    inclusive: children.token() == Token::DotDotEqual,
+                    end: lower_expression(&children.node()),
+                },
+                _ => Pattern::Literal(start),
+            }
+        }
+    }
+}
+
+/// Lowers an array literal, which is `[value; count]` if it has a `;`.
+fn lower_array(node: &SyntaxNode) -> Expression {
+    let mut children = Children::new(node);
+    children.token();
+    let mut elements = Vec::new();
+    while let Some(element) = children.next_node() {
+        elements.push(lower_expression(&element));
+        if children.eat(&Token::Semi) {
+            return ArrayRepeat {
+                value: Box::new(elements.remove(0)),
+                count: Box::new(lower_expression(&children.node())),
+            }
+            .into();
+        }
+    }
+    Expression::Array(elements)
+}
+
+/// Lowers a block. A statement is its last expression if no `;` follows it.
+fn lower_block(node: &SyntaxNode) -> Block {
+    let mut children = Children::new(node);
+    let mut body = Vec::new();
+    let mut last = None;
+    while let Some(node) = children.next_node() {
+        let statement = match node.kind() {
+            SyntaxKind::LetStatement => lower_let(&node).into(),
+            _ => Statement::Expression(lower_expression(&node)),
+        };
+        if children.eat(&Token::Semi) {
+            body.push(statement);
+        } else {
+            let Statement::Expression(expression) = statement else {
+                unreachable!("a block can't end in a let statement");
+            };
+            last = Some(Box::new(expression));
+        }
+    }
+    Block { body, last }
+}
+
+fn lower_let(node: &SyntaxNode) -> VariableDefinition {
+    let mut children = Children::new(node);
+    children.token();
+    let is_mutable = children.eat(&Token::Mut);
+    let name = children.identifier();
+    let mut ty = None;
+    let mut value = None;
+    while let Some(node) = children.next_node() {
+        if is_type(node.kind()) {
+            ty = Some(lower_type(&node));
+        } else {
+            value = Some(Box::new(lower_expression(&node)));
+        }
+    }
+    VariableDefinition {
+        spec: VariableSpec {
+            name,
+            is_mutable,
+            ty,
+        },
+        value,
+    }
+}
+
+/// Lowers inline assembly. Its template strings, `clobber_abi(...)` and `options(...)` are
+/// tokens of the node itself, while each operand is a node.
+fn lower_asm(node: &SyntaxNode) -> Asm {
+    let mut children = Children::new(node);
+    for _ in 0..3 {
+        children.token();
+    }
+    let mut asm = Asm {
+        template: vec![children.string()],
+        operands: Vec::new(),
+        clobber_abis: Vec::new(),
+        options: AsmOptions::default(),
+    };
+    while children.eat(&Token::Comma) {
+        if children.at_node() {
+            asm.operands.push(lower_asm_operand(&children.node()));
+            continue;
+        }
+        match children.token() {
+            Token::String(line) => asm.template.push(line),
+            Token::Identifier(name) if &*name == "clobber_abi" => {
+                children.token();
+                asm.clobber_abis.push(children.string());
+                children.token();
+            }
+            Token::Identifier(name) if &*name == "options" => {
+                children.token();
+                while !children.eat(&Token::CloseParen) {
+                    let option = match &*children.identifier() {
+                        "volatile" => &mut asm.options.volatile,
+                        "nomem" => &mut asm.options.nomem,
+                        "nostack" => &mut asm.options.nostack,
+                        "preserves_flags" => &mut asm.options.preserves_flags,
+                        name => panic!("unknown assembly option {name}"),
+                    };
+                    *option = true;
+                    children.eat(&Token::Comma);
+                }
+            }
+            // The trailing comma before the closing parenthesis
+            Token::CloseParen => break,
+            token => panic!("unexpected {token:?} in inline assembly"),
+        }
+    }
+    asm
+}
+
+fn lower_asm_operand(node: &SyntaxNode) -> AsmOperand {
+    let mut children = Children::new(node);
+    let direction = children.token();
+    children.token();
+    let register = match children.token() {
+        Token::Identifier(class) => AsmRegister::Class(class),
+        Token::String(name) => AsmRegister::Explicit(name),
+        token => panic!("expected a register, found {token:?}"),
+    };
+    children.token();
+    let value = children.next_node().map(|value| lower_expression(&value));
+    match direction {
+        Token::In => AsmOperand::In {
+            register,
+            value: value.expect("an input has a value"),
+        },
+        Token::Identifier(name) if &*name == "out" => AsmOperand::Out {
+            register,
+            place: value,
+        },
+        _ => AsmOperand::InOut {
+            register,
+            place: value.expect("an input and output has a place"),
+        },
+    }
+}
@@ -1,9 +1,15 @@
 use crate::ast::{
-    BinOp, Block, Definition, Expression, Function, Module, Statement, Variable,
-    VariableAssignment, VariableDefinition, VariableSpec,
+    ArrayRepeat, Asm, AsmOperand, AsmOptions, AsmRegister, Assignment, Attribute, BinOp, Bitfield,
+    Block, Call, Cast, Definition, Enum, Expression, ExternFunction, Field, FieldInit, For,
+    Function, Index, Match, MatchArm, Module, Operator, Path, Pattern, Peripheral, Range,
+    Reference, Register, Statement, Static, Struct, StructField, StructLiteral, Type,
+    UnaryOperator, Variable, VariableDefinition, VariableSpec, Variant,
 };
 use crate::lexer::TokenStream;
-use crate::tokens::Token;
+use crate::syntax::SyntaxKind;
+use crate::tokens::{LexError, Token};
+use crate::types::RegisterAccess;
+use std::fmt;
 use std::rc::Rc;
 
 #[derive(Debug)]
@@ -12,162 +18,1191 @@ pub enum ParseError {
     ExpectedToken(Token),
     UnexpectedEoF,
     ExpectedExpression,
+    InvalidAssignmentTarget,
+    /// Expressions were nested more deeply than the limit.
+    NestingTooDeep(usize),
+    Lex(LexError),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedToken(token) => write!(f, "unexpected token {token:?}"),
+            Self::ExpectedToken(token) => write!(f, "expected token {token:?}"),
+            Self::UnexpectedEoF => write!(f, "unexpected end of file"),
+            Self::ExpectedExpression => write!(f, "expected an expression"),
+            Self::InvalidAssignmentTarget => write!(f, "invalid left-hand side of assignment"),
+            Self::NestingTooDeep(limit) => {
+                write!(f, "expression is nested too deeply (the limit is {limit})")
+            }
+            Self::Lex(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ParseError {
+    /// Reports that `token` was not expected, or why the input there could not be lexed.
+    pub fn unexpected(token: Token) -> Self {
+        match token {
+            Token::Error(err) => Self::Lex(err),
+            token => Self::UnexpectedToken(token),
+        }
+    }
 }
 
 impl Module {
     pub fn parse_body(tokens: &mut TokenStream, name: Rc<str>) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Module);
+        let docs = parse_docs(tokens, |token| match token {
+            Token::InnerDocComment(line) => Some(line.clone()),
+            _ => None,
+        });
         let mut definitions = Vec::new();
-        while let Some(token) = tokens.peek() {
+        loop {
+            // Look past any doc comments and attributes to find out what kind of item they belong to
+            let index = skip_item_prelude(tokens);
+            let Some(token) = tokens.peek_ahead(index) else {
+                if index > 0 {
+                    return Err(ParseError::UnexpectedEoF);
+                }
+                break;
+            };
             match token {
-                Token::Fn => {
-                    definitions.push(Definition::Function(Function::parse(tokens)?));
+                Token::Fn | Token::Extern => {
+                    definitions.push(Definition::parse_function(tokens)?);
                 }
                 Token::Const => {
                     definitions.push(Definition::parse_constant(tokens)?);
                 }
+                Token::Static => {
+                    definitions.push(Definition::Static(Static::parse(tokens)?));
+                }
+                Token::Struct => {
+                    definitions.push(Definition::Struct(Struct::parse(tokens)?));
+                }
+                Token::Enum => {
+                    definitions.push(Definition::Enum(Enum::parse(tokens)?));
+                }
+                Token::Peripheral => {
+                    definitions.push(Definition::Peripheral(Peripheral::parse(tokens)?));
+                }
                 _ => {
                     dbg!(token);
-                    return Err(ParseError::UnexpectedToken(token.clone()));
+                    return Err(ParseError::unexpected(token.clone()));
                 }
             }
         }
-        Ok(Module { name, definitions })
+        tokens.finish_node();
+        Ok(Module {
+            name,
+            docs,
+            definitions,
+        })
+    }
+}
+
+/// Collects consecutive doc comment lines, as selected by `line`, joining them by newlines.
+fn parse_docs(
+    tokens: &mut TokenStream,
+    line: impl Fn(&Token) -> Option<Rc<str>>,
+) -> Option<Rc<str>> {
+    let mut lines = Vec::new();
+    while let Some(text) = tokens.peek().and_then(&line) {
+        tokens.next();
+        lines.push(text);
+    }
+    (!lines.is_empty()).then(|| lines.join("\n").into())
+}
+
+fn parse_outer_docs(tokens: &mut TokenStream) -> Option<Rc<str>> {
+    parse_docs(tokens, |token| match token {
+        Token::DocComment(line) => Some(line.clone()),
+        _ => None,
+    })
+}
+
+/// Runs `parse` with struct literals allowed or not, then restores the previous setting.
+fn with_struct_literals<T>(
+    tokens: &mut TokenStream,
+    allowed: bool,
+    parse: impl FnOnce(&mut TokenStream) -> Result<T, ParseError>,
+) -> Result<T, ParseError> {
+    let previous = tokens.allow_struct_literals(allowed);
+    let result = parse(tokens);
+    tokens.allow_struct_literals(previous);
+    result
+}
+
+/// Consumes a `mut` keyword if there is one, returning whether there was.
+fn parse_mut(tokens: &mut TokenStream) -> bool {
+    let mutable = matches!(tokens.peek(), Some(Token::Mut));
+    if mutable {
+        tokens.next();
+    }
+    mutable
+}
+
+/// Consumes a string literal, returning its contents.
+fn expect_string(tokens: &mut TokenStream) -> Result<Rc<str>, ParseError> {
+    match tokens.next() {
+        Some(Token::String(string)) => Ok(string),
+        Some(token) => Err(ParseError::unexpected(token)),
+        None => Err(ParseError::UnexpectedEoF),
+    }
+}
+
+/// Returns how many tokens of doc comments and `#[...]` attributes come before the next item.
+fn skip_item_prelude(tokens: &mut TokenStream) -> usize {
+    let mut index = 0;
+    loop {
+        match tokens.peek_ahead(index) {
+            Some(Token::DocComment(_)) => index += 1,
+            Some(Token::Hash) => {
+                index += 1;
+                let mut depth = 0;
+                loop {
+                    match tokens.peek_ahead(index) {
+                        Some(Token::OpenBracket) => depth += 1,
+                        Some(Token::CloseBracket) => depth -= 1,
+                        None => return index,
+                        _ => {}
+                    }
+                    index += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            _ => return index,
+        }
+    }
+}
+
+/// Parses the doc comments and attributes before an item, which may be interleaved.
+fn parse_item_prelude(
+    tokens: &mut TokenStream,
+) -> Result<(Option<Rc<str>>, Vec<Attribute>), ParseError> {
+    let mut lines = Vec::new();
+    let mut attributes = Vec::new();
+    loop {
+        match tokens.peek() {
+            Some(Token::DocComment(line)) => {
+                lines.push(line.clone());
+                tokens.next();
+            }
+            Some(Token::Hash) => attributes.push(Attribute::parse(tokens)?),
+            _ => break,
+        }
+    }
+    let docs = (!lines.is_empty()).then(|| lines.join("\n").into());
+    Ok((docs, attributes))
+}
+
+impl Attribute {
+    /// Parses an attribute, written `#[...]`.
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Attribute);
+        tokens.expect(Token::Hash)?;
+        tokens.expect(Token::OpenBracket)?;
+        // Only the arguments of an attribute can be literals
+        match tokens.peek() {
+            Some(Token::Identifier(_)) => {}
+            Some(token) => return Err(ParseError::unexpected(token.clone())),
+            None => return Err(ParseError::UnexpectedEoF),
+        }
+        let attribute = Self::parse_meta(tokens)?;
+        tokens.expect(Token::CloseBracket)?;
+        tokens.finish_node();
+        Ok(attribute)
+    }
+
+    /// Parses the contents of an attribute, or one of the arguments of a list.
+    fn parse_meta(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        if !matches!(tokens.peek(), Some(Token::Identifier(_))) {
+            return Ok(Self::Literal(Expression::parse_literal(tokens)?));
+        }
+        let name: Rc<str> = tokens.expect_identifier()?.into();
+        match tokens.peek() {
+            Some(Token::Equal) => {
+                tokens.next();
+                Ok(Self::NameValue(name, Expression::parse_literal(tokens)?))
+            }
+            Some(Token::OpenParen) => {
+                tokens.next();
+                let mut arguments = Vec::new();
+                while !matches!(tokens.peek(), Some(Token::CloseParen)) {
+                    arguments.push(Self::parse_meta(tokens)?);
+                    match tokens.peek() {
+                        Some(Token::Comma) => {
+                            tokens.next();
+                        }
+                        Some(Token::CloseParen) => break,
+                        None => return Err(ParseError::UnexpectedEoF),
+                        Some(token) => return Err(ParseError::unexpected(token.clone())),
+                    }
+                }
+                tokens.expect(Token::CloseParen)?;
+                Ok(Self::List(name, arguments))
+            }
+            _ => Ok(Self::Word(name)),
+        }
+    }
+}
+
+impl Struct {
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Struct);
+        let (docs, attributes) = parse_item_prelude(tokens)?;
+        tokens.expect(Token::Struct)?;
+        let name = tokens.expect_identifier()?.into();
+        tokens.expect(Token::OpenBrace)?;
+        let mut fields = Vec::new();
+        while !matches!(tokens.peek(), Some(Token::CloseBrace)) {
+            tokens.start_node(SyntaxKind::StructField);
+            let docs = parse_outer_docs(tokens);
+            let name = tokens.expect_identifier()?.into();
+            tokens.expect(Token::Colon)?;
+            let ty = Type::parse(tokens)?;
+            tokens.finish_node();
+            fields.push(StructField { name, docs, ty });
+            match tokens.peek() {
+                Some(Token::Comma) => {
+                    tokens.next();
+                }
+                Some(Token::CloseBrace) => break,
+                None => return Err(ParseError::UnexpectedEoF),
+                Some(token) => return Err(ParseError::unexpected(token.clone())),
+            }
+        }
+        tokens.expect(Token::CloseBrace)?;
+        tokens.finish_node();
+        Ok(Self {
+            name,
+            docs,
+            attributes,
+            fields,
+        })
+    }
+}
+
+impl Enum {
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Enum);
+        let (docs, attributes) = parse_item_prelude(tokens)?;
+        tokens.expect(Token::Enum)?;
+        let name = tokens.expect_identifier()?.into();
+        tokens.expect(Token::OpenBrace)?;
+        let mut variants = Vec::new();
+        while !matches!(tokens.peek(), Some(Token::CloseBrace)) {
+            variants.push(Variant::parse(tokens)?);
+            match tokens.peek() {
+                Some(Token::Comma) => {
+                    tokens.next();
+                }
+                Some(Token::CloseBrace) => break,
+                None => return Err(ParseError::UnexpectedEoF),
+                Some(token) => return Err(ParseError::unexpected(token.clone())),
+            }
+        }
+        tokens.expect(Token::CloseBrace)?;
+        tokens.finish_node();
+        Ok(Self {
+            name,
+            docs,
+            attributes,
+            variants,
+        })
+    }
+}
+
+impl Variant {
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Variant);
+        let docs = parse_outer_docs(tokens);
+        let name = tokens.expect_identifier()?.into();
+        let mut fields = Vec::new();
+        if matches!(tokens.peek(), Some(Token::OpenParen)) {
+            tokens.next();
+            while !matches!(tokens.peek(), Some(Token::CloseParen)) {
+                fields.push(Type::parse(tokens)?);
+                match tokens.peek() {
+                    Some(Token::Comma) => {
+                        tokens.next();
+                    }
+                    Some(Token::CloseParen) => break,
+                    None => return Err(ParseError::UnexpectedEoF),
+                    Some(token) => return Err(ParseError::unexpected(token.clone())),
+                }
+            }
+            tokens.expect(Token::CloseParen)?;
+        }
+        let discriminant = if matches!(tokens.peek(), Some(Token::Equal)) {
+            tokens.next();
+            Some(Expression::parse(tokens)?)
+        } else {
+            None
+        };
+        tokens.finish_node();
+        Ok(Self {
+            name,
+            docs,
+            fields,
+            discriminant,
+        })
+    }
+}
+
+impl Peripheral {
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Peripheral);
+        let docs = parse_outer_docs(tokens);
+        tokens.expect(Token::Peripheral)?;
+        let name = tokens.expect_identifier()?.into();
+        tokens.expect(Token::At)?;
+        // The address can be a constant, which must not take the registers as a struct literal
+        let address = with_struct_literals(tokens, false, Expression::parse)?;
+        tokens.expect(Token::OpenBrace)?;
+        let mut registers = Vec::new();
+        while !matches!(tokens.peek(), Some(Token::CloseBrace)) {
+            registers.push(Register::parse(tokens)?);
+            match tokens.peek() {
+                Some(Token::Comma) => {
+                    tokens.next();
+                }
+                Some(Token::CloseBrace) => break,
+                None => return Err(ParseError::UnexpectedEoF),
+                Some(token) => return Err(ParseError::unexpected(token.clone())),
+            }
+        }
+        tokens.expect(Token::CloseBrace)?;
+        tokens.finish_node();
+        Ok(Self {
+            name,
+            docs,
+            address,
+            registers,
+        })
+    }
+}
+
+impl Register {
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Register);
+        let docs = parse_outer_docs(tokens);
+        let name = tokens.expect_identifier()?.into();
+        tokens.expect(Token::Colon)?;
+        let ty = Type::parse(tokens)?;
+        tokens.expect(Token::At)?;
+        let offset = Expression::parse(tokens)?;
+        // The access mode is a plain identifier, so `r` and `w` can still be used as names
+        let access = match tokens.next() {
+            Some(Token::Identifier(name)) => RegisterAccess::from_name(&name)
+                .ok_or(ParseError::UnexpectedToken(Token::Identifier(name)))?,
+            Some(token) => return Err(ParseError::unexpected(token)),
+            None => return Err(ParseError::UnexpectedEoF),
+        };
+        let mut fields = Vec::new();
+        if matches!(tokens.peek(), Some(Token::OpenBrace)) {
+            tokens.next();
+            while !matches!(tokens.peek(), Some(Token::CloseBrace)) {
+                fields.push(Bitfield::parse(tokens)?);
+                match tokens.peek() {
+                    Some(Token::Comma) => {
+                        tokens.next();
+                    }
+                    Some(Token::CloseBrace) => break,
+                    None => return Err(ParseError::UnexpectedEoF),
+                    Some(token) => return Err(ParseError::unexpected(token.clone())),
+                }
+            }
+            tokens.expect(Token::CloseBrace)?;
+        }
+        tokens.finish_node();
+        Ok(Self {
+            name,
+            docs,
+            ty,
+            offset,
+            access,
+            fields,
+        })
+    }
+}
+
+impl Bitfield {
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Bitfield);
+        let docs = parse_outer_docs(tokens);
+        let name = tokens.expect_identifier()?.into();
+        tokens.expect(Token::Colon)?;
+        let bits = Expression::parse(tokens)?;
+        tokens.finish_node();
+        Ok(Self { name, docs, bits })
     }
 }
 
 impl Definition {
     pub fn parse_constant(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Constant);
+        let docs = parse_outer_docs(tokens);
         tokens.expect(Token::Const)?;
         let name = tokens.expect_identifier()?;
+        let ty = Type::parse_annotation(tokens)?;
         tokens.expect(Token::Equal)?;
         let value = Expression::parse(tokens)?;
         tokens.expect(Token::Semi)?;
+        tokens.finish_node();
         Ok(Self::Constant(Variable {
             spec: VariableSpec {
                 name: name.into(),
                 is_mutable: false,
+                ty,
             },
+            docs,
             value: Box::new(value),
         }))
     }
 }
 
-impl Function {
-    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+impl Definition {
+    /// Parses a function, or the declaration of an `extern` function, which ends in `;` instead
+    /// of a body. `extern` without an ABI string means `extern "C"`.
+    pub fn parse_function(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Function);
+        let (docs, attributes) = parse_item_prelude(tokens)?;
+        let abi = if matches!(tokens.peek(), Some(Token::Extern)) {
+            tokens.start_node(SyntaxKind::Abi);
+            tokens.next();
+            let abi = match tokens.peek() {
+                Some(Token::String(_)) => expect_string(tokens)?,
+                _ => "C".into(),
+            };
+            tokens.finish_node();
+            Some(abi)
+        } else {
+            None
+        };
         tokens.expect(Token::Fn)?;
         let name = tokens.expect_identifier()?;
-        tokens.expect(Token::OpenParen)?;
-        tokens.expect(Token::CloseParen)?;
+        let parameters = Function::parse_parameters(tokens)?;
+        let return_type = if matches!(tokens.peek(), Some(Token::Arrow)) {
+            tokens.start_node(SyntaxKind::ReturnType);
+            tokens.next();
+            let ty = Type::parse(tokens)?;
+            tokens.finish_node();
+            Some(ty)
+        } else {
+            None
+        };
+        if let (Some(abi), Some(Token::Semi)) = (&abi, tokens.peek()) {
+            tokens.next();
+            tokens.finish_node();
+            return Ok(ExternFunction {
+                name,
+                docs,
+                attributes,
+                abi: abi.clone(),
+                parameters,
+                return_type,
+            }
+            .into());
+        }
         let body = Expression::parse(tokens)?;
+        tokens.finish_node();
         Ok(Function {
             name,
-            parameters: Vec::new(),
+            docs,
+            attributes,
+            abi,
+            parameters,
+            return_type,
             body,
+        }
+        .into())
+    }
+}
+
+impl Static {
+    /// Parses a static, such as `static mut TICKS: u32 = 0;`. Unlike a constant, it needs a type.
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Static);
+        let (docs, attributes) = parse_item_prelude(tokens)?;
+        tokens.expect(Token::Static)?;
+        let is_mutable = parse_mut(tokens);
+        let name = tokens.expect_identifier()?;
+        tokens.expect(Token::Colon)?;
+        let ty = Type::parse(tokens)?;
+        tokens.expect(Token::Equal)?;
+        let value = Expression::parse(tokens)?;
+        tokens.expect(Token::Semi)?;
+        tokens.finish_node();
+        Ok(Static {
+            spec: VariableSpec {
+                name: name.into(),
+                is_mutable,
+                ty: Some(ty),
+            },
+            docs,
+            attributes,
+            value: Box::new(value),
         })
     }
 }
 
+impl Function {
+    /// Parses a parameter list, such as `(a: u32, mut b: Point)`. Every parameter needs a type.
+    fn parse_parameters(tokens: &mut TokenStream) -> Result<Vec<VariableSpec>, ParseError> {
+        tokens.start_node(SyntaxKind::ParameterList);
+        tokens.expect(Token::OpenParen)?;
+        let mut parameters = Vec::new();
+        while !matches!(tokens.peek(), Some(Token::CloseParen)) {
+            tokens.start_node(SyntaxKind::Parameter);
+            let is_mutable = parse_mut(tokens);
+            let name = tokens.expect_identifier()?.into();
+            tokens.expect(Token::Colon)?;
+            let ty = Type::parse(tokens)?;
+            tokens.finish_node();
+            parameters.push(VariableSpec {
+                name,
+                is_mutable,
+                ty: Some(ty),
+            });
+            match tokens.peek() {
+                Some(Token::Comma) => {
+                    tokens.next();
+                }
+                Some(Token::CloseParen) => break,
+                None => return Err(ParseError::UnexpectedEoF),
+                Some(token) => return Err(ParseError::unexpected(token.clone())),
+            }
+        }
+        tokens.expect(Token::CloseParen)?;
+        tokens.finish_node();
+        Ok(parameters)
+    }
+}
+
+/// How tightly an operator holds on to its operands, from loosest to tightest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Precedence {
+    Assignment,
+    Range,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Sum,
+    Product,
+    Cast,
+    Prefix,
+    Postfix,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Associativity {
+    Left,
+    Right,
+}
+
+impl Precedence {
+    fn binding_power(self) -> u8 {
+        (self as u8 + 1) * 2
+    }
+
+    /// The binding powers of an infix operator on its left and right. An operator binds its
+    /// operands more loosely on the side it associates to, so chains of it group on that side.
+    fn infix_binding_power(self, associativity: Associativity) -> (u8, u8) {
+        let power = self.binding_power();
+        match associativity {
+            Associativity::Left => (power, power + 1),
+            Associativity::Right => (power + 1, power),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Infix {
+    Binary(Operator),
+    /// A plain (`None`) or compound assignment.
+    Assign(Option<Operator>),
+    /// `..`, or `..=` if the range includes its end.
+    Range(bool),
+    Cast,
+}
+
+impl Infix {
+    fn syntax_kind(self) -> SyntaxKind {
+        match self {
+            Self::Binary(_) => SyntaxKind::BinaryExpression,
+            Self::Assign(_) => SyntaxKind::Assignment,
+            Self::Range(_) => SyntaxKind::RangeExpression,
+            Self::Cast => SyntaxKind::CastExpression,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Postfix {
+    Call,
+    Index,
+    Field,
+}
+
+impl Postfix {
+    fn syntax_kind(self) -> SyntaxKind {
+        match self {
+            Self::Call => SyntaxKind::CallExpression,
+            Self::Index => SyntaxKind::IndexExpression,
+            Self::Field => SyntaxKind::FieldExpression,
+        }
+    }
+}
+
+fn prefix_operator(token: &Token) -> Option<UnaryOperator> {
+    match token {
+        Token::Minus => Some(UnaryOperator::Neg),
+        _ => None,
+    }
+}
+
+/// The infix operators, with their precedence and associativity.
+pub(crate) fn infix_operator(token: &Token) -> Option<(Infix, Precedence, Associativity)> {
+    use Associativity::{Left, Right};
+    use Precedence as P;
+    Some(match token {
+        Token::Equal => (Infix::Assign(None), P::Assignment, Right),
+        Token::PlusEqual => (Infix::Assign(Some(Operator::Add)), P::Assignment, Right),
+        Token::MinusEqual => (Infix::Assign(Some(Operator::Sub)), P::Assignment, Right),
+        Token::StarEqual => (Infix::Assign(Some(Operator::Mul)), P::Assignment, Right),
+        Token::SlashEqual => (Infix::Assign(Some(Operator::Div)), P::Assignment, Right),
+        Token::PercentEqual => (Infix::Assign(Some(Operator::Rem)), P::Assignment, Right),
+        Token::AmpersandEqual => (Infix::Assign(Some(Operator::BitAnd)), P::Assignment, Right),
+        Token::PipeEqual => (Infix::Assign(Some(Operator::BitOr)), P::Assignment, Right),
+        Token::CaretEqual => (Infix::Assign(Some(Operator::BitXor)), P::Assignment, Right),
+        Token::ShiftLeftEqual => (Infix::Assign(Some(Operator::Shl)), P::Assignment, Right),
+        Token::ShiftRightEqual => (Infix::Assign(Some(Operator::Shr)), P::Assignment, Right),
+        Token::DotDot => (Infix::Range(false), P::Range, Left),
+        Token::DotDotEqual => (Infix::Range(true), P::Range, Left),
+        Token::Pipe => (Infix::Binary(Operator::BitOr), P::BitOr, Left),
+        Token::Caret => (Infix::Binary(Operator::BitXor), P::BitXor, Left),
+        Token::Ampersand => (Infix::Binary(Operator::BitAnd), P::BitAnd, Left),
+        Token::ShiftLeft => (Infix::Binary(Operator::Shl), P::Shift, Left),
+        Token::ShiftRight => (Infix::Binary(Operator::Shr), P::Shift, Left),
+        Token::Plus => (Infix::Binary(Operator::Add), P::Sum, Left),
+        Token::Minus => (Infix::Binary(Operator::Sub), P::Sum, Left),
+        Token::Star => (Infix::Binary(Operator::Mul), P::Product, Left),
+        Token::Slash => (Infix::Binary(Operator::Div), P::Product, Left),
+        Token::Percent => (Infix::Binary(Operator::Rem), P::Product, Left),
+        Token::As => (Infix::Cast, P::Cast, Left),
+        _ => return None,
+    })
+}
+
+/// The postfix operators, which all have [`Precedence::Postfix`].
+fn postfix_operator(token: &Token) -> Option<Postfix> {
+    match token {
+        Token::OpenParen => Some(Postfix::Call),
+        Token::OpenBracket => Some(Postfix::Index),
+        Token::Dot => Some(Postfix::Field),
+        _ => None,
+    }
+}
+
 impl Expression {
     pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
-        let head = tokens.peek().ok_or(ParseError::UnexpectedEoF)?.clone();
-        match head {
-            Token::Loop => return Self::parse_loop(tokens),
-            Token::Break => {
+        Self::parse_binding_power(tokens, 0)
+    }
+
+    /// Parses an expression made up of operators which bind at least as tightly as `min_power`.
+    ///
+    /// Every nested expression passes through here, so this is where nesting is limited. Chains of
+    /// left-associative and postfix operators are built in a loop, so they only nest once per
+    /// precedence level. The AST still nests them, so it is dropped, folded and compiled in loops
+    /// along them too.
+    fn parse_binding_power(tokens: &mut TokenStream, min_power: u8) -> Result<Self, ParseError> {
+        tokens.enter_nesting()?;
+        let expression = Self::parse_operators(tokens, min_power);
+        tokens.exit_nesting();
+        expression
+    }
+
+    fn parse_operators(tokens: &mut TokenStream, min_power: u8) -> Result<Self, ParseError> {
+        let checkpoint = tokens.checkpoint();
+        let mut expression = Self::parse_prefix(tokens)?;
+        while let Some(token) = tokens.peek() {
+            if let Some(operator) = postfix_operator(token) {
+                if Precedence::Postfix.binding_power() < min_power {
+                    break;
+                }
+                tokens.start_node_at(checkpoint, operator.syntax_kind());
+                expression = Self::parse_postfix(tokens, operator, expression)?;
+                tokens.finish_node();
+                continue;
+            }
+            let Some((operator, precedence, associativity)) = infix_operator(token) else {
+                break;
+            };
+            let (left_power, right_power) = precedence.infix_binding_power(associativity);
+            if left_power < min_power {
+                break;
+            }
+            tokens.start_node_at(checkpoint, operator.syntax_kind());
+            tokens.next();
+            expression = match operator {
+                Infix::Binary(operator) => {
+                    let rhs = Self::parse_binding_power(tokens, right_power)?;
+                    BinOp::new(operator, expression, rhs).into()
+                }
+                Infix::Assign(operator) => {
+                    if !expression.is_assignable() {
+                        return Err(ParseError::InvalidAssignmentTarget);
+                    }
+                    let value = Self::parse_binding_power(tokens, right_power)?;
+                    Assignment {
+                        target: Box::new(expression),
+                        operator,
+                        value: Box::new(value),
+                    }
+                    .into()
+                }
+                Infix::Range(inclusive) => Range {
+                    start: Some(Box::new(expression)),
+                    end: Self::parse_range_end(tokens, inclusive)?,
+                    inclusive,
+                }
+                .into(),
+                Infix::Cast => Cast {
+                    value: Box::new(expression),
+                    ty: Type::parse(tokens)?,
+                }
+                .into(),
+            };
+            tokens.finish_node();
+        }
+        Ok(expression)
+    }
+
+    fn parse_prefix(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        if let Some(operator) = tokens.peek().and_then(prefix_operator) {
+            tokens.start_node(SyntaxKind::PrefixExpression);
+            tokens.next();
+            let operand = Self::parse_binding_power(tokens, Precedence::Prefix.binding_power())?;
+            tokens.finish_node();
+            return Ok(Self::Unary(operator, Box::new(operand)));
+        }
+        match tokens.peek() {
+            Some(Token::Ampersand) => Self::parse_reference(tokens),
+            Some(Token::Star) => Self::parse_deref(tokens),
+            Some(Token::DotDot | Token::DotDotEqual) => Self::parse_range_to(tokens),
+            Some(Token::Loop) => Self::parse_loop(tokens),
+            Some(Token::For) => Self::parse_for(tokens),
+            Some(Token::Break) => {
+                tokens.start_node(SyntaxKind::BreakExpression);
                 tokens.next();
-                return Ok(Self::Break);
+                tokens.finish_node();
+                Ok(Self::Break)
             }
-            Token::Continue => {
+            Some(Token::Continue) => {
+                tokens.start_node(SyntaxKind::ContinueExpression);
                 tokens.next();
-                return Ok(Self::Continue);
-            }
-            Token::Identifier(name) => {
-                let next_two = (tokens.peek_ahead(1).cloned(), tokens.peek_ahead(2).cloned());
-                match next_two {
-                    (Some(Token::Equal), Some(Token::Equal)) => (),
-                    (Some(Token::Equal), Some(_)) => {
-                        tokens.expect_identifier()?;
-                        tokens.expect(Token::Equal)?;
-                        let value = Expression::parse(tokens)?;
-                        return Ok(Self::VariableAssignment(VariableAssignment {
-                            name: name.clone(),
-                            value: Box::new(value),
-                        }));
-                    }
-                    _ => (),
+                tokens.finish_node();
+                Ok(Self::Continue)
+            }
+            Some(Token::Match) => Self::parse_match(tokens),
+            Some(Token::Unsafe) => Self::parse_unsafe(tokens),
+            Some(Token::OpenParen) => {
+                tokens.start_node(SyntaxKind::ParenExpression);
+                tokens.next();
+                let expression = with_struct_literals(tokens, true, Expression::parse)?;
+                tokens.expect(Token::CloseParen)?;
+                tokens.finish_node();
+                Ok(expression)
+            }
+            Some(Token::OpenBrace) => Self::parse_block(tokens),
+            Some(Token::OpenBracket) => with_struct_literals(tokens, true, Self::parse_array),
+            Some(Token::Identifier(_)) => {
+                if Self::at_struct_literal(tokens) {
+                    return Self::parse_struct_literal(tokens);
                 }
+                if Self::at_asm(tokens) {
+                    return Self::parse_asm(tokens);
+                }
+                if matches!(tokens.peek_ahead(1), Some(Token::PathSeparator)) {
+                    return Path::parse(tokens).map(Self::Path);
+                }
+                tokens.start_node(SyntaxKind::Name);
+                let name = tokens.expect_identifier()?;
+                tokens.finish_node();
+                Ok(Expression::Variable(name.into()))
             }
-            _ => (),
+            Some(Token::CoreRegister(_)) => Self::parse_core_register(tokens),
+            _ => Self::parse_literal(tokens),
         }
-        Self::parse_expression(tokens)
     }
 
-    fn parse_expression(tokens: &mut TokenStream) -> Result<Self, ParseError> {
-        let mut expression = Self::parse_term(tokens)?;
-        while let Some(token) = tokens.peek() {
-            match token {
-                Token::Plus => {
+    /// Parses a register pseudo-variable, as in `$sp = 0x2000_2000`.
+    fn parse_core_register(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::CoreRegister);
+        let name = match tokens.next() {
+            Some(Token::CoreRegister(name)) => name,
+            Some(token) => return Err(ParseError::unexpected(token)),
+            None => return Err(ParseError::UnexpectedEoF),
+        };
+        tokens.finish_node();
+        Ok(Self::CoreRegister(name))
+    }
+
+    /// Parses a borrow, as in `&a[1..]` or `&mut buffer`.
+    fn parse_reference(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::ReferenceExpression);
+        tokens.expect(Token::Ampersand)?;
+        let mutable = parse_mut(tokens);
+        let value = Self::parse_binding_power(tokens, Precedence::Prefix.binding_power())?;
+        tokens.finish_node();
+        Ok(Reference {
+            value: Box::new(value),
+            mutable,
+        }
+        .into())
+    }
+
+    /// Parses a dereference, as in `*counter += 1`.
+    fn parse_deref(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::DerefExpression);
+        tokens.expect(Token::Star)?;
+        let value = Self::parse_binding_power(tokens, Precedence::Prefix.binding_power())?;
+        tokens.finish_node();
+        Ok(Self::Deref(Box::new(value)))
+    }
+
+    /// Parses a range without a start, as in `..4` or `..`.
+    fn parse_range_to(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::RangeExpression);
+        let inclusive = matches!(tokens.next(), Some(Token::DotDotEqual));
+        let end = Self::parse_range_end(tokens, inclusive)?;
+        tokens.finish_node();
+        Ok(Range {
+            start: None,
+            end,
+            inclusive,
+        }
+        .into())
+    }
+
+    /// Parses the end of a range after its `..` or `..=`. Only a range with `..` can leave it out,
+    /// as in `a[1..]` or `for i in 0.. { }`.
+    fn parse_range_end(
+        tokens: &mut TokenStream,
+        inclusive: bool,
+    ) -> Result<Option<Box<Self>>, ParseError> {
+        let has_end = match tokens.peek() {
+            None => false,
+            Some(
+                Token::CloseBracket
+                | Token::CloseParen
+                | Token::CloseBrace
+                | Token::Comma
+                | Token::Semi,
+            ) => false,
+            // Where a struct literal would be cut off, the brace starts the block after the range
+            Some(Token::OpenBrace) => tokens.struct_literals_allowed(),
+            Some(_) => true,
+        };
+        if has_end {
+            let (_, power) = Precedence::Range.infix_binding_power(Associativity::Left);
+            return Self::parse_binding_power(tokens, power).map(|end| Some(Box::new(end)));
+        }
+        match tokens.peek() {
+            _ if !inclusive => Ok(None),
+            Some(token) => Err(ParseError::unexpected(token.clone())),
+            None => Err(ParseError::UnexpectedEoF),
+        }
+    }
+
+    fn parse_literal(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let literal = match tokens.peek() {
+            Some(Token::Integer(value)) => Expression::Integer(*value),
+            Some(Token::TypedInteger(value, ty)) => Expression::TypedInteger(*value, *ty),
+            Some(Token::Char(c)) => Expression::Char(*c),
+            Some(Token::Byte(b)) => Expression::Byte(*b),
+            Some(Token::String(s)) => Expression::String(s.clone()),
+            Some(Token::ByteString(s)) => Expression::ByteString(s.clone()),
+            Some(token) => return Err(ParseError::unexpected(token.clone())),
+            None => return Err(ParseError::UnexpectedEoF),
+        };
+        tokens.start_node(SyntaxKind::Literal);
+        tokens.next();
+        tokens.finish_node();
+        Ok(literal)
+    }
+
+    /// Whether the tokens start a struct literal rather than a name followed by a block.
+    ///
+    /// A name followed by `{` is only taken as a struct literal if the braces are empty or start
+    /// with a field, which a block can't: `Point { x: 1 }` and `Point { x }` are struct literals.
+    fn at_struct_literal(tokens: &mut TokenStream) -> bool {
+        if !tokens.struct_literals_allowed()
+            || !matches!(tokens.peek_ahead(1), Some(Token::OpenBrace))
+        {
+            return false;
+        }
+        match tokens.peek_ahead(2) {
+            Some(Token::CloseBrace) => true,
+            Some(Token::Identifier(_)) => matches!(
+                tokens.peek_ahead(3),
+                Some(Token::Colon | Token::Comma | Token::CloseBrace)
+            ),
+            _ => false,
+        }
+    }
+
+    fn parse_struct_literal(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::StructLiteral);
+        let name = tokens.expect_identifier()?.into();
+        tokens.expect(Token::OpenBrace)?;
+        let mut fields = Vec::new();
+        while !matches!(tokens.peek(), Some(Token::CloseBrace)) {
+            tokens.start_node(SyntaxKind::FieldInit);
+            let name: Rc<str> = tokens.expect_identifier()?.into();
+            let value = if matches!(tokens.peek(), Some(Token::Colon)) {
+                tokens.next();
+                Expression::parse(tokens)?
+            } else {
+                Expression::Variable(name.clone())
+            };
+            tokens.finish_node();
+            fields.push(FieldInit { name, value });
+            match tokens.peek() {
+                Some(Token::Comma) => {
+                    tokens.next();
+                }
+                Some(Token::CloseBrace) => break,
+                None => return Err(ParseError::UnexpectedEoF),
+                Some(token) => return Err(ParseError::unexpected(token.clone())),
+            }
+        }
+        tokens.expect(Token::CloseBrace)?;
+        tokens.finish_node();
+        Ok(Self::StructLiteral(StructLiteral { name, fields }))
+    }
+
+    /// Parses an array literal, either listing its elements or as `[value; count]`.
+    fn parse_array(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::ArrayExpression);
+        tokens.expect(Token::OpenBracket)?;
+        let mut elements = Vec::new();
+        while !matches!(tokens.peek(), Some(Token::CloseBracket)) {
+            elements.push(Expression::parse(tokens)?);
+            match tokens.peek() {
+                Some(Token::Comma) => {
                     tokens.next();
-                    expression = Expression::BinOp(BinOp::Add(
-                        Box::new(expression),
-                        Box::new(Self::parse_term(tokens)?),
-                    ));
                 }
-                Token::Minus => {
+                Some(Token::Semi) if elements.len() == 1 => {
                     tokens.next();
-                    expression = Expression::BinOp(BinOp::Sub(
-                        Box::new(expression),
-                        Box::new(Self::parse_term(tokens)?),
-                    ));
+                    let count = Expression::parse(tokens)?;
+                    tokens.expect(Token::CloseBracket)?;
+                    tokens.finish_node();
+                    return Ok(ArrayRepeat {
+                        value: Box::new(elements.remove(0)),
+                        count: Box::new(count),
+                    }
+                    .into());
                 }
-                _ => break,
+                Some(Token::CloseBracket) => break,
+                None => return Err(ParseError::UnexpectedEoF),
+                Some(token) => return Err(ParseError::unexpected(token.clone())),
             }
         }
-        Ok(expression)
+        tokens.expect(Token::CloseBracket)?;
+        tokens.finish_node();
+        Ok(Self::Array(elements))
     }
 
-    fn parse_term(tokens: &mut TokenStream) -> Result<Self, ParseError> {
-        let mut expression = Self::parse_factor(tokens)?;
-        while let Some(token) = tokens.peek() {
-            match token {
-                Token::Star => {
+    fn parse_postfix(
+        tokens: &mut TokenStream,
+        operator: Postfix,
+        base: Self,
+    ) -> Result<Self, ParseError> {
+        let base = Box::new(base);
+        match operator {
+            Postfix::Call => Ok(Self::Call(Call {
+                function: base,
+                arguments: with_struct_literals(tokens, true, Self::parse_arguments)?,
+            })),
+            Postfix::Index => {
+                tokens.expect(Token::OpenBracket)?;
+                let index = Box::new(with_struct_literals(tokens, true, Expression::parse)?);
+                tokens.expect(Token::CloseBracket)?;
+                Ok(Self::Index(Index { base, index }))
+            }
+            Postfix::Field => {
+                tokens.expect(Token::Dot)?;
+                let name = tokens.expect_identifier()?.into();
+                Ok(Self::Field(Field { base, name }))
+            }
+        }
+    }
+
+    fn parse_arguments(tokens: &mut TokenStream) -> Result<Vec<Self>, ParseError> {
+        tokens.start_node(SyntaxKind::ArgumentList);
+        tokens.expect(Token::OpenParen)?;
+        let mut arguments = Vec::new();
+        while !matches!(tokens.peek(), Some(Token::CloseParen)) {
+            arguments.push(Expression::parse(tokens)?);
+            match tokens.peek() {
+                Some(Token::Comma) => {
                     tokens.next();
-                    expression = Expression::BinOp(BinOp::Mul(
-                        Box::new(expression),
-                        Box::new(Self::parse_factor(tokens)?),
-                    ));
                 }
-                Token::Slash => {
+                Some(Token::CloseParen) => break,
+                None => return Err(ParseError::UnexpectedEoF),
+                Some(token) => return Err(ParseError::unexpected(token.clone())),
+            }
+        }
+        tokens.expect(Token::CloseParen)?;
+        tokens.finish_node();
+        Ok(arguments)
+    }
+
+    /// Parses an `unsafe` block.
+    fn parse_unsafe(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::UnsafeExpression);
+        tokens.expect(Token::Unsafe)?;
+        let body = Self::parse_block(tokens)?;
+        tokens.finish_node();
+        Ok(Self::Unsafe(Box::new(body)))
+    }
+
+    /// Whether the next tokens start inline assembly, `asm!(...)`. `asm` is only special before
+    /// the `!`, so it can still be used as a name.
+    fn at_asm(tokens: &mut TokenStream) -> bool {
+        matches!(tokens.peek(), Some(Token::Identifier(name)) if &**name == "asm")
+            && matches!(tokens.peek_ahead(1), Some(Token::Bang))
+    }
+
+    /// Parses inline assembly, as in `asm!("mrs {}, primask", out(reg) mask, options(nomem))`.
+    /// The template strings come first, then the operands, `clobber_abi(...)` and `options(...)`.
+    fn parse_asm(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::AsmExpression);
+        tokens.expect_identifier()?;
+        tokens.expect(Token::Bang)?;
+        tokens.expect(Token::OpenParen)?;
+        let mut asm = Asm {
+            template: vec![expect_string(tokens)?],
+            operands: Vec::new(),
+            clobber_abis: Vec::new(),
+            options: AsmOptions::default(),
+        };
+        while matches!(tokens.peek(), Some(Token::Comma)) {
+            tokens.next();
+            match tokens.peek() {
+                Some(Token::CloseParen) => break,
+                Some(Token::String(_)) if asm.operands.is_empty() => {
+                    asm.template.push(expect_string(tokens)?);
+                }
+                Some(Token::Identifier(name)) if &**name == "clobber_abi" => {
                     tokens.next();
-                    expression = Expression::BinOp(BinOp::Div(
-                        Box::new(expression),
-                        Box::new(Self::parse_factor(tokens)?),
-                    ));
+                    tokens.expect(Token::OpenParen)?;
+                    asm.clobber_abis.push(expect_string(tokens)?);
+                    tokens.expect(Token::CloseParen)?;
                 }
-                _ => break,
+                Some(Token::Identifier(name)) if &**name == "options" => {
+                    Self::parse_asm_options(tokens, &mut asm.options)?;
+                }
+                _ => asm.operands.push(Self::parse_asm_operand(tokens)?),
             }
         }
-        Ok(expression)
+        tokens.expect(Token::CloseParen)?;
+        tokens.finish_node();
+        Ok(Self::Asm(Box::new(asm)))
     }
 
-    fn parse_factor(tokens: &mut TokenStream) -> Result<Self, ParseError> {
-        match tokens.next() {
-            Some(Token::OpenParen) => {
-                let expression = Expression::parse(tokens)?;
-                tokens.expect(Token::CloseParen)?;
-                Ok(expression)
+    /// Parses an operand of inline assembly, such as `in(reg) x` or `out("r0") _`.
+    fn parse_asm_operand(tokens: &mut TokenStream) -> Result<AsmOperand, ParseError> {
+        tokens.start_node(SyntaxKind::AsmOperand);
+        let direction: Rc<str> = match tokens.next() {
+            Some(Token::In) => "in".into(),
+            Some(Token::Identifier(name)) if matches!(&*name, "out" | "inout") => name,
+            Some(token) => return Err(ParseError::unexpected(token)),
+            None => return Err(ParseError::UnexpectedEoF),
+        };
+        tokens.expect(Token::OpenParen)?;
+        let register = match tokens.next() {
+            Some(Token::Identifier(class)) => AsmRegister::Class(class),
+            Some(Token::String(name)) => AsmRegister::Explicit(name),
+            Some(token) => return Err(ParseError::unexpected(token)),
+            None => return Err(ParseError::UnexpectedEoF),
+        };
+        tokens.expect(Token::CloseParen)?;
+        let operand = match &*direction {
+            "in" => AsmOperand::In {
+                register,
+                value: with_struct_literals(tokens, true, Expression::parse)?,
+            },
+            "out" if matches!(tokens.peek(), Some(Token::Identifier(name)) if &**name == "_") => {
+                tokens.next();
+                AsmOperand::Out {
+                    register,
+                    place: None,
+                }
+            }
+            "out" => AsmOperand::Out {
+                register,
+                place: Some(Expression::parse(tokens)?),
+            },
+            _ => AsmOperand::InOut {
+                register,
+                place: Expression::parse(tokens)?,
+            },
+        };
+        tokens.finish_node();
+        Ok(operand)
+    }
+
+    /// Parses `options(...)` of inline assembly, setting each option it lists.
+    fn parse_asm_options(
+        tokens: &mut TokenStream,
+        options: &mut AsmOptions,
+    ) -> Result<(), ParseError> {
+        tokens.expect_identifier()?;
+        tokens.expect(Token::OpenParen)?;
+        while !matches!(tokens.peek(), Some(Token::CloseParen)) {
+            let option = match tokens.next() {
+                Some(Token::Identifier(name)) => match &*name {
+                    "volatile" => &mut options.volatile,
+                    "nomem" => &mut options.nomem,
+                    "nostack" => &mut options.nostack,
+                    "preserves_flags" => &mut options.preserves_flags,
+                    _ => return Err(ParseError::UnexpectedToken(Token::Identifier(name))),
+                },
+                Some(token) => return Err(ParseError::unexpected(token)),
+                None => return Err(ParseError::UnexpectedEoF),
+            };
+            *option = true;
+            if !matches!(tokens.peek(), Some(Token::CloseParen)) {
+                tokens.expect(Token::Comma)?;
             }
-            Some(Token::OpenBrace) => Self::parse_block(tokens),
-            Some(Token::Identifier(name)) => Ok(Expression::Variable(name)),
-            Some(Token::Integer(value)) => Ok(Expression::Integer(value)),
-            Some(token) => Err(ParseError::UnexpectedToken(token)),
-            None => Err(ParseError::UnexpectedEoF),
         }
+        tokens.expect(Token::CloseParen)?;
+        Ok(())
     }
 
     fn parse_block(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        with_struct_literals(tokens, true, Self::parse_block_contents)
+    }
+
+    fn parse_block_contents(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Block);
+        tokens.expect(Token::OpenBrace)?;
         let mut body = Vec::new();
         let mut last = None;
         while !matches!(tokens.peek(), Some(Token::CloseBrace | Token::Semi)) {
+            // Doc comments on statements don't document anything, so they are treated like regular comments
+            if matches!(tokens.peek(), Some(Token::DocComment(_))) {
+                tokens.next();
+                continue;
+            }
             let expr = Statement::parse(tokens)?;
             match tokens.peek() {
                 Some(Token::Semi) => {
@@ -176,10 +1211,11 @@ impl Expression {
                 }
                 Some(Token::CloseBrace) => last = Some(expr),
                 None => return Err(ParseError::UnexpectedEoF),
-                Some(token) => return Err(ParseError::UnexpectedToken(token.clone())),
+                Some(token) => return Err(ParseError::unexpected(token.clone())),
             }
         }
         tokens.expect(Token::CloseBrace)?;
+        tokens.finish_node();
 
         let last = match last {
             None => None,
@@ -193,7 +1229,68 @@ impl Expression {
         }))
     }
 
+    fn parse_match(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::MatchExpression);
+        tokens.expect(Token::Match)?;
+        // The brace after the scrutinee starts the arms, so it can't be a struct literal
+        let scrutinee = Box::new(with_struct_literals(tokens, false, Expression::parse)?);
+        tokens.expect(Token::OpenBrace)?;
+        let arms = with_struct_literals(tokens, true, Self::parse_match_arms)?;
+        tokens.expect(Token::CloseBrace)?;
+        tokens.finish_node();
+        Ok(Self::Match(Match { scrutinee, arms }))
+    }
+
+    /// Parses arms up to the closing brace. The comma after an arm can be left out if its body is a block.
+    fn parse_match_arms(tokens: &mut TokenStream) -> Result<Vec<MatchArm>, ParseError> {
+        let mut arms = Vec::new();
+        while !matches!(tokens.peek(), Some(Token::CloseBrace)) {
+            tokens.start_node(SyntaxKind::MatchArm);
+            let pattern = Pattern::parse(tokens)?;
+            tokens.expect(Token::FatArrow)?;
+            // A block ends the arm, like it ends a statement, so no comma is needed after it
+            let is_block = matches!(tokens.peek(), Some(Token::OpenBrace));
+            let body = if is_block {
+                Self::parse_block(tokens)?
+            } else {
+                Expression::parse(tokens)?
+            };
+            tokens.finish_node();
+            arms.push(MatchArm { pattern, body });
+            match tokens.peek() {
+                Some(Token::Comma) => {
+                    tokens.next();
+                }
+                Some(Token::CloseBrace) => break,
+                Some(_) if is_block => {}
+                None => return Err(ParseError::UnexpectedEoF),
+                Some(token) => return Err(ParseError::unexpected(token.clone())),
+            }
+        }
+        Ok(arms)
+    }
+
+    fn parse_for(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::ForExpression);
+        tokens.expect(Token::For)?;
+        let binding = tokens.expect_identifier()?.into();
+        tokens.expect(Token::In)?;
+        // The brace after the iterable starts the body, so it can't be a struct literal
+        let iterable = Box::new(with_struct_literals(tokens, false, Expression::parse)?);
+        if !matches!(tokens.peek(), Some(Token::OpenBrace)) {
+            return Err(ParseError::ExpectedToken(Token::OpenBrace));
+        }
+        let body = Box::new(Self::parse_block(tokens)?);
+        tokens.finish_node();
+        Ok(Self::For(For {
+            binding,
+            iterable,
+            body,
+        }))
+    }
+
     fn parse_loop(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::LoopExpression);
         tokens.expect(Token::Loop)?;
         let needs_semi = tokens
             .peek()
@@ -202,10 +1299,113 @@ impl Expression {
         if needs_semi {
             tokens.expect(Token::Semi)?;
         }
+        tokens.finish_node();
         Ok(Self::Loop(Box::new(body)))
     }
 }
 
+impl Pattern {
+    /// Parses a pattern, or several separated by `|`. Variant patterns contain patterns, so their
+    /// nesting is limited here, as that of expressions is.
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.enter_nesting()?;
+        let pattern = Self::parse_alternatives(tokens);
+        tokens.exit_nesting();
+        pattern
+    }
+
+    fn parse_alternatives(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let checkpoint = tokens.checkpoint();
+        let first = Self::parse_single(tokens)?;
+        if !matches!(tokens.peek(), Some(Token::Pipe)) {
+            return Ok(first);
+        }
+        tokens.start_node_at(checkpoint, SyntaxKind::Pattern);
+        let mut alternatives = vec![first];
+        while matches!(tokens.peek(), Some(Token::Pipe)) {
+            tokens.next();
+            alternatives.push(Self::parse_single(tokens)?);
+        }
+        tokens.finish_node();
+        Ok(Self::Or(alternatives))
+    }
+
+    fn parse_single(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Pattern);
+        let is_path = matches!(tokens.peek_ahead(1), Some(Token::PathSeparator));
+        let pattern = match tokens.peek() {
+            Some(Token::Identifier(name)) if &**name == "_" => {
+                tokens.next();
+                Self::Wildcard
+            }
+            Some(Token::Identifier(_)) if is_path => {
+                let path = Path::parse(tokens)?;
+                let mut fields = Vec::new();
+                if matches!(tokens.peek(), Some(Token::OpenParen)) {
+                    tokens.next();
+                    while !matches!(tokens.peek(), Some(Token::CloseParen)) {
+                        fields.push(Self::parse(tokens)?);
+                        match tokens.peek() {
+                            Some(Token::Comma) => {
+                                tokens.next();
+                            }
+                            Some(Token::CloseParen) => break,
+                            None => return Err(ParseError::UnexpectedEoF),
+                            Some(token) => return Err(ParseError::unexpected(token.clone())),
+                        }
+                    }
+                    tokens.expect(Token::CloseParen)?;
+                }
+                Self::Variant(path, fields)
+            }
+            Some(Token::Identifier(_)) => Self::Binding(tokens.expect_identifier()?.into()),
+            _ => {
+                let start = Self::parse_literal(tokens)?;
+                let inclusive = match tokens.peek() {
+                    Some(Token::DotDotEqual) => true,
+                    Some(Token::DotDot) => false,
+                    _ => {
+                        tokens.finish_node();
+                        return Ok(Self::Literal(start));
+                    }
+                };
+                tokens.next();
+                let end = Self::parse_literal(tokens)?;
+                Self::Range {
+                    start,
+                    end,
+                    inclusive,
+                }
+            }
+        };
+        tokens.finish_node();
+        Ok(pattern)
+    }
+
+    /// Parses a literal, which in a pattern can be negated but is otherwise not an expression.
+    fn parse_literal(tokens: &mut TokenStream) -> Result<Expression, ParseError> {
+        if !matches!(tokens.peek(), Some(Token::Minus)) {
+            return Expression::parse_literal(tokens);
+        }
+        tokens.start_node(SyntaxKind::PrefixExpression);
+        tokens.next();
+        let literal = Expression::parse_literal(tokens)?;
+        tokens.finish_node();
+        Ok(Expression::Unary(UnaryOperator::Neg, Box::new(literal)))
+    }
+}
+
+impl Path {
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::Path);
+        let ty = tokens.expect_identifier()?;
+        tokens.expect(Token::PathSeparator)?;
+        let name = tokens.expect_identifier()?;
+        tokens.finish_node();
+        Ok(Self::new(ty, name))
+    }
+}
+
 impl Statement {
     pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
         match tokens.peek() {
@@ -217,32 +1417,108 @@ impl Statement {
 
 impl VariableDefinition {
     pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::LetStatement);
         tokens.expect(Token::Let)?;
-        let is_mutable = if matches!(tokens.peek(), Some(Token::Mut)) {
-            tokens.next();
-            true
-        } else {
-            false
-        };
+        let is_mutable = parse_mut(tokens);
         let identifier = tokens.expect_identifier()?;
+        let ty = Type::parse_annotation(tokens)?;
         if matches!(tokens.peek(), Some(Token::Equal)) {
             tokens.expect(Token::Equal)?;
             let value = Expression::parse(tokens)?;
+            tokens.finish_node();
             Ok(Self {
                 spec: VariableSpec {
                     name: identifier.into(),
                     is_mutable,
+                    ty,
                 },
                 value: Some(Box::new(value)),
             })
         } else {
+            tokens.finish_node();
             Ok(Self {
                 spec: VariableSpec {
                     name: identifier.into(),
                     is_mutable,
+                    ty,
                 },
                 value: None,
             })
         }
     }
 }
+
+impl Type {
+    /// Parses a type. Array, reference and pointer types contain types, so their nesting is
+    /// limited here, as that of expressions is.
+    pub fn parse(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.enter_nesting()?;
+        let ty = Self::parse_unnested(tokens);
+        tokens.exit_nesting();
+        ty
+    }
+
+    fn parse_unnested(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        match tokens.peek() {
+            Some(Token::OpenBracket) => return Self::parse_array(tokens),
+            Some(Token::Ampersand) => return Self::parse_reference(tokens),
+            Some(Token::Star) => return Self::parse_pointer(tokens),
+            _ => {}
+        }
+        tokens.start_node(SyntaxKind::Type);
+        let name = tokens.expect_identifier()?;
+        tokens.finish_node();
+        Ok(Self::Named(name.into()))
+    }
+
+    /// Parses an array type, or a slice type if it has no length.
+    fn parse_array(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        let checkpoint = tokens.checkpoint();
+        tokens.expect(Token::OpenBracket)?;
+        let element = Self::parse(tokens)?;
+        if matches!(tokens.peek(), Some(Token::CloseBracket)) {
+            tokens.start_node_at(checkpoint, SyntaxKind::SliceType);
+            tokens.next();
+            tokens.finish_node();
+            return Ok(Self::Slice(Box::new(element)));
+        }
+        tokens.start_node_at(checkpoint, SyntaxKind::ArrayType);
+        tokens.expect(Token::Semi)?;
+        let length = with_struct_literals(tokens, true, Expression::parse)?;
+        tokens.expect(Token::CloseBracket)?;
+        tokens.finish_node();
+        Ok(Self::Array(Box::new(element), Box::new(length)))
+    }
+
+    fn parse_reference(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::ReferenceType);
+        tokens.expect(Token::Ampersand)?;
+        let mutable = parse_mut(tokens);
+        let ty = Self::parse(tokens)?;
+        tokens.finish_node();
+        Ok(Self::Reference(Box::new(ty), mutable))
+    }
+
+    fn parse_pointer(tokens: &mut TokenStream) -> Result<Self, ParseError> {
+        tokens.start_node(SyntaxKind::PointerType);
+        tokens.expect(Token::Star)?;
+        let mutable = match tokens.next() {
+            Some(Token::Const) => false,
+            Some(Token::Mut) => true,
+            Some(token) => return Err(ParseError::unexpected(token)),
+            None => return Err(ParseError::UnexpectedEoF),
+        };
+        let ty = Self::parse(tokens)?;
+        tokens.finish_node();
+        Ok(Self::Pointer(Box::new(ty), mutable))
+    }
+
+    /// Parses an optional `: Type` annotation, as found after the name of a variable.
+    fn parse_annotation(tokens: &mut TokenStream) -> Result<Option<Self>, ParseError> {
+        if !matches!(tokens.peek(), Some(Token::Colon)) {
+            return Ok(None);
+        }
+        tokens.expect(Token::Colon)?;
+        Self::parse(tokens).map(Some)
+    }
+}
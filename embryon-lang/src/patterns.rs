@@ -0,0 +1,297 @@
+//! Checks the arms of a `match`: whether together they cover every value, and whether each
+//! can match anything the arms before it don't.
+//!
+//! This is the usefulness algorithm from "Warnings for pattern matching" (Maranget, 2007):
+//! a pattern is useful after some others if there is a value only it matches.
+use crate::types::{IntType, Type};
+
+/// A pattern, reduced to which values it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pat {
+    /// Matches any value, like `_` or a binding.
+    Wild,
+    /// An inclusive range of integers. A literal is a range of one value.
+    Range(i128, i128),
+    /// An enum variant, by index, and patterns for its fields.
+    Variant(usize, Vec<Pat>),
+    Or(Vec<Pat>),
+}
+
+/// The result of checking the arms of a `match`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MatchCheck {
+    /// The indices of arms which can't match anything the arms before them don't.
+    pub unreachable: Vec<usize>,
+    /// A pattern for values that no arm matches, if there are any.
+    pub missing: Option<Pat>,
+}
+
+/// Checks the patterns of the arms of a `match` on a value of type `ty`.
+pub fn check_match(arms: &[Pat], ty: &Type) -> MatchCheck {
+    let tys = [ty.clone()];
+    let mut rows: Vec<Vec<Pat>> = Vec::new();
+    let mut unreachable = Vec::new();
+    for (index, arm) in arms.iter().enumerate() {
+        let row = vec![arm.clone()];
+        if find_witness(&rows, &row, &tys).is_none() {
+            unreachable.push(index);
+        }
+        rows.push(row);
+    }
+    let missing = find_witness(&rows, &[Pat::Wild], &tys).map(|mut witness| witness.remove(0));
+    MatchCheck {
+        unreachable,
+        missing,
+    }
+}
+
+/// Writes `pat` the way it would appear in source code, for error messages.
+pub fn describe(pat: &Pat, ty: &Type) -> String {
+    match (pat, ty) {
+        (Pat::Range(start, end), Type::Int(int)) => describe_range(*start, *end, *int),
+        (Pat::Variant(index, fields), Type::Enum(ty)) => {
+            let variant = &ty.variants[*index];
+            if fields.is_empty() {
+                return format!("{}::{}", ty.name, variant.name);
+            }
+            let fields: Vec<_> = fields
+                .iter()
+                .zip(&variant.payload.fields)
+                .map(|(pat, field)| describe(pat, &field.ty))
+                .collect();
+            format!("{}::{}({})", ty.name, variant.name, fields.join(", "))
+        }
+        (Pat::Or(alternatives), ty) => {
+            let alternatives: Vec<_> = alternatives.iter().map(|pat| describe(pat, ty)).collect();
+            alternatives.join(" | ")
+        }
+        _ => "_".to_string(),
+    }
+}
+
+fn describe_range(start: i128, end: i128, ty: IntType) -> String {
+    let bound = |value: i128| match value {
+        value if value == ty.min() && ty.signed => format!("{ty}::MIN"),
+        value if value == ty.max() => format!("{ty}::MAX"),
+        value => value.to_string(),
+    };
+    if start == end {
+        bound(start)
+    } else {
+        format!("{}..={}", bound(start), bound(end))
+    }
+}
+
+/// A kind of value that a pattern can match, used to split up the values of a type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Constructor {
+    Range(i128, i128),
+    Variant(usize),
+    /// Any value of a type that patterns can't look inside of, such as a struct.
+    Single,
+}
+
+/// Finds values that `row` matches but no row of `matrix` does, given one pattern per column
+/// of type `tys`. Returns them as a pattern per column, or `None` if `row` is not useful.
+fn find_witness(matrix: &[Vec<Pat>], row: &[Pat], tys: &[Type]) -> Option<Vec<Pat>> {
+    let Some((head, rest)) = row.split_first() else {
+        return matrix.is_empty().then(Vec::new);
+    };
+    let matrix = expand_or(matrix);
+    if let Pat::Or(alternatives) = head {
+        return alternatives.iter().find_map(|alternative| {
+            let row: Vec<_> = std::iter::once(alternative.clone())
+                .chain(rest.iter().cloned())
+                .collect();
+            find_witness(&matrix, &row, tys)
+        });
+    }
+    let ty = &tys[0];
+    for constructor in constructors(head, &matrix, ty) {
+        let field_types = field_types(ty, constructor);
+        let arity = field_types.len();
+        let Some(row) = specialize(row, constructor, arity) else {
+            continue;
+        };
+        let specialized: Vec<_> = matrix
+            .iter()
+            .filter_map(|other| specialize(other, constructor, arity))
+            .collect();
+        let tys: Vec<_> = field_types
+            .into_iter()
+            .chain(tys[1..].iter().cloned())
+            .collect();
+        if let Some(mut witness) = find_witness(&specialized, &row, &tys) {
+            let fields: Vec<_> = witness.drain(..arity).collect();
+            let pat = match constructor {
+                Constructor::Range(start, end) => Pat::Range(start, end),
+                Constructor::Variant(index) => Pat::Variant(index, fields),
+                Constructor::Single => Pat::Wild,
+            };
+            witness.insert(0, pat);
+            return Some(witness);
+        }
+    }
+    None
+}
+
+/// Replaces rows starting with an or-pattern by a row for each alternative.
+fn expand_or(matrix: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    let mut expanded = Vec::new();
+    for row in matrix {
+        match row.split_first() {
+            Some((Pat::Or(alternatives), rest)) => {
+                let rows: Vec<_> = alternatives
+                    .iter()
+                    .map(|alternative| {
+                        std::iter::once(alternative.clone())
+                            .chain(rest.iter().cloned())
+                            .collect()
+                    })
+                    .collect();
+                expanded.extend(expand_or(&rows));
+            }
+            _ => expanded.push(row.clone()),
+        }
+    }
+    expanded
+}
+
+/// The constructors to try for `head`, split so that every pattern at the head of `matrix`
+/// either matches all values of a constructor or none of them.
+fn constructors(head: &Pat, matrix: &[Vec<Pat>], ty: &Type) -> Vec<Constructor> {
+    match ty {
+        Type::Int(int) => {
+            let (start, end) = match head {
+                Pat::Range(start, end) => (*start, *end),
+                _ => (int.min(), int.max()),
+            };
+            split_range(start, end, matrix)
+        }
+        Type::Enum(ty) => match head {
+            Pat::Variant(index, _) => vec![Constructor::Variant(*index)],
+            _ => (0..ty.variants.len()).map(Constructor::Variant).collect(),
+        },
+        _ => vec![Constructor::Single],
+    }
+}
+
+/// Splits `start..=end` at the boundaries of the ranges at the head of `matrix`.
+fn split_range(start: i128, end: i128, matrix: &[Vec<Pat>]) -> Vec<Constructor> {
+    let mut boundaries = vec![start];
+    for row in matrix {
+        if let Some(Pat::Range(row_start, row_end)) = row.first() {
+            boundaries.push(*row_start);
+            boundaries.push(row_end + 1);
+        }
+    }
+    boundaries.retain(|boundary| (start..=end).contains(boundary));
+    boundaries.sort_unstable();
+    boundaries.dedup();
+    boundaries
+        .iter()
+        .zip(boundaries[1..].iter().map(|next| next - 1).chain([end]))
+        .map(|(start, end)| Constructor::Range(*start, end))
+        .collect()
+}
+
+fn field_types(ty: &Type, constructor: Constructor) -> Vec<Type> {
+    match (ty, constructor) {
+        (Type::Enum(ty), Constructor::Variant(index)) => ty.variants[index]
+            .payload
+            .fields
+            .iter()
+            .map(|field| field.ty.clone())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The rest of `row` if its head matches the values of `constructor`, with the head replaced by
+/// patterns for the constructor's `arity` fields.
+fn specialize(row: &[Pat], constructor: Constructor, arity: usize) -> Option<Vec<Pat>> {
+    let (head, rest) = row.split_first()?;
+    let fields = match (head, constructor) {
+        (Pat::Wild, _) => vec![Pat::Wild; arity],
+        (Pat::Range(start, end), Constructor::Range(from, to)) if *start <= from && to <= *end => {
+            Vec::new()
+        }
+        (Pat::Variant(index, fields), Constructor::Variant(variant)) if *index == variant => {
+            fields.clone()
+        }
+        _ => return None,
+    };
+    Some(fields.into_iter().chain(rest.iter().cloned()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::patterns::{MatchCheck, Pat, check_match, describe};
+    use crate::types::{EnumType, IntType, Type};
+    use std::rc::Rc;
+
+    fn u8() -> Type {
+        Type::Int(IntType::new(8, false))
+    }
+
+    fn command() -> Type {
+        Type::Enum(Rc::new(EnumType::new(
+            "Command".into(),
+            IntType::DEFAULT,
+            vec![("Stop".into(), 0, vec![]), ("Write".into(), 1, vec![u8()])],
+            4,
+        )))
+    }
+
+    #[test]
+    fn integer_ranges() {
+        let arms = [Pat::Range(0, 9), Pat::Range(10, 200), Pat::Range(5, 5)];
+        let check = check_match(&arms, &u8());
+        assert_eq!(check.unreachable, vec![2]);
+        assert_eq!(check.missing, Some(Pat::Range(201, 255)));
+        assert_eq!(describe(&Pat::Range(201, 255), &u8()), "201..=u8::MAX");
+
+        let arms = [Pat::Range(0, 127), Pat::Range(128, 255)];
+        assert_eq!(
+            check_match(&arms, &u8()),
+            MatchCheck {
+                unreachable: vec![],
+                missing: None,
+            }
+        );
+    }
+
+    #[test]
+    fn wildcards_and_or_patterns() {
+        let arms = [
+            Pat::Or(vec![Pat::Range(1, 1), Pat::Range(3, 3)]),
+            Pat::Wild,
+            Pat::Wild,
+        ];
+        let check = check_match(&arms, &u8());
+        assert_eq!(check.unreachable, vec![2]);
+        assert_eq!(check.missing, None);
+    }
+
+    #[test]
+    fn enum_variants() {
+        let ty = command();
+        let arms = [
+            Pat::Variant(1, vec![Pat::Range(0, 0)]),
+            Pat::Variant(0, vec![]),
+        ];
+        let check = check_match(&arms, &ty);
+        assert_eq!(check.unreachable, vec![]);
+        let missing = check.missing.unwrap();
+        assert_eq!(describe(&missing, &ty), "Command::Write(1..=u8::MAX)");
+
+        let arms = [
+            Pat::Variant(1, vec![Pat::Wild]),
+            Pat::Variant(0, vec![]),
+            Pat::Variant(1, vec![Pat::Range(3, 3)]),
+        ];
+        let check = check_match(&arms, &ty);
+        assert_eq!(check.unreachable, vec![2]);
+        assert_eq!(check.missing, None);
+    }
+}
@@ -0,0 +1,470 @@
+//! A lossless concrete syntax tree.
+//!
+//! The tree comes in two layers. Green nodes are immutable, know only their own text and can be
+//! shared between trees. Red nodes ([`SyntaxNode`]) wrap a green node with its parent and its
+//! offset in the source, and are created on demand while walking the tree.
+//!
+//! Every byte of the source belongs to exactly one token in the tree, including whitespace and
+//! comments, so [`SyntaxNode::text`] of the root always reproduces the source.
+
+use crate::tokens::Token;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    Module,
+    Function,
+    Abi,
+    ParameterList,
+    Parameter,
+    ReturnType,
+    Constant,
+    Static,
+    Struct,
+    StructField,
+    Enum,
+    Variant,
+    Peripheral,
+    Register,
+    Bitfield,
+    Attribute,
+    Type,
+    ArrayType,
+    SliceType,
+    ReferenceType,
+    PointerType,
+    LetStatement,
+    Block,
+    Literal,
+    Name,
+    CoreRegister,
+    Path,
+    ParenExpression,
+    PrefixExpression,
+    BinaryExpression,
+    CastExpression,
+    Assignment,
+    CallExpression,
+    ArgumentList,
+    IndexExpression,
+    FieldExpression,
+    StructLiteral,
+    FieldInit,
+    ArrayExpression,
+    RangeExpression,
+    ReferenceExpression,
+    DerefExpression,
+    MatchExpression,
+    UnsafeExpression,
+    AsmExpression,
+    AsmOperand,
+    MatchArm,
+    Pattern,
+    LoopExpression,
+    ForExpression,
+    BreakExpression,
+    ContinueExpression,
+    /// Input that could not be parsed.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenToken {
+    Whitespace(Rc<str>),
+    /// A regular comment. Doc comments are tokens, as they are part of the syntax.
+    Comment(Rc<str>),
+    Token(Token, Rc<str>),
+}
+
+impl GreenToken {
+    pub fn text(&self) -> &str {
+        match self {
+            GreenToken::Whitespace(text)
+            | GreenToken::Comment(text)
+            | GreenToken::Token(_, text) => text,
+        }
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        !matches!(self, GreenToken::Token(..))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(GreenToken),
+}
+
+impl GreenElement {
+    fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.text_len,
+            GreenElement::Token(token) => token.text().len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<GreenElement>,
+    text_len: usize,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        let text_len = children.iter().map(GreenElement::text_len).sum();
+        Self {
+            kind,
+            children,
+            text_len,
+        }
+    }
+
+    pub fn text_len(&self) -> usize {
+        self.text_len
+    }
+
+    fn write_text(&self, out: &mut String) {
+        let mut stack = vec![self.children.iter()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                Some(GreenElement::Node(node)) => stack.push(node.children.iter()),
+                Some(GreenElement::Token(token)) => out.push_str(token.text()),
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Chains of operators nest a node per operator, so trees can be much deeper than the stack
+/// allows recursing. They are dropped in a loop instead.
+impl Drop for GreenNode {
+    fn drop(&mut self) {
+        let mut children = std::mem::take(&mut self.children);
+        while let Some(child) = children.pop() {
+            let GreenElement::Node(node) = child else {
+                continue;
+            };
+            if let Ok(mut node) = Rc::try_unwrap(node) {
+                children.append(&mut node.children);
+            }
+        }
+    }
+}
+
+/// A position in the children of the node being built, used to wrap already built children in
+/// a new node once it is known that they belong to one, such as the left operand of an operator.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint(usize);
+
+#[derive(Debug, Default)]
+pub struct GreenNodeBuilder {
+    parents: Vec<(SyntaxKind, usize)>,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNodeBuilder {
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.children.len())
+    }
+
+    /// Starts a node containing everything built since `checkpoint`.
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+        let Checkpoint(start) = checkpoint;
+        assert!(
+            self.parents.last().is_none_or(|&(_, first)| first <= start),
+            "checkpoint is outside of the current node"
+        );
+        self.parents.push((kind, start));
+    }
+
+    pub fn token(&mut self, token: GreenToken) {
+        self.children.push(GreenElement::Token(token));
+    }
+
+    pub fn finish_node(&mut self) {
+        let (kind, first) = self.parents.pop().expect("no node to finish");
+        let children = self.children.split_off(first);
+        self.children
+            .push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+    }
+
+    /// The number of nodes which have been started but not finished.
+    pub fn depth(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Finishes any open nodes and returns the root.
+    pub fn finish(mut self) -> Rc<GreenNode> {
+        while self.depth() > 0 {
+            self.finish_node();
+        }
+        match self.children.pop() {
+            Some(GreenElement::Node(root)) if self.children.is_empty() => root,
+            _ => panic!("a syntax tree must have exactly one root node"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    offset: usize,
+}
+
+/// A red node deep in the tree holds a long chain of parents, which is dropped in a loop.
+impl Drop for NodeData {
+    fn drop(&mut self) {
+        let mut parent = self.parent.take();
+        while let Some(SyntaxNode(data)) = parent {
+            parent = Rc::try_unwrap(data)
+                .ok()
+                .and_then(|mut data| data.parent.take());
+        }
+    }
+}
+
+/// A node in the syntax tree, aware of its parent and where it is in the source.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            parent: None,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    /// The byte range of the source covered by this node.
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.text_len
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.0.green.text_len);
+        self.0.green.write_text(&mut text);
+        text
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children
+            .iter()
+            .map(|child| {
+                let start = offset;
+                offset += child.text_len();
+                match child {
+                    GreenElement::Node(green) => {
+                        SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                            green: green.clone(),
+                            parent: Some(self.clone()),
+                            offset: start,
+                        })))
+                    }
+                    GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                        green: green.clone(),
+                        parent: self.clone(),
+                        offset: start,
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    /// All tokens in this node, in source order, including trivia.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        let mut stack = vec![self.children_with_tokens().into_iter()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                Some(SyntaxElement::Node(node)) => {
+                    stack.push(node.children_with_tokens().into_iter());
+                }
+                Some(SyntaxElement::Token(token)) => tokens.push(token),
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        tokens
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+impl Eq for SyntaxNode {}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{:?}", self.kind(), self.text_range())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    green: GreenToken,
+    parent: SyntaxNode,
+    offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn green(&self) -> &GreenToken {
+        &self.green
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.text().len()
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        self.green.is_trivia()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// Splits the text between two tokens into whitespace and comment tokens.
+pub(crate) fn split_trivia(mut text: &str) -> Vec<GreenToken> {
+    let mut trivia = Vec::new();
+    while !text.is_empty() {
+        let (length, is_comment) = if text.starts_with("//") {
+            (text.find('\n').unwrap_or(text.len()), true)
+        } else if text.starts_with("/*") {
+            (block_comment_len(text), true)
+        } else {
+            let length = text
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(text.len());
+            // Anything else would have been lexed as a token, but never loop forever on it
+            (
+                length.max(text.chars().next().map_or(0, char::len_utf8)),
+                false,
+            )
+        };
+        let (piece, rest) = text.split_at(length);
+        trivia.push(if is_comment {
+            GreenToken::Comment(piece.into())
+        } else {
+            GreenToken::Whitespace(piece.into())
+        });
+        text = rest;
+    }
+    trivia
+}
+
+/// The length of the (possibly nested) block comment at the start of `text`.
+fn block_comment_len(text: &str) -> usize {
+    let mut depth = 0;
+    let mut cursor = 0;
+    while cursor < text.len() {
+        let rest = &text[cursor..];
+        if rest.starts_with("/*") {
+            depth += 1;
+            cursor += 2;
+        } else if rest.starts_with("*/") {
+            depth -= 1;
+            cursor += 2;
+            if depth == 0 {
+                break;
+            }
+        } else {
+            cursor += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    cursor
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::{GreenNodeBuilder, GreenToken, SyntaxKind, SyntaxNode, split_trivia};
+    use crate::tokens::Token;
+
+    #[test]
+    fn split_trivia_pieces() {
+        assert_eq!(
+            split_trivia(" \n// line\n\t/* a /* b */ c */ "),
+            vec![
+                GreenToken::Whitespace(" \n".into()),
+                GreenToken::Comment("// line".into()),
+                GreenToken::Whitespace("\n\t".into()),
+                GreenToken::Comment("/* a /* b */ c */".into()),
+                GreenToken::Whitespace(" ".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn red_nodes_know_their_offsets() {
+        let mut builder = GreenNodeBuilder::default();
+        builder.start_node(SyntaxKind::Block);
+        builder.token(GreenToken::Token(Token::OpenBrace, "{".into()));
+        let checkpoint = builder.checkpoint();
+        builder.token(GreenToken::Whitespace(" ".into()));
+        builder.token(GreenToken::Token(Token::Integer(1), "1".into()));
+        builder.start_node_at(checkpoint, SyntaxKind::Literal);
+        builder.finish_node();
+        builder.token(GreenToken::Token(Token::CloseBrace, "}".into()));
+        let root = SyntaxNode::new_root(builder.finish());
+
+        assert_eq!(root.text(), "{ 1}");
+        let literal = &root.children()[0];
+        assert_eq!(literal.kind(), SyntaxKind::Literal);
+        assert_eq!(literal.text_range(), 1..3);
+        assert_eq!(literal.parent(), Some(&root));
+        let ranges: Vec<_> = root.tokens().iter().map(|t| t.text_range()).collect();
+        assert_eq!(ranges, vec![0..1, 1..2, 2..3, 3..4]);
+    }
+}
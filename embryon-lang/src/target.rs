@@ -0,0 +1,136 @@
+//! The processors that programs can be compiled for, and what inline assembly can use on each.
+
+use std::fmt;
+
+/// A processor to compile for. Programs for the host can be run with `lli`, while the Cortex-M
+/// cores are what microcontrollers have, such as the Cortex-M4 of the micro:bit's nRF52833.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Target {
+    /// The machine the compiler runs on, whatever it is.
+    #[default]
+    Host,
+    CortexM0,
+    CortexM0Plus,
+    CortexM3,
+    CortexM4,
+    CortexM7,
+}
+
+impl Target {
+    pub const ALL: [Self; 6] = [
+        Self::Host,
+        Self::CortexM0,
+        Self::CortexM0Plus,
+        Self::CortexM3,
+        Self::CortexM4,
+        Self::CortexM7,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|target| target.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Host => "host",
+            Self::CortexM0 => "cortex-m0",
+            Self::CortexM0Plus => "cortex-m0plus",
+            Self::CortexM3 => "cortex-m3",
+            Self::CortexM4 => "cortex-m4",
+            Self::CortexM7 => "cortex-m7",
+        }
+    }
+
+    /// The LLVM target triple, or `None` for the host, which LLVM knows already.
+    pub fn triple(&self) -> Option<&'static str> {
+        match self {
+            Self::Host => None,
+            Self::CortexM0 | Self::CortexM0Plus => Some("thumbv6m-none-eabi"),
+            Self::CortexM3 => Some("thumbv7m-none-eabi"),
+            Self::CortexM4 | Self::CortexM7 => Some("thumbv7em-none-eabi"),
+        }
+    }
+
+    pub fn is_arm(&self) -> bool {
+        *self != Self::Host
+    }
+
+    /// Whether the core only has the ARMv6-M instructions, most of which can only use the low
+    /// registers `r0` to `r7`.
+    pub fn is_armv6m(&self) -> bool {
+        matches!(self, Self::CortexM0 | Self::CortexM0Plus)
+    }
+
+    /// The width in bits of a general purpose register.
+    pub fn register_bits(&self) -> u32 {
+        if self.is_arm() { 32 } else { 64 }
+    }
+
+    /// The size in bytes of a pointer, which is as wide as a register.
+    pub fn pointer_size(&self) -> u64 {
+        u64::from(self.register_bits() / 8)
+    }
+
+    /// Whether inline assembly can name `register` as an operand. The stack pointer, program
+    /// counter, frame pointer (`r7`) and base pointer (`r6`) are kept for the compiler, and the
+    /// names of the host's registers aren't known.
+    pub fn has_register(&self, register: &str) -> bool {
+        if !self.is_arm() {
+            return false;
+        }
+        let low = ["r0", "r1", "r2", "r3", "r4", "r5"];
+        let high = ["r8", "r9", "r10", "r11", "r12", "lr"];
+        low.contains(&register) || (!self.is_armv6m() && high.contains(&register))
+    }
+
+    /// Whether `$name` is a register pseudo-variable. Every Cortex-M core has the stack pointers
+    /// and the special registers, but the host's registers aren't known.
+    pub fn has_core_register(&self, name: &str) -> bool {
+        let registers = ["sp", "lr", "msp", "psp", "primask", "control"];
+        self.is_arm() && registers.contains(&name)
+    }
+
+    /// The registers that a function following the C calling convention may change, which
+    /// `clobber_abi("C")` marks as clobbered. `None` if they aren't known.
+    pub fn caller_saved_registers(&self) -> Option<&'static [&'static str]> {
+        self.is_arm()
+            .then_some(&["r0", "r1", "r2", "r3", "r12", "lr"][..])
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::target::Target;
+
+    #[test]
+    fn target_names() {
+        for target in Target::ALL {
+            assert_eq!(Target::from_name(target.name()), Some(target));
+        }
+        assert_eq!(Target::from_name("cortex-m33"), None);
+    }
+
+    #[test]
+    fn asm_registers() {
+        assert!(Target::CortexM4.has_register("r8"));
+        assert!(Target::CortexM0.has_register("r3"));
+        assert!(!Target::CortexM0.has_register("r8"));
+        assert!(!Target::CortexM4.has_register("sp"));
+        assert!(!Target::CortexM4.has_register("r7"));
+        assert!(!Target::Host.has_register("rax"));
+    }
+
+    #[test]
+    fn core_registers() {
+        assert!(Target::CortexM0.has_core_register("primask"));
+        assert!(Target::CortexM4.has_core_register("sp"));
+        assert!(!Target::CortexM4.has_core_register("r0"));
+        assert!(!Target::Host.has_core_register("sp"));
+    }
+}
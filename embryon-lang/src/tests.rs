@@ -1,5 +1,75 @@
+mod compile_arithmetic;
+mod compile_arrays;
+mod compile_attributes;
+mod compile_borrows;
+mod compile_handlers;
+mod compile_peripherals;
+mod compile_slices;
+mod compile_statics;
+mod compile_structs;
 mod parse_arithmetic;
+mod parse_arrays;
+mod parse_asm;
 mod parse_blocks;
 mod parse_declarations;
 mod parse_loops;
+mod parse_match;
+mod parse_nesting;
+mod parse_peripherals;
+mod parse_pointers;
+mod parse_references;
+mod parse_slices;
+mod parse_structs;
+mod parse_syntax_tree;
 mod parse_variables;
+
+use crate::ast::{Definition, Expression, Module};
+use crate::compile::{CompileError, Compiler};
+use crate::parse::ParseError;
+use crate::target::Target;
+use inkwell::context::Context;
+use inkwell::module::Module as LLVMModule;
+
+/// Parses `source` and returns the body of its first definition, which must be a function.
+pub(super) fn try_parse_main_body(source: &str, name: &str) -> Result<Expression, ParseError> {
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, name.into())?;
+    let Definition::Function(main) = &program.definitions[0] else {
+        panic!("expected a function");
+    };
+    Ok(main.body.clone())
+}
+
+pub(super) fn parse_main_body(source: &str, name: &str) -> Expression {
+    try_parse_main_body(source, name).unwrap()
+}
+
+pub(super) fn variable(name: &str) -> Box<Expression> {
+    Box::new(Expression::Variable(name.into()))
+}
+
+/// Compiles `source` for the host, keeping the IR in memory.
+pub(super) fn compile_source(source: &str) -> Result<(), CompileError> {
+    compile_with(source, |compiler| compiler, |_| {})
+}
+
+/// Compiles `source` for `target`, keeping the IR in memory.
+pub(super) fn compile_for(source: &str, target: Target) -> Result<(), CompileError> {
+    compile_with(source, |compiler| compiler.with_target(target), |_| {})
+}
+
+/// Compiles `source` with the options that `configure` sets, then passes the LLVM module to
+/// `inspect` if it compiled.
+pub(super) fn compile_with(
+    source: &str,
+    configure: impl for<'a, 'ctx> FnOnce(Compiler<'a, 'ctx>) -> Compiler<'a, 'ctx>,
+    inspect: impl FnOnce(&LLVMModule),
+) -> Result<(), CompileError> {
+    let program = crate::parse(crate::lex(source)).unwrap();
+    let context = Context::create();
+    let module = context.create_module("main");
+    let builder = context.create_builder();
+    configure(Compiler::new(&context, &builder, &module)).compile_module(&program)?;
+    inspect(&module);
+    Ok(())
+}
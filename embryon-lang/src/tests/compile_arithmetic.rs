@@ -0,0 +1,138 @@
+use crate::compile::CompileError;
+use crate::tests::compile_source;
+
+#[test]
+fn constant_zero_divisor() {
+    let sources = [
+        "fn main() -> i32 { let x = 1; x / 0 }",
+        "fn main() -> i32 { let x = 1; x % 0 }",
+        "fn main() { let mut x = 1; x /= 0; }",
+        "fn main() -> i32 { let x = 1; unchecked_div(x, 0) }",
+        "fn main() -> i32 { let x = 1; unchecked_rem(x, 1 - 1) }",
+    ];
+    for source in sources {
+        assert!(
+            matches!(compile_source(source), Err(CompileError::DivisionByZero)),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn division_overflow() {
+    let sources = [
+        "fn main() -> i32 { -2147483648 / -1 }",
+        "fn main() -> i32 { let x: i32 = -2147483648 % -1; x }",
+        "fn main() -> i32 { unchecked_div(-2147483648, -1) }",
+    ];
+    for source in sources {
+        assert!(
+            matches!(
+                compile_source(source),
+                Err(CompileError::DivisionOverflow(_))
+            ),
+            "{source}"
+        );
+    }
+    // Otherwise the dividend and divisor are checked at runtime
+    let source = "fn divide(x: i32, y: i32) -> i32 { x / -1 + x % y + -2147483648 / y }
+fn main() -> i32 { divide(1, 2) }";
+    assert!(compile_source(source).is_ok());
+}
+
+#[test]
+fn constant_divisor() {
+    assert!(compile_source("fn main() -> i32 { let x = 7; x / 2 + unchecked_rem(x, 3) }").is_ok());
+}
+
+#[test]
+fn constant_shift_out_of_range() {
+    let sources = [
+        ("fn main() -> u32 { let x = 1u32; x << 32 }", 32),
+        ("fn main() -> u8 { let mut x = 1u8; x >>= 9; x }", 9),
+        ("fn main() -> i64 { let x = 1i64; x << -1 }", -1),
+    ];
+    for (source, expected) in sources {
+        assert!(
+            matches!(
+                compile_source(source),
+                Err(CompileError::ShiftOverflow { amount, .. }) if amount == expected
+            ),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn shifts_in_range() {
+    let source = "fn shift(n: u8) -> u32 { 1u32 << n }
+fn main() -> u32 { let x = 1u32; (x << 31) >> 31 + shift(3) }";
+    assert!(compile_source(source).is_ok());
+}
+
+#[test]
+fn negative_literal_minimums() {
+    let source =
+        "fn main() -> i32 { let a = -128i8; let b: i16 = -32768; let c = -2147483648i32; c }";
+    assert!(compile_source(source).is_ok());
+    assert!(matches!(
+        compile_source("fn main() -> i8 { -129i8 }"),
+        Err(CompileError::LiteralOutOfRange { value: -129, .. })
+    ));
+    assert!(matches!(
+        compile_source("fn main() -> u8 { -1u8 }"),
+        Err(CompileError::UnsignedNegation(_))
+    ));
+}
+
+#[test]
+fn deep_expressions_compile() {
+    // Parses, compiles and drops as the CLI does, on the 8 MiB stack of its main thread
+    let depth = crate::lexer::TokenStream::DEFAULT_NESTING_LIMIT - 2;
+    let chain = 100_000;
+    let sources = [
+        (
+            format!(
+                "fn main() -> i32 {{ {}1{} }}",
+                "(".repeat(depth - 1),
+                ")".repeat(depth - 1)
+            ),
+            Ok(()),
+        ),
+        // Chains of operators don't count towards the limit, however long they are
+        (
+            format!(
+                "fn main() -> i32 {{ let x = 1; x{} }}",
+                " + x".repeat(chain)
+            ),
+            Ok(()),
+        ),
+        (
+            format!(
+                "fn main() -> i32 {{ let x = 1; x{} }}",
+                " as i32".repeat(chain)
+            ),
+            Ok(()),
+        ),
+        (
+            format!(
+                "fn main() -> i32 {{ let x = 1; x{} }}",
+                ".len()".repeat(chain)
+            ),
+            Err("no method named `len` found for `i32`".to_string()),
+        ),
+    ];
+    for (source, expected) in sources {
+        let compiled = std::thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(move || {
+                compile_source(&source)
+                    .map(|_| ())
+                    .map_err(|err| err.to_string())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(compiled, expected);
+    }
+}
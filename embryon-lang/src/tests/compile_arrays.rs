@@ -0,0 +1,79 @@
+use crate::compile::CompileError;
+use crate::tests::{compile_source, compile_with};
+use inkwell::module::Module;
+
+#[test]
+fn zero_sized_elements() {
+    let source = "struct Empty {}
+
+fn main() -> i32 {
+    let a = [[0u8; 0]; 4];
+    let b = a[1];
+    let c = a[3];
+    let e = [Empty {}, Empty {}, Empty {}];
+    let f = e[2];
+    0
+}";
+    assert!(compile_source(source).is_ok());
+}
+
+#[test]
+fn constant_index_out_of_bounds() {
+    let source = "fn main() -> u32 {
+    let table = [1u32, 2, 3];
+    table[3]
+}";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::IndexOutOfBounds {
+            index: 3,
+            length: 3
+        })
+    ));
+}
+
+/// Whether `function` checks that an index is in bounds when it runs.
+fn checks_index(module: &Module, function: &str) -> bool {
+    module
+        .get_function(function)
+        .unwrap()
+        .get_basic_blocks()
+        .iter()
+        .any(|block| {
+            block
+                .get_name()
+                .to_bytes()
+                .starts_with(b"index_out_of_bounds")
+        })
+}
+
+#[test]
+fn runtime_index_checks() {
+    let source = "fn by_byte(i: u8) -> u32 {
+    let table = [0u32; 256];
+    table[i]
+}
+
+fn by_index(i: u32) -> u32 {
+    let table = [0u32; 256];
+    table[i]
+}
+
+fn by_constant() -> u32 {
+    let table = [0u32; 256];
+    table[255]
+}
+
+fn main() {}";
+    compile_with(
+        source,
+        |compiler| compiler,
+        |module| {
+            // No `u8` is past the end of 256 elements
+            assert!(!checks_index(module, "by_byte"));
+            assert!(checks_index(module, "by_index"));
+            assert!(!checks_index(module, "by_constant"));
+        },
+    )
+    .unwrap();
+}
@@ -0,0 +1,69 @@
+use crate::compile::CompileError;
+use crate::target::Target;
+use crate::tests::{compile_for, compile_with};
+use inkwell::module::Linkage;
+
+#[test]
+fn symbol_attributes() {
+    let source = r#"#[weak]
+fn on_fault() {}
+
+#[used]
+#[link_section = ".text.boot"]
+fn boot() {}
+
+fn main() {}"#;
+    compile_with(
+        source,
+        |compiler| compiler,
+        |module| {
+            let on_fault = module.get_function("on_fault").unwrap();
+            assert_eq!(on_fault.get_linkage(), Linkage::WeakAny);
+            let boot = module.get_function("boot").unwrap().as_global_value();
+            assert_eq!(boot.get_section(), Some(c".text.boot"));
+            assert!(module.get_global("llvm.used").is_some());
+            let main = module.get_function("main").unwrap().as_global_value();
+            assert_eq!(main.get_section(), None);
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn misplaced_attribute() {
+    let source = r#"#[naked]
+extern "C" fn on_fault();"#;
+    assert!(matches!(
+        compile_for(source, Target::CortexM4),
+        Err(CompileError::MisplacedAttribute { attribute, .. }) if &*attribute == "naked"
+    ));
+}
+
+#[test]
+fn malformed_attribute() {
+    let source = "#[inline(sometimes)]
+fn main() {}";
+    assert!(matches!(
+        compile_for(source, Target::CortexM4),
+        Err(CompileError::MalformedAttribute(name)) if &*name == "inline"
+    ));
+}
+
+#[test]
+fn naked_functions_are_only_assembly() {
+    let source = r#"#[naked]
+extern "C" fn start() {
+    unsafe { asm!("b main") }
+}"#;
+    assert!(compile_for(source, Target::CortexM4).is_ok());
+
+    let source = r#"#[naked]
+extern "C" fn start() {
+    let stack = 0;
+    unsafe { asm!("b main") }
+}"#;
+    assert!(matches!(
+        compile_for(source, Target::CortexM4),
+        Err(CompileError::InvalidNakedFunction(name)) if &*name == "start"
+    ));
+}
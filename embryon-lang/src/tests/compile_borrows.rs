@@ -0,0 +1,77 @@
+use crate::compile::CompileError;
+use crate::tests::compile_source;
+
+#[test]
+fn borrows_end_with_their_scope() {
+    let source = "fn main() -> u32 {
+    let mut value = 3u32;
+    {
+        let r = &mut value;
+        *r += 1;
+    };
+    let a = &value;
+    let b = &value;
+    *a + *b + value
+}";
+    assert!(compile_source(source).is_ok());
+}
+
+#[test]
+fn conflicting_borrows() {
+    let source = "fn main() -> u32 { let mut x = 1u32; let a = &x; let b = &mut x; *a }";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::BorrowConflict { name, mutable: true, existing_mutable: false })
+            if &*name == "x"
+    ));
+
+    let source = "fn main() -> u32 { let mut x = 1u32; let a = &mut x; let b = &x; *a }";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::BorrowConflict {
+            mutable: false,
+            existing_mutable: true,
+            ..
+        })
+    ));
+
+    let source = "fn main() -> u32 { let mut x = 1u32; let a = &mut x; let b = &mut x; *a }";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::BorrowConflict {
+            mutable: true,
+            existing_mutable: true,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn used_while_mutably_borrowed() {
+    let source = "fn main() -> u32 { let mut x = 1u32; let r = &mut x; let y = x; *r }";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::UsedWhileBorrowed(name)) if &*name == "x"
+    ));
+
+    let source = "fn main() -> u32 { let mut x = 1u32; let r = &x; x = 2; *r }";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::AssignedWhileBorrowed(name)) if &*name == "x"
+    ));
+}
+
+#[test]
+fn references_outliving_their_variable() {
+    let source = "fn main() -> u32 { let r = { let y = 1u32; &y }; *r }";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::DoesNotLiveLongEnough(Some(name))) if &*name == "y"
+    ));
+
+    let source = "fn get() -> &u32 { let y = 1u32; &y }";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::ReturnsLocalReference(Some(name))) if &*name == "y"
+    ));
+}
@@ -0,0 +1,59 @@
+use crate::compile::CompileError;
+use crate::target::Target;
+use crate::tests::{compile_for, compile_with};
+
+#[test]
+fn reset_handlers() {
+    let source = "#[exception(Reset)]
+fn start() {}
+
+fn main() {}";
+    assert!(compile_for(source, Target::CortexM4).is_ok());
+
+    let source = "#[exception(Reset)]
+fn Reset() {}";
+    assert!(matches!(
+        compile_for(source, Target::CortexM4),
+        Err(CompileError::DuplicateDefinition(name)) if &*name == "Reset"
+    ));
+}
+
+#[test]
+fn vector_table_is_only_generated_when_needed() {
+    let source = "fn main() {}";
+    compile_with(
+        source,
+        |compiler| compiler.with_target(Target::CortexM4),
+        |module| {
+            assert!(module.get_global("__vector_table").is_none());
+            assert!(module.get_function("Reset").is_none());
+        },
+    )
+    .unwrap();
+    compile_with(
+        source,
+        |compiler| {
+            compiler
+                .with_target(Target::CortexM4)
+                .with_vector_table(true)
+        },
+        |module| {
+            assert!(module.get_global("__vector_table").is_some());
+            assert!(module.get_function("Reset").is_some());
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn handler_names_are_not_taken() {
+    // `HardFault` isn't a handler without the attribute, so it would be renamed out of the table
+    let source = "#[exception(SysTick)]
+fn tick() {}
+
+fn HardFault() {}";
+    assert!(matches!(
+        compile_for(source, Target::CortexM4),
+        Err(CompileError::DuplicateDefinition(name)) if &*name == "HardFault"
+    ));
+}
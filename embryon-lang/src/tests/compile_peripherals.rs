@@ -0,0 +1,105 @@
+use crate::compile::CompileError;
+use crate::tests::compile_source;
+use crate::types::IntType;
+
+const UART: &str = "peripheral UART @ 0x4000_2000 {
+    TXD: u32 @ 0x51c w,
+}
+
+";
+
+#[test]
+fn peripherals_are_moved() {
+    let source = format!(
+        "{UART}fn send(uart: UART) {{ uart.TXD.write(1); }}
+fn main() -> u32 {{ let p = take_peripherals(); send(p.UART); 0 }}"
+    );
+    assert!(compile_source(&source).is_ok());
+
+    let source = format!(
+        "{UART}fn main() -> u32 {{ let p = take_peripherals(); let a = p.UART; let b = p.UART; 0 }}"
+    );
+    assert!(matches!(
+        compile_source(&source),
+        Err(CompileError::UseOfMoved(name)) if &*name == "p.UART"
+    ));
+
+    let source = format!(
+        "{UART}fn main() -> u32 {{ let p = take_peripherals(); let r = &p; let a = p.UART; 0 }}"
+    );
+    assert!(matches!(
+        compile_source(&source),
+        Err(CompileError::MovedWhileBorrowed(name)) if &*name == "p"
+    ));
+}
+
+#[test]
+fn peripherals_are_taken_once() {
+    let source = format!(
+        "{UART}fn main() -> u32 {{ let p = take_peripherals(); let q = take_peripherals(); 0 }}"
+    );
+    assert!(matches!(
+        compile_source(&source),
+        Err(CompileError::PeripheralsAlreadyTaken)
+    ));
+
+    let source = format!("{UART}fn main() -> u32 {{ loop {{ let p = take_peripherals(); }} }}");
+    assert!(matches!(
+        compile_source(&source),
+        Err(CompileError::PeripheralsAlreadyTaken)
+    ));
+}
+
+const GPIO: &str = "peripheral GPIO @ 0x5000_0000 {
+    OUT: u32 @ 0x504 rw {
+        PIN21: 21,
+        DRIVE: 8..=10,
+    },
+    IN: u32 @ 0x510 r,
+    OUTSET: u32 @ 0x508 w,
+}
+
+";
+
+/// Compiles `body` as the body of `main`, which has the peripherals in `p`.
+fn compile_gpio(body: &str) -> Result<(), CompileError> {
+    let source = format!("{GPIO}fn main() -> u32 {{ let p = take_peripherals(); {body} 0 }}");
+    compile_source(&source)
+}
+
+#[test]
+fn register_access() {
+    let body = "let level = p.GPIO.IN.read();
+        p.GPIO.OUTSET.write(level);
+        p.GPIO.OUT.PIN21.write(1);
+        p.GPIO.OUT.DRIVE.modify(p.GPIO.OUT.DRIVE.read() + 1);";
+    assert!(compile_gpio(body).is_ok());
+
+    assert!(matches!(
+        compile_gpio("let set = p.GPIO.OUTSET.read();"),
+        Err(CompileError::RegisterNotReadable(name)) if &*name == "GPIO.OUTSET"
+    ));
+    assert!(matches!(
+        compile_gpio("p.GPIO.IN.write(1);"),
+        Err(CompileError::RegisterNotWritable(name)) if &*name == "GPIO.IN"
+    ));
+}
+
+#[test]
+fn only_bitfields_are_modified() {
+    // A whole register is written instead, as there is nothing of it to keep
+    assert!(matches!(
+        compile_gpio("p.GPIO.OUT.modify(1);"),
+        Err(CompileError::UnknownMethod { method, .. }) if &*method == "modify"
+    ));
+}
+
+#[test]
+fn constant_bitfield_values_fit() {
+    assert!(compile_gpio("p.GPIO.OUT.DRIVE.write(7);").is_ok());
+    assert!(matches!(
+        compile_gpio("p.GPIO.OUT.DRIVE.write(8);"),
+        Err(CompileError::LiteralOutOfRange { value: 8, ty })
+            if ty == IntType::new(3, false)
+    ));
+}
@@ -0,0 +1,32 @@
+use crate::tests::compile_with;
+use inkwell::module::Linkage;
+use inkwell::types::AnyTypeEnum;
+
+#[test]
+fn byte_strings() {
+    let source = "fn first(bytes: &[u8]) -> u8 {
+    bytes[0]
+}
+
+fn main() {
+    let greeting: &[u8] = b\"hi\\n\";
+    first(greeting);
+    first(b\"\\x00\");
+}";
+    compile_with(
+        source,
+        |compiler| compiler,
+        |module| {
+            let lengths: Vec<_> = module
+                .get_globals()
+                .filter(|global| global.is_constant() && global.get_linkage() == Linkage::Private)
+                .filter_map(|global| match global.get_value_type() {
+                    AnyTypeEnum::ArrayType(ty) => Some(ty.len()),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(lengths, [3, 1]);
+        },
+    )
+    .unwrap();
+}
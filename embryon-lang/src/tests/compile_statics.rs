@@ -0,0 +1,59 @@
+use crate::compile::CompileError;
+use crate::tests::compile_source;
+
+#[test]
+fn mutable_statics_require_unsafe() {
+    let source = "static mut TICKS: u32 = 0;
+
+fn main() {
+    let ticks = TICKS;
+}";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::RequiresUnsafe(_))
+    ));
+
+    let source = "static mut TICKS: u32 = 0;
+
+fn main() {
+    TICKS = 1;
+}";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::RequiresUnsafe(_))
+    ));
+
+    let source = "static mut TICKS: u32 = 0;
+
+fn main() {
+    unsafe {
+        TICKS += 1;
+    }
+}";
+    assert!(compile_source(source).is_ok());
+}
+
+#[test]
+fn immutable_statics_are_not_assigned() {
+    let source = "static LIMIT: u32 = 10;
+
+fn main() {
+    LIMIT = 5;
+}";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::NotMutable(name)) if &*name == "LIMIT"
+    ));
+}
+
+#[test]
+fn constants_and_statics_share_names() {
+    let source = "const LIMIT: u32 = 10;
+static LIMIT: u32 = 10;
+
+fn main() {}";
+    assert!(matches!(
+        compile_source(source),
+        Err(CompileError::DuplicateDefinition(name)) if &*name == "LIMIT"
+    ));
+}
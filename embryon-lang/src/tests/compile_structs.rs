@@ -0,0 +1,51 @@
+use crate::compile::CompileError;
+use crate::tests::compile_source;
+
+const POINT: &str = "struct Point {
+    x: i32,
+    y: i32,
+}
+
+";
+
+#[test]
+fn invalid_repr() {
+    let sources = [
+        "#[repr(packed, align(4))]\nstruct Header { id: u8 }\n\nfn main() {}",
+        "#[repr(align(3))]\nstruct Header { id: u8 }\n\nfn main() {}",
+    ];
+    for source in sources {
+        assert!(
+            matches!(compile_source(source), Err(CompileError::InvalidRepr(_))),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn structs_by_value() {
+    let source = format!(
+        "{POINT}fn flip(point: Point) -> Point {{
+    Point {{ x: point.y, y: point.x }}
+}}
+
+fn main() -> i32 {{
+    let point = flip(Point {{ x: 1, y: 2 }});
+    point.x - point.y
+}}"
+    );
+    assert!(compile_source(&source).is_ok());
+}
+
+#[test]
+fn fields_of_immutable_variables() {
+    let source = format!("{POINT}fn main() {{ let point = Point {{ x: 1, y: 2 }}; point.x = 3; }}");
+    assert!(matches!(
+        compile_source(&source),
+        Err(CompileError::NotMutable(name)) if &*name == "point"
+    ));
+
+    let source =
+        format!("{POINT}fn main() {{ let mut point = Point {{ x: 1, y: 2 }}; point.x = 3; }}");
+    assert!(compile_source(&source).is_ok());
+}
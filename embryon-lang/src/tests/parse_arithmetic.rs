@@ -10,9 +10,14 @@ fn fn_add() {
         program,
         Module {
             name: "add".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Expression::BinOp(BinOp::Add(
                     Box::new(Expression::Integer(1)),
                     Box::new(Expression::Integer(2)),
@@ -32,9 +37,14 @@ fn fn_sub() {
         program,
         Module {
             name: "sub".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Expression::BinOp(BinOp::Sub(
                     Box::new(Expression::Integer(1)),
                     Box::new(Expression::Integer(2)),
@@ -54,9 +64,14 @@ fn fn_mul() {
         program,
         Module {
             name: "mul".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Expression::BinOp(BinOp::Mul(
                     Box::new(Expression::Integer(1)),
                     Box::new(Expression::Integer(2)),
@@ -76,9 +91,14 @@ fn fn_div() {
         program,
         Module {
             name: "div".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Expression::BinOp(BinOp::Div(
                     Box::new(Expression::Integer(1)),
                     Box::new(Expression::Integer(2)),
@@ -87,3 +107,199 @@ fn fn_div() {
         },
     );
 }
+
+#[test]
+fn fn_unchecked_div() {
+    let source = "fn main() unchecked_div(1, 2)";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "unchecked_div".into()).unwrap();
+
+    assert_eq!(
+        program,
+        Module {
+            name: "unchecked_div".into(),
+            docs: None,
+            definitions: vec![Definition::Function(Function {
+                name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
+                parameters: vec![],
+                return_type: None,
+                body: Expression::Call(Call {
+                    function: Box::new(Expression::Variable("unchecked_div".into())),
+                    arguments: vec![Expression::Integer(1), Expression::Integer(2)],
+                })
+            })],
+        },
+    );
+}
+
+#[test]
+fn fn_rem() {
+    let source = "fn main() 1 % 2";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "rem".into()).unwrap();
+
+    assert_eq!(
+        program,
+        Module {
+            name: "rem".into(),
+            docs: None,
+            definitions: vec![Definition::Function(Function {
+                name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
+                parameters: vec![],
+                return_type: None,
+                body: Expression::BinOp(BinOp::Rem(
+                    Box::new(Expression::Integer(1)),
+                    Box::new(Expression::Integer(2)),
+                ))
+            })],
+        },
+    );
+}
+
+#[test]
+fn fn_precedence() {
+    // Parsed as `a | (b ^ (c & (d << (e + (f * g)))))`
+    let source = "fn main() a | b ^ c & d << e + f * g";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "precedence".into()).unwrap();
+
+    let var = |name: &str| Expression::Variable(name.into());
+    let op = |operator, lhs, rhs| Expression::BinOp(BinOp::new(operator, lhs, rhs));
+    assert_eq!(
+        program,
+        Module {
+            name: "precedence".into(),
+            docs: None,
+            definitions: vec![Definition::Function(Function {
+                name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
+                parameters: vec![],
+                return_type: None,
+                body: op(
+                    Operator::BitOr,
+                    var("a"),
+                    op(
+                        Operator::BitXor,
+                        var("b"),
+                        op(
+                            Operator::BitAnd,
+                            var("c"),
+                            op(
+                                Operator::Shl,
+                                var("d"),
+                                op(
+                                    Operator::Add,
+                                    var("e"),
+                                    op(Operator::Mul, var("f"), var("g"))
+                                )
+                            )
+                        )
+                    )
+                )
+            })],
+        },
+    );
+}
+
+#[test]
+fn fn_left_associative() {
+    let source = "fn main() 1 - 2 - 3";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "left_associative".into()).unwrap();
+
+    assert_eq!(
+        program,
+        Module {
+            name: "left_associative".into(),
+            docs: None,
+            definitions: vec![Definition::Function(Function {
+                name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
+                parameters: vec![],
+                return_type: None,
+                body: Expression::BinOp(BinOp::Sub(
+                    Box::new(Expression::BinOp(BinOp::Sub(
+                        Box::new(Expression::Integer(1)),
+                        Box::new(Expression::Integer(2)),
+                    ))),
+                    Box::new(Expression::Integer(3)),
+                ))
+            })],
+        },
+    );
+}
+
+#[test]
+fn fn_prefix_and_cast() {
+    // Parsed as `((-a) as u8) * b`
+    let source = "fn main() -a as u8 * b";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "prefix_and_cast".into()).unwrap();
+
+    let var = |name: &str| Box::new(Expression::Variable(name.into()));
+    assert_eq!(
+        program.definitions,
+        vec![Definition::Function(Function {
+            name: "main".into(),
+            docs: None,
+            attributes: vec![],
+            abi: None,
+            parameters: vec![],
+            return_type: None,
+            body: Expression::BinOp(BinOp::Mul(
+                Box::new(Expression::Cast(Cast {
+                    value: Box::new(Expression::Unary(UnaryOperator::Neg, var("a"))),
+                    ty: Type::Named("u8".into()),
+                })),
+                var("b"),
+            )),
+        })],
+    );
+}
+
+#[test]
+fn fn_postfix() {
+    // Postfix operators bind tighter than prefix ones, and chain from left to right
+    let source = "fn main() -f(x, 1)[i + 1].y";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "postfix".into()).unwrap();
+
+    let var = |name: &str| Box::new(Expression::Variable(name.into()));
+    let call = Expression::Call(Call {
+        function: var("f"),
+        arguments: vec![*var("x"), Expression::Integer(1)],
+    });
+    let index = Expression::Index(Index {
+        base: Box::new(call),
+        index: Box::new(Expression::BinOp(BinOp::Add(
+            var("i"),
+            Box::new(Expression::Integer(1)),
+        ))),
+    });
+    let field = Expression::Field(Field {
+        base: Box::new(index),
+        name: "y".into(),
+    });
+    assert_eq!(
+        program.definitions,
+        vec![Definition::Function(Function {
+            name: "main".into(),
+            docs: None,
+            attributes: vec![],
+            abi: None,
+            parameters: vec![],
+            return_type: None,
+            body: Expression::Unary(UnaryOperator::Neg, Box::new(field)),
+        })],
+    );
+}
@@ -0,0 +1,146 @@
+use crate::ast::*;
+use crate::parse::ParseError;
+use crate::tokens::Token;
+use crate::utils::IntoExpression;
+
+fn u8_array(length: Expression) -> Type {
+    Type::Array(Box::new(Type::Named("u8".into())), Box::new(length))
+}
+
+#[test]
+fn array_constant() {
+    let source = "const TABLE: [u8; SIZE + 1] = [1, 2, 4,];";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "array_constant".into()).unwrap();
+
+    assert_eq!(
+        program.definitions,
+        vec![Definition::Constant(Variable {
+            spec: VariableSpec {
+                ty: Some(u8_array(
+                    BinOp::Add(
+                        Box::new(Expression::Variable("SIZE".into())),
+                        Box::new(Expression::Integer(1)),
+                    )
+                    .into()
+                )),
+                ..VariableSpec::new("TABLE")
+            },
+            docs: None,
+            value: Box::new(Expression::Array(vec![
+                Expression::Integer(1),
+                Expression::Integer(2),
+                Expression::Integer(4),
+            ])),
+        })]
+    );
+}
+
+#[test]
+fn nested_array_type() {
+    let source = "fn main() { let grid: [[u8; 4]; 2] = [[0u8; 4]; 2]; }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "nested_array_type".into()).unwrap();
+
+    let Definition::Function(main) = &program.definitions[0] else {
+        panic!("expected a function");
+    };
+    let row = ArrayRepeat {
+        value: Box::new(Expression::TypedInteger(
+            0,
+            crate::types::IntType::new(8, false),
+        )),
+        count: Box::new(Expression::Integer(4)),
+    };
+    assert_eq!(
+        main.body,
+        Block {
+            body: vec![
+                VariableDefinition {
+                    spec: VariableSpec {
+                        ty: Some(Type::Array(
+                            Box::new(u8_array(Expression::Integer(4))),
+                            Box::new(Expression::Integer(2)),
+                        )),
+                        ..VariableSpec::new("grid")
+                    },
+                    value: ArrayRepeat {
+                        value: Box::new(row.into_expression()),
+                        count: Box::new(Expression::Integer(2)),
+                    }
+                    .into_expression()
+                    .into(),
+                }
+                .into()
+            ],
+            last: None,
+        }
+        .into_expression()
+    );
+}
+
+#[test]
+fn index_assignment() {
+    let source = "fn main() { buffer[i + 1] = [][0]; }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "index_assignment".into()).unwrap();
+
+    let Definition::Function(main) = &program.definitions[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(
+        main.body,
+        Block {
+            body: vec![
+                Assignment {
+                    target: Box::new(
+                        Index {
+                            base: Box::new(Expression::Variable("buffer".into())),
+                            index: Box::new(
+                                BinOp::Add(
+                                    Box::new(Expression::Variable("i".into())),
+                                    Box::new(Expression::Integer(1)),
+                                )
+                                .into()
+                            ),
+                        }
+                        .into_expression()
+                    ),
+                    operator: None,
+                    value: Box::new(
+                        Index {
+                            base: Box::new(Expression::Array(vec![])),
+                            index: Box::new(Expression::Integer(0)),
+                        }
+                        .into_expression()
+                    ),
+                }
+                .into_expression()
+                .into()
+            ],
+            last: None,
+        }
+        .into_expression()
+    );
+}
+
+#[test]
+fn repeat_takes_one_value() {
+    let source = "fn main() { [1, 2; 3] }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "repeat_takes_one_value".into());
+
+    assert!(matches!(
+        result,
+        Err(ParseError::UnexpectedToken(Token::Semi))
+    ));
+}
+
+#[test]
+fn element_of_literal_is_not_assignable() {
+    let source = "fn main() { [1, 2][0] = 3 }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "element_of_literal_is_not_assignable".into());
+
+    assert!(matches!(result, Err(ParseError::InvalidAssignmentTarget)));
+}
@@ -0,0 +1,110 @@
+use crate::ast::*;
+use crate::parse::ParseError;
+use crate::tests::try_parse_main_body;
+use crate::tokens::Token;
+
+/// Parses a function and returns the expression its body ends in.
+fn parse_last_expression(source: &str, name: &str) -> Result<Expression, ParseError> {
+    let Expression::Block(body) = &try_parse_main_body(source, name)? else {
+        panic!("expected a block");
+    };
+    Ok(*body.last.clone().expect("the body ends in an expression"))
+}
+
+#[test]
+fn asm_operands_and_options() {
+    let source = r#"fn main() {
+        asm!(
+            "mrs {}, primask",
+            "adds {1}, {1}, {2}",
+            out(reg) mask,
+            inout(reg) total,
+            in("r0") 1,
+            out("r1") _,
+            clobber_abi("C"),
+            options(nomem, nostack),
+        )
+    }"#;
+    let body = parse_last_expression(source, "asm_operands_and_options").unwrap();
+
+    let class = || AsmRegister::Class("reg".into());
+    assert_eq!(
+        body,
+        Expression::Asm(Box::new(Asm {
+            template: vec!["mrs {}, primask".into(), "adds {1}, {1}, {2}".into()],
+            operands: vec![
+                AsmOperand::Out {
+                    register: class(),
+                    place: Some(Expression::Variable("mask".into())),
+                },
+                AsmOperand::InOut {
+                    register: class(),
+                    place: Expression::Variable("total".into()),
+                },
+                AsmOperand::In {
+                    register: AsmRegister::Explicit("r0".into()),
+                    value: Expression::Integer(1),
+                },
+                AsmOperand::Out {
+                    register: AsmRegister::Explicit("r1".into()),
+                    place: None,
+                },
+            ],
+            clobber_abis: vec!["C".into()],
+            options: AsmOptions {
+                nomem: true,
+                nostack: true,
+                ..AsmOptions::default()
+            },
+        }))
+    );
+}
+
+#[test]
+fn asm_is_a_name_without_bang() {
+    let body = parse_last_expression("fn main() { asm }", "asm_is_a_name_without_bang").unwrap();
+
+    assert_eq!(body, Expression::Variable("asm".into()));
+}
+
+#[test]
+fn unknown_asm_option() {
+    let source = r#"fn main() { asm!("nop", options(pure)) }"#;
+    let result = parse_last_expression(source, "unknown_asm_option");
+
+    assert!(matches!(
+        result,
+        Err(ParseError::UnexpectedToken(Token::Identifier(name))) if &*name == "pure"
+    ));
+}
+
+#[test]
+fn core_register_assignment() {
+    let source = "fn main() { $primask |= $control & 1 }";
+    let body = parse_last_expression(source, "core_register_assignment").unwrap();
+
+    assert_eq!(
+        body,
+        Assignment {
+            target: Box::new(Expression::CoreRegister("primask".into())),
+            operator: Some(Operator::BitOr),
+            value: Box::new(
+                BinOp::BitAnd(
+                    Box::new(Expression::CoreRegister("control".into())),
+                    Box::new(Expression::Integer(1)),
+                )
+                .into()
+            ),
+        }
+        .into()
+    );
+}
+
+#[test]
+fn core_register_is_not_a_place() {
+    let body = parse_last_expression("fn main() { $sp }", "core_register_is_not_a_place").unwrap();
+
+    assert_eq!(body, Expression::CoreRegister("sp".into()));
+    assert!(!body.is_place());
+    assert!(body.is_assignable());
+}
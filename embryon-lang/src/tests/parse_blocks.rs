@@ -10,9 +10,14 @@ fn fn_empty_block() {
         program,
         Module {
             name: "block".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Expression::Block(Block::empty())
             })],
         },
@@ -29,9 +34,14 @@ fn fn_simple_block() {
         program,
         Module {
             name: "simple_block".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Block::from(Expression::Integer(0)).into(),
             })],
         },
@@ -48,9 +58,14 @@ fn fn_compound_block() {
         program,
         Module {
             name: "compound_block".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Expression::Block(Block {
                     body: vec![Expression::Integer(1).into()],
                     last: Some(Box::new(Expression::Integer(2)))
@@ -10,9 +10,14 @@ fn simple_function() {
         program,
         Module {
             name: "simple_function".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Expression::Integer(0),
             })]
         }
@@ -29,13 +34,271 @@ fn simple_constant() {
         program,
         Module {
             name: "simple_constant".into(),
+            docs: None,
             definitions: vec![Definition::Constant(Variable {
                 spec: VariableSpec {
                     name: "x".into(),
-                    is_mutable: false
+                    is_mutable: false,
+                    ty: None,
                 },
+                docs: None,
                 value: Box::new(Expression::Integer(0)),
             })],
         },
     );
 }
+
+#[test]
+fn typed_constant() {
+    let source = "const x: u8 = 0;";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "typed_constant".into()).unwrap();
+
+    assert_eq!(
+        program,
+        Module {
+            name: "typed_constant".into(),
+            docs: None,
+            definitions: vec![Definition::Constant(Variable {
+                spec: VariableSpec::new("x").with_type(Type::Named("u8".into())),
+                docs: None,
+                value: Box::new(Expression::Integer(0)),
+            })],
+        },
+    );
+}
+
+#[test]
+fn lex_error_is_reported() {
+    let source = "fn main() 0\n$";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "lex_error_is_reported".into());
+
+    let Err(crate::parse::ParseError::Lex(err)) = result else {
+        panic!("expected a lex error, found {result:?}");
+    };
+    assert_eq!(
+        err.kind,
+        crate::tokens::LexErrorKind::UnexpectedCharacter('$')
+    );
+    assert_eq!(err.position, crate::tokens::Position::new(2, 1));
+}
+
+#[test]
+fn literal_constants() {
+    let source = r#"const A = 'a'; const B = b'\n'; const C = "héllo"; const D = b"hi";"#;
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "literal_constants".into()).unwrap();
+
+    let constant = |name: &str, value| {
+        Definition::Constant(Variable {
+            spec: VariableSpec::new(name),
+            docs: None,
+            value: Box::new(value),
+        })
+    };
+    assert_eq!(
+        program,
+        Module {
+            name: "literal_constants".into(),
+            docs: None,
+            definitions: vec![
+                constant("A", Expression::Char('a')),
+                constant("B", Expression::Byte(b'\n')),
+                constant("C", Expression::String("héllo".into())),
+                constant("D", Expression::ByteString(b"hi".as_slice().into())),
+            ],
+        },
+    );
+}
+
+#[test]
+fn doc_comments() {
+    let source = "//! The module.\n\n/// The answer.\n/// Always 42.\nconst X = 42;\n\n/// Entry point.\nfn main() { /// Not attached.\n X }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "doc_comments".into()).unwrap();
+
+    assert_eq!(
+        program,
+        Module {
+            name: "doc_comments".into(),
+            docs: Some("The module.".into()),
+            definitions: vec![
+                Definition::Constant(Variable {
+                    spec: VariableSpec::new("X"),
+                    docs: Some("The answer.\nAlways 42.".into()),
+                    value: Box::new(Expression::Integer(42)),
+                }),
+                Definition::Function(Function {
+                    name: "main".into(),
+                    docs: Some("Entry point.".into()),
+                    attributes: vec![],
+                    abi: None,
+                    parameters: vec![],
+                    return_type: None,
+                    body: Block::from(Expression::Variable("X".into())).into(),
+                }),
+            ],
+        },
+    );
+}
+
+#[test]
+fn extern_functions() {
+    let source = r#"extern "C" fn puts(s: *const u8) -> i32;
+extern fn tick();
+extern "C" fn on_event(code: u32) {}"#;
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "extern_functions".into()).unwrap();
+
+    assert_eq!(
+        program.definitions,
+        vec![
+            Definition::ExternFunction(ExternFunction {
+                name: "puts".into(),
+                docs: None,
+                attributes: vec![],
+                abi: "C".into(),
+                parameters: vec![VariableSpec {
+                    ty: Some(Type::Pointer(Box::new(Type::Named("u8".into())), false)),
+                    ..VariableSpec::new("s")
+                }],
+                return_type: Some(Type::Named("i32".into())),
+            }),
+            Definition::ExternFunction(ExternFunction {
+                name: "tick".into(),
+                docs: None,
+                attributes: vec![],
+                abi: "C".into(),
+                parameters: vec![],
+                return_type: None,
+            }),
+            Definition::Function(Function {
+                name: "on_event".into(),
+                docs: None,
+                attributes: vec![],
+                abi: Some("C".into()),
+                parameters: vec![VariableSpec {
+                    ty: Some(Type::Named("u32".into())),
+                    ..VariableSpec::new("code")
+                }],
+                return_type: None,
+                body: Block {
+                    body: vec![],
+                    last: None,
+                }
+                .into(),
+            }),
+        ]
+    );
+}
+
+#[test]
+fn function_without_body() {
+    let source = "fn tick();";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "function_without_body".into());
+
+    assert!(matches!(
+        result,
+        Err(crate::parse::ParseError::UnexpectedToken(
+            crate::tokens::Token::Semi
+        ))
+    ));
+}
+
+#[test]
+fn function_attributes() {
+    let source = r#"#[link_section = ".text.reset"]
+/// Runs first.
+#[inline(never)]
+extern "C" fn reset() {}
+
+#[weak]
+extern "C" fn on_fault();"#;
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "function_attributes".into()).unwrap();
+
+    assert_eq!(
+        program.definitions,
+        vec![
+            Definition::Function(Function {
+                name: "reset".into(),
+                docs: Some("Runs first.".into()),
+                attributes: vec![
+                    Attribute::NameValue(
+                        "link_section".into(),
+                        Expression::String(".text.reset".into())
+                    ),
+                    Attribute::List("inline".into(), vec![Attribute::Word("never".into())]),
+                ],
+                abi: Some("C".into()),
+                parameters: vec![],
+                return_type: None,
+                body: Block {
+                    body: vec![],
+                    last: None,
+                }
+                .into(),
+            }),
+            Definition::ExternFunction(ExternFunction {
+                name: "on_fault".into(),
+                docs: None,
+                attributes: vec![Attribute::Word("weak".into())],
+                abi: "C".into(),
+                parameters: vec![],
+                return_type: None,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn statics() {
+    let source =
+        "static LIMIT: u32 = 10;\n#[link_section = \".noinit\"]\nstatic mut TICKS: u32 = 0;";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "statics".into()).unwrap();
+
+    let u32_type = || Some(Type::Named("u32".into()));
+    assert_eq!(
+        program.definitions,
+        vec![
+            Definition::Static(Static {
+                spec: VariableSpec {
+                    ty: u32_type(),
+                    ..VariableSpec::new("LIMIT")
+                },
+                docs: None,
+                attributes: vec![],
+                value: Box::new(Expression::Integer(10)),
+            }),
+            Definition::Static(Static {
+                spec: VariableSpec {
+                    ty: u32_type(),
+                    ..VariableSpec::new("TICKS").mutable()
+                },
+                docs: None,
+                attributes: vec![Attribute::NameValue(
+                    "link_section".into(),
+                    Expression::String(".noinit".into())
+                )],
+                value: Box::new(Expression::Integer(0)),
+            }),
+        ]
+    );
+}
+
+#[test]
+fn static_needs_type() {
+    let source = "static LIMIT = 10;";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "static_needs_type".into());
+
+    assert!(matches!(
+        result,
+        Err(crate::parse::ParseError::UnexpectedToken(
+            crate::tokens::Token::Equal
+        ))
+    ));
+}
@@ -11,9 +11,14 @@ fn empty_loop() {
         program,
         Module {
             name: "empty_loop".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Block::from(Expression::Loop(Box::new(Block::empty().into()))).into()
             })],
         },
@@ -31,9 +36,14 @@ fn simple_expression_loop() {
         program,
         Module {
             name: "simple_expression_loop".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Block::from(Expression::Loop(Box::new(Expression::Integer(5)))).into()
             })],
         },
@@ -51,9 +61,14 @@ fn complex_block_loop() {
         program,
         Module {
             name: "complex_block_loop".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Block::from(Expression::Loop(Box::new(
                     Block {
                         body: vec![
@@ -88,11 +103,16 @@ fn break_loop() {
         program,
         Module {
             name: "break_loop".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Block::from(Expression::Loop(Box::new(Expression::Break))).into()
             })]
         }
     )
-}
\ No newline at end of file
+}
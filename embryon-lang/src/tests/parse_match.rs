@@ -0,0 +1,195 @@
+use crate::ast::*;
+use crate::parse::ParseError;
+use crate::tests::parse_main_body;
+use crate::tokens::Token;
+use crate::utils::IntoExpression;
+
+fn match_of(scrutinee: &str, arms: Vec<MatchArm>) -> Expression {
+    Block::from(
+        Match {
+            scrutinee: Box::new(Expression::Variable(scrutinee.into())),
+            arms,
+        }
+        .into_expression(),
+    )
+    .into_expression()
+}
+
+fn arm(pattern: Pattern, body: impl Into<Expression>) -> MatchArm {
+    MatchArm {
+        pattern,
+        body: body.into(),
+    }
+}
+
+#[test]
+fn enum_definition() {
+    let source = "/// What a UART is doing.\n#[repr(u8)]\nenum State {\n    Idle = 1,\n    /// Sending bytes.\n    Sending(u32, u8),\n    Done,\n}";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "enum_definition".into()).unwrap();
+
+    assert_eq!(
+        program.definitions,
+        vec![Definition::Enum(Enum {
+            name: "State".into(),
+            docs: Some("What a UART is doing.".into()),
+            attributes: vec![Attribute::List(
+                "repr".into(),
+                vec![Attribute::Word("u8".into())]
+            )],
+            variants: vec![
+                Variant {
+                    name: "Idle".into(),
+                    docs: None,
+                    fields: vec![],
+                    discriminant: Some(Expression::Integer(1)),
+                },
+                Variant {
+                    name: "Sending".into(),
+                    docs: Some("Sending bytes.".into()),
+                    fields: vec![Type::Named("u32".into()), Type::Named("u8".into())],
+                    discriminant: None,
+                },
+                Variant {
+                    name: "Done".into(),
+                    docs: None,
+                    fields: vec![],
+                    discriminant: None,
+                },
+            ],
+        })]
+    );
+}
+
+#[test]
+fn variant_expressions() {
+    let source = "fn main() { let s = State::Idle; State::Sending(1, 2) }";
+    let body = parse_main_body(source, "variant_expressions");
+
+    assert_eq!(
+        body,
+        Block {
+            body: vec![
+                VariableDefinition {
+                    spec: VariableSpec::new("s"),
+                    value: Path::new("State", "Idle").into_expression().into(),
+                }
+                .into()
+            ],
+            last: Call {
+                function: Box::new(Path::new("State", "Sending").into_expression()),
+                arguments: vec![Expression::Integer(1), Expression::Integer(2)],
+            }
+            .into_expression()
+            .into(),
+        }
+        .into_expression()
+    );
+}
+
+#[test]
+fn match_patterns() {
+    let source = "fn main() { match c { 'a' | 'b' => 1, '0'..='9' => { 2 } -1..0 => 3, x => x } }";
+    let body = parse_main_body(source, "match_patterns");
+
+    let negative =
+        |value: u64| Expression::Unary(UnaryOperator::Neg, Box::new(Expression::Integer(value)));
+    assert_eq!(
+        body,
+        match_of(
+            "c",
+            vec![
+                arm(
+                    Pattern::Or(vec![
+                        Pattern::Literal(Expression::Char('a')),
+                        Pattern::Literal(Expression::Char('b')),
+                    ]),
+                    1,
+                ),
+                arm(
+                    Pattern::Range {
+                        start: Expression::Char('0'),
+                        end: Expression::Char('9'),
+                        inclusive: true,
+                    },
+                    Block::from(Expression::Integer(2)),
+                ),
+                arm(
+                    Pattern::Range {
+                        start: negative(1),
+                        end: Expression::Integer(0),
+                        inclusive: false,
+                    },
+                    3,
+                ),
+                arm(
+                    Pattern::Binding("x".into()),
+                    Expression::Variable("x".into())
+                ),
+            ],
+        )
+    );
+}
+
+#[test]
+fn match_variant_patterns() {
+    let source = "fn main() { match s { State::Sending(_, 0) | State::Done => 0, State::Sending(n, _) => n, _ => 1, } }";
+    let body = parse_main_body(source, "match_variant_patterns");
+
+    assert_eq!(
+        body,
+        match_of(
+            "s",
+            vec![
+                arm(
+                    Pattern::Or(vec![
+                        Pattern::Variant(
+                            Path::new("State", "Sending"),
+                            vec![Pattern::Wildcard, Pattern::Literal(Expression::Integer(0))],
+                        ),
+                        Pattern::Variant(Path::new("State", "Done"), vec![]),
+                    ]),
+                    0,
+                ),
+                arm(
+                    Pattern::Variant(
+                        Path::new("State", "Sending"),
+                        vec![Pattern::Binding("n".into()), Pattern::Wildcard],
+                    ),
+                    Expression::Variable("n".into()),
+                ),
+                arm(Pattern::Wildcard, 1),
+            ],
+        )
+    );
+}
+
+#[test]
+fn match_scrutinee_is_not_struct_literal() {
+    // `s { x }` would be a struct literal elsewhere, but here the braces hold the arms
+    let source = "fn main() { match s { x => x } }";
+    let body = parse_main_body(source, "match_scrutinee_is_not_struct_literal");
+
+    assert_eq!(
+        body,
+        match_of(
+            "s",
+            vec![arm(
+                Pattern::Binding("x".into()),
+                Expression::Variable("x".into())
+            )],
+        )
+    );
+}
+
+#[test]
+fn match_arms_need_commas() {
+    let source = "fn main() { match s { 1 => 2 3 => 4 } }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "match_arms_need_commas".into());
+
+    assert!(matches!(
+        result,
+        Err(ParseError::UnexpectedToken(Token::Integer(3)))
+    ));
+}
@@ -0,0 +1,105 @@
+use crate::ast::*;
+use crate::parse::ParseError;
+
+fn parse(source: &str, limit: usize) -> Result<Module, ParseError> {
+    let mut tokens = crate::lexer::TokenStream::new(source.into()).with_nesting_limit(limit);
+    Module::parse_body(&mut tokens, "nesting".into())
+}
+
+#[test]
+fn nested_parentheses_within_limit() {
+    let depth = crate::lexer::TokenStream::DEFAULT_NESTING_LIMIT - 1;
+    let source = format!(
+        "fn main() {}1{}",
+        "(".repeat(depth - 1),
+        ")".repeat(depth - 1)
+    );
+    assert!(parse(&source, crate::lexer::TokenStream::DEFAULT_NESTING_LIMIT).is_ok());
+}
+
+#[test]
+fn nested_blocks_within_limit() {
+    let depth = crate::lexer::TokenStream::DEFAULT_NESTING_LIMIT / 2;
+    let source = format!(
+        "fn main() {}1{}",
+        "{ -".repeat(depth - 1),
+        "}".repeat(depth - 1)
+    );
+    assert!(parse(&source, crate::lexer::TokenStream::DEFAULT_NESTING_LIMIT).is_ok());
+}
+
+#[test]
+fn nesting_too_deep() {
+    let source = format!("fn main() {}1{}", "(".repeat(100_000), ")".repeat(100_000));
+    let result = parse(&source, crate::lexer::TokenStream::DEFAULT_NESTING_LIMIT);
+    assert!(matches!(result, Err(ParseError::NestingTooDeep(256))));
+
+    let source = format!("fn main() {}1{}", "{".repeat(100_000), "}".repeat(100_000));
+    let result = parse(&source, crate::lexer::TokenStream::DEFAULT_NESTING_LIMIT);
+    assert!(matches!(result, Err(ParseError::NestingTooDeep(256))));
+
+    let source = format!("fn main() {}1", "-".repeat(100_000));
+    let result = parse(&source, 10);
+    assert!(matches!(result, Err(ParseError::NestingTooDeep(10))));
+}
+
+#[test]
+fn nested_types_too_deep() {
+    let limit = crate::lexer::TokenStream::DEFAULT_NESTING_LIMIT;
+    let sources = [
+        format!(
+            "const X: {}u8{} = 0;",
+            "[".repeat(100_000),
+            "; 1]".repeat(100_000)
+        ),
+        format!("const X: {}u8 = 0;", "&".repeat(100_000)),
+        format!("const X: {}u8 = 0;", "*const ".repeat(100_000)),
+    ];
+    for source in sources {
+        assert!(matches!(
+            parse(&source, limit),
+            Err(ParseError::NestingTooDeep(256))
+        ));
+    }
+
+    let source = "fn main() { let x: &[*const u8; 2] = y; }";
+    assert!(parse(source, 4).is_err());
+    assert!(parse(source, 5).is_ok());
+}
+
+#[test]
+fn nested_patterns_too_deep() {
+    let source = format!(
+        "fn main() match x {{ {}_{} => 1 }}",
+        "E::A(".repeat(100_000),
+        ")".repeat(100_000)
+    );
+    let result = parse(&source, crate::lexer::TokenStream::DEFAULT_NESTING_LIMIT);
+    assert!(matches!(result, Err(ParseError::NestingTooDeep(256))));
+
+    let source = "fn main() match x { E::A(E::A(_) | E::B) => 1 }";
+    assert!(parse(source, 3).is_err());
+    assert!(parse(source, 4).is_ok());
+}
+
+#[test]
+fn long_chains_use_constant_stack() {
+    // Parse and drop on a small stack, which would overflow if every operator took a stack frame
+    let sources = [
+        format!("fn main() 1{}", " + 1 * 2".repeat(100_000)),
+        format!("fn main() f{}", "(x)[0].y".repeat(100_000)),
+        format!("fn main() x{}", " as u8".repeat(100_000)),
+    ];
+    for source in sources {
+        let parsed = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(move || {
+                let module = parse(&source, 8).map_err(|err| err.to_string())?;
+                Ok::<_, String>(module.definitions.len())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(parsed, Ok(1));
+    }
+}
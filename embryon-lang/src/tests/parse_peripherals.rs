@@ -0,0 +1,105 @@
+use crate::ast::*;
+use crate::parse::ParseError;
+use crate::tokens::Token;
+use crate::types::RegisterAccess;
+use crate::utils::IntoExpression;
+
+fn parse_peripheral(source: &str, name: &str) -> Peripheral {
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, name.into()).unwrap();
+    let Definition::Peripheral(peripheral) = &program.definitions[0] else {
+        panic!("expected a peripheral");
+    };
+    peripheral.clone()
+}
+
+#[test]
+fn registers_and_bitfields() {
+    let source = "
+        /// General purpose I/O
+        peripheral GPIO @ 0x5000_0000 {
+            /// Output levels
+            OUT: u32 @ 0x504 rw {
+                PIN21: 21,
+                DRIVE: 8..=10,
+            },
+            IN: u32 @ 0x510 r,
+            OUTSET: u32 @ 0x508 w
+        }";
+    let peripheral = parse_peripheral(source, "registers_and_bitfields");
+
+    let register = |name: &str, offset, access, fields| Register {
+        name: name.into(),
+        docs: None,
+        ty: Type::Named("u32".into()),
+        offset: Expression::Integer(offset),
+        access,
+        fields,
+    };
+    assert_eq!(
+        peripheral,
+        Peripheral {
+            name: "GPIO".into(),
+            docs: Some("General purpose I/O".into()),
+            address: Expression::Integer(0x5000_0000),
+            registers: vec![
+                Register {
+                    docs: Some("Output levels".into()),
+                    ..register(
+                        "OUT",
+                        0x504,
+                        RegisterAccess::ReadWrite,
+                        vec![
+                            Bitfield {
+                                name: "PIN21".into(),
+                                docs: None,
+                                bits: Expression::Integer(21),
+                            },
+                            Bitfield {
+                                name: "DRIVE".into(),
+                                docs: None,
+                                bits: Range {
+                                    start: Some(Box::new(Expression::Integer(8))),
+                                    end: Some(Box::new(Expression::Integer(10))),
+                                    inclusive: true,
+                                }
+                                .into_expression(),
+                            },
+                        ]
+                    )
+                },
+                register("IN", 0x510, RegisterAccess::ReadOnly, vec![]),
+                register("OUTSET", 0x508, RegisterAccess::WriteOnly, vec![]),
+            ],
+        }
+    );
+}
+
+#[test]
+fn address_from_constant() {
+    let source = "peripheral TIMER0 @ TIMER_BASE + 0x1000 { TASKS_START: u32 @ 0 w }";
+    let peripheral = parse_peripheral(source, "address_from_constant");
+
+    // The registers aren't mistaken for a struct literal of type `TIMER_BASE`
+    assert_eq!(
+        peripheral.address,
+        BinOp::Add(
+            Box::new(Expression::Variable("TIMER_BASE".into())),
+            Box::new(Expression::Integer(0x1000)),
+        )
+        .into()
+    );
+    assert_eq!(peripheral.registers.len(), 1);
+}
+
+#[test]
+fn unknown_access_mode() {
+    let source = "peripheral GPIO @ 0x5000_0000 { OUT: u32 @ 0x504 rx }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "unknown_access_mode".into());
+
+    assert!(matches!(
+        result,
+        Err(ParseError::UnexpectedToken(Token::Identifier(name))) if &*name == "rx"
+    ));
+}
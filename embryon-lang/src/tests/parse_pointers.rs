@@ -0,0 +1,99 @@
+use crate::ast::*;
+use crate::parse::ParseError;
+use crate::tests::{parse_main_body, variable};
+use crate::tokens::Token;
+use crate::utils::IntoExpression;
+
+#[test]
+fn pointer_types() {
+    let source = "fn copy(from: *const u8, to: *mut *const u8) {}";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "pointer_types".into()).unwrap();
+
+    let Definition::Function(copy) = &program.definitions[0] else {
+        panic!("expected a function");
+    };
+    let u8 = || Box::new(Type::Named("u8".into()));
+    assert_eq!(
+        copy.parameters,
+        vec![
+            VariableSpec {
+                ty: Some(Type::Pointer(u8(), false)),
+                ..VariableSpec::new("from")
+            },
+            VariableSpec {
+                ty: Some(Type::Pointer(Box::new(Type::Pointer(u8(), false)), true)),
+                ..VariableSpec::new("to")
+            },
+        ]
+    );
+}
+
+#[test]
+fn cast_reference_to_pointer() {
+    let source = "fn main() { &mut value as *mut u32 }";
+    let body = parse_main_body(source, "cast_reference_to_pointer");
+
+    // The borrow is cast, as prefix operators bind more tightly than `as`
+    assert_eq!(
+        body,
+        Block {
+            body: vec![],
+            last: Some(Box::new(
+                Cast {
+                    value: Box::new(
+                        Reference {
+                            value: variable("value"),
+                            mutable: true,
+                        }
+                        .into_expression()
+                    ),
+                    ty: Type::Pointer(Box::new(Type::Named("u32".into())), true),
+                }
+                .into_expression()
+            )),
+        }
+        .into_expression()
+    );
+}
+
+#[test]
+fn unsafe_block() {
+    let source = "fn main() { unsafe { *register = 1; } }";
+    let body = parse_main_body(source, "unsafe_block");
+
+    assert_eq!(
+        body,
+        Block {
+            body: vec![],
+            last: Some(Box::new(Expression::Unsafe(Box::new(
+                Block {
+                    body: vec![
+                        Assignment {
+                            target: Box::new(Expression::Deref(variable("register"))),
+                            operator: None,
+                            value: Box::new(Expression::Integer(1)),
+                        }
+                        .into_expression()
+                        .into()
+                    ],
+                    last: None,
+                }
+                .into_expression()
+            )))),
+        }
+        .into_expression()
+    );
+}
+
+#[test]
+fn pointer_needs_mutability() {
+    let source = "fn read(p: *u8) {}";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "pointer_needs_mutability".into());
+
+    assert!(matches!(
+        result,
+        Err(ParseError::UnexpectedToken(Token::Identifier(name))) if &*name == "u8"
+    ));
+}
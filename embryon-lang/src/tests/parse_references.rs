@@ -0,0 +1,112 @@
+use crate::ast::*;
+use crate::parse::ParseError;
+use crate::tests::{parse_main_body, variable};
+use crate::tokens::Token;
+use crate::utils::IntoExpression;
+
+#[test]
+fn reference_types() {
+    let source = "fn tick(counter: &mut Counter, limit: & &u32) {}";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "reference_types".into()).unwrap();
+
+    let Definition::Function(tick) = &program.definitions[0] else {
+        panic!("expected a function");
+    };
+    let named = |name: &str| Box::new(Type::Named(name.into()));
+    assert_eq!(
+        tick.parameters,
+        vec![
+            VariableSpec {
+                ty: Some(Type::Reference(named("Counter"), true)),
+                ..VariableSpec::new("counter")
+            },
+            VariableSpec {
+                ty: Some(Type::Reference(
+                    Box::new(Type::Reference(named("u32"), false)),
+                    false
+                )),
+                ..VariableSpec::new("limit")
+            },
+        ]
+    );
+}
+
+#[test]
+fn deref_assignment() {
+    let source = "fn main() { *counter.ticks += 1; }";
+    let body = parse_main_body(source, "deref_assignment");
+
+    // The field is accessed before dereferencing, as postfix operators bind more tightly
+    assert_eq!(
+        body,
+        Block {
+            body: vec![
+                Assignment {
+                    target: Box::new(Expression::Deref(Box::new(
+                        Field {
+                            base: variable("counter"),
+                            name: "ticks".into(),
+                        }
+                        .into_expression()
+                    ))),
+                    operator: Some(Operator::Add),
+                    value: Box::new(Expression::Integer(1)),
+                }
+                .into_expression()
+                .into()
+            ],
+            last: None,
+        }
+        .into_expression()
+    );
+}
+
+#[test]
+fn reborrow() {
+    let source = "fn main() { let r = &mut *state; (*r).count * 2 }";
+    let body = parse_main_body(source, "reborrow");
+
+    assert_eq!(
+        body,
+        Block {
+            body: vec![
+                VariableDefinition {
+                    spec: VariableSpec::new("r"),
+                    value: Reference {
+                        value: Box::new(Expression::Deref(variable("state"))),
+                        mutable: true,
+                    }
+                    .into_expression()
+                    .into(),
+                }
+                .into()
+            ],
+            last: BinOp::Mul(
+                Box::new(
+                    Field {
+                        base: Box::new(Expression::Deref(variable("r"))),
+                        name: "count".into(),
+                    }
+                    .into_expression()
+                ),
+                Box::new(Expression::Integer(2)),
+            )
+            .into_expression()
+            .into(),
+        }
+        .into_expression()
+    );
+}
+
+#[test]
+fn deref_needs_operand() {
+    let source = "fn main() { * }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "deref_needs_operand".into());
+
+    assert!(matches!(
+        result,
+        Err(ParseError::UnexpectedToken(Token::CloseBrace))
+    ));
+}
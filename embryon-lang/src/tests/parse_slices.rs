@@ -0,0 +1,151 @@
+use crate::ast::*;
+use crate::parse::ParseError;
+use crate::tests::{parse_main_body, variable};
+use crate::tokens::Token;
+use crate::utils::IntoExpression;
+
+#[test]
+fn slice_parameters() {
+    let source = "fn transmit(data: &[u8], out: &mut [[u16; 2]]) {}";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "slice_parameters".into()).unwrap();
+
+    let Definition::Function(transmit) = &program.definitions[0] else {
+        panic!("expected a function");
+    };
+    let slice_of = |element: Type| Box::new(Type::Slice(Box::new(element)));
+    assert_eq!(
+        transmit.parameters,
+        vec![
+            VariableSpec {
+                ty: Some(Type::Reference(slice_of(Type::Named("u8".into())), false)),
+                ..VariableSpec::new("data")
+            },
+            VariableSpec {
+                ty: Some(Type::Reference(
+                    slice_of(Type::Array(
+                        Box::new(Type::Named("u16".into())),
+                        Box::new(Expression::Integer(2)),
+                    )),
+                    true,
+                )),
+                ..VariableSpec::new("out")
+            },
+        ]
+    );
+}
+
+#[test]
+fn range_slices() {
+    let source = "fn main() { [&a[1..4], &mut a[..=n + 1], &a[2..], &a[..]] }";
+    let body = parse_main_body(source, "range_slices");
+
+    let slice = |start: Option<Expression>, end: Option<Expression>, inclusive, mutable| {
+        Reference {
+            value: Box::new(
+                Index {
+                    base: variable("a"),
+                    index: Box::new(
+                        Range {
+                            start: start.map(Box::new),
+                            end: end.map(Box::new),
+                            inclusive,
+                        }
+                        .into_expression(),
+                    ),
+                }
+                .into_expression(),
+            ),
+            mutable,
+        }
+        .into_expression()
+    };
+    let Expression::Block(block) = &body else {
+        panic!("expected a block");
+    };
+    let Some(Expression::Array(slices)) = block.last.as_deref() else {
+        panic!("expected an array");
+    };
+    assert_eq!(
+        slices,
+        &vec![
+            slice(
+                Some(Expression::Integer(1)),
+                Some(Expression::Integer(4)),
+                false,
+                false
+            ),
+            slice(
+                None,
+                Some(BinOp::Add(variable("n"), Box::new(Expression::Integer(1))).into()),
+                true,
+                true
+            ),
+            slice(Some(Expression::Integer(2)), None, false, false),
+            slice(None, None, false, false),
+        ]
+    );
+}
+
+#[test]
+fn for_loops() {
+    let source = "fn main() { for i in 0..n { total += i; }; for byte in data {} }";
+    let body = parse_main_body(source, "for_loops");
+
+    assert_eq!(
+        body,
+        Block {
+            body: vec![
+                For {
+                    binding: "i".into(),
+                    iterable: Box::new(
+                        Range {
+                            start: Some(Box::new(Expression::Integer(0))),
+                            end: Some(variable("n")),
+                            inclusive: false,
+                        }
+                        .into_expression()
+                    ),
+                    body: Box::new(
+                        Block {
+                            body: vec![
+                                Assignment {
+                                    target: variable("total"),
+                                    operator: Some(Operator::Add),
+                                    value: variable("i"),
+                                }
+                                .into_expression()
+                                .into()
+                            ],
+                            last: None,
+                        }
+                        .into_expression()
+                    ),
+                }
+                .into_expression()
+                .into(),
+            ],
+            last: Some(Box::new(
+                For {
+                    binding: "byte".into(),
+                    iterable: variable("data"),
+                    body: Box::new(Block::empty().into()),
+                }
+                .into_expression()
+            )),
+        }
+        .into_expression()
+    );
+}
+
+#[test]
+fn inclusive_range_needs_end() {
+    let source = "fn main() { &a[1..=] }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "inclusive_range_needs_end".into());
+
+    assert!(matches!(
+        result,
+        Err(ParseError::UnexpectedToken(Token::CloseBracket))
+    ));
+}
@@ -0,0 +1,230 @@
+use crate::ast::*;
+use crate::parse::ParseError;
+use crate::tokens::Token;
+use crate::utils::IntoExpression;
+
+fn field(name: &str, ty: &str) -> StructField {
+    StructField {
+        name: name.into(),
+        docs: None,
+        ty: Type::Named(ty.into()),
+    }
+}
+
+#[test]
+fn struct_definition() {
+    let source = "/// A DMA descriptor.\n#[repr(C, align(16))]\nstruct Descriptor {\n    /// Where to copy from.\n    source: u32,\n    length: u16,\n}";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "struct_definition".into()).unwrap();
+
+    assert_eq!(
+        program.definitions,
+        vec![Definition::Struct(Struct {
+            name: "Descriptor".into(),
+            docs: Some("A DMA descriptor.".into()),
+            attributes: vec![Attribute::List(
+                "repr".into(),
+                vec![
+                    Attribute::Word("C".into()),
+                    Attribute::List(
+                        "align".into(),
+                        vec![Attribute::Literal(Expression::Integer(16))]
+                    ),
+                ]
+            )],
+            fields: vec![
+                StructField {
+                    docs: Some("Where to copy from.".into()),
+                    ..field("source", "u32")
+                },
+                field("length", "u16"),
+            ],
+        })]
+    );
+}
+
+#[test]
+fn struct_empty() {
+    let source = "#[repr(packed)] struct Empty {}";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "struct_empty".into()).unwrap();
+
+    assert_eq!(
+        program.definitions,
+        vec![Definition::Struct(Struct {
+            name: "Empty".into(),
+            docs: None,
+            attributes: vec![Attribute::List(
+                "repr".into(),
+                vec![Attribute::Word("packed".into())]
+            )],
+            fields: vec![],
+        })]
+    );
+}
+
+#[test]
+fn struct_attribute_must_be_named() {
+    let source = "#[4] struct S {}";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "struct_attribute_must_be_named".into());
+
+    assert!(matches!(
+        result,
+        Err(ParseError::UnexpectedToken(Token::Integer(4)))
+    ));
+}
+
+#[test]
+fn function_parameters_and_return_type() {
+    let source = "fn offset(p: Point, mut by: i32,) -> Point { Point { x: p.x + by, y: p.y } }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program =
+        Module::parse_body(&mut tokens, "function_parameters_and_return_type".into()).unwrap();
+
+    let p_field = |name: &str| {
+        Field {
+            base: Box::new(Expression::Variable("p".into())),
+            name: name.into(),
+        }
+        .into_expression()
+    };
+    assert_eq!(
+        program.definitions,
+        vec![Definition::Function(Function {
+            name: "offset".into(),
+            docs: None,
+            attributes: vec![],
+            abi: None,
+            parameters: vec![
+                VariableSpec {
+                    ty: Some(Type::Named("Point".into())),
+                    ..VariableSpec::new("p")
+                },
+                VariableSpec {
+                    ty: Some(Type::Named("i32".into())),
+                    ..VariableSpec::new("by").mutable()
+                },
+            ],
+            return_type: Some(Type::Named("Point".into())),
+            body: Block::from(
+                StructLiteral {
+                    name: "Point".into(),
+                    fields: vec![
+                        FieldInit {
+                            name: "x".into(),
+                            value: BinOp::Add(
+                                Box::new(p_field("x")),
+                                Box::new(Expression::Variable("by".into())),
+                            )
+                            .into(),
+                        },
+                        FieldInit {
+                            name: "y".into(),
+                            value: p_field("y"),
+                        },
+                    ],
+                }
+                .into_expression()
+            )
+            .into(),
+        })]
+    );
+}
+
+#[test]
+fn struct_literal_shorthand() {
+    let source = "fn main() { let p = Point { x, y: 2 }; Unit {} }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "struct_literal_shorthand".into()).unwrap();
+
+    let Definition::Function(main) = &program.definitions[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(
+        main.body,
+        Block {
+            body: vec![
+                VariableDefinition {
+                    spec: VariableSpec::new("p"),
+                    value: StructLiteral {
+                        name: "Point".into(),
+                        fields: vec![
+                            FieldInit {
+                                name: "x".into(),
+                                value: Expression::Variable("x".into()),
+                            },
+                            FieldInit {
+                                name: "y".into(),
+                                value: Expression::Integer(2),
+                            },
+                        ],
+                    }
+                    .into_expression()
+                    .into(),
+                }
+                .into()
+            ],
+            last: StructLiteral {
+                name: "Unit".into(),
+                fields: vec![],
+            }
+            .into_expression()
+            .into(),
+        }
+        .into_expression()
+    );
+}
+
+#[test]
+fn field_assignment() {
+    let source = "fn main() { a.b.c = 1; a.b += 2 }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "field_assignment".into()).unwrap();
+
+    let a_b = Field {
+        base: Box::new(Expression::Variable("a".into())),
+        name: "b".into(),
+    }
+    .into_expression();
+    let Definition::Function(main) = &program.definitions[0] else {
+        panic!("expected a function");
+    };
+    assert_eq!(
+        main.body,
+        Block {
+            body: vec![
+                Assignment {
+                    target: Box::new(
+                        Field {
+                            base: Box::new(a_b.clone()),
+                            name: "c".into(),
+                        }
+                        .into_expression()
+                    ),
+                    operator: None,
+                    value: Box::new(Expression::Integer(1)),
+                }
+                .into_expression()
+                .into()
+            ],
+            last: Assignment {
+                target: Box::new(a_b),
+                operator: Some(Operator::Add),
+                value: Box::new(Expression::Integer(2)),
+            }
+            .into_expression()
+            .into(),
+        }
+        .into_expression()
+    );
+}
+
+#[test]
+fn field_of_call_is_not_assignable() {
+    let source = "fn main() { f().x = 1 }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "field_of_call_is_not_assignable".into());
+
+    assert!(matches!(result, Err(ParseError::InvalidAssignmentTarget)));
+}
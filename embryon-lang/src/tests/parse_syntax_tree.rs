@@ -0,0 +1,222 @@
+use crate::syntax::{SyntaxKind, SyntaxNode};
+
+fn kinds(node: &SyntaxNode) -> Vec<SyntaxKind> {
+    node.children().iter().map(SyntaxNode::kind).collect()
+}
+
+#[test]
+fn round_trips_source() {
+    let sources = [
+        "",
+        "  \n",
+        "fn main() 0",
+        "//! Module docs.\n\n/// Docs.\nconst X: u8 = (1 + 2) * 3; // trailing\n",
+        "fn main() {\n    /* nested /* comment */ */\n    let mut x = 'a';\n    x += 1;\n    loop { break; };\n    unchecked_div(x, 2)\n}\n\n",
+        "fn main() 0\n$ more input",
+        "fn main() { 1 +",
+        "const X = 1; /* unterminated",
+    ];
+    for source in sources {
+        let (tree, _) = crate::parse_lossless(source);
+        assert_eq!(tree.text(), source);
+        assert_eq!(tree.kind(), SyntaxKind::Module);
+        assert_eq!(tree.text_range(), 0..source.len());
+    }
+}
+
+#[test]
+fn same_ast_as_plain_parse() {
+    let source = "const X = 2;\nfn main() { let y: u8 = X << 1; y | (3 & 4) }\n";
+    let (_, module) = crate::parse_lossless(source);
+    let expected = crate::parse(crate::lex(source)).unwrap();
+    assert_eq!(module.unwrap(), expected);
+}
+
+#[test]
+fn lowers_every_construct() {
+    let source = r#"//! A module.
+//! With docs.
+
+/// A point.
+#[repr(C, align(4))]
+struct Point { /// Across.
+    x: u32, y: [u8; 2 * 2], }
+
+enum Command { Idle = 1, Write(*mut u32, &[u8]), Read(&mut u8) }
+
+/// The GPIO.
+peripheral GPIO @ BASE + 0x500 {
+    /// Output.
+    OUT: u32 @ 0x4 rw { PIN: 21, DRIVE: 8..=10 },
+    IN: u32 @ 0x8 r,
+}
+
+#[link_section = ".data"]
+static mut TICKS: u32 = 0;
+/// A constant.
+const BASE: u32 = 0x5000_0000;
+const COUNT = 4;
+
+#[weak]
+extern "C" fn handler();
+extern fn reset() -> u32 { loop {} }
+
+/// The entry.
+/// With docs.
+#[inline(always)]
+fn main(mut a: u32, b: u8) -> u32 {
+    /// Not a doc comment.
+    let mut p = Point { x: a, y: [b; 4] };
+    let table: [u32; 3] = [1, 2, 3];
+    p.x += *&a;
+    let mut x;
+    let y: u8 = 'a' as u8;
+    x = unchecked_div(BASE, 2) + unchecked_rem(COUNT, (3 - 1)) as u32;
+    x += -1 * 2 % 3 << 1 >> 1 ^ 4 | 5 & 6 - b'x' as u32;
+    x = y = 1;
+    x = match a {
+        0 | 1 => 'a' as u32,
+        2..=9 | -1..0 => b'x' as u32,
+        Command::Write(_, rest) => rest.len(),
+        Command::Idle => { 1u8 as u32 }
+        n => n,
+    };
+    x.field[y.len](1, 2);
+    let text = "text";
+    let bytes: &[u8] = b"hi\n";
+    let rest = &mut table[1..];
+    let pin = unsafe { *(BASE as *const u32) };
+    for i in 0.. { if_it(i); break; };
+    for i in ..=3 { continue; };
+    let p = take_peripherals();
+    p.GPIO.OUT.PIN.write(p.GPIO.IN.read());
+    unsafe {
+        $sp = *&a as u32;
+        asm!("mov {0}, {1}", "nop", out(reg) x, in("r0") a, inout(reg) a, out("r1") _,
+            clobber_abi("C"), options(nomem, nostack, preserves_flags, volatile),);
+    };
+    loop { if_it(x); break; };
+    loop continue;;
+    { 7u8 }
+}
+"#;
+    let (_, module) = crate::parse_lossless(source);
+    let expected = crate::parse(crate::lex(source)).unwrap();
+    assert_eq!(module.unwrap(), expected);
+}
+
+#[test]
+fn nodes_keep_parentheses_and_trivia() {
+    let source = "fn main() (1 + /* two */ 2) * 3\n";
+    let (tree, module) = crate::parse_lossless(source);
+    module.unwrap();
+
+    let function = &tree.children()[0];
+    assert_eq!(function.kind(), SyntaxKind::Function);
+    assert_eq!(function.text(), "fn main() (1 + /* two */ 2) * 3");
+    assert_eq!(
+        kinds(function),
+        vec![SyntaxKind::ParameterList, SyntaxKind::BinaryExpression]
+    );
+
+    let product = &function.children()[1];
+    assert_eq!(
+        kinds(product),
+        vec![SyntaxKind::ParenExpression, SyntaxKind::Literal]
+    );
+    let sum = &product.children()[0].children()[0];
+    assert_eq!(sum.kind(), SyntaxKind::BinaryExpression);
+    assert_eq!(sum.text_range(), 11..26);
+    assert_eq!(
+        sum.parent().map(SyntaxNode::kind),
+        Some(SyntaxKind::ParenExpression)
+    );
+    let comment = sum
+        .tokens()
+        .into_iter()
+        .find(|token| token.is_trivia() && token.text() != " ")
+        .unwrap();
+    assert_eq!(comment.text(), "/* two */");
+    assert_eq!(comment.text_range(), 15..24);
+}
+
+#[test]
+fn left_associative_chains_nest() {
+    let (tree, _) = crate::parse_lossless("const X = 1 - 2 - 3;");
+    let outer = &tree.children()[0].children()[0];
+    assert_eq!(outer.text(), "1 - 2 - 3");
+    assert_eq!(
+        kinds(outer),
+        vec![SyntaxKind::BinaryExpression, SyntaxKind::Literal]
+    );
+    assert_eq!(outer.children()[0].text(), "1 - 2");
+}
+
+#[test]
+fn long_chains_use_constant_stack() {
+    // Walking, lowering and dropping the tree would overflow the small stack if every operator
+    // took a stack frame
+    let sources = [
+        format!("fn main() 1{}", " + 1 * 2".repeat(100_000)),
+        format!("fn main() f{}", "(x)[0].y".repeat(100_000)),
+        format!("fn main() x{}", " as u8".repeat(100_000)),
+    ];
+    for source in sources {
+        let parsed = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(move || {
+                let (tree, module) = crate::parse_lossless(&source);
+                assert_eq!(tree.text(), source);
+                let end = tree.tokens().last().map(|token| token.text_range().end);
+                assert_eq!(end, Some(source.len()));
+                module.map(|module| module.definitions.len()).is_ok()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(parsed);
+    }
+}
+
+#[test]
+fn nodes_start_at_their_first_token() {
+    let source = "  fn main() {\n    let x = /* one */ 1;\n    x\n}\n";
+    let (tree, _) = crate::parse_lossless(source);
+    let function = &tree.children()[0];
+    assert_eq!(function.text_range().start, 2);
+    let block = &function.children()[1];
+    let statement = &block.children()[0];
+    assert_eq!(statement.kind(), SyntaxKind::LetStatement);
+    assert_eq!(statement.text(), "let x = /* one */ 1");
+    assert_eq!(statement.children()[0].text(), "1");
+}
+
+#[test]
+fn examples_round_trip() {
+    let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/../embryon-cli/examples");
+    let mut count = 0;
+    for entry in std::fs::read_dir(examples).unwrap() {
+        let path = entry.unwrap().path();
+        if path
+            .extension()
+            .is_none_or(|extension| extension != "embryon")
+        {
+            continue;
+        }
+        let source = std::fs::read_to_string(&path).unwrap();
+        let (tree, module) = crate::parse_lossless(&source);
+        assert_eq!(tree.text(), source, "{}", path.display());
+        let expected = crate::parse(crate::lex(&source)).unwrap();
+        assert_eq!(module.unwrap(), expected, "{}", path.display());
+        count += 1;
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn unparsed_input_is_kept_in_error_node() {
+    let (tree, module) = crate::parse_lossless("fn main() 0\n$ more");
+    assert!(module.is_err());
+    assert_eq!(kinds(&tree), vec![SyntaxKind::Function, SyntaxKind::Error]);
+    assert_eq!(tree.children()[1].text(), "$ more");
+}
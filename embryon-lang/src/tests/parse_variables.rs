@@ -11,9 +11,14 @@ fn let_immutable() {
         program,
         Module {
             name: "let_immutable".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Block {
                     body: vec![
                         VariableDefinition {
@@ -40,9 +45,14 @@ fn let_mutable() {
         program,
         Module {
             name: "let_mutable".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Block::from(vec![
                     VariableDefinition {
                         spec: VariableSpec::new("x").mutable(),
@@ -66,9 +76,14 @@ fn variable_access() {
         program,
         Module {
             name: "variable_access".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Block::from(Expression::Variable("x".into())).into()
             })],
         },
@@ -85,12 +100,18 @@ fn variable_assign() {
         program,
         Module {
             name: "variable_assign".into(),
+            docs: None,
             definitions: vec![Definition::Function(Function {
                 name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
                 parameters: vec![],
+                return_type: None,
                 body: Block::from(
-                    VariableAssignment {
-                        name: "x".into(),
+                    Assignment {
+                        target: Box::new(Expression::Variable("x".into())),
+                        operator: None,
                         value: Box::new(Expression::Integer(2))
                     }
                     .into_expression()
@@ -100,3 +121,126 @@ fn variable_assign() {
         },
     );
 }
+
+#[test]
+fn let_typed() {
+    let source = "fn main() { let mut x: u16 = 1; }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "let_typed".into()).unwrap();
+
+    assert_eq!(
+        program,
+        Module {
+            name: "let_typed".into(),
+            docs: None,
+            definitions: vec![Definition::Function(Function {
+                name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
+                parameters: vec![],
+                return_type: None,
+                body: Block::from(vec![
+                    VariableDefinition {
+                        spec: VariableSpec::new("x")
+                            .mutable()
+                            .with_type(Type::Named("u16".into())),
+                        value: Expression::Integer(1).into()
+                    }
+                    .into()
+                ],)
+                .into()
+            })],
+        },
+    );
+}
+
+#[test]
+fn variable_compound_assign() {
+    let source = "fn main() { x <<= 1 + 2 }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program = Module::parse_body(&mut tokens, "variable_compound_assign".into()).unwrap();
+
+    assert_eq!(
+        program,
+        Module {
+            name: "variable_compound_assign".into(),
+            docs: None,
+            definitions: vec![Definition::Function(Function {
+                name: "main".into(),
+                docs: None,
+                attributes: vec![],
+                abi: None,
+                parameters: vec![],
+                return_type: None,
+                body: Block::from(
+                    Assignment {
+                        target: Box::new(Expression::Variable("x".into())),
+                        operator: Some(Operator::Shl),
+                        value: Box::new(
+                            BinOp::Add(
+                                Box::new(Expression::Integer(1)),
+                                Box::new(Expression::Integer(2)),
+                            )
+                            .into_expression()
+                        )
+                    }
+                    .into_expression()
+                )
+                .into()
+            })],
+        },
+    );
+}
+
+#[test]
+fn variable_assign_right_associative() {
+    let source = "fn main() { x = y += 1 | 2 }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let program =
+        Module::parse_body(&mut tokens, "variable_assign_right_associative".into()).unwrap();
+
+    let inner = Assignment {
+        target: Box::new(Expression::Variable("y".into())),
+        operator: Some(Operator::Add),
+        value: Box::new(
+            BinOp::BitOr(
+                Box::new(Expression::Integer(1)),
+                Box::new(Expression::Integer(2)),
+            )
+            .into_expression(),
+        ),
+    };
+    assert_eq!(
+        program.definitions,
+        vec![Definition::Function(Function {
+            name: "main".into(),
+            docs: None,
+            attributes: vec![],
+            abi: None,
+            parameters: vec![],
+            return_type: None,
+            body: Block::from(
+                Assignment {
+                    target: Box::new(Expression::Variable("x".into())),
+                    operator: None,
+                    value: Box::new(inner.into_expression()),
+                }
+                .into_expression()
+            )
+            .into()
+        })],
+    );
+}
+
+#[test]
+fn variable_assign_invalid_target() {
+    let source = "fn main() { x + 1 = 2 }";
+    let mut tokens = crate::lexer::TokenStream::new(source.into());
+    let result = Module::parse_body(&mut tokens, "variable_assign_invalid_target".into());
+
+    assert!(matches!(
+        result,
+        Err(crate::parse::ParseError::InvalidAssignmentTarget)
+    ));
+}
@@ -1,24 +1,162 @@
+use crate::types::IntType;
+use std::fmt;
 use std::rc::Rc;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Token {
     Const,
+    Static,
     Let,
     Mut,
     Fn,
+    Extern,
+    Struct,
+    Enum,
+    Peripheral,
+    Match,
+    Unsafe,
     Loop,
+    For,
+    In,
     Break,
     Continue,
+    As,
     OpenParen,
     CloseParen,
     OpenBrace,
     CloseBrace,
+    OpenBracket,
+    CloseBracket,
     Semi,
+    Comma,
+    Colon,
+    Dot,
+    Hash,
+    /// `@`, before the address of a peripheral or the offset of one of its registers.
+    At,
+    /// `!`, after the name of a macro such as `asm!`.
+    Bang,
+    Arrow,
+    /// `=>`, between the pattern and the body of a `match` arm.
+    FatArrow,
+    /// `::`, between the segments of a path such as `State::Idle`.
+    PathSeparator,
+    DotDot,
+    DotDotEqual,
     Equal,
     Plus,
     Minus,
     Star,
     Slash,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    ShiftLeft,
+    ShiftRight,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    AmpersandEqual,
+    PipeEqual,
+    CaretEqual,
+    ShiftLeftEqual,
+    ShiftRightEqual,
     Identifier(Rc<str>),
+    /// A register pseudo-variable such as `$sp`, holding the name without the `$`.
+    CoreRegister(Rc<str>),
     Integer(u64),
+    /// An integer literal with a type suffix, such as `10u8`.
+    TypedInteger(u64, IntType),
+    Char(char),
+    Byte(u8),
+    String(Rc<str>),
+    ByteString(Rc<[u8]>),
+    /// A `///` comment, documenting the item after it.
+    DocComment(Rc<str>),
+    /// A `//!` comment, documenting the item it is inside.
+    InnerDocComment(Rc<str>),
+    Error(LexError),
+}
+
+/// A location in the source code. Lines and columns both start at 1, and columns count characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Input that could not be turned into a token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    /// Where the offending input starts.
+    pub position: Position,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.kind)
+    }
+}
+
+/// The ways in which input can fail to lex. Integer variants hold the offending literal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LexErrorKind {
+    UnexpectedCharacter(char),
+    UnterminatedComment,
+    UnterminatedCharacter,
+    UnterminatedString,
+    /// A character literal that is empty or holds more than one character.
+    InvalidCharacterLiteral,
+    InvalidEscape(Rc<str>),
+    NonAsciiByte(char),
+    IntegerOutOfRange(Rc<str>),
+    InvalidDigit(Rc<str>),
+    InvalidSuffix(Rc<str>),
+    MissingDigits(Rc<str>),
+}
+
+impl fmt::Display for LexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedCharacter(c) => write!(f, "unexpected character `{c}`"),
+            Self::UnterminatedComment => write!(f, "unterminated block comment"),
+            Self::UnterminatedCharacter => write!(f, "unterminated character literal"),
+            Self::UnterminatedString => write!(f, "unterminated string literal"),
+            Self::InvalidCharacterLiteral => {
+                write!(f, "character literals must contain exactly one character")
+            }
+            Self::InvalidEscape(escape) => write!(f, "invalid escape sequence `{escape}`"),
+            Self::NonAsciiByte(c) => {
+                write!(
+                    f,
+                    "non-ASCII character `{c}` in byte literal; use a `\\x` escape"
+                )
+            }
+            Self::IntegerOutOfRange(text) => {
+                write!(
+                    f,
+                    "integer literal `{text}` is too large for any integer type"
+                )
+            }
+            Self::InvalidDigit(text) => write!(f, "invalid digit in integer literal `{text}`"),
+            Self::InvalidSuffix(text) => write!(f, "invalid suffix on integer literal `{text}`"),
+            Self::MissingDigits(text) => write!(f, "integer literal `{text}` has no digits"),
+        }
+    }
 }
@@ -0,0 +1,760 @@
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntType {
+    pub bits: u32,
+    pub signed: bool,
+}
+
+impl IntType {
+    /// The type of an integer literal that has no other type to take.
+    pub const DEFAULT: Self = Self::new(32, true);
+    /// The type of array lengths, and of integer literals used as indices.
+    /// It is as wide as a pointer on the Cortex-M cores, and narrower on a 64-bit host.
+    pub const INDEX: Self = Self::new(32, false);
+
+    pub const fn new(bits: u32, signed: bool) -> Self {
+        Self { bits, signed }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (signed, bits) = match name.split_at_checked(1)? {
+            ("u", bits) => (false, bits),
+            ("i", bits) => (true, bits),
+            _ => return None,
+        };
+        match bits {
+            "8" | "16" | "32" | "64" => Some(Self::new(bits.parse().ok()?, signed)),
+            _ => None,
+        }
+    }
+
+    pub fn min(&self) -> i128 {
+        if self.signed {
+            -(1 << (self.bits - 1))
+        } else {
+            0
+        }
+    }
+
+    pub fn max(&self) -> i128 {
+        if self.signed {
+            (1 << (self.bits - 1)) - 1
+        } else {
+            (1 << self.bits) - 1
+        }
+    }
+
+    pub fn contains(&self, value: i128) -> bool {
+        (self.min()..=self.max()).contains(&value)
+    }
+
+    /// The size in bytes. Integers are aligned to their size.
+    pub fn size(&self) -> u64 {
+        u64::from(self.bits.div_ceil(8))
+    }
+}
+
+impl fmt::Display for IntType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = if self.signed { 'i' } else { 'u' };
+        write!(f, "{prefix}{}", self.bits)
+    }
+}
+
+/// Whether a peripheral register can be read, written or both, written `r`, `w` or `rw`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterAccess {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+}
+
+impl RegisterAccess {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "r" => Some(Self::ReadOnly),
+            "w" => Some(Self::WriteOnly),
+            "rw" => Some(Self::ReadWrite),
+            _ => None,
+        }
+    }
+
+    pub fn can_read(self) -> bool {
+        self != Self::WriteOnly
+    }
+
+    pub fn can_write(self) -> bool {
+        self != Self::ReadOnly
+    }
+}
+
+impl fmt::Display for RegisterAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReadOnly => write!(f, "r"),
+            Self::WriteOnly => write!(f, "w"),
+            Self::ReadWrite => write!(f, "rw"),
+        }
+    }
+}
+
+/// The type of a value, as known to the compiler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Unit,
+    Int(IntType),
+    Struct(Rc<StructType>),
+    Enum(Rc<EnumType>),
+    /// An array of a type and a length.
+    Array(Box<Type>, u64),
+    /// A reference to a slice, `&[T]` or `&mut [T]`: a pointer to the first element, and the
+    /// number of elements.
+    Slice {
+        element: Box<Type>,
+        mutable: bool,
+    },
+    /// A reference, `&T` or `&mut T`: a pointer to a value that is borrowed for as long as the
+    /// reference is in use.
+    Reference {
+        target: Box<Type>,
+        mutable: bool,
+    },
+    /// A raw pointer, `*const T` or `*mut T`, which isn't checked by the compiler at all.
+    Pointer {
+        target: Box<Type>,
+        mutable: bool,
+    },
+    /// Ownership of a peripheral. It has no data, as the registers are at a fixed address, but
+    /// there is only ever one of it, so it can be moved but not copied.
+    Peripheral(Rc<PeripheralType>),
+}
+
+impl Type {
+    /// The size in bytes, where `pointer_size` is the size of a pointer on the target.
+    pub fn size(&self, pointer_size: u64) -> u64 {
+        match self {
+            Type::Unit => 0,
+            Type::Int(ty) => ty.size(),
+            Type::Struct(ty) => ty.size,
+            Type::Enum(ty) => ty.size,
+            // The size of every type is a multiple of its alignment, so elements need no padding
+            Type::Array(element, length) => element.size(pointer_size) * length,
+            // The length is an index, which is no wider than a pointer, so it is padded to one
+            Type::Slice { .. } => 2 * pointer_size,
+            Type::Reference { .. } | Type::Pointer { .. } => pointer_size,
+            Type::Peripheral(_) => 0,
+        }
+    }
+
+    /// The alignment in bytes, where `pointer_size` is the size of a pointer on the target.
+    pub fn align(&self, pointer_size: u64) -> u64 {
+        match self {
+            Type::Unit | Type::Peripheral(_) => 1,
+            Type::Int(ty) => ty.size(),
+            Type::Struct(ty) => ty.align,
+            Type::Enum(ty) => ty.align,
+            Type::Array(element, _) => element.align(pointer_size),
+            Type::Slice { .. } | Type::Reference { .. } | Type::Pointer { .. } => pointer_size,
+        }
+    }
+
+    /// Whether values of the type hold references or slices, and so borrow what they refer to.
+    pub fn contains_references(&self) -> bool {
+        match self {
+            // Raw pointers aren't borrow checked
+            Type::Unit | Type::Int(_) | Type::Pointer { .. } | Type::Peripheral(_) => false,
+            Type::Struct(ty) => ty.fields.iter().any(|field| field.ty.contains_references()),
+            Type::Enum(ty) => ty
+                .variants
+                .iter()
+                .any(|variant| Type::Struct(variant.payload.clone()).contains_references()),
+            Type::Array(element, _) => element.contains_references(),
+            Type::Slice { .. } | Type::Reference { .. } => true,
+        }
+    }
+
+    /// Whether values of the type are copied when used. Otherwise they are moved, and the place
+    /// they were in can't be used again until it is assigned to.
+    pub fn is_copy(&self) -> bool {
+        match self {
+            Type::Peripheral(_) => false,
+            Type::Struct(ty) => ty.fields.iter().all(|field| field.ty.is_copy()),
+            Type::Enum(ty) => ty
+                .variants
+                .iter()
+                .all(|variant| Type::Struct(variant.payload.clone()).is_copy()),
+            Type::Array(element, _) => element.is_copy(),
+            Type::Unit
+            | Type::Int(_)
+            | Type::Slice { .. }
+            | Type::Reference { .. }
+            | Type::Pointer { .. } => true,
+        }
+    }
+
+    /// Whether C passes values of the type the same way, so that they can be parameters and
+    /// return values of `extern "C"` functions. How structs, enums and arrays are passed depends on
+    /// the target's C ABI, which isn't implemented, and slices have no C equivalent.
+    pub fn is_ffi_safe(&self) -> bool {
+        matches!(
+            self,
+            Type::Int(_) | Type::Pointer { .. } | Type::Reference { .. }
+        )
+    }
+}
+
+impl From<IntType> for Type {
+    fn from(ty: IntType) -> Self {
+        Self::Int(ty)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Unit => write!(f, "()"),
+            Type::Int(ty) => write!(f, "{ty}"),
+            Type::Struct(ty) => write!(f, "{}", ty.name),
+            Type::Enum(ty) => write!(f, "{}", ty.name),
+            Type::Array(element, length) => write!(f, "[{element}; {length}]"),
+            Type::Slice {
+                element,
+                mutable: false,
+            } => write!(f, "&[{element}]"),
+            Type::Slice {
+                element,
+                mutable: true,
+            } => write!(f, "&mut [{element}]"),
+            Type::Reference {
+                target,
+                mutable: false,
+            } => write!(f, "&{target}"),
+            Type::Reference {
+                target,
+                mutable: true,
+            } => write!(f, "&mut {target}"),
+            Type::Pointer {
+                target,
+                mutable: false,
+            } => write!(f, "*const {target}"),
+            Type::Pointer {
+                target,
+                mutable: true,
+            } => write!(f, "*mut {target}"),
+            Type::Peripheral(ty) => write!(f, "{}", ty.name),
+        }
+    }
+}
+
+/// How a struct is laid out in memory, as set by `#[repr(...)]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Repr {
+    /// `repr(C)`: fields are kept in declaration order, with C's padding rules.
+    pub c: bool,
+    /// `repr(packed)`: fields have no padding between them, and the struct is byte aligned.
+    pub packed: bool,
+    /// `repr(align(N))`: the struct is aligned to at least `N` bytes.
+    pub align: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct StructType {
+    pub name: Rc<str>,
+    pub fields: Vec<StructField>,
+    pub repr: Repr,
+    pub size: u64,
+    pub align: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructField {
+    pub name: Rc<str>,
+    pub ty: Type,
+    /// The offset in bytes from the start of the struct.
+    pub offset: u64,
+    /// The size in bytes of the field's type on the target.
+    pub size: u64,
+}
+
+/// A part of a struct's memory: either one of its fields, or padding bytes between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Member {
+    Field(usize),
+    Padding(u64),
+}
+
+impl StructType {
+    /// Lays out `fields` in declaration order, as C does. Fields are currently never reordered,
+    /// so this is also the layout of structs without `repr(C)`. `pointer_size` is the size of a
+    /// pointer on the target.
+    pub fn new(name: Rc<str>, fields: Vec<(Rc<str>, Type)>, repr: Repr, pointer_size: u64) -> Self {
+        let mut size: u64 = 0;
+        let mut align: u64 = 1;
+        let fields = fields
+            .into_iter()
+            .map(|(name, ty)| {
+                let field_align = if repr.packed {
+                    1
+                } else {
+                    ty.align(pointer_size)
+                };
+                let offset = size.next_multiple_of(field_align);
+                let field_size = ty.size(pointer_size);
+                size = offset + field_size;
+                align = align.max(field_align);
+                StructField {
+                    name,
+                    ty,
+                    offset,
+                    size: field_size,
+                }
+            })
+            .collect();
+        let align = align.max(repr.align.unwrap_or(1));
+        Self {
+            name,
+            fields,
+            repr,
+            size: size.next_multiple_of(align),
+            align,
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<(usize, &StructField)> {
+        self.fields
+            .iter()
+            .enumerate()
+            .find(|(_, field)| *field.name == *name)
+    }
+
+    /// The fields and padding of the struct, in memory order.
+    pub fn members(&self) -> Vec<Member> {
+        let mut members = Vec::new();
+        let mut end = 0;
+        for (index, field) in self.fields.iter().enumerate() {
+            if field.offset > end {
+                members.push(Member::Padding(field.offset - end));
+            }
+            members.push(Member::Field(index));
+            end = field.offset + field.size;
+        }
+        if self.size > end {
+            members.push(Member::Padding(self.size - end));
+        }
+        members
+    }
+
+    /// The position of field `index` in [`Self::members`].
+    pub fn member_index(&self, index: usize) -> u32 {
+        let position = self
+            .members()
+            .iter()
+            .position(|member| *member == Member::Field(index))
+            .expect("the field should be one of the members");
+        position as u32
+    }
+}
+
+/// An enum, stored as an integer tag followed by the fields of the current variant.
+#[derive(Debug, PartialEq, Eq)]
+pub struct EnumType {
+    pub name: Rc<str>,
+    /// The type of the tag, which holds the discriminant of the current variant.
+    pub tag: IntType,
+    pub variants: Vec<EnumVariant>,
+    /// The offset in bytes of the variants' fields, which all start at the same place.
+    pub payload_offset: u64,
+    pub size: u64,
+    pub align: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct EnumVariant {
+    pub name: Rc<str>,
+    pub discriminant: i128,
+    /// The fields of the variant, laid out as a struct named `0`, `1`, ... and so on.
+    pub payload: Rc<StructType>,
+}
+
+impl EnumType {
+    /// Lays out an enum from its variants, given as their names, discriminants and field types.
+    /// `pointer_size` is the size of a pointer on the target.
+    pub fn new(
+        name: Rc<str>,
+        tag: IntType,
+        variants: Vec<(Rc<str>, i128, Vec<Type>)>,
+        pointer_size: u64,
+    ) -> Self {
+        let variants: Vec<_> = variants
+            .into_iter()
+            .map(|(variant, discriminant, fields)| {
+                let fields = fields
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, ty)| (index.to_string().into(), ty))
+                    .collect();
+                let payload_name = format!("{name}::{variant}").into();
+                EnumVariant {
+                    name: variant,
+                    discriminant,
+                    payload: Rc::new(StructType::new(
+                        payload_name,
+                        fields,
+                        Repr::default(),
+                        pointer_size,
+                    )),
+                }
+            })
+            .collect();
+        let payload_align = variants
+            .iter()
+            .map(|variant| variant.payload.align)
+            .max()
+            .unwrap_or(1);
+        let payload_size = variants
+            .iter()
+            .map(|variant| variant.payload.size)
+            .max()
+            .unwrap_or(0);
+        let align = tag.size().max(payload_align);
+        let payload_offset = tag.size().next_multiple_of(payload_align);
+        Self {
+            name,
+            tag,
+            variants,
+            payload_offset,
+            size: (payload_offset + payload_size).next_multiple_of(align),
+            align,
+        }
+    }
+
+    pub fn variant(&self, name: &str) -> Option<(usize, &EnumVariant)> {
+        self.variants
+            .iter()
+            .enumerate()
+            .find(|(_, variant)| *variant.name == *name)
+    }
+}
+
+/// A block of memory-mapped registers at a fixed address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeripheralType {
+    pub name: Rc<str>,
+    pub address: u64,
+    pub registers: Vec<Register>,
+}
+
+impl PeripheralType {
+    pub fn register(&self, name: &str) -> Option<&Register> {
+        self.registers
+            .iter()
+            .find(|register| *register.name == *name)
+    }
+}
+
+/// A register of a peripheral, at an offset from the address of the peripheral.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub name: Rc<str>,
+    pub ty: IntType,
+    pub offset: u64,
+    pub access: RegisterAccess,
+    pub fields: Vec<Bitfield>,
+}
+
+impl Register {
+    pub fn field(&self, name: &str) -> Option<&Bitfield> {
+        self.fields.iter().find(|field| *field.name == *name)
+    }
+}
+
+/// A named run of bits in a register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitfield {
+    pub name: Rc<str>,
+    /// The lowest bit of the field.
+    pub offset: u32,
+    pub width: u32,
+}
+
+impl Bitfield {
+    /// The largest value the field can hold, which is also its mask before shifting into place.
+    pub fn max(&self) -> u64 {
+        u64::MAX >> (64 - self.width)
+    }
+
+    /// The bits of the register that the field is.
+    pub fn mask(&self) -> u64 {
+        self.max() << self.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{
+        Bitfield, EnumType, IntType, Member, PeripheralType, Repr, StructType, Type,
+    };
+    use std::rc::Rc;
+
+    /// The size of a pointer on the Cortex-M cores.
+    const POINTER_SIZE: u64 = 4;
+
+    fn fields(types: &[(&str, u32)]) -> Vec<(Rc<str>, Type)> {
+        types
+            .iter()
+            .map(|&(name, bits)| (name.into(), IntType::new(bits, false).into()))
+            .collect()
+    }
+
+    #[test]
+    fn int_type_names() {
+        assert_eq!(IntType::from_name("u8"), Some(IntType::new(8, false)));
+        assert_eq!(IntType::from_name("i64"), Some(IntType::new(64, true)));
+        assert_eq!(IntType::from_name("u7"), None);
+        assert_eq!(IntType::from_name("x"), None);
+        assert_eq!(IntType::new(16, true).to_string(), "i16");
+    }
+
+    #[test]
+    fn int_type_ranges() {
+        assert_eq!(IntType::new(8, false).max(), 255);
+        assert_eq!(IntType::new(8, true).min(), -128);
+        assert!(IntType::new(64, false).contains(u64::MAX as i128));
+        assert!(!IntType::new(32, true).contains(1 << 31));
+    }
+
+    #[test]
+    fn struct_layout_c() {
+        let ty = StructType::new(
+            "S".into(),
+            fields(&[("a", 8), ("b", 32), ("c", 16)]),
+            Repr::default(),
+            POINTER_SIZE,
+        );
+        let offsets: Vec<_> = ty.fields.iter().map(|field| field.offset).collect();
+        assert_eq!(offsets, vec![0, 4, 8]);
+        assert_eq!((ty.size, ty.align), (12, 4));
+        assert_eq!(
+            ty.members(),
+            vec![
+                Member::Field(0),
+                Member::Padding(3),
+                Member::Field(1),
+                Member::Field(2),
+                Member::Padding(2),
+            ]
+        );
+        assert_eq!(ty.member_index(2), 3);
+    }
+
+    #[test]
+    fn struct_layout_packed_and_aligned() {
+        let packed = Repr {
+            packed: true,
+            ..Repr::default()
+        };
+        let ty = StructType::new(
+            "S".into(),
+            fields(&[("a", 8), ("b", 32)]),
+            packed,
+            POINTER_SIZE,
+        );
+        assert_eq!(ty.fields[1].offset, 1);
+        assert_eq!((ty.size, ty.align), (5, 1));
+        assert_eq!(ty.members(), vec![Member::Field(0), Member::Field(1)]);
+
+        let aligned = Repr {
+            align: Some(16),
+            ..Repr::default()
+        };
+        let ty = StructType::new(
+            "S".into(),
+            fields(&[("a", 8), ("b", 32)]),
+            aligned,
+            POINTER_SIZE,
+        );
+        assert_eq!((ty.size, ty.align), (16, 16));
+        assert_eq!(ty.members().last(), Some(&Member::Padding(8)));
+    }
+
+    #[test]
+    fn array_layout() {
+        let u16 = Type::Int(IntType::new(16, false));
+        let array = Type::Array(Box::new(u16), 3);
+        assert_eq!(
+            (array.size(POINTER_SIZE), array.align(POINTER_SIZE)),
+            (6, 2)
+        );
+        assert_eq!(array.to_string(), "[u16; 3]");
+
+        let slice = Type::Slice {
+            element: Box::new(array.clone()),
+            mutable: true,
+        };
+        assert_eq!(
+            (slice.size(POINTER_SIZE), slice.align(POINTER_SIZE)),
+            (8, 4)
+        );
+        assert_eq!((slice.size(8), slice.align(8)), (16, 8));
+        assert_eq!(slice.to_string(), "&mut [[u16; 3]]");
+
+        let ty = StructType::new(
+            "Buffer".into(),
+            vec![
+                ("data".into(), array),
+                ("length".into(), IntType::new(32, false).into()),
+            ],
+            Repr::default(),
+            POINTER_SIZE,
+        );
+        assert_eq!(ty.fields[1].offset, 8);
+        assert_eq!((ty.size, ty.align), (12, 4));
+        assert!(!Type::Struct(Rc::new(ty)).contains_references());
+    }
+
+    #[test]
+    fn reference_layout() {
+        let u64 = Type::Int(IntType::new(64, false));
+        let reference = Type::Reference {
+            target: Box::new(u64.clone()),
+            mutable: false,
+        };
+        assert_eq!(
+            (reference.size(POINTER_SIZE), reference.align(POINTER_SIZE)),
+            (4, 4)
+        );
+        assert_eq!((reference.size(8), reference.align(8)), (8, 8));
+        assert_eq!(reference.to_string(), "&u64");
+
+        let ty = StructType::new(
+            "Cursor".into(),
+            vec![
+                ("position".into(), u64),
+                (
+                    "data".into(),
+                    Type::Reference {
+                        target: Box::new(reference),
+                        mutable: true,
+                    },
+                ),
+            ],
+            Repr::default(),
+            POINTER_SIZE,
+        );
+        assert_eq!(ty.fields[1].ty.to_string(), "&mut &u64");
+        assert_eq!((ty.size, ty.align), (16, 8));
+        assert!(Type::Array(Box::new(Type::Struct(Rc::new(ty))), 2).contains_references());
+
+        let pointer = Type::Pointer {
+            target: Box::new(Type::Unit),
+            mutable: true,
+        };
+        assert_eq!(
+            (pointer.size(POINTER_SIZE), pointer.align(POINTER_SIZE)),
+            (4, 4)
+        );
+        assert_eq!((pointer.size(8), pointer.align(8)), (8, 8));
+        assert_eq!(pointer.to_string(), "*mut ()");
+        assert!(!pointer.contains_references());
+
+        // The payload holds a whole pointer of the target
+        let slot = EnumType::new(
+            "Slot".into(),
+            IntType::DEFAULT,
+            vec![
+                ("Empty".into(), 0, vec![]),
+                ("Full".into(), 1, vec![pointer]),
+            ],
+            8,
+        );
+        assert_eq!((slot.payload_offset, slot.size), (8, 16));
+    }
+
+    #[test]
+    fn enum_layout() {
+        let u8 = Type::Int(IntType::new(8, false));
+        let u32 = Type::Int(IntType::new(32, false));
+        let ty = EnumType::new(
+            "Command".into(),
+            IntType::new(8, false),
+            vec![
+                ("Stop".into(), 0, vec![]),
+                ("Write".into(), 1, vec![u32, u8.clone()]),
+                ("Blink".into(), 2, vec![u8]),
+            ],
+            POINTER_SIZE,
+        );
+        assert_eq!(ty.payload_offset, 4);
+        assert_eq!((ty.size, ty.align), (12, 4));
+        assert_eq!(ty.variants[1].payload.size, 8);
+        assert_eq!(ty.variant("Blink").map(|(index, _)| index), Some(2));
+
+        let ty = EnumType::new(
+            "State".into(),
+            IntType::DEFAULT,
+            vec![("Idle".into(), 0, vec![]), ("Running".into(), 1, vec![])],
+            POINTER_SIZE,
+        );
+        assert_eq!((ty.size, ty.align), (4, 4));
+    }
+
+    #[test]
+    fn bitfield_masks() {
+        let field = |offset, width| Bitfield {
+            name: "FIELD".into(),
+            offset,
+            width,
+        };
+        assert_eq!((field(21, 1).max(), field(21, 1).mask()), (1, 1 << 21));
+        assert_eq!((field(8, 3).max(), field(8, 3).mask()), (0b111, 0b111 << 8));
+        assert_eq!(field(0, 64).mask(), u64::MAX);
+    }
+
+    #[test]
+    fn peripherals_are_not_copy() {
+        let peripheral = Type::Peripheral(Rc::new(PeripheralType {
+            name: "UART0".into(),
+            address: 0x4000_2000,
+            registers: Vec::new(),
+        }));
+        let holder = StructType::new(
+            "Driver".into(),
+            vec![
+                ("uart".into(), peripheral.clone()),
+                ("baud".into(), IntType::INDEX.into()),
+            ],
+            Repr::default(),
+            POINTER_SIZE,
+        );
+        assert!(!peripheral.is_copy());
+        assert_eq!(peripheral.size(POINTER_SIZE), 0);
+        assert!(!Type::Struct(Rc::new(holder)).is_copy());
+        assert!(!Type::Array(Box::new(peripheral.clone()), 2).is_copy());
+        assert!(
+            Type::Reference {
+                target: Box::new(peripheral),
+                mutable: true,
+            }
+            .is_copy()
+        );
+    }
+
+    #[test]
+    fn ffi_safe_types() {
+        let int: Type = IntType::new(32, false).into();
+        let pointer = Type::Pointer {
+            target: Box::new(int.clone()),
+            mutable: true,
+        };
+        let slice = Type::Slice {
+            element: Box::new(int.clone()),
+            mutable: false,
+        };
+        assert!(int.is_ffi_safe());
+        assert!(pointer.is_ffi_safe());
+        assert!(!slice.is_ffi_safe());
+        assert!(!Type::Array(Box::new(int), 4).is_ffi_safe());
+    }
+}
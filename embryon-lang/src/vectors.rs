@@ -0,0 +1,134 @@
+//! The vector table of the Cortex-M cores, which holds the initial stack pointer and the address
+//! of the handler of each exception and interrupt, such as `#[exception(HardFault)]`.
+
+use crate::target::Target;
+use std::fmt;
+
+/// The enum listing a device's interrupts, with each numbered as in the device's datasheet. The
+/// SVD generator declares it from the `<interrupt>` elements of the device's peripherals.
+pub const INTERRUPTS: &str = "Interrupt";
+
+/// The position in the vector table of interrupt 0, after the stack pointer and the exceptions.
+pub const FIRST_INTERRUPT: u32 = 16;
+
+/// How many interrupts the vector table of a Cortex-M core can hold.
+pub const MAX_INTERRUPTS: u32 = 496;
+
+/// The symbol the linker script defines as the top of the stack, the first entry of the table.
+pub const STACK_START: &str = "_stack_start";
+
+/// The section the vector table is put in, which the linker script places at the start of flash.
+pub const SECTION: &str = ".vector_table";
+
+/// The handler that every vector without one of its own ends up in.
+pub const DEFAULT_HANDLER: &str = "DefaultHandler";
+
+/// The exceptions of the core, which come before the device's interrupts in the vector table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exception {
+    /// Runs the program after the core starts or is reset.
+    Reset,
+    NonMaskableInt,
+    /// Any fault without its own handler, or every fault on ARMv6-M.
+    HardFault,
+    MemoryManagement,
+    BusFault,
+    UsageFault,
+    /// Runs on an `svc` instruction.
+    SVCall,
+    DebugMonitor,
+    PendSV,
+    SysTick,
+}
+
+impl Exception {
+    pub const ALL: [Self; 10] = [
+        Self::Reset,
+        Self::NonMaskableInt,
+        Self::HardFault,
+        Self::MemoryManagement,
+        Self::BusFault,
+        Self::UsageFault,
+        Self::SVCall,
+        Self::DebugMonitor,
+        Self::PendSV,
+        Self::SysTick,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|exception| exception.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Reset => "Reset",
+            Self::NonMaskableInt => "NonMaskableInt",
+            Self::HardFault => "HardFault",
+            Self::MemoryManagement => "MemoryManagement",
+            Self::BusFault => "BusFault",
+            Self::UsageFault => "UsageFault",
+            Self::SVCall => "SVCall",
+            Self::DebugMonitor => "DebugMonitor",
+            Self::PendSV => "PendSV",
+            Self::SysTick => "SysTick",
+        }
+    }
+
+    /// The position of the exception's handler in the vector table.
+    pub fn number(&self) -> u32 {
+        match self {
+            Self::Reset => 1,
+            Self::NonMaskableInt => 2,
+            Self::HardFault => 3,
+            Self::MemoryManagement => 4,
+            Self::BusFault => 5,
+            Self::UsageFault => 6,
+            Self::SVCall => 11,
+            Self::DebugMonitor => 12,
+            Self::PendSV => 14,
+            Self::SysTick => 15,
+        }
+    }
+
+    /// Whether `target` has the exception. ARMv6-M turns every fault into a hard fault and has
+    /// no debug monitor, and the host has no vector table at all.
+    pub fn is_available(&self, target: Target) -> bool {
+        match self {
+            Self::MemoryManagement | Self::BusFault | Self::UsageFault | Self::DebugMonitor => {
+                target.is_arm() && !target.is_armv6m()
+            }
+            _ => target.is_arm(),
+        }
+    }
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::target::Target;
+    use crate::vectors::Exception;
+
+    #[test]
+    fn exception_names() {
+        for exception in Exception::ALL {
+            assert_eq!(Exception::from_name(exception.name()), Some(exception));
+        }
+        assert_eq!(Exception::from_name("SecureFault"), None);
+    }
+
+    #[test]
+    fn exceptions_on_cores() {
+        assert_eq!(Exception::SysTick.number(), 15);
+        assert!(Exception::HardFault.is_available(Target::CortexM0));
+        assert!(!Exception::BusFault.is_available(Target::CortexM0Plus));
+        assert!(Exception::BusFault.is_available(Target::CortexM4));
+        assert!(!Exception::Reset.is_available(Target::Host));
+    }
+}